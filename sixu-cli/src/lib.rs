@@ -0,0 +1,184 @@
+//! Command line tools for sixu.
+//!
+//! `sixu check [--format=text|json] <files...>` lints story files and reports
+//! their diagnostics.
+//!
+//! `sixu graph <file>` prints the paragraph transitions of a story as a Graphviz
+//! digraph, see `Story::to_dot`.
+//!
+//! Exit codes:
+//! - `0`: no error-severity diagnostic was reported
+//! - `1`: at least one error-severity diagnostic was reported, or the story given to
+//!   `graph` failed to parse
+//! - `2`: invalid usage or a file could not be read
+
+use std::io::Write;
+
+use serde::Serialize;
+use sixu::lint::{Diagnostic, Severity};
+
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_DIAGNOSTIC_ERRORS: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+
+const USAGE: &str = "usage: sixu check [--format=text|json] <files...>\n       sixu graph <file>";
+
+/// A diagnostic together with the file it was reported for.
+///
+/// With `--format=json` the output is an array of these objects:
+///
+/// ```json
+/// [{
+///   "file": "story.sixu",
+///   "range": { "start": { "line": 1, "column": 9 }, "end": { "line": 1, "column": 10 } },
+///   "severity": "error",
+///   "source": "sixu",
+///   "message": "Syntax error: ...",
+///   "code": "syntax-error"
+/// }]
+/// ```
+///
+/// Lines and columns are 0-based, columns count characters, and `code` is omitted
+/// when the diagnostic has none.
+#[derive(Debug, Serialize)]
+pub struct FileDiagnostic<'a> {
+    pub file: &'a str,
+    #[serde(flatten)]
+    pub diagnostic: &'a Diagnostic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Run the command line with `args` (without the program name), returning the exit code
+pub fn run(args: &[String], out: &mut impl Write, err: &mut impl Write) -> i32 {
+    match args.split_first() {
+        Some((command, rest)) if command == "check" => check(rest, out, err),
+        Some((command, rest)) if command == "graph" => graph(rest, out, err),
+        _ => {
+            let _ = writeln!(err, "{}", USAGE);
+            EXIT_USAGE
+        }
+    }
+}
+
+fn check(args: &[String], out: &mut impl Write, err: &mut impl Write) -> i32 {
+    let mut format = OutputFormat::Text;
+    let mut files = Vec::new();
+
+    for arg in args {
+        match arg.strip_prefix("--format=") {
+            Some("text") => format = OutputFormat::Text,
+            Some("json") => format = OutputFormat::Json,
+            Some(other) => {
+                let _ = writeln!(err, "unknown format `{}`\n{}", other, USAGE);
+                return EXIT_USAGE;
+            }
+            None if arg.starts_with("--") => {
+                let _ = writeln!(err, "unknown option `{}`\n{}", arg, USAGE);
+                return EXIT_USAGE;
+            }
+            None => files.push(arg.as_str()),
+        }
+    }
+
+    if files.is_empty() {
+        let _ = writeln!(err, "{}", USAGE);
+        return EXIT_USAGE;
+    }
+
+    let mut results = Vec::new();
+    for file in files {
+        match std::fs::read_to_string(file) {
+            Ok(text) => results.push((file, sixu::lint(&text))),
+            Err(e) => {
+                let _ = writeln!(err, "{}: {}", file, e);
+                return EXIT_USAGE;
+            }
+        }
+    }
+
+    let diagnostics: Vec<FileDiagnostic> = results
+        .iter()
+        .flat_map(|(file, diagnostics)| {
+            diagnostics
+                .iter()
+                .map(move |diagnostic| FileDiagnostic { file, diagnostic })
+        })
+        .collect();
+
+    let written = match format {
+        OutputFormat::Json => serde_json::to_writer_pretty(&mut *out, &diagnostics)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writeln!(out)),
+        OutputFormat::Text => diagnostics.iter().try_for_each(|d| {
+            writeln!(
+                out,
+                "{}:{}:{}: {}: {}",
+                d.file,
+                d.diagnostic.range.start.line + 1,
+                d.diagnostic.range.start.column + 1,
+                severity_name(d.diagnostic.severity),
+                d.diagnostic.message
+            )
+        }),
+    };
+    if let Err(e) = written {
+        let _ = writeln!(err, "failed to write diagnostics: {}", e);
+        return EXIT_USAGE;
+    }
+
+    if diagnostics
+        .iter()
+        .any(|d| d.diagnostic.severity == Severity::Error)
+    {
+        EXIT_DIAGNOSTIC_ERRORS
+    } else {
+        EXIT_OK
+    }
+}
+
+fn graph(args: &[String], out: &mut impl Write, err: &mut impl Write) -> i32 {
+    let [file] = args else {
+        let _ = writeln!(err, "{}", USAGE);
+        return EXIT_USAGE;
+    };
+
+    let text = match std::fs::read_to_string(file) {
+        Ok(text) => text,
+        Err(e) => {
+            let _ = writeln!(err, "{}: {}", file, e);
+            return EXIT_USAGE;
+        }
+    };
+    // the story is named like its file, as when loaded by a runtime
+    let name = std::path::Path::new(file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let story = match sixu::parser::parse_story(&name, &text) {
+        Ok(story) => story,
+        Err(e) => {
+            let _ = writeln!(err, "{}: {}", file, e);
+            return EXIT_DIAGNOSTIC_ERRORS;
+        }
+    };
+
+    if let Err(e) = write!(out, "{}", story.to_dot()) {
+        let _ = writeln!(err, "failed to write graph: {}", e);
+        return EXIT_USAGE;
+    }
+    EXIT_OK
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Information => "info",
+        Severity::Hint => "hint",
+    }
+}
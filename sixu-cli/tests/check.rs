@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+fn fixture(name: &str) -> String {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("check")
+        .join(name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn sixu(args: &[&str]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_sixu"))
+        .args(args)
+        .output()
+        .expect("failed to run sixu");
+    (
+        output
+            .status
+            .code()
+            .expect("sixu was terminated by a signal"),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn test_check_json_reports_errors() {
+    let broken = fixture("broken.sixu");
+    let valid = fixture("valid.sixu");
+    let (code, stdout) = sixu(&["check", "--format=json", &valid, &broken]);
+
+    assert_eq!(code, 1);
+
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let diagnostics = json.as_array().expect("output should be an array");
+    assert_eq!(diagnostics.len(), 1);
+
+    let diagnostic = &diagnostics[0];
+    assert_eq!(diagnostic["file"], broken.as_str());
+    assert_eq!(diagnostic["severity"], "error");
+    assert_eq!(diagnostic["source"], "sixu");
+    assert_eq!(diagnostic["code"], "syntax-error");
+    assert_eq!(diagnostic["range"]["start"]["line"], 1);
+    assert_eq!(diagnostic["range"]["start"]["column"], 27);
+    assert_eq!(diagnostic["range"]["end"]["line"], 1);
+    assert_eq!(diagnostic["range"]["end"]["column"], 28);
+    assert!(
+        diagnostic["message"]
+            .as_str()
+            .unwrap()
+            .contains("expected `,` or `)`")
+    );
+}
+
+#[test]
+fn test_check_json_clean_file() {
+    let (code, stdout) = sixu(&["check", "--format=json", &fixture("valid.sixu")]);
+
+    assert_eq!(code, 0);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json, serde_json::json!([]));
+}
+
+#[test]
+fn test_check_text_format() {
+    let broken = fixture("broken.sixu");
+    let (code, stdout) = sixu(&["check", &broken]);
+
+    assert_eq!(code, 1);
+    assert!(
+        stdout.starts_with(&format!("{}:2:28: error: Syntax error:", broken)),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn test_check_usage_errors() {
+    assert_eq!(sixu(&[]).0, 2);
+    assert_eq!(sixu(&["check"]).0, 2);
+    assert_eq!(
+        sixu(&["check", "--format=xml", &fixture("valid.sixu")]).0,
+        2
+    );
+    assert_eq!(sixu(&["check", &fixture("missing.sixu")]).0, 2);
+}
+
+#[test]
+fn test_graph_prints_dot() {
+    let (code, stdout) = sixu(&["graph", &fixture("valid.sixu")]);
+
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "digraph \"valid\" {\n    \"entry\";\n}\n");
+}
+
+#[test]
+fn test_graph_errors() {
+    assert_eq!(sixu(&["graph"]).0, 2);
+    assert_eq!(sixu(&["graph", &fixture("missing.sixu")]).0, 2);
+    assert_eq!(sixu(&["graph", &fixture("broken.sixu")]).0, 1);
+}
@@ -0,0 +1,535 @@
+//! 补全上下文分析
+//!
+//! 只看光标前的文本，用一个小型词法分析器判断光标处应补全的内容。
+//! 字符串、模板、注释、属性与代码块中的 `@`、`#` 不会被当作命令开头。
+
+/// 光标处的补全上下文
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletionContext {
+    /// 正在输入命令名，如 `@chan`
+    CommandName,
+    /// 正在输入系统调用名，如 `#go`
+    SystemCallName,
+    /// 正在输入参数名，如 `@changebg src="a.png" `
+    ArgumentName {
+        command: String,
+        /// 是否为系统调用
+        system_call: bool,
+        /// 光标前已写完的参数名，不含正在输入的参数
+        existing: Vec<String>,
+    },
+    /// 正在输入参数值，如 `@changebg fadeTime=`
+    ArgumentValue {
+        command: String,
+        system_call: bool,
+        arg: String,
+    },
+    /// 在模板字符串未闭合的 `${` 插值中，如 `` `Hello ${pla ``
+    VariableInTemplate,
+    /// 不需要补全，如光标在字符串或注释中
+    None,
+}
+
+/// 当前参数的输入状态
+enum ArgState {
+    /// 参数之间
+    Between,
+    /// 正在输入参数名
+    Name(String),
+    /// 参数名之后的空白，可能是布尔标志，也可能后面跟 `=`
+    AfterName(String),
+    /// `=` 之后，值还没开始
+    AfterEquals(String),
+    /// 正在输入值，记录方括号与花括号的嵌套深度
+    Value(String, usize),
+}
+
+/// 光标所在的命令或系统调用
+struct Call {
+    name: String,
+    system_call: bool,
+    paren: bool,
+    existing: Vec<String>,
+    arg: ArgState,
+    /// 括号语法中光标所在参数的序号，即已经过的顶层逗号数
+    index: u32,
+}
+
+impl Call {
+    fn into_context(self) -> CompletionContext {
+        match self.arg {
+            ArgState::Between | ArgState::Name(_) | ArgState::AfterName(_) => {
+                CompletionContext::ArgumentName {
+                    command: self.name,
+                    system_call: self.system_call,
+                    existing: self.existing,
+                }
+            }
+            ArgState::AfterEquals(arg) | ArgState::Value(arg, _) => {
+                CompletionContext::ArgumentValue {
+                    command: self.name,
+                    system_call: self.system_call,
+                    arg,
+                }
+            }
+        }
+    }
+
+    /// 结束正在输入的参数名，记入已有参数
+    fn finish_name(&mut self) -> Option<String> {
+        match std::mem::replace(&mut self.arg, ArgState::Between) {
+            ArgState::Name(name) => {
+                self.existing.push(name.clone());
+                Some(name)
+            }
+            ArgState::AfterName(name) => Some(name),
+            other => {
+                self.arg = other;
+                None
+            }
+        }
+    }
+}
+
+/// 分析光标前的文本 `line_prefix`，返回光标处的补全上下文
+pub fn analyze_completion_context(line_prefix: &str) -> CompletionContext {
+    match analyze(&line_prefix.chars().collect::<Vec<_>>()) {
+        Ok(call) => call.map_or(CompletionContext::None, Call::into_context),
+        Err(context) => context,
+    }
+}
+
+/// 光标位于 `@cmd(` 的参数列表内时，返回命令名和光标所在参数的序号
+///
+/// 序号按光标前的顶层逗号计数，字符串、数组和对象中的逗号不计入
+pub fn analyze_signature_context(line_prefix: &str) -> Option<(String, u32)> {
+    let call = analyze(&line_prefix.chars().collect::<Vec<_>>()).ok()??;
+    (call.paren && !call.system_call).then_some((call.name, call.index))
+}
+
+/// 逐字符扫描，返回光标所在的调用；光标处于命令名、字符串、注释等内部时，
+/// 以 `Err` 提前返回对应的补全上下文
+fn analyze(chars: &[char]) -> Result<Option<Call>, CompletionContext> {
+    let mut call: Option<Call> = None;
+    // 上一个字符之后能否开始一条命令或系统调用
+    let mut boundary = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        // 注释
+        if c == '/' && next == Some('/') {
+            return Err(CompletionContext::None);
+        }
+        if c == '/' && next == Some('*') {
+            i = skip_block_comment(chars, i)?;
+            if let Some(call) = &mut call {
+                call.finish_name();
+                if let ArgState::Value(..) = call.arg {
+                    call.arg = ArgState::Between;
+                }
+            }
+            boundary = true;
+            continue;
+        }
+
+        let Some(current) = &mut call else {
+            match c {
+                '"' | '\'' => {
+                    i = skip_string(chars, i)?;
+                    boundary = false;
+                    continue;
+                }
+                '`' => {
+                    i = skip_template(chars, i)?;
+                    boundary = false;
+                    continue;
+                }
+                '#' if boundary && next == Some('[') => {
+                    i = skip_attribute(chars, i)?;
+                    boundary = true;
+                    continue;
+                }
+                '#' if boundary && next == Some('#') => {
+                    i = skip_until(chars, i + 2, "##")?;
+                    boundary = true;
+                    continue;
+                }
+                '@' if boundary && next == Some('{') => {
+                    i = skip_until(chars, i + 2, "}")?;
+                    boundary = true;
+                    continue;
+                }
+                '@' | '#' if boundary => {
+                    let start = i + 1;
+                    let mut end = start;
+                    while end < chars.len() && is_identifier_char(chars[end]) {
+                        end += 1;
+                    }
+                    if end == chars.len() {
+                        return Err(if c == '@' {
+                            CompletionContext::CommandName
+                        } else {
+                            CompletionContext::SystemCallName
+                        });
+                    }
+                    if end > start && (chars[end] == '(' || chars[end].is_whitespace()) {
+                        call = Some(Call {
+                            name: chars[start..end].iter().collect(),
+                            system_call: c == '#',
+                            paren: chars[end] == '(',
+                            existing: Vec::new(),
+                            arg: ArgState::Between,
+                            index: 0,
+                        });
+                        i = end + 1;
+                    } else {
+                        boundary = false;
+                        i = end;
+                    }
+                    continue;
+                }
+                _ => {
+                    boundary = c.is_whitespace() || matches!(c, '{' | '}' | ']' | ')');
+                    i += 1;
+                    continue;
+                }
+            }
+        };
+
+        match c {
+            '"' | '\'' | '`' => {
+                i = if c == '`' {
+                    skip_template(chars, i)?
+                } else {
+                    skip_string(chars, i)?
+                };
+                if let ArgState::AfterEquals(arg) = &current.arg {
+                    current.arg = ArgState::Value(arg.clone(), 0);
+                }
+                continue;
+            }
+            '(' if !current.paren
+                && current.existing.is_empty()
+                && matches!(current.arg, ArgState::Between) =>
+            {
+                current.paren = true;
+            }
+            ')' if current.paren && !matches!(current.arg, ArgState::Value(_, 1..)) => {
+                current.finish_name();
+                call = None;
+                boundary = true;
+            }
+            ',' if current.paren && !matches!(current.arg, ArgState::Value(_, 1..)) => {
+                current.finish_name();
+                current.arg = ArgState::Between;
+                current.index += 1;
+            }
+            '=' => {
+                if let Some(name) = current.finish_name() {
+                    current.arg = ArgState::AfterEquals(name);
+                } else if let ArgState::Value(..) = current.arg {
+                    // 值中的 = 属于值本身
+                } else {
+                    current.arg = ArgState::Between;
+                }
+            }
+            c if c.is_whitespace() => match &current.arg {
+                ArgState::Name(name) => {
+                    let name = name.clone();
+                    current.existing.push(name.clone());
+                    current.arg = ArgState::AfterName(name);
+                }
+                ArgState::Value(_, 0) => current.arg = ArgState::Between,
+                _ => {}
+            },
+            _ => match &mut current.arg {
+                ArgState::Value(_, depth) => match c {
+                    '[' | '{' => *depth += 1,
+                    ']' | '}' => *depth = depth.saturating_sub(1),
+                    _ => {}
+                },
+                ArgState::AfterEquals(arg) => {
+                    let depth = usize::from(matches!(c, '[' | '{'));
+                    current.arg = ArgState::Value(arg.clone(), depth);
+                }
+                ArgState::Name(name) if is_identifier_char(c) => name.push(c),
+                ArgState::Between | ArgState::AfterName(_) if is_identifier_char(c) => {
+                    current.arg = ArgState::Name(c.to_string());
+                }
+                _ => {}
+            },
+        }
+        i += 1;
+    }
+
+    Ok(call)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// 跳过从 `start` 开始的引号字符串，返回其后的位置；未闭合时光标在字符串内
+fn skip_string(chars: &[char], start: usize) -> Result<usize, CompletionContext> {
+    let quote = chars[start];
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            c if c == quote => return Ok(i + 1),
+            _ => i += 1,
+        }
+    }
+    Err(CompletionContext::None)
+}
+
+/// 跳过从 `start` 开始的模板字符串；光标在未闭合的 `${` 中时为变量补全
+fn skip_template(chars: &[char], start: usize) -> Result<usize, CompletionContext> {
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '`' => return Ok(i + 1),
+            '$' if chars.get(i + 1) == Some(&'{') => {
+                i += 2;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(CompletionContext::VariableInTemplate),
+                        Some('}') => break,
+                        Some('"' | '\'') => i = skip_string(chars, i)?,
+                        Some(_) => i += 1,
+                    }
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    Err(CompletionContext::None)
+}
+
+/// 跳过 `#[...]` 属性，条件中的字符串可能含有 `]`
+fn skip_attribute(chars: &[char], start: usize) -> Result<usize, CompletionContext> {
+    let mut i = start + 2;
+    while i < chars.len() {
+        match chars[i] {
+            ']' => return Ok(i + 1),
+            '"' | '\'' => i = skip_string(chars, i)?,
+            _ => i += 1,
+        }
+    }
+    Err(CompletionContext::None)
+}
+
+/// 跳过 `/* ... */` 注释
+fn skip_block_comment(chars: &[char], start: usize) -> Result<usize, CompletionContext> {
+    skip_until(chars, start + 2, "*/")
+}
+
+/// 从 `start` 开始查找 `end`，返回其后的位置；找不到时光标在其中，不需要补全
+fn skip_until(chars: &[char], start: usize, end: &str) -> Result<usize, CompletionContext> {
+    let end: Vec<char> = end.chars().collect();
+    (start..chars.len())
+        .find(|&i| chars[i..].starts_with(&end))
+        .map(|i| i + end.len())
+        .ok_or(CompletionContext::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argument_name(command: &str, system_call: bool, existing: &[&str]) -> CompletionContext {
+        CompletionContext::ArgumentName {
+            command: command.to_string(),
+            system_call,
+            existing: existing.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn argument_value(command: &str, system_call: bool, arg: &str) -> CompletionContext {
+        CompletionContext::ArgumentValue {
+            command: command.to_string(),
+            system_call,
+            arg: arg.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_command_and_system_call_names() {
+        assert_eq!(
+            analyze_completion_context("@"),
+            CompletionContext::CommandName
+        );
+        assert_eq!(
+            analyze_completion_context("    @chan"),
+            CompletionContext::CommandName
+        );
+        assert_eq!(
+            analyze_completion_context("#go"),
+            CompletionContext::SystemCallName
+        );
+        // 属性之后的命令
+        assert_eq!(
+            analyze_completion_context("#[cond(\"a]b\")] @sh"),
+            CompletionContext::CommandName
+        );
+        // 同一行中闭合的调用之后
+        assert_eq!(
+            analyze_completion_context("@a(x=1) #"),
+            CompletionContext::SystemCallName
+        );
+        assert_eq!(
+            analyze_completion_context("::main { @"),
+            CompletionContext::CommandName
+        );
+    }
+
+    #[test]
+    fn test_argument_names() {
+        assert_eq!(
+            analyze_completion_context("@changebg "),
+            argument_name("changebg", false, &[])
+        );
+        assert_eq!(
+            analyze_completion_context("@changebg src=\"a b.png\" fadeTime=600 "),
+            argument_name("changebg", false, &["src", "fadeTime"])
+        );
+        assert_eq!(
+            analyze_completion_context("@changebg(src=\"a, b)\", "),
+            argument_name("changebg", false, &["src"])
+        );
+        // 布尔标志与正在输入的参数名
+        assert_eq!(
+            analyze_completion_context("@changebg skippable fa"),
+            argument_name("changebg", false, &["skippable"])
+        );
+        assert_eq!(
+            analyze_completion_context("#call paragraph=\"abc\" "),
+            argument_name("call", true, &["paragraph"])
+        );
+        // 数组值中的空格与逗号不结束参数
+        assert_eq!(
+            analyze_completion_context("@choose(options=[1, 2], "),
+            argument_name("choose", false, &["options"])
+        );
+        assert_eq!(
+            analyze_completion_context("@a x=1 /* c */ "),
+            argument_name("a", false, &["x"])
+        );
+    }
+
+    #[test]
+    fn test_argument_values() {
+        assert_eq!(
+            analyze_completion_context("@changebg fadeTime="),
+            argument_value("changebg", false, "fadeTime")
+        );
+        assert_eq!(
+            analyze_completion_context("@changebg(src = "),
+            argument_value("changebg", false, "src")
+        );
+        assert_eq!(
+            analyze_completion_context("#goto paragraph=\"main\""),
+            argument_value("goto", true, "paragraph")
+        );
+        assert_eq!(
+            analyze_completion_context("@choose options=[1, "),
+            argument_value("choose", false, "options")
+        );
+    }
+
+    #[test]
+    fn test_variable_in_template() {
+        assert_eq!(
+            analyze_completion_context("`Hello, ${pla"),
+            CompletionContext::VariableInTemplate
+        );
+        assert_eq!(
+            analyze_completion_context("@say text=`Hi ${"),
+            CompletionContext::VariableInTemplate
+        );
+        // 插值已闭合，光标在模板文本中
+        assert_eq!(
+            analyze_completion_context("`Hello, ${name} @"),
+            CompletionContext::None
+        );
+    }
+
+    #[test]
+    fn test_no_completion_in_strings_and_comments() {
+        // 字符串中的 @ 与 #
+        assert_eq!(
+            analyze_completion_context("\"mail me @"),
+            CompletionContext::None
+        );
+        assert_eq!(
+            analyze_completion_context("@say text='#"),
+            CompletionContext::None
+        );
+        assert_eq!(
+            analyze_completion_context("@changebg src=\"test "),
+            CompletionContext::None
+        );
+        assert_eq!(
+            analyze_completion_context("'it\\'s @"),
+            CompletionContext::None
+        );
+        // 注释
+        assert_eq!(analyze_completion_context("// @"), CompletionContext::None);
+        assert_eq!(
+            analyze_completion_context("@a x=1 // #"),
+            CompletionContext::None
+        );
+        assert_eq!(analyze_completion_context("/* @"), CompletionContext::None);
+        // 代码块与属性
+        assert_eq!(
+            analyze_completion_context("@{ let a = '@"),
+            CompletionContext::None
+        );
+        assert_eq!(analyze_completion_context("## #"), CompletionContext::None);
+        assert_eq!(
+            analyze_completion_context("#[cond(\"@"),
+            CompletionContext::None
+        );
+        // 闭合括号之后、单词中间的 @
+        assert_eq!(
+            analyze_completion_context("@a(x=1) "),
+            CompletionContext::None
+        );
+        assert_eq!(analyze_completion_context("user@"), CompletionContext::None);
+        assert_eq!(
+            analyze_completion_context("plain text"),
+            CompletionContext::None
+        );
+    }
+
+    #[test]
+    fn test_signature_context() {
+        let context = |name: &str, index| Some((name.to_string(), index));
+        assert_eq!(
+            analyze_signature_context("@changebg("),
+            context("changebg", 0)
+        );
+        assert_eq!(
+            analyze_signature_context("@cmd(a=\"x@y\", b=1, "),
+            context("cmd", 2)
+        );
+        assert_eq!(
+            analyze_signature_context("@choose(options=[1, 2], data={a: 1, b: 2}"),
+            context("choose", 1)
+        );
+        assert_eq!(
+            analyze_signature_context("`${a}` @cmd(a=`x, ${b}`, "),
+            context("cmd", 1)
+        );
+        // 无括号语法、系统调用、参数列表已闭合、字符串内
+        assert_eq!(analyze_signature_context("@cmd a=1, "), None);
+        assert_eq!(analyze_signature_context("#goto("), None);
+        assert_eq!(analyze_signature_context("@cmd(a=1) "), None);
+        assert_eq!(analyze_signature_context("@cmd(a=\"x, "), None);
+    }
+}
@@ -0,0 +1,69 @@
+use serde::Deserialize;
+
+/// 每个文件默认最多发布的诊断数量
+pub const DEFAULT_MAX_DIAGNOSTICS: usize = 1000;
+
+/// 客户端通过 `initializationOptions` 传入的服务端配置
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ServerOptions {
+    pub region_markers: RegionMarkers,
+    /// 代码块最大嵌套深度，超过时给出提示；未设置时不检查
+    pub max_block_depth: Option<usize>,
+    /// 缩进混用制表符与空格时给出警告，默认关闭
+    pub check_mixed_indentation: bool,
+    /// 命令与系统调用混用括号语法和空格分隔语法时，在少数写法上给出提示，默认关闭
+    pub check_call_syntax: bool,
+    /// 从 `entry` 段落出发无法到达的段落给出提示，默认关闭
+    pub check_dead_code: bool,
+    /// 每个文件最多发布的诊断数量，超出时优先保留最严重的诊断，并追加一条汇总提示
+    pub max_diagnostics: usize,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        ServerOptions {
+            region_markers: RegionMarkers::default(),
+            max_block_depth: None,
+            check_mixed_indentation: false,
+            check_call_syntax: false,
+            check_dead_code: false,
+            max_diagnostics: DEFAULT_MAX_DIAGNOSTICS,
+        }
+    }
+}
+
+/// 折叠区域标记，写在行注释中，如 `// #region 开场` 与 `// #endregion`
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RegionMarkers {
+    pub start: String,
+    pub end: String,
+}
+
+impl Default for RegionMarkers {
+    fn default() -> Self {
+        RegionMarkers {
+            start: "#region".to_string(),
+            end: "#endregion".to_string(),
+        }
+    }
+}
+
+impl RegionMarkers {
+    /// 注释内容（不含 //）是否以指定标记开头，标记后只能是结尾或空白
+    fn matches(comment: &str, marker: &str) -> bool {
+        comment
+            .trim_start()
+            .strip_prefix(marker)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+    }
+
+    pub fn is_start(&self, comment: &str) -> bool {
+        Self::matches(comment, &self.start)
+    }
+
+    pub fn is_end(&self, comment: &str) -> bool {
+        Self::matches(comment, &self.end)
+    }
+}
@@ -1,5 +1,8 @@
+use crate::config::RegionMarkers;
 use sixu::cst::{node::*, span::SpanInfo};
-use tower_lsp_server::ls_types::{Position, Range};
+use tower_lsp_server::ls_types::{
+    Position, Range, SemanticToken, SemanticTokenModifier, SemanticTokenType, TextEdit,
+};
 
 /// 将 CST SpanInfo 转换为 LSP Range
 pub fn span_to_range(span: &SpanInfo) -> Range {
@@ -32,62 +35,375 @@ pub fn contains(range: &Range, pos: &Position) -> bool {
 
 /// 从 CST 中提取所有命令节点
 pub fn extract_commands(cst: &CstRoot) -> Vec<&CstCommand> {
-    let mut commands = Vec::new();
+    cst.descendants()
+        .filter_map(|node| match node {
+            CstNode::Command(cmd) => Some(cmd),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 将所有 `@old` 命令调用的命令名改为 `new`，只修改命令名本身以保留原有格式
+///
+/// 文本、系统调用和嵌入代码中恰好同名的内容不受影响。
+pub fn command_rename_edits(cst: &CstRoot, old: &str, new: &str) -> Vec<TextEdit> {
+    extract_commands(cst)
+        .into_iter()
+        .filter(|cmd| cmd.command == old)
+        .map(|cmd| TextEdit {
+            range: span_to_range(&cmd.name_span),
+            new_text: new.to_string(),
+        })
+        .collect()
+}
+
+/// 收集作者写在命令旁的注释，用于悬停提示。以下注释计入：
+/// - 命令前紧邻的连续独占一行的注释（中间不能有空行）
+/// - 命令同一行末尾的注释
+/// - 命令自身 leading trivia 中的注释
+pub fn command_comments(cst: &CstRoot, cmd: &CstCommand) -> Vec<String> {
+    fn find_siblings<'a>(nodes: &'a [CstNode], cmd: &CstCommand) -> Option<(&'a [CstNode], usize)> {
+        nodes.iter().enumerate().find_map(|(i, node)| match node {
+            CstNode::Command(c) if std::ptr::eq(c, cmd) => Some((nodes, i)),
+            CstNode::Paragraph(para) => find_siblings(&para.block.children, cmd),
+            CstNode::Block(block) => find_siblings(&block.children, cmd),
+            _ => None,
+        })
+    }
+
+    fn comment_text(node: &CstNode) -> Option<&str> {
+        match node {
+            CstNode::Trivia(CstTrivia::LineComment { content, .. })
+            | CstNode::Trivia(CstTrivia::BlockComment { content, .. }) => Some(content.trim()),
+            _ => None,
+        }
+    }
 
-    fn visit_node<'a>(node: &'a CstNode, commands: &mut Vec<&'a CstCommand>) {
+    fn newlines(node: &CstNode) -> Option<usize> {
         match node {
-            CstNode::Command(cmd) => commands.push(cmd),
-            CstNode::Paragraph(para) => {
-                visit_block(&para.block, commands);
+            CstNode::Trivia(CstTrivia::Whitespace { content, .. }) => {
+                Some(content.matches('\n').count())
             }
-            CstNode::Block(block) => {
-                visit_block(block, commands);
+            _ => None,
+        }
+    }
+
+    let mut comments: Vec<String> =
+        cmd.leading_trivia
+            .iter()
+            .filter_map(|trivia| match trivia {
+                CstTrivia::LineComment { content, .. }
+                | CstTrivia::BlockComment { content, .. } => Some(content.trim().to_string()),
+                CstTrivia::Whitespace { .. } => None,
+            })
+            .collect();
+
+    let Some((siblings, index)) = find_siblings(&cst.nodes, cmd) else {
+        return comments;
+    };
+
+    // 向前查找：换行 + 注释 + 换行 ... 注释必须独占一行
+    let mut leading = Vec::new();
+    let mut i = index;
+    while i >= 3
+        && newlines(&siblings[i - 1]) == Some(1)
+        && newlines(&siblings[i - 3]).is_some_and(|n| n > 0)
+    {
+        match comment_text(&siblings[i - 2]) {
+            Some(text) => leading.push(text.to_string()),
+            None => break,
+        }
+        i -= 2;
+    }
+    leading.reverse();
+    comments.splice(0..0, leading);
+
+    // 同一行末尾的注释
+    if let (Some(ws), Some(next)) = (siblings.get(index + 1), siblings.get(index + 2))
+        && newlines(ws) == Some(0)
+        && let Some(text) = comment_text(next)
+    {
+        comments.push(text.to_string());
+    }
+
+    comments
+}
+
+/// 按源码顺序收集所有单行注释（内容不含 //）
+pub fn extract_line_comments(cst: &CstRoot) -> Vec<(&str, &SpanInfo)> {
+    fn visit_trivia<'a>(trivia: &'a [CstTrivia], comments: &mut Vec<(&'a str, &'a SpanInfo)>) {
+        for t in trivia {
+            if let CstTrivia::LineComment { content, span } = t {
+                comments.push((content, span));
             }
-            _ => {}
         }
     }
 
-    fn visit_block<'a>(block: &'a CstBlock, commands: &mut Vec<&'a CstCommand>) {
-        for child in &block.children {
-            visit_node(child, commands);
+    let mut comments = Vec::new();
+    for node in cst.descendants() {
+        let trivia = match node {
+            CstNode::Trivia(trivia) => std::slice::from_ref(trivia),
+            CstNode::Paragraph(para) => &para.leading_trivia,
+            CstNode::Command(cmd) => &cmd.leading_trivia,
+            CstNode::SystemCall(call) => &call.leading_trivia,
+            CstNode::TextLine(line) => &line.leading_trivia,
+            CstNode::Attribute(attr) => &attr.leading_trivia,
+            _ => continue,
+        };
+        visit_trivia(trivia, &mut comments);
+    }
+    comments.sort_by_key(|(_, span)| span.start);
+    comments
+}
+
+/// 根据注释中的区域标记计算折叠范围，返回 0-based 的 (起始行, 结束行)。
+/// 区域可以嵌套，与代码块结构无关；未配对的标记被忽略
+pub fn region_folds(cst: &CstRoot, markers: &RegionMarkers) -> Vec<(u32, u32)> {
+    let mut stack = Vec::new();
+    let mut folds = Vec::new();
+
+    for (content, span) in extract_line_comments(cst) {
+        let line = (span.start_line - 1) as u32;
+        if markers.is_end(content) {
+            if let Some(start) = stack.pop()
+                && line > start
+            {
+                folds.push((start, line));
+            }
+        } else if markers.is_start(content) {
+            stack.push(line);
         }
     }
 
-    for node in &cst.nodes {
-        visit_node(node, &mut commands);
+    folds.sort();
+    folds
+}
+
+/// 计算段落与代码块的折叠范围，返回 0-based 的 (起始行, 结束行)。
+/// 段落从 `::name` 所在行折叠到右花括号所在行，代码块从块的起始行（含 group 标签）
+/// 开始；写在同一行内的段落和代码块不折叠
+pub fn block_folds(cst: &CstRoot) -> Vec<(u32, u32)> {
+    cst.descendants()
+        .filter_map(|node| {
+            let (start, block) = match node {
+                CstNode::Paragraph(para) => (&para.colon_token, &para.block),
+                CstNode::Block(block) => (&block.span, block),
+                _ => return None,
+            };
+            let start = (start.start_line - 1) as u32;
+            let end = (block.close_brace.start_line - 1) as u32;
+            (end > start).then_some((start, end))
+        })
+        .collect()
+}
+
+/// 找出嵌套深度超过 `max_depth` 的代码块，返回 (深度, 左花括号位置)。
+/// 段落本身的代码块深度为 0；同一条嵌套链只报告最内层的代码块
+pub fn deep_blocks(cst: &CstRoot, max_depth: usize) -> Vec<(usize, &SpanInfo)> {
+    fn visit_nodes<'a>(
+        nodes: &'a [CstNode],
+        depth: usize,
+        max_depth: usize,
+        found: &mut Vec<(usize, &'a SpanInfo)>,
+    ) {
+        for node in nodes {
+            if let CstNode::Block(block) = node {
+                let nested = block
+                    .children
+                    .iter()
+                    .any(|child| matches!(child, CstNode::Block(_)));
+                if depth + 1 > max_depth && !nested {
+                    found.push((depth + 1, &block.open_brace));
+                }
+                visit_nodes(&block.children, depth + 1, max_depth, found);
+            }
+        }
     }
 
-    commands
+    let mut found = Vec::new();
+    for paragraph in extract_paragraphs(cst) {
+        visit_nodes(&paragraph.block.children, 0, max_depth, &mut found);
+    }
+    found
 }
 
-/// 从 CST 中提取所有系统调用节点
-pub fn extract_system_calls(cst: &CstRoot) -> Vec<&CstSystemCall> {
-    let mut system_calls = Vec::new();
+/// 文件中命令与系统调用的参数写法不统一时，返回多数写法是否为括号语法，以及少数写法的调用范围
+///
+/// 没有参数的调用不参与统计；两种写法数量相同时以文件中第一个调用的写法为准。
+pub fn minority_call_syntax(cst: &CstRoot) -> Option<(bool, Vec<&SpanInfo>)> {
+    let mut calls: Vec<(bool, &SpanInfo)> = extract_commands(cst)
+        .into_iter()
+        .filter(|cmd| !cmd.arguments.is_empty())
+        .map(|cmd| {
+            (
+                matches!(cmd.syntax, CommandSyntax::Parenthesized { .. }),
+                &cmd.span,
+            )
+        })
+        .chain(
+            extract_system_calls(cst)
+                .into_iter()
+                .filter(|call| !call.arguments.is_empty())
+                .map(|call| {
+                    let parenthesized = matches!(call.syntax, CommandSyntax::Parenthesized { .. });
+                    (parenthesized, &call.span)
+                }),
+        )
+        .collect();
+    calls.sort_by_key(|(_, span)| span.start);
 
-    fn visit_node<'a>(node: &'a CstNode, calls: &mut Vec<&'a CstSystemCall>) {
-        match node {
-            CstNode::SystemCall(call) => calls.push(call),
-            CstNode::Paragraph(para) => {
-                visit_block(&para.block, calls);
+    let parenthesized = calls.iter().filter(|(paren, _)| *paren).count();
+    let space_separated = calls.len() - parenthesized;
+    if parenthesized == 0 || space_separated == 0 {
+        return None;
+    }
+
+    let majority = match parenthesized.cmp(&space_separated) {
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Less => false,
+        std::cmp::Ordering::Equal => calls[0].0,
+    };
+    let minority = calls
+        .into_iter()
+        .filter(|(paren, _)| *paren != majority)
+        .map(|(_, span)| span)
+        .collect();
+    Some((majority, minority))
+}
+
+/// 从 `entry` 段落出发，沿同文件内的 `#goto`/`#call`/`#replace` 以及段落末尾的顺延执行，
+/// 返回无法到达的段落
+///
+/// 以下情况按保守处理：
+/// - 带 `story` 参数的跳转可能来自其他故事，目标段落视为可达
+/// - 可达段落中存在以变量为目标的跳转时无法确定去向，不报告任何段落
+/// - 没有 `entry` 段落的文件只能从其他故事进入，同样不报告
+/// - 宏定义本身不参与检查，`@@name` 调用处会展开宏体中的跳转
+pub fn unreachable_paragraphs(cst: &CstRoot) -> Vec<&CstParagraph> {
+    fn collect_calls<'a>(
+        block: &'a CstBlock,
+        macros: &[&'a CstParagraph],
+        expanding: &mut Vec<&'a str>,
+        calls: &mut Vec<&'a CstSystemCall>,
+    ) {
+        for node in block.descendants() {
+            match node {
+                CstNode::SystemCall(call) => calls.push(call),
+                CstNode::Command(cmd) => {
+                    // 递归调用的宏只展开一次
+                    let Some(body) = cmd.macro_name().and_then(|name| {
+                        macros
+                            .iter()
+                            .find(|para| para.name == name && !expanding.contains(&name))
+                    }) else {
+                        continue;
+                    };
+                    expanding.push(&body.name);
+                    collect_calls(&body.block, macros, expanding, calls);
+                    expanding.pop();
+                }
+                _ => {}
             }
-            CstNode::Block(block) => {
-                visit_block(block, calls);
+        }
+    }
+
+    // 与运行时一致：段落中没有无条件的 goto/replace/finish/return 时会顺延到下一个段落
+    fn falls_through(nodes: &[CstNode]) -> bool {
+        let mut conditional = false;
+        for node in nodes {
+            match node {
+                CstNode::Trivia(_) => continue,
+                CstNode::Attribute(_) => {
+                    conditional = true;
+                    continue;
+                }
+                CstNode::SystemCall(call)
+                    if !conditional
+                        && ["goto", "replace", "finish", "return"]
+                            .contains(&call.command.as_str()) =>
+                {
+                    return false;
+                }
+                CstNode::Block(block) if !conditional && !falls_through(&block.children) => {
+                    return false;
+                }
+                _ => {}
             }
-            _ => {}
+            conditional = false;
         }
+        true
     }
 
-    fn visit_block<'a>(block: &'a CstBlock, calls: &mut Vec<&'a CstSystemCall>) {
-        for child in &block.children {
-            visit_node(child, calls);
+    let (macros, paragraphs): (Vec<&CstParagraph>, Vec<&CstParagraph>) = extract_paragraphs(cst)
+        .into_iter()
+        .partition(|para| para.macro_keyword.is_some());
+    let Some(entry) = paragraphs.iter().position(|para| para.name == "entry") else {
+        return Vec::new();
+    };
+
+    let mut reachable = vec![false; paragraphs.len()];
+    let mut queue = vec![entry];
+    for call in extract_system_calls(cst) {
+        if get_systemcall_argument_value(call, "story").is_some()
+            && let Some((name, _)) = paragraph_argument(call)
+            && let Some(index) = paragraphs.iter().position(|para| para.name == name)
+        {
+            queue.push(index);
         }
     }
 
-    for node in &cst.nodes {
-        visit_node(node, &mut system_calls);
+    while let Some(index) = queue.pop() {
+        if reachable[index] {
+            continue;
+        }
+        reachable[index] = true;
+
+        let para = paragraphs[index];
+        let mut calls = Vec::new();
+        collect_calls(&para.block, &macros, &mut Vec::new(), &mut calls);
+        for call in calls {
+            if !PARAGRAPH_CALLS.contains(&call.command.as_str())
+                || get_systemcall_argument_value(call, "story").is_some()
+            {
+                continue;
+            }
+            let Some((name, _)) = paragraph_argument(call) else {
+                if call.arguments.iter().any(|arg| arg.name == "paragraph") {
+                    return Vec::new();
+                }
+                continue;
+            };
+            if let Some(target) = paragraphs.iter().position(|para| para.name == name) {
+                queue.push(target);
+            }
+        }
+        if index + 1 < paragraphs.len() && falls_through(&para.block.children) {
+            queue.push(index + 1);
+        }
     }
 
-    system_calls
+    paragraphs
+        .into_iter()
+        .zip(reachable)
+        .filter(|(_, reachable)| !reachable)
+        .map(|(para, _)| para)
+        .collect()
+}
+
+/// 判断 CST 中是否存在 Error 节点（包括段落和代码块内部）
+pub fn has_error_nodes(cst: &CstRoot) -> bool {
+    cst.descendants()
+        .any(|node| matches!(node, CstNode::Error { .. }))
+}
+
+/// 从 CST 中提取所有系统调用节点
+pub fn extract_system_calls(cst: &CstRoot) -> Vec<&CstSystemCall> {
+    cst.descendants()
+        .filter_map(|node| match node {
+            CstNode::SystemCall(call) => Some(call),
+            _ => None,
+        })
+        .collect()
 }
 
 /// 从 CST 中提取所有段落节点
@@ -101,6 +417,117 @@ pub fn extract_paragraphs(cst: &CstRoot) -> Vec<&CstParagraph> {
         .collect()
 }
 
+/// 查找字节偏移 offset 处最内层的模板片段
+///
+/// 在条件片段的分支内时返回分支中的片段，落在 `${?cond}` 等标记上时返回条件片段本身。
+pub fn find_template_part_at(cst: &CstRoot, offset: usize) -> Option<&CstTemplatePart> {
+    fn in_span(span: &SpanInfo, offset: usize) -> bool {
+        span.start <= offset && offset < span.end
+    }
+
+    fn visit_parts(parts: &[CstTemplatePart], offset: usize) -> Option<&CstTemplatePart> {
+        let part = parts.iter().find(|part| in_span(&part.span(), offset))?;
+        match part {
+            CstTemplatePart::Conditional {
+                then, otherwise, ..
+            } => visit_parts(then, offset)
+                .or_else(|| visit_parts(otherwise, offset))
+                .or(Some(part)),
+            _ => Some(part),
+        }
+    }
+
+    fn visit_leading(leading: &CstLeadingText, offset: usize) -> Option<&CstTemplatePart> {
+        match &leading.content {
+            CstLeadingTextContent::Template(tpl) => visit_parts(&tpl.parts, offset),
+            CstLeadingTextContent::Text(_) => None,
+        }
+    }
+
+    fn visit_text(text: &CstText, offset: usize) -> Option<&CstTemplatePart> {
+        match &text.kind {
+            CstTextKind::Template(tpl) => visit_parts(&tpl.parts, offset),
+            _ => None,
+        }
+    }
+
+    fn visit_node(node: &CstNode, offset: usize) -> Option<&CstTemplatePart> {
+        if !in_span(&node.span(), offset) {
+            return None;
+        }
+        match node {
+            CstNode::Paragraph(para) => visit_block(&para.block, offset),
+            CstNode::Block(block) => visit_block(block, offset),
+            CstNode::TextLine(line) => line
+                .leading
+                .as_ref()
+                .and_then(|leading| visit_leading(leading, offset))
+                .or_else(|| line.text.as_ref().and_then(|text| visit_text(text, offset)))
+                .or_else(|| {
+                    line.segments.iter().find_map(|segment| {
+                        visit_leading(&segment.leading, offset).or_else(|| {
+                            segment
+                                .text
+                                .as_ref()
+                                .and_then(|text| visit_text(text, offset))
+                        })
+                    })
+                }),
+            _ => None,
+        }
+    }
+
+    fn visit_block(block: &CstBlock, offset: usize) -> Option<&CstTemplatePart> {
+        block
+            .children
+            .iter()
+            .find_map(|child| visit_node(child, offset))
+    }
+
+    cst.nodes.iter().find_map(|node| visit_node(node, offset))
+}
+
+/// 找出跳转调用中与目标段落参数类型标注不符的参数，返回参数及其期望类型
+///
+/// 只检查字面量，变量的值在运行时才能确定。
+pub fn parameter_type_mismatches<'a>(
+    call: &'a CstSystemCall,
+    paragraph: &'a CstParagraph,
+) -> Vec<(&'a CstArgument, &'a str)> {
+    call.arguments
+        .iter()
+        .filter(|arg| arg.name != "story" && arg.name != "paragraph")
+        .filter_map(|arg| {
+            let param = paragraph.parameters.iter().find(|p| p.name == arg.name)?;
+            let type_name = param.type_.as_deref()?;
+            let sixu::format::RValue::Literal(value) = arg.to_ast().ok()?.value else {
+                return None;
+            };
+            (!param.to_ast().ok()?.accepts(&value)).then_some((arg, type_name))
+        })
+        .collect()
+}
+
+/// 为段落参数生成补全片段
+///
+/// 可选参数以默认值作为占位符（`time=${1:"morning"}`），必选参数为空占位符。
+pub fn parameter_snippet(param: &CstParameter) -> String {
+    match &param.default_value {
+        Some(default) => {
+            // 占位符内的 $、}、\ 需要转义
+            let escaped = default
+                .raw
+                .trim()
+                .replace('\\', "\\\\")
+                .replace('$', "\\$")
+                .replace('}', "\\}");
+            format!("{}=${{1:{}}}", param.name, escaped)
+        }
+        None if param.type_.as_deref() == Some("string") => format!("{}=\"$1\"", param.name),
+        None => format!("{}=$1", param.name),
+    }
+}
+
 /// 从系统调用中获取参数值（字符串形式）
 pub fn get_systemcall_argument_value(call: &CstSystemCall, arg_name: &str) -> Option<String> {
     call.arguments.iter().find_map(|arg| {
@@ -125,6 +552,246 @@ pub fn get_systemcall_argument_value(call: &CstSystemCall, arg_name: &str) -> Op
     })
 }
 
+/// `paragraph` 参数指向段落的系统调用
+pub const PARAGRAPH_CALLS: [&str; 3] = ["goto", "call", "replace"];
+
+/// 获取跳转类系统调用中 `paragraph` 参数的段落名及其引号内的范围
+///
+/// 参数值不是字符串字面量（如变量）时无法确定目标段落，返回 None
+pub fn paragraph_argument(call: &CstSystemCall) -> Option<(String, Range)> {
+    if !PARAGRAPH_CALLS.contains(&call.command.as_str()) {
+        return None;
+    }
+    let value = call
+        .arguments
+        .iter()
+        .find(|arg| arg.name == "paragraph")?
+        .value
+        .as_ref()?;
+    if !matches!(value.kind, CstValueKind::String { .. }) || value.raw.len() < 2 {
+        return None;
+    }
+    let mut range = span_to_range(&value.span);
+    range.start.character += 1;
+    range.end.character -= 1;
+    Some((value.raw[1..value.raw.len() - 1].to_string(), range))
+}
+
+/// 查找光标处的段落：位于段落定义名上，或位于跳转调用的 `paragraph` 参数值上。
+/// 返回 (段落所在故事, 段落名, 名称范围)，不带 `story` 参数的调用指向当前故事 `home`
+pub fn paragraph_at_position(
+    cst: &CstRoot,
+    home: &str,
+    position: &Position,
+) -> Option<(String, String, Range)> {
+    for para in extract_paragraphs(cst) {
+        let range = span_to_range(&para.name_span);
+        if contains(&range, position) {
+            return Some((home.to_string(), para.name.clone(), range));
+        }
+    }
+    extract_system_calls(cst).into_iter().find_map(|call| {
+        let (name, range) = paragraph_argument(call)?;
+        // 光标紧贴在结束引号前时也算
+        let on_value = contains(&range, position) || range.end == *position;
+        if !on_value {
+            return None;
+        }
+        let story =
+            get_systemcall_argument_value(call, "story").unwrap_or_else(|| home.to_string());
+        Some((story, name, range))
+    })
+}
+
+/// 查找故事 `story` 中段落 `name` 的所有引用，返回跳转调用中段落名（引号内）的范围，
+/// `include_declaration` 为 true 时同时包含段落定义名
+///
+/// `home` 为 cst 所在文件的故事名：定义只在 `story == home` 时计入；带 `story`
+/// 参数的调用按参数值判断目标故事，不带的调用指向 `home`。
+pub fn paragraph_references(
+    cst: &CstRoot,
+    home: &str,
+    story: &str,
+    name: &str,
+    include_declaration: bool,
+) -> Vec<Range> {
+    let mut ranges = Vec::new();
+    if include_declaration && home == story {
+        for para in extract_paragraphs(cst) {
+            if para.name == name {
+                ranges.push(span_to_range(&para.name_span));
+            }
+        }
+    }
+    for call in extract_system_calls(cst) {
+        let Some((paragraph, range)) = paragraph_argument(call) else {
+            continue;
+        };
+        let target = get_systemcall_argument_value(call, "story");
+        if paragraph == name && target.as_deref().unwrap_or(home) == story {
+            ranges.push(range);
+        }
+    }
+    ranges
+}
+
+/// 将故事 `story` 中的段落 `old` 改名为 `new`，修改其定义以及所有指向它的跳转，
+/// 只替换名称本身以保留原有格式。`home` 的含义同 [`paragraph_references`]
+pub fn paragraph_rename_edits(
+    cst: &CstRoot,
+    home: &str,
+    story: &str,
+    old: &str,
+    new: &str,
+) -> Vec<TextEdit> {
+    paragraph_references(cst, home, story, old, true)
+        .into_iter()
+        .map(|range| TextEdit {
+            range,
+            new_text: new.to_string(),
+        })
+        .collect()
+}
+
+/// 语义高亮的 token 类型，下标即 [`SemanticToken::token_type`]
+pub const SEMANTIC_TOKEN_TYPES: [SemanticTokenType; 8] = [
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::MACRO,
+    SemanticTokenType::PARAMETER,
+    SemanticTokenType::STRING,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::CLASS,
+    SemanticTokenType::DECORATOR,
+];
+
+/// 语义高亮的 token 修饰符，第 i 项对应 bitset 的第 i 位
+pub const SEMANTIC_TOKEN_MODIFIERS: [SemanticTokenModifier; 1] =
+    [SemanticTokenModifier::DECLARATION];
+
+/// 遍历 CST 生成按 LSP 相对位置编码的语义 token 列表：
+/// 命令名、系统调用名、参数名、参数值（字符串/数字/变量）、段落名及属性关键字。
+///
+/// CST 的列号按字节计算，这里借助源码 `text` 换算为 UTF-16 列号；
+/// 跨行的 token（如多行模板字符串）不支持，直接跳过
+pub fn semantic_tokens(cst: &CstRoot, text: &str) -> Vec<SemanticToken> {
+    const FUNCTION: u32 = 0;
+    const MACRO: u32 = 1;
+    const PARAMETER: u32 = 2;
+    const STRING: u32 = 3;
+    const NUMBER: u32 = 4;
+    const VARIABLE: u32 = 5;
+    const CLASS: u32 = 6;
+    const DECORATOR: u32 = 7;
+    const DECLARATION: u32 = 1;
+
+    fn push_value<'a>(value: &'a CstValue, tokens: &mut Vec<(&'a SpanInfo, u32, u32)>) {
+        let token_type = match value.kind {
+            CstValueKind::String { .. } | CstValueKind::TemplateString => STRING,
+            CstValueKind::Integer | CstValueKind::Float => NUMBER,
+            CstValueKind::Variable => VARIABLE,
+            _ => return,
+        };
+        tokens.push((&value.span, token_type, 0));
+    }
+
+    fn push_arguments<'a>(args: &'a [CstArgument], tokens: &mut Vec<(&'a SpanInfo, u32, u32)>) {
+        for arg in args {
+            tokens.push((&arg.name_span, PARAMETER, 0));
+            if let Some(value) = &arg.value {
+                push_value(value, tokens);
+            }
+        }
+    }
+
+    fn visit_nodes<'a>(nodes: &'a [CstNode], tokens: &mut Vec<(&'a SpanInfo, u32, u32)>) {
+        for node in nodes {
+            match node {
+                CstNode::Paragraph(para) => {
+                    tokens.push((&para.name_span, CLASS, DECLARATION));
+                    for param in &para.parameters {
+                        tokens.push((&param.name_span, PARAMETER, DECLARATION));
+                        if let Some(value) = &param.default_value {
+                            push_value(value, tokens);
+                        }
+                    }
+                    visit_block(&para.block, tokens);
+                }
+                CstNode::Block(block) => visit_block(block, tokens),
+                CstNode::Command(cmd) => {
+                    tokens.push((&cmd.name_span, FUNCTION, 0));
+                    push_arguments(&cmd.arguments, tokens);
+                }
+                CstNode::SystemCall(call) => {
+                    tokens.push((&call.name_span, MACRO, 0));
+                    push_arguments(&call.arguments, tokens);
+                }
+                CstNode::Attribute(attr) => {
+                    tokens.push((&attr.keyword_span, DECORATOR, 0));
+                    if let Some(span) = &attr.condition_span {
+                        tokens.push((span, STRING, 0));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn visit_block<'a>(block: &'a CstBlock, tokens: &mut Vec<(&'a SpanInfo, u32, u32)>) {
+        if let Some(label) = &block.label {
+            tokens.push((&label.name_span, STRING, 0));
+        }
+        visit_nodes(&block.children, tokens);
+    }
+
+    let mut tokens = Vec::new();
+    visit_nodes(&cst.nodes, &mut tokens);
+    tokens.retain(|(span, _, _)| span.start_line == span.end_line && !span.is_empty());
+    tokens.sort_by_key(|(span, _, _)| span.start);
+
+    let utf16_len = |s: &str| s.encode_utf16().count() as u32;
+    let mut encoded = Vec::with_capacity(tokens.len());
+    let (mut prev_line, mut prev_start) = (0, 0);
+    for (span, token_type, modifiers) in tokens {
+        let (Some(line_text), Some(token_text)) = (
+            text.get(span.start - span.start_column..span.start),
+            text.get(span.start..span.end),
+        ) else {
+            continue;
+        };
+        let line = (span.start_line - 1) as u32;
+        let start = utf16_len(line_text);
+        encoded.push(SemanticToken {
+            delta_line: line - prev_line,
+            delta_start: if line == prev_line {
+                start - prev_start
+            } else {
+                start
+            },
+            length: utf16_len(token_text),
+            token_type,
+            token_modifiers_bitset: modifiers,
+        });
+        (prev_line, prev_start) = (line, start);
+    }
+    encoded
+}
+
+/// 两个范围是否相交，相接也算（光标位于范围边界时请求的范围为空）
+pub fn overlaps(a: &Range, b: &Range) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+/// 将参数值整体替换为带双引号的字符串，用于把未加引号的值（如 `src=test.jpg`）
+/// 改为字符串；值中的 `\` 与 `"` 会被转义
+pub fn quote_value_edit(value: &CstValue) -> TextEdit {
+    let escaped = value.raw.replace('\\', "\\\\").replace('"', "\\\"");
+    TextEdit {
+        range: span_to_range(&value.span),
+        new_text: format!("\"{}\"", escaped),
+    }
+}
+
 /// 检查位置是否在字符串内部
 /// 简单检查：统计光标前的引号数量
 pub fn is_inside_string(line_prefix: &str) -> bool {
@@ -201,11 +868,8 @@ pub fn find_command_at_position(
 
     // 提取命令名
     let separator_pos = after_trigger.find(|c: char| c.is_whitespace() || c == '(');
-    let cmd_end = match separator_pos {
-        Some(pos) => pos,
-        // 光标仍在命令名上（没有分隔符），应由命令名补全处理
-        None => return None,
-    };
+    // 光标仍在命令名上（没有分隔符），应由命令名补全处理
+    let cmd_end = separator_pos?;
 
     if cmd_end == 0 {
         // 命令名为空，不补全参数
@@ -338,6 +1002,36 @@ mod tests {
         assert!(!is_inside_string(r#"@command arg="test \"" "#));
     }
 
+    #[test]
+    fn test_find_template_part_at() {
+        let source = "::main {\n    `Hello, ${user.name}!`\n}\n";
+        let cst = sixu::cst::parse_tolerant("test", source);
+
+        let offset = source.find("user").unwrap();
+        let part = find_template_part_at(&cst, offset + 2).unwrap();
+        assert!(matches!(part, CstTemplatePart::Value { .. }));
+
+        let span = part.variable_span().unwrap();
+        assert_eq!(&source[span.start..span.end], "user.name");
+        assert_eq!((span.start_line, span.start_column), (2, 14));
+
+        // 文本部分没有变量
+        let part = find_template_part_at(&cst, source.find("Hello").unwrap()).unwrap();
+        assert!(part.variable_span().is_none());
+
+        // 模板之外
+        assert!(find_template_part_at(&cst, 0).is_none());
+
+        // 条件片段：分支内返回分支中的片段，条件上返回条件片段本身
+        let source = "::main {\n    `${?lucky}yes${/}`\n}\n";
+        let cst = sixu::cst::parse_tolerant("test", source);
+        let part = find_template_part_at(&cst, source.find("yes").unwrap()).unwrap();
+        assert!(matches!(part, CstTemplatePart::Text { .. }));
+        let part = find_template_part_at(&cst, source.find("lucky").unwrap()).unwrap();
+        let span = part.variable_span().unwrap();
+        assert_eq!(&source[span.start..span.end], "lucky");
+    }
+
     #[test]
     fn test_find_command_at_position() {
         // 基本命令
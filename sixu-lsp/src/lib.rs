@@ -1,34 +1,295 @@
 use dashmap::DashMap;
-use nom::Finish;
 use ropey::Rope;
+use sixu::ValueParsers;
 use sixu::cst::formatter::CstFormatter;
-use sixu::cst::node::CstValueKind;
+use sixu::cst::node::{CstRoot, CstValueKind};
 use sixu::cst::parser::parse_tolerant;
 use sixu::parser;
+use sixu::runtime::{ONCE_ATTRIBUTE, PLATFORM_ATTRIBUTE};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
 use tokio::sync::RwLock;
 use tower_lsp_server::jsonrpc::Result;
 use tower_lsp_server::ls_types::*;
 use tower_lsp_server::{Client, LanguageServer, LspService};
 
-pub mod schema;
-pub use schema::*;
+pub use sixu::schema::{self, *};
+pub mod config;
+pub use config::*;
+pub mod completion_context;
+// 与 LSP 的 `CompletionContext` 同名，不整体导出
+use completion_context::{
+    CompletionContext as CursorContext, analyze_completion_context, analyze_signature_context,
+};
 pub mod cst_helper;
 pub use cst_helper::*;
 
+/// `workspace/executeCommand` 命令：在所有打开的文档和工作区文件中重命名命令，
+/// 参数为 `[旧命令名, 新命令名]`
+pub const RENAME_COMMAND: &str = "sixu.renameCommand";
+
+/// 工作区符号搜索使用的段落信息
+#[derive(Debug, Clone)]
+struct ParagraphSymbol {
+    name: String,
+    range: Range,
+    is_macro: bool,
+}
+
+impl ParagraphSymbol {
+    fn parse(text: &str) -> Vec<Self> {
+        let cst = parse_tolerant("workspace_symbol", text);
+        extract_paragraphs(&cst)
+            .into_iter()
+            .map(|p| ParagraphSymbol {
+                name: p.name.clone(),
+                range: span_to_range(&p.name_span),
+                is_macro: p.macro_keyword.is_some(),
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug)]
 pub struct Backend {
     client: Client,
     schema: Arc<RwLock<Option<CommandSchema>>>,
+    options: Arc<RwLock<ServerOptions>>,
     documents: DashMap<Uri, Rope>,
+    workspace_root: Arc<RwLock<Option<PathBuf>>>,
+    /// 加载 schema 的文件路径，文件变化时据此重新加载
+    schema_path: Arc<RwLock<Option<PathBuf>>>,
+    /// 客户端是否支持 `workspace/applyEdit`
+    apply_edit: AtomicBool,
+    /// 客户端是否支持动态注册 `workspace/didChangeWatchedFiles`
+    watch_files: AtomicBool,
+    /// 工作区符号搜索时未打开文件的段落，按修改时间失效
+    symbol_cache: DashMap<PathBuf, (SystemTime, Vec<ParagraphSymbol>)>,
+    /// 跳转调用指向的其他故事的解析结果，用于校验参数类型。打开的文档在变化、保存
+    /// 或关闭时失效，未打开的文件按修改时间失效
+    story_cache: DashMap<Uri, (Option<SystemTime>, Arc<CstRoot>)>,
+    /// 按 schema 属性的 `format` 校验参数值的解析器
+    value_parsers: Arc<ValueParsers>,
 }
 
 impl Backend {
     pub fn new(client: Client) -> Self {
+        Self::with_value_parsers(client, ValueParsers::new())
+    }
+
+    /// 使用嵌入方注册的值解析器创建 Backend
+    pub fn with_value_parsers(client: Client, value_parsers: ValueParsers) -> Self {
         Backend {
             client,
             schema: Arc::new(RwLock::new(None)),
+            options: Arc::new(RwLock::new(ServerOptions::default())),
             documents: DashMap::new(),
+            workspace_root: Arc::new(RwLock::new(None)),
+            schema_path: Arc::new(RwLock::new(None)),
+            apply_edit: AtomicBool::new(false),
+            watch_files: AtomicBool::new(false),
+            symbol_cache: DashMap::new(),
+            story_cache: DashMap::new(),
+            value_parsers: Arc::new(value_parsers),
+        }
+    }
+
+    /// 预置命令 schema，如由 [`SchemaBuilder`] 构造的 schema。
+    /// 工作区中的 `commands.schema.json` 优先
+    pub fn with_schema(self, schema: CommandSchema) -> Self {
+        Backend {
+            schema: Arc::new(RwLock::new(Some(schema))),
+            ..self
+        }
+    }
+
+    /// 读取文档内容：编辑器中已打开的文档（可能未保存）优先，否则从磁盘读取
+    async fn get_text(&self, uri: &Uri) -> Option<String> {
+        if let Some(rope) = self.documents.get(uri) {
+            return Some(rope.to_string());
+        }
+        tokio::fs::read_to_string(uri.to_file_path()?).await.ok()
+    }
+
+    /// 读取并解析跳转目标故事。打开的文档缓存到其变化、保存或关闭为止，
+    /// 未打开的文件缓存到其修改时间变化为止
+    async fn target_story(&self, uri: &Uri) -> Option<Arc<CstRoot>> {
+        let modified = if self.documents.contains_key(uri) {
+            None
+        } else {
+            let path = uri.to_file_path()?;
+            tokio::fs::metadata(&path)
+                .await
+                .and_then(|m| m.modified())
+                .ok()
+        };
+        if let Some(cached) = self.story_cache.get(uri)
+            && cached.0 == modified
+        {
+            return Some(cached.1.clone());
+        }
+        let text = self.get_text(uri).await?;
+        let cst = Arc::new(parse_tolerant("validate_target", &text));
+        self.story_cache
+            .insert(uri.clone(), (modified, cst.clone()));
+        Some(cst)
+    }
+
+    /// 收集所有打开的文档以及工作区中未打开的 .sixu 文件的内容
+    async fn story_sources(&self) -> Vec<(Uri, String)> {
+        let open_documents: Vec<(Uri, String)> = self
+            .documents
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().to_string()))
+            .collect();
+        let root = self.workspace_root.read().await.clone();
+        let files = root.map(|root| story_files(&root)).unwrap_or_default();
+
+        let mut sources = open_documents.clone();
+        for path in files {
+            let Some(uri) = Uri::from_file_path(&path) else {
+                continue;
+            };
+            if open_documents.iter().any(|(open, _)| *open == uri) {
+                continue;
+            }
+            if let Ok(text) = tokio::fs::read_to_string(&path).await {
+                sources.push((uri, text));
+            }
+        }
+        sources
+    }
+
+    /// 收集所有打开的文档以及工作区中未打开的 .sixu 文件中的段落。
+    /// 未打开的文件按修改时间缓存解析结果，未变化的文件不再重新读取
+    async fn workspace_paragraphs(&self) -> Vec<(Uri, Vec<ParagraphSymbol>)> {
+        let open_documents: Vec<(Uri, String)> = self
+            .documents
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().to_string()))
+            .collect();
+        let root = self.workspace_root.read().await.clone();
+        let files = root.map(|root| story_files(&root)).unwrap_or_default();
+
+        let mut paragraphs: Vec<_> = open_documents
+            .iter()
+            .map(|(uri, text)| (uri.clone(), ParagraphSymbol::parse(text)))
+            .collect();
+        for path in files {
+            let Some(uri) = Uri::from_file_path(&path) else {
+                continue;
+            };
+            if open_documents.iter().any(|(open, _)| *open == uri) {
+                continue;
+            }
+            let Ok(modified) = tokio::fs::metadata(&path).await.and_then(|m| m.modified()) else {
+                continue;
+            };
+            if let Some(cached) = self.symbol_cache.get(&path)
+                && cached.0 == modified
+            {
+                paragraphs.push((uri, cached.1.clone()));
+                continue;
+            }
+            if let Ok(text) = tokio::fs::read_to_string(&path).await {
+                let symbols = ParagraphSymbol::parse(&text);
+                self.symbol_cache.insert(path, (modified, symbols.clone()));
+                paragraphs.push((uri, symbols));
+            }
+        }
+        paragraphs
+    }
+
+    /// 生成把 `@old` 重命名为 `@new` 的 WorkspaceEdit，覆盖所有打开的文档
+    /// 以及工作区中未打开的 .sixu 文件
+    async fn rename_command_edit(&self, old: &str, new: &str) -> WorkspaceEdit {
+        let mut changes = HashMap::new();
+        for (uri, text) in self.story_sources().await {
+            let cst = parse_tolerant("rename", &text);
+            let edits = command_rename_edits(&cst, old, new);
+            if !edits.is_empty() {
+                changes.insert(uri, edits);
+            }
+        }
+
+        WorkspaceEdit {
+            changes: Some(changes),
+            ..Default::default()
+        }
+    }
+
+    /// schema 中为命令声明的实现位置（`definitionPath`/`definitionLine`）。
+    /// 相对路径相对于 schema 文件所在目录，预置的 schema 相对于工作区根目录
+    async fn command_definition(&self, command: &str) -> Option<Location> {
+        let (path, line) = {
+            let schema_guard = self.schema.read().await;
+            let def = schema_guard
+                .as_ref()?
+                .commands
+                .iter()
+                .find(|c| c.get_command_name().as_deref() == Some(command))?;
+            (def.definition_path.clone()?, def.definition_line)
+        };
+
+        let mut path = PathBuf::from(path);
+        if path.is_relative() {
+            let schema_dir = self
+                .schema_path
+                .read()
+                .await
+                .as_ref()
+                .and_then(|schema_path| schema_path.parent().map(Path::to_path_buf));
+            let base = match schema_dir {
+                Some(dir) => dir,
+                None => self.workspace_root.read().await.clone()?,
+            };
+            path = base.join(path);
+        }
+
+        let position = Position::new(line.unwrap_or(1).saturating_sub(1), 0);
+        Some(Location {
+            uri: Uri::from_file_path(&path)?,
+            range: Range::new(position, position),
+        })
+    }
+
+    /// 重新加载 schema 并重新校验所有打开的文档。
+    /// 文件无法读取或解析失败时保留原有的 schema
+    async fn reload_schema(&self, path: &Path) {
+        let schema = match tokio::fs::read_to_string(path).await {
+            Ok(content) => {
+                serde_json::from_str::<CommandSchema>(&content).map_err(|e| e.to_string())
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        match schema {
+            Ok(schema) => {
+                *self.schema.write().await = Some(schema);
+                self.client
+                    .log_message(MessageType::INFO, "Schema reloaded")
+                    .await;
+            }
+            Err(e) => {
+                self.client
+                    .log_message(
+                        MessageType::ERROR,
+                        format!("Failed to reload schema, keeping the previous one: {}", e),
+                    )
+                    .await;
+                return;
+            }
+        }
+
+        let open_documents: Vec<(Uri, String)> = self
+            .documents
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().to_string()))
+            .collect();
+        for (uri, text) in open_documents {
+            self.validate(uri, text).await;
         }
     }
 
@@ -37,42 +298,61 @@ impl Backend {
         let mut diagnostics = Vec::new();
 
         // 1. Syntax Check
-        match parser::parse("check", &text).finish() {
-            Ok(_) => {}
-            Err(e) => {
-                if let Some((substring, kind)) = e.errors.first() {
-                    let offset = text.offset(substring);
-                    let (line, col) = offset_to_position(offset, &rope);
-
-                    let range = Range {
-                        start: Position {
-                            line: line as u32,
-                            character: col as u32,
-                        },
-                        end: Position {
-                            line: line as u32,
-                            character: (col + 1) as u32,
-                        },
-                    };
-
-                    diagnostics.push(Diagnostic {
-                        range,
-                        severity: Some(DiagnosticSeverity::ERROR),
-                        source: Some("sixu".to_string()),
-                        message: format!("Syntax error: {:?}", kind),
-                        ..Default::default()
-                    });
-                }
-            }
-        };
+        if let Err(e) = parser::parse_story("check", &text) {
+            let (line, col) = offset_to_position(e.offset.min(text.len()), &rope);
+
+            let range = Range {
+                start: Position {
+                    line: line as u32,
+                    character: col as u32,
+                },
+                end: Position {
+                    line: line as u32,
+                    character: (col + 1) as u32,
+                },
+            };
+
+            diagnostics.push(Diagnostic {
+                range,
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("sixu".to_string()),
+                message: format!("Syntax error: {}", e.message()),
+                ..Default::default()
+            });
+        }
 
         // 2. CST Error Check (解析失败但以 @ 或 # 开头的行)
         let cst = parse_tolerant("validate", &text);
         fn collect_errors(nodes: &[sixu::cst::node::CstNode], diagnostics: &mut Vec<Diagnostic>) {
             use sixu::cst::node::CstNode;
 
-            for node in nodes {
+            for (i, node) in nodes.iter().enumerate() {
                 match node {
+                    // 属性作用于其后的语句；段落只能带 platform 和 once 属性
+                    CstNode::Attribute(attr) => {
+                        let target = nodes[i + 1..]
+                            .iter()
+                            .find(|n| !matches!(n, CstNode::Trivia(_) | CstNode::Attribute(_)));
+                        let paragraph_attribute =
+                            [PLATFORM_ATTRIBUTE, ONCE_ATTRIBUTE].contains(&attr.keyword.as_str());
+                        let dangling = match target {
+                            None => true,
+                            Some(CstNode::Paragraph(_)) => !paragraph_attribute,
+                            Some(_) => false,
+                        };
+                        if dangling {
+                            diagnostics.push(Diagnostic {
+                                range: Range {
+                                    start: span_to_range(&attr.open_token).start,
+                                    end: span_to_range(&attr.close_token).end,
+                                },
+                                severity: Some(DiagnosticSeverity::ERROR),
+                                source: Some("sixu-syntax".to_string()),
+                                message: "Attribute not attached to a statement".to_string(),
+                                ..Default::default()
+                            });
+                        }
+                    }
                     CstNode::Error {
                         content: _,
                         span,
@@ -98,12 +378,158 @@ impl Backend {
         }
         collect_errors(&cst.nodes, &mut diagnostics);
 
-        // 3. Schema Check
+        // 3. 嵌套过深的代码块
+        if let Some(max_depth) = self.options.read().await.max_block_depth {
+            for (depth, open_brace) in deep_blocks(&cst, max_depth) {
+                diagnostics.push(Diagnostic {
+                    range: span_to_range(open_brace),
+                    severity: Some(DiagnosticSeverity::HINT),
+                    source: Some("sixu-lint".to_string()),
+                    message: format!(
+                        "Block nested {} levels deep, more than the maximum of {}",
+                        depth, max_depth
+                    ),
+                    ..Default::default()
+                });
+            }
+        }
+
+        // 4. 缩进混用制表符与空格（直接扫描原文，CST 的 trivia 会抹掉差异）
+        if self.options.read().await.check_mixed_indentation
+            && let Some((line, expected, width)) = mixed_indentation(&rope)
+        {
+            diagnostics.push(Diagnostic {
+                range: Range {
+                    start: Position {
+                        line: line as u32,
+                        character: 0,
+                    },
+                    end: Position {
+                        line: line as u32,
+                        character: width as u32,
+                    },
+                },
+                severity: Some(DiagnosticSeverity::WARNING),
+                source: Some("sixu-lint".to_string()),
+                message: format!(
+                    "Indentation mixes tabs and spaces, this file is indented with {}",
+                    expected
+                ),
+                ..Default::default()
+            });
+        }
+
+        // 5. 参数写法不统一
+        if self.options.read().await.check_call_syntax
+            && let Some((parenthesized, spans)) = minority_call_syntax(&cst)
+        {
+            let (majority, minority) = if parenthesized {
+                ("parenthesized", "space-separated")
+            } else {
+                ("space-separated", "parenthesized")
+            };
+            for span in spans {
+                diagnostics.push(Diagnostic {
+                    range: span_to_range(span),
+                    severity: Some(DiagnosticSeverity::HINT),
+                    source: Some("sixu-lint".to_string()),
+                    message: format!(
+                        "Call uses {} arguments, most calls in this file are {}",
+                        minority, majority
+                    ),
+                    ..Default::default()
+                });
+            }
+        }
+
+        // 6. 跳转调用的参数类型与目标段落的类型标注
+        for call in extract_system_calls(&cst) {
+            if !["goto", "call", "replace"].contains(&call.command.as_str()) {
+                continue;
+            }
+            let Some(paragraph_name) = get_systemcall_argument_value(call, "paragraph") else {
+                continue;
+            };
+
+            let story = get_systemcall_argument_value(call, "story");
+            let other_story;
+            let target = match &story {
+                Some(story_name) => {
+                    let Some(target_uri) = uri.to_file_path().and_then(|path| {
+                        Uri::from_file_path(path.parent()?.join(format!("{}.sixu", story_name)))
+                    }) else {
+                        continue;
+                    };
+                    let Some(cst) = self.target_story(&target_uri).await else {
+                        continue;
+                    };
+                    other_story = cst;
+                    &*other_story
+                }
+                None => &cst,
+            };
+
+            let paragraphs = extract_paragraphs(target);
+            let Some(paragraph) = paragraphs.iter().find(|p| p.name == paragraph_name) else {
+                // 只检查同文件的跳转；变量值无法静态确定目标
+                let value = call
+                    .arguments
+                    .iter()
+                    .find(|arg| arg.name == "paragraph")
+                    .and_then(|arg| arg.value.as_ref())
+                    .filter(|value| matches!(value.kind, CstValueKind::String { .. }));
+                if story.is_none()
+                    && let Some(value) = value
+                {
+                    diagnostics.push(Diagnostic {
+                        range: span_to_range(&value.span),
+                        severity: Some(DiagnosticSeverity::WARNING),
+                        source: Some("sixu-params".to_string()),
+                        message: format!("Unknown paragraph: {}", paragraph_name),
+                        ..Default::default()
+                    });
+                }
+                continue;
+            };
+            for (arg, type_name) in parameter_type_mismatches(call, paragraph) {
+                diagnostics.push(Diagnostic {
+                    range: span_to_range(&arg.span),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    source: Some("sixu-params".to_string()),
+                    message: format!(
+                        "Type mismatch for parameter {}. Expected: {}",
+                        arg.name, type_name
+                    ),
+                    ..Default::default()
+                });
+            }
+        }
+
+        // 7. 从 entry 无法到达的段落
+        if self.options.read().await.check_dead_code {
+            for para in unreachable_paragraphs(&cst) {
+                diagnostics.push(Diagnostic {
+                    range: span_to_range(&para.name_span),
+                    severity: Some(DiagnosticSeverity::HINT),
+                    source: Some("sixu-lint".to_string()),
+                    message: "Paragraph never referenced".to_string(),
+                    tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                    ..Default::default()
+                });
+            }
+        }
+
+        // 8. Schema Check
         let schema_guard = self.schema.read().await;
         if let Some(schema) = &*schema_guard {
             let cst = parse_tolerant("validate", &text);
             let commands = extract_commands(&cst);
             for cmd in &commands {
+                // 宏调用 @@name 不是命令，不做 schema 检查
+                if cmd.macro_name().is_some() {
+                    continue;
+                }
+
                 // Find command definition
                 let def = schema
                     .commands
@@ -157,6 +583,9 @@ impl Backend {
                                         CstValueKind::Array => {
                                             expected_types.contains(&"array".to_string())
                                         }
+                                        CstValueKind::Object { .. } => {
+                                            expected_types.contains(&"object".to_string())
+                                        }
                                     }
                                 } else {
                                     true // No value means boolean flag
@@ -175,6 +604,24 @@ impl Backend {
                                     });
                                 }
                             }
+
+                            // 用注册的解析器校验声明了 format 的字面量
+                            if let (Some(format), Some(value)) = (&prop.format, &arg.value)
+                                && let Ok(sixu::format::RValue::Literal(literal)) = value.to_ast()
+                                && let Some(Err(reason)) =
+                                    self.value_parsers.parse(format, &literal)
+                            {
+                                diagnostics.push(Diagnostic {
+                                    range: span_to_range(&value.span),
+                                    severity: Some(DiagnosticSeverity::WARNING),
+                                    source: Some("sixu-schema".to_string()),
+                                    message: format!(
+                                        "Invalid {} value for {}: {}",
+                                        format, arg.name, reason
+                                    ),
+                                    ..Default::default()
+                                });
+                            }
                         } else {
                             // Unknown parameter
                             diagnostics.push(Diagnostic {
@@ -199,6 +646,8 @@ impl Backend {
             }
         }
 
+        let max_diagnostics = self.options.read().await.max_diagnostics;
+        let diagnostics = limit_diagnostics(diagnostics, max_diagnostics);
         self.client
             .publish_diagnostics(uri, diagnostics, None)
             .await;
@@ -207,6 +656,36 @@ impl Backend {
 
 impl LanguageServer for Backend {
     async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        if let Some(options) = params.initialization_options {
+            match serde_json::from_value::<ServerOptions>(options) {
+                Ok(options) => *self.options.write().await = options,
+                Err(e) => {
+                    self.client
+                        .log_message(
+                            MessageType::WARNING,
+                            format!("Invalid initialization options: {}", e),
+                        )
+                        .await;
+                }
+            }
+        }
+
+        let apply_edit = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.apply_edit)
+            .unwrap_or(false);
+        self.apply_edit.store(apply_edit, Ordering::Relaxed);
+        let watch_files = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.did_change_watched_files.as_ref())
+            .and_then(|watched| watched.dynamic_registration)
+            .unwrap_or(false);
+        self.watch_files.store(watch_files, Ordering::Relaxed);
+
         if let Some(workspace_folders) = params.workspace_folders {
             if workspace_folders.len() > 1 {
                 self.client
@@ -219,6 +698,7 @@ impl LanguageServer for Backend {
 
             let root_uri = &workspace_folders[0].uri;
             if let Some(path) = root_uri.to_file_path() {
+                *self.workspace_root.write().await = Some(path.to_path_buf());
                 let mut schema_path = path.join("commands.schema.json");
                 if !schema_path.exists() {
                     let sample_path = path.join("sample-project").join("commands.schema.json");
@@ -227,6 +707,7 @@ impl LanguageServer for Backend {
                     }
                 }
 
+                *self.schema_path.write().await = Some(schema_path.clone());
                 if schema_path.exists() {
                     if let Ok(content) = tokio::fs::read_to_string(schema_path).await {
                         if let Ok(schema) = serde_json::from_str::<CommandSchema>(&content) {
@@ -250,8 +731,13 @@ impl LanguageServer for Backend {
 
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::FULL,
+                text_document_sync: Some(TextDocumentSyncCapability::Options(
+                    TextDocumentSyncOptions {
+                        open_close: Some(true),
+                        change: Some(TextDocumentSyncKind::INCREMENTAL),
+                        save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                        ..Default::default()
+                    },
                 )),
                 completion_provider: Some(CompletionOptions {
                     resolve_provider: Some(false),
@@ -269,6 +755,40 @@ impl LanguageServer for Backend {
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 definition_provider: Some(OneOf::Left(true)),
                 document_symbol_provider: Some(OneOf::Left(true)),
+                folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                signature_help_provider: Some(SignatureHelpOptions {
+                    trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                    ..Default::default()
+                }),
+                references_provider: Some(OneOf::Left(true)),
+                workspace_symbol_provider: Some(OneOf::Left(true)),
+                inlay_hint_provider: Some(OneOf::Left(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+                        ..Default::default()
+                    },
+                )),
+                semantic_tokens_provider: Some(
+                    SemanticTokensServerCapabilities::SemanticTokensOptions(
+                        SemanticTokensOptions {
+                            legend: SemanticTokensLegend {
+                                token_types: SEMANTIC_TOKEN_TYPES.to_vec(),
+                                token_modifiers: SEMANTIC_TOKEN_MODIFIERS.to_vec(),
+                            },
+                            full: Some(SemanticTokensFullOptions::Bool(true)),
+                            ..Default::default()
+                        },
+                    ),
+                ),
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: Default::default(),
+                })),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![RENAME_COMMAND.to_string()],
+                    ..Default::default()
+                }),
                 ..Default::default()
             },
             ..Default::default()
@@ -279,6 +799,43 @@ impl LanguageServer for Backend {
         self.client
             .log_message(MessageType::INFO, "sixu-lsp initialized!")
             .await;
+
+        if self.watch_files.load(Ordering::Relaxed) {
+            let options = DidChangeWatchedFilesRegistrationOptions {
+                watchers: vec![FileSystemWatcher {
+                    glob_pattern: GlobPattern::String("**/commands.schema.json".to_string()),
+                    kind: None,
+                }],
+            };
+            let registration = Registration {
+                id: "sixu-schema-watcher".to_string(),
+                method: "workspace/didChangeWatchedFiles".to_string(),
+                register_options: serde_json::to_value(options).ok(),
+            };
+            if let Err(e) = self.client.register_capability(vec![registration]).await {
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("Failed to watch commands.schema.json: {}", e),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let Some(schema_path) = self.schema_path.read().await.clone() else {
+            return;
+        };
+        let changed = params.changes.iter().any(|event| {
+            event
+                .uri
+                .to_file_path()
+                .is_some_and(|path| *path == schema_path)
+        });
+        if changed {
+            self.reload_schema(&schema_path).await;
+        }
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -295,13 +852,29 @@ impl LanguageServer for Backend {
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
-        if let Some(change) = params.content_changes.into_iter().next() {
-            self.documents.insert(
-                params.text_document.uri.clone(),
-                Rope::from_str(&change.text),
-            );
-            self.validate(params.text_document.uri, change.text).await;
-        }
+        let uri = params.text_document.uri;
+        let text = {
+            let Some(mut rope) = self.documents.get_mut(&uri) else {
+                return;
+            };
+            for change in &params.content_changes {
+                apply_content_change(&mut rope, change);
+            }
+            rope.to_string()
+        };
+        self.story_cache.remove(&uri);
+        self.validate(uri, text).await;
+    }
+
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        self.story_cache.remove(&params.text_document.uri);
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        // 关闭后以磁盘上的内容为准
+        let uri = params.text_document.uri;
+        self.documents.remove(&uri);
+        self.story_cache.remove(&uri);
     }
 
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
@@ -339,128 +912,141 @@ impl LanguageServer for Backend {
         };
         let line_prefix = &line[..slice_end];
 
-        // 检查是否在等号后面（正在输入值）
-        let trimmed = line_prefix.trim_end();
-        if trimmed.ends_with('=') {
-            return Ok(None);
-        }
+        match analyze_completion_context(line_prefix) {
+            CursorContext::ArgumentName {
+                command: cmd_name,
+                system_call: is_system_call,
+                existing: existing_args,
+            } => {
+                if is_system_call {
+                    // 系统调用参数补全
+                    if ["goto", "call", "replace"].contains(&cmd_name.as_str()) {
+                        let mut items = Vec::new();
+
+                        // Named args（排除已有参数）
+                        for arg in ["paragraph", "story"] {
+                            if !existing_args.contains(&arg.to_string()) {
+                                items.push(CompletionItem {
+                                    label: arg.to_string(),
+                                    kind: Some(CompletionItemKind::FIELD),
+                                    insert_text: Some(format!("{}=", arg)),
+                                    ..Default::default()
+                                });
+                            }
+                        }
 
-        // 尝试找到当前位置的命令
-        if let Some((cmd_name, _is_paren, existing_args)) = find_command_at_position(&line, col) {
-            // 判断是命令还是系统调用
-            let is_system_call = line_prefix
-                .rfind(&format!("#{}", cmd_name))
-                .map(|hash_pos| {
-                    let at_pos = line_prefix.rfind(&format!("@{}", cmd_name));
-                    at_pos.map(|ap| hash_pos > ap).unwrap_or(true)
-                })
-                .unwrap_or(false);
-
-            if is_system_call {
-                // 系统调用参数补全
-                if ["goto", "call", "replace"].contains(&cmd_name.as_str()) {
-                    let mut items = Vec::new();
-
-                    // Named args（排除已有参数）
-                    for arg in ["paragraph", "story"] {
-                        if !existing_args.contains(&arg.to_string()) {
-                            items.push(CompletionItem {
-                                label: arg.to_string(),
-                                kind: Some(CompletionItemKind::FIELD),
-                                insert_text: Some(format!("{}=", arg)),
-                                ..Default::default()
-                            });
+                        let cst = parse_tolerant("completion", &rope.to_string());
+
+                        // 目标段落的参数（可选参数以默认值作为占位符）
+                        let call = extract_system_calls(&cst)
+                            .into_iter()
+                            .find(|c| c.hash_token.start_line == line_idx + 1);
+                        if let Some(call) = call
+                            && let Some(paragraph_name) =
+                                get_systemcall_argument_value(call, "paragraph")
+                            && get_systemcall_argument_value(call, "story").is_none()
+                            && let Some(paragraph) = extract_paragraphs(&cst)
+                                .into_iter()
+                                .find(|p| p.name == paragraph_name)
+                        {
+                            for param in &paragraph.parameters {
+                                if existing_args.contains(&param.name) {
+                                    continue;
+                                }
+                                items.push(CompletionItem {
+                                    label: param.name.clone(),
+                                    kind: Some(CompletionItemKind::VARIABLE),
+                                    detail: param.type_.clone(),
+                                    insert_text: Some(parameter_snippet(param)),
+                                    insert_text_format: Some(InsertTextFormat::SNIPPET),
+                                    ..Default::default()
+                                });
+                            }
                         }
-                    }
 
-                    // Paragraph names from current file
-                    let cst = parse_tolerant("completion", &rope.to_string());
-                    let paragraphs = extract_paragraphs(&cst);
-                    for p in paragraphs {
-                        if !existing_args.contains(&"paragraph".to_string()) {
-                            items.push(CompletionItem {
-                                label: p.name.clone(),
-                                kind: Some(CompletionItemKind::REFERENCE),
-                                insert_text: Some(format!("paragraph=\"{}\"", p.name)),
-                                detail: Some("Paragraph".to_string()),
-                                ..Default::default()
-                            });
+                        // Paragraph names from current file
+                        let paragraphs = extract_paragraphs(&cst);
+                        for p in paragraphs {
+                            if !existing_args.contains(&"paragraph".to_string()) {
+                                items.push(CompletionItem {
+                                    label: p.name.clone(),
+                                    kind: Some(CompletionItemKind::REFERENCE),
+                                    insert_text: Some(format!("paragraph=\"{}\"", p.name)),
+                                    detail: Some("Paragraph".to_string()),
+                                    ..Default::default()
+                                });
+                            }
                         }
-                    }
 
-                    return Ok(Some(CompletionResponse::Array(items)));
-                }
-            } else {
-                // 命令参数补全
-                let schema_guard = self.schema.read().await;
-                let schema = match &*schema_guard {
-                    Some(s) => s,
-                    None => return Ok(None),
-                };
+                        return Ok(Some(CompletionResponse::Array(items)));
+                    }
+                } else {
+                    // 命令参数补全
+                    let schema_guard = self.schema.read().await;
+                    let schema = match &*schema_guard {
+                        Some(s) => s,
+                        None => return Ok(None),
+                    };
 
-                if let Some(cmd_def) = schema
-                    .commands
-                    .iter()
-                    .find(|c| c.get_command_name().as_deref() == Some(&cmd_name))
-                {
-                    let items: Vec<CompletionItem> = cmd_def
-                        .properties
+                    if let Some(cmd_def) = schema
+                        .commands
                         .iter()
-                        .filter(|(key, _)| *key != "command")
-                        .filter(|(key, _)| !existing_args.contains(*key)) // 排除已有参数
-                        .map(|(key, prop)| {
-                            let is_string = prop
-                                .type_
-                                .as_ref()
-                                .map(|t| match t {
-                                    StringOrArray::String(s) => s == "string",
-                                    StringOrArray::Array(arr) => {
-                                        arr.contains(&"string".to_string())
-                                    }
-                                })
-                                .unwrap_or(false);
-
-                            let is_pure_boolean = prop
-                                .type_
-                                .as_ref()
-                                .map(|t| match t {
-                                    StringOrArray::String(s) => s == "boolean",
-                                    StringOrArray::Array(_) => false,
-                                })
-                                .unwrap_or(false);
-
-                            let insert_text = if let Some(default) = &prop.default {
-                                format!("{}={}", key, default)
-                            } else if is_string {
-                                format!("{}=\"$1\"", key)
-                            } else if is_pure_boolean {
-                                key.to_string()
-                            } else {
-                                format!("{}=", key)
-                            };
-
-                            CompletionItem {
-                                label: key.clone(),
-                                kind: Some(CompletionItemKind::FIELD),
-                                detail: prop.description.clone(),
-                                insert_text: Some(insert_text),
-                                insert_text_format: Some(InsertTextFormat::SNIPPET),
-                                ..Default::default()
-                            }
-                        })
-                        .collect();
-                    return Ok(Some(CompletionResponse::Array(items)));
-                }
-            }
+                        .find(|c| c.get_command_name().as_deref() == Some(&cmd_name))
+                    {
+                        let items: Vec<CompletionItem> = cmd_def
+                            .properties
+                            .iter()
+                            .filter(|(key, _)| *key != "command")
+                            .filter(|(key, _)| !existing_args.contains(*key)) // 排除已有参数
+                            .map(|(key, prop)| {
+                                let is_string = prop
+                                    .type_
+                                    .as_ref()
+                                    .map(|t| match t {
+                                        StringOrArray::String(s) => s == "string",
+                                        StringOrArray::Array(arr) => {
+                                            arr.contains(&"string".to_string())
+                                        }
+                                    })
+                                    .unwrap_or(false);
+
+                                let is_pure_boolean = prop
+                                    .type_
+                                    .as_ref()
+                                    .map(|t| match t {
+                                        StringOrArray::String(s) => s == "boolean",
+                                        StringOrArray::Array(_) => false,
+                                    })
+                                    .unwrap_or(false);
+
+                                let insert_text = if let Some(default) = &prop.default {
+                                    format!("{}={}", key, default)
+                                } else if is_string {
+                                    format!("{}=\"$1\"", key)
+                                } else if is_pure_boolean {
+                                    key.to_string()
+                                } else {
+                                    format!("{}=", key)
+                                };
 
-            // 找到了命令但没有 schema，返回空
-            return Ok(None);
-        }
+                                CompletionItem {
+                                    label: key.clone(),
+                                    kind: Some(CompletionItemKind::FIELD),
+                                    detail: prop.description.clone(),
+                                    insert_text: Some(insert_text),
+                                    insert_text_format: Some(InsertTextFormat::SNIPPET),
+                                    ..Default::default()
+                                }
+                            })
+                            .collect();
+                        return Ok(Some(CompletionResponse::Array(items)));
+                    }
+                }
 
-        // 检查是否在输入命令名（@ 或 # 后面没有空格/括号）
-        if let Some(at_idx) = line_prefix.rfind('@') {
-            let after_at = &line_prefix[at_idx + 1..];
-            if !after_at.contains(|c: char| c.is_whitespace() || c == '(') {
+                // 找到了命令但没有 schema，返回空
+                Ok(None)
+            }
+            CursorContext::CommandName => {
                 // Command Completion
                 let schema_guard = self.schema.read().await;
                 let schema = match &*schema_guard {
@@ -486,14 +1072,12 @@ impl LanguageServer for Backend {
                         })
                     })
                     .collect();
-                return Ok(Some(CompletionResponse::Array(items)));
+                Ok(Some(CompletionResponse::Array(items)))
             }
-        } else if let Some(hash_idx) = line_prefix.rfind('#') {
-            let after_hash = &line_prefix[hash_idx + 1..];
-            if !after_hash.contains(|c: char| c.is_whitespace() || c == '(') {
+            CursorContext::SystemCallName => {
                 // System Call Name Completion
                 let sys_calls = vec![
-                    "call", "goto", "replace", "leave", "break", "continue", "finish",
+                    "call", "goto", "replace", "leave", "return", "break", "continue", "finish",
                 ];
                 let items: Vec<CompletionItem> = sys_calls
                     .into_iter()
@@ -509,11 +1093,13 @@ impl LanguageServer for Backend {
                         ..Default::default()
                     })
                     .collect();
-                return Ok(Some(CompletionResponse::Array(items)));
+                Ok(Some(CompletionResponse::Array(items)))
             }
+            // 参数值、模板变量以及字符串和注释中暂不补全
+            CursorContext::ArgumentValue { .. }
+            | CursorContext::VariableInTemplate
+            | CursorContext::None => Ok(None),
         }
-
-        Ok(None)
     }
 
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
@@ -533,27 +1119,38 @@ impl LanguageServer for Backend {
             let cmd_range = span_to_range(&cmd.span);
             if contains(&cmd_range, &position) {
                 let schema_guard = self.schema.read().await;
-                let schema = match &*schema_guard {
-                    Some(s) => s,
-                    None => return Ok(None),
-                };
-
-                if let Some(def) = schema
-                    .commands
-                    .iter()
-                    .find(|c| c.get_command_name().as_deref() == Some(&cmd.command))
-                {
-                    let name_range = span_to_range(&cmd.name_span);
-                    if contains(&name_range, &position) {
-                        return Ok(Some(Hover {
-                            contents: HoverContents::Markup(MarkupContent {
-                                kind: MarkupKind::Markdown,
-                                value: def.description.clone().unwrap_or_default(),
-                            }),
-                            range: Some(name_range),
-                        }));
+                let def = schema_guard.as_ref().and_then(|schema| {
+                    schema
+                        .commands
+                        .iter()
+                        .find(|c| c.get_command_name().as_deref() == Some(&cmd.command))
+                });
+
+                let name_range = span_to_range(&cmd.name_span);
+                if contains(&name_range, &position) {
+                    // schema 描述在前，作者注释附在后面
+                    let mut sections = Vec::new();
+                    if let Some(description) = def.and_then(|d| d.description.clone()) {
+                        sections.push(description);
+                    }
+                    let comments = command_comments(&cst, cmd);
+                    if !comments.is_empty() {
+                        sections.push(comments.join("\n\n"));
+                    }
+                    if sections.is_empty() {
+                        return Ok(None);
                     }
 
+                    return Ok(Some(Hover {
+                        contents: HoverContents::Markup(MarkupContent {
+                            kind: MarkupKind::Markdown,
+                            value: sections.join("\n\n---\n\n"),
+                        }),
+                        range: Some(name_range),
+                    }));
+                }
+
+                if let Some(def) = def {
                     for arg in &cmd.arguments {
                         let arg_range = span_to_range(&arg.span);
                         if contains(&arg_range, &position)
@@ -575,6 +1172,66 @@ impl LanguageServer for Backend {
         Ok(None)
     }
 
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        let line_prefix = {
+            let Some(rope) = self.documents.get(&uri) else {
+                return Ok(None);
+            };
+            let line_idx = position.line as usize;
+            if line_idx >= rope.len_lines() {
+                return Ok(None);
+            }
+            let col = position_to_char(&rope, position) - rope.line_to_char(line_idx);
+            rope.line(line_idx).slice(..col).to_string()
+        };
+        let Some((cmd_name, active)) = analyze_signature_context(&line_prefix) else {
+            return Ok(None);
+        };
+
+        let schema_guard = self.schema.read().await;
+        let Some(def) = schema_guard.as_ref().and_then(|schema| {
+            schema
+                .commands
+                .iter()
+                .find(|c| c.get_command_name().as_deref() == Some(&cmd_name))
+        }) else {
+            return Ok(None);
+        };
+
+        let required = def.required.as_deref().unwrap_or_default();
+        let labels: Vec<String> = def
+            .parameters()
+            .into_iter()
+            .map(|(name, prop)| {
+                let optional = if required.contains(name) { "" } else { "?" };
+                format!("{}{}: {}", name, optional, prop.type_label())
+            })
+            .collect();
+        let parameters = def
+            .parameters()
+            .into_iter()
+            .zip(&labels)
+            .map(|((_, prop), label)| ParameterInformation {
+                label: ParameterLabel::Simple(label.clone()),
+                documentation: prop.description.clone().map(Documentation::String),
+            })
+            .collect();
+
+        Ok(Some(SignatureHelp {
+            signatures: vec![SignatureInformation {
+                label: format!("@{}({})", cmd_name, labels.join(", ")),
+                documentation: def.description.clone().map(Documentation::String),
+                parameters: Some(parameters),
+                active_parameter: Some(active),
+            }],
+            active_signature: Some(0),
+            active_parameter: Some(active),
+        }))
+    }
+
     async fn goto_definition(
         &self,
         params: GotoDefinitionParams,
@@ -589,6 +1246,16 @@ impl LanguageServer for Backend {
         let text = rope.to_string();
 
         let cst = parse_tolerant("goto_def", &text);
+
+        // 命令名：跳转到 schema 中声明的命令实现
+        if let Some(cmd) = extract_commands(&cst)
+            .into_iter()
+            .find(|cmd| contains(&span_to_range(&cmd.name_span), &position))
+            && let Some(location) = self.command_definition(&cmd.command).await
+        {
+            return Ok(Some(GotoDefinitionResponse::Scalar(location)));
+        }
+
         let system_calls = extract_system_calls(&cst);
 
         for call in &system_calls {
@@ -642,7 +1309,7 @@ impl LanguageServer for Backend {
 
                 target_uri = Uri::from_file_path(&target_path).expect("Process file path failed");
 
-                if let Ok(content) = tokio::fs::read_to_string(target_path).await {
+                if let Some(content) = self.get_text(&target_uri).await {
                     target_text = content;
                 } else {
                     continue;
@@ -692,22 +1359,115 @@ impl LanguageServer for Backend {
         let mut symbols = Vec::new();
 
         for p in paragraphs {
+            let groups = group_symbols(&p.block);
             #[allow(deprecated)]
             symbols.push(DocumentSymbol {
                 name: p.name.clone(),
-                detail: None,
-                kind: SymbolKind::CLASS,
+                detail: p.macro_keyword.map(|_| "macro".to_string()),
+                kind: if p.macro_keyword.is_some() {
+                    SymbolKind::FUNCTION
+                } else {
+                    SymbolKind::CLASS
+                },
                 tags: None,
                 deprecated: None,
                 range: span_to_range(&p.span),
                 selection_range: span_to_range(&p.name_span),
-                children: None,
+                children: (!groups.is_empty()).then_some(groups),
             });
         }
 
         Ok(Some(DocumentSymbolResponse::Nested(symbols)))
     }
 
+    /// 在所有打开的文档和工作区文件中按名称搜索段落，忽略大小写的子串匹配
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<WorkspaceSymbolResponse>> {
+        let query = params.query.to_lowercase();
+        let mut symbols = Vec::new();
+        for (uri, paragraphs) in self.workspace_paragraphs().await {
+            let story = story_name(&uri);
+            for p in paragraphs {
+                if !p.name.to_lowercase().contains(&query) {
+                    continue;
+                }
+                #[allow(deprecated)]
+                symbols.push(SymbolInformation {
+                    name: p.name,
+                    kind: if p.is_macro {
+                        SymbolKind::FUNCTION
+                    } else {
+                        SymbolKind::CLASS
+                    },
+                    tags: None,
+                    deprecated: None,
+                    location: Location {
+                        uri: uri.clone(),
+                        range: p.range,
+                    },
+                    container_name: Some(story.clone()),
+                });
+            }
+        }
+        symbols.sort_by(|a, b| {
+            (a.location.uri.as_str(), a.location.range.start.line)
+                .cmp(&(b.location.uri.as_str(), b.location.range.start.line))
+        });
+        Ok(Some(WorkspaceSymbolResponse::Flat(symbols)))
+    }
+
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        let uri = params.text_document.uri;
+        let rope = match self.documents.get(&uri) {
+            Some(r) => r,
+            None => return Ok(None),
+        };
+        let text = rope.to_string();
+        drop(rope);
+
+        let cst = parse_tolerant("folding", &text);
+        let options = self.options.read().await;
+        let mut ranges: Vec<FoldingRange> = block_folds(&cst)
+            .into_iter()
+            .map(|(start_line, end_line)| FoldingRange {
+                start_line,
+                end_line,
+                ..Default::default()
+            })
+            .collect();
+        ranges.extend(region_folds(&cst, &options.region_markers).into_iter().map(
+            |(start_line, end_line)| FoldingRange {
+                start_line,
+                end_line,
+                kind: Some(FoldingRangeKind::Region),
+                ..Default::default()
+            },
+        ));
+        ranges.sort_by_key(|range| (range.start_line, range.end_line));
+
+        Ok(Some(ranges))
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        let Some(text) = self
+            .documents
+            .get(&params.text_document.uri)
+            .map(|r| r.to_string())
+        else {
+            return Ok(None);
+        };
+        let cst = parse_tolerant("semantic_tokens", &text);
+        Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: semantic_tokens(&cst, &text),
+        })))
+    }
+
     async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
         let uri = params.text_document.uri;
         let rope = match self.documents.get(&uri) {
@@ -718,6 +1478,27 @@ impl LanguageServer for Backend {
 
         // 使用 CST formatter
         let cst = parse_tolerant("format", &text);
+
+        // 文档存在语法错误（如未闭合的字符串或代码块）时，容错解析可能丢弃或错位内容，
+        // 此时拒绝格式化，避免保存时格式化破坏正在编辑的文件
+        let reason = if has_error_nodes(&cst) {
+            Some("document contains unparsable lines".to_string())
+        } else {
+            parser::parse_story("format", &text)
+                .err()
+                .map(|e| e.to_string())
+        };
+        if let Some(reason) = reason {
+            drop(rope);
+            self.client
+                .log_message(
+                    MessageType::INFO,
+                    format!("Skip formatting {}: {}", uri.as_str(), reason),
+                )
+                .await;
+            return Ok(None);
+        }
+
         let formatter = CstFormatter::new();
         let formatted_text = formatter.format(&cst);
 
@@ -738,6 +1519,405 @@ impl LanguageServer for Backend {
             new_text: formatted_text,
         }]))
     }
+
+    /// 查找段落的引用：光标位于段落定义名或跳转调用的 `paragraph` 参数上时，
+    /// 列出所有打开的文档和工作区文件中指向该段落的跳转
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+
+        let Some(text) = self.documents.get(&uri).map(|r| r.to_string()) else {
+            return Ok(None);
+        };
+        let cst = parse_tolerant("references", &text);
+        let Some((story, name, _)) = paragraph_at_position(&cst, &story_name(&uri), &position)
+        else {
+            return Ok(None);
+        };
+
+        let include_declaration = params.context.include_declaration;
+        let mut locations = Vec::new();
+        for (uri, text) in self.story_sources().await {
+            let cst = parse_tolerant("references", &text);
+            let home = story_name(&uri);
+            for range in paragraph_references(&cst, &home, &story, &name, include_declaration) {
+                locations.push(Location {
+                    uri: uri.clone(),
+                    range,
+                });
+            }
+        }
+        Ok(Some(locations))
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let Some(text) = self
+            .documents
+            .get(&params.text_document.uri)
+            .map(|r| r.to_string())
+        else {
+            return Ok(None);
+        };
+        let cst = parse_tolerant("prepare_rename", &text);
+        let home = story_name(&params.text_document.uri);
+        Ok(paragraph_at_position(&cst, &home, &params.position)
+            .map(|(_, _, range)| PrepareRenameResponse::Range(range)))
+    }
+
+    /// 重命名段落：光标位于段落定义名或跳转调用的 `paragraph` 参数上时，
+    /// 修改定义以及所有打开的文档和工作区文件中指向该段落的跳转
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let new_name = params.new_name;
+
+        let Some(text) = self.documents.get(&uri).map(|r| r.to_string()) else {
+            return Ok(None);
+        };
+        let cst = parse_tolerant("rename", &text);
+        let Some((story, old, _)) = paragraph_at_position(&cst, &story_name(&uri), &position)
+        else {
+            return Ok(None);
+        };
+
+        let mut chars = new_name.chars();
+        let valid = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(tower_lsp_server::jsonrpc::Error::invalid_params(format!(
+                "Invalid paragraph name: {}",
+                new_name
+            )));
+        }
+
+        let mut changes = HashMap::new();
+        for (uri, text) in self.story_sources().await {
+            let cst = parse_tolerant("rename", &text);
+            let edits = paragraph_rename_edits(&cst, &story_name(&uri), &story, &old, &new_name);
+            if !edits.is_empty() {
+                changes.insert(uri, edits);
+            }
+        }
+
+        Ok(Some(WorkspaceEdit {
+            changes: Some(changes),
+            ..Default::default()
+        }))
+    }
+
+    /// 在命令末尾以 `name=default` 提示省略了的、schema 中带默认值的参数
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let Some(text) = self
+            .documents
+            .get(&params.text_document.uri)
+            .map(|r| r.to_string())
+        else {
+            return Ok(None);
+        };
+        let schema_guard = self.schema.read().await;
+        let Some(schema) = schema_guard.as_ref() else {
+            return Ok(None);
+        };
+
+        let cst = parse_tolerant("inlay_hint", &text);
+        let mut hints = Vec::new();
+        for cmd in extract_commands(&cst) {
+            let cmd_range = span_to_range(&cmd.span);
+            if !overlaps(&cmd_range, &params.range) {
+                continue;
+            }
+            let Some(def) = schema
+                .commands
+                .iter()
+                .find(|c| c.get_command_name().as_deref() == Some(&cmd.command))
+            else {
+                continue;
+            };
+            for (name, prop) in def.parameters() {
+                let Some(default) = &prop.default else {
+                    continue;
+                };
+                if cmd.arguments.iter().any(|arg| &arg.name == name) {
+                    continue;
+                }
+                hints.push(InlayHint {
+                    position: cmd_range.end,
+                    label: InlayHintLabel::String(format!("{}={}", name, default)),
+                    kind: Some(InlayHintKind::PARAMETER),
+                    text_edits: None,
+                    tooltip: prop.description.clone().map(InlayHintTooltip::String),
+                    padding_left: Some(true),
+                    padding_right: None,
+                    data: None,
+                });
+            }
+        }
+
+        Ok(Some(hints))
+    }
+
+    /// 快速修复：schema 期望字符串的参数写成了未加引号的值（如 `src=test.jpg`）时，
+    /// 提供把值用双引号包起来的修改
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let Some(text) = self.documents.get(&uri).map(|r| r.to_string()) else {
+            return Ok(None);
+        };
+        let schema_guard = self.schema.read().await;
+        let Some(schema) = schema_guard.as_ref() else {
+            return Ok(None);
+        };
+
+        let cst = parse_tolerant("code_action", &text);
+        let mut actions = Vec::new();
+        for cmd in extract_commands(&cst) {
+            let Some(def) = schema
+                .commands
+                .iter()
+                .find(|c| c.get_command_name().as_deref() == Some(&cmd.command))
+            else {
+                continue;
+            };
+            for arg in &cmd.arguments {
+                let (Some(value), Some(prop)) = (&arg.value, def.properties.get(&arg.name)) else {
+                    continue;
+                };
+                let expects_string = match &prop.type_ {
+                    Some(StringOrArray::String(s)) => s == "string",
+                    Some(StringOrArray::Array(arr)) => arr.iter().any(|s| s == "string"),
+                    None => false,
+                };
+                let unquoted = matches!(
+                    value.kind,
+                    CstValueKind::Variable
+                        | CstValueKind::Integer
+                        | CstValueKind::Float
+                        | CstValueKind::Boolean
+                );
+                let arg_range = span_to_range(&arg.span);
+                if !expects_string || !unquoted || !overlaps(&arg_range, &params.range) {
+                    continue;
+                }
+
+                // 关联该参数上的类型不匹配诊断
+                let diagnostics: Vec<Diagnostic> = params
+                    .context
+                    .diagnostics
+                    .iter()
+                    .filter(|d| d.range == arg_range)
+                    .cloned()
+                    .collect();
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: "Wrap value in quotes".to_string(),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: (!diagnostics.is_empty()).then_some(diagnostics),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(HashMap::from([(
+                            uri.clone(),
+                            vec![quote_value_edit(value)],
+                        )])),
+                        ..Default::default()
+                    }),
+                    // 变量也可能本就是有意为之，只在字面量类型不匹配时作为首选修复
+                    is_preferred: Some(!matches!(value.kind, CstValueKind::Variable)),
+                    ..Default::default()
+                }));
+            }
+        }
+
+        Ok(Some(actions))
+    }
+
+    async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<LSPAny>> {
+        if params.command != RENAME_COMMAND {
+            return Err(tower_lsp_server::jsonrpc::Error::invalid_params(format!(
+                "Unknown command: {}",
+                params.command
+            )));
+        }
+
+        let names: Vec<String> = params
+            .arguments
+            .into_iter()
+            .filter_map(|arg| arg.as_str().map(str::to_string))
+            .collect();
+        let [old, new] = names.as_slice() else {
+            return Err(tower_lsp_server::jsonrpc::Error::invalid_params(
+                "Expected the old and the new command name",
+            ));
+        };
+
+        let edit = self.rename_command_edit(old, new).await;
+        if self.apply_edit.load(Ordering::Relaxed) {
+            let _ = self.client.apply_edit(edit.clone()).await;
+        }
+
+        Ok(serde_json::to_value(edit).ok())
+    }
+}
+
+/// 文档对应的故事名，即文件名去掉扩展名，与跳转时按 `{story}.sixu` 查找文件一致
+fn story_name(uri: &Uri) -> String {
+    uri.to_file_path()
+        .and_then(|path| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default()
+}
+
+/// 递归收集目录下的 .sixu 文件，跳过隐藏目录与构建产物目录
+fn story_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return files;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" && name != "node_modules" {
+                files.extend(story_files(&path));
+            }
+        } else if path.extension().is_some_and(|ext| ext == "sixu") {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// 收集代码块内带标签的 group 块作为文档符号，无标签的块不产生符号，其内部的 group 直接上提
+fn group_symbols(block: &sixu::cst::node::CstBlock) -> Vec<DocumentSymbol> {
+    use sixu::cst::node::CstNode;
+
+    let mut symbols = Vec::new();
+    for child in &block.children {
+        if let CstNode::Block(inner) = child {
+            let children = group_symbols(inner);
+            match &inner.label {
+                Some(label) => {
+                    #[allow(deprecated)]
+                    symbols.push(DocumentSymbol {
+                        name: label.name.clone(),
+                        detail: None,
+                        kind: SymbolKind::NAMESPACE,
+                        tags: None,
+                        deprecated: None,
+                        range: span_to_range(&inner.span),
+                        selection_range: span_to_range(&label.name_span),
+                        children: (!children.is_empty()).then_some(children),
+                    });
+                }
+                None => symbols.extend(children),
+            }
+        }
+    }
+    symbols
+}
+
+/// 找到第一处与文件缩进风格不一致的行，返回（行号, 期望的风格, 缩进宽度）
+///
+/// 文件的风格由第一行有缩进的行决定；空白行不参与判断。
+fn mixed_indentation(rope: &Rope) -> Option<(usize, &'static str, usize)> {
+    let mut style = None;
+    for (index, line) in rope.lines().enumerate() {
+        let indent: String = line
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        let rest = line.chars().nth(indent.chars().count());
+        if indent.is_empty() || rest.is_none_or(|c| c == '\n' || c == '\r') {
+            continue;
+        }
+
+        let first = *style.get_or_insert(indent.chars().next()?);
+        if indent.chars().any(|c| c != first) {
+            let expected = if first == '\t' { "tabs" } else { "spaces" };
+            return Some((index, expected, indent.chars().count()));
+        }
+    }
+    None
+}
+
+/// 把诊断数量限制在 max 条以内：按严重程度保留（同级按原顺序），保持原有顺序，
+/// 并在末尾追加一条说明被省略数量的提示
+fn limit_diagnostics(diagnostics: Vec<Diagnostic>, max: usize) -> Vec<Diagnostic> {
+    if diagnostics.len() <= max {
+        return diagnostics;
+    }
+
+    // 未标明严重程度的诊断排在最后
+    let rank = |d: &Diagnostic| match d.severity {
+        Some(DiagnosticSeverity::ERROR) => 0,
+        Some(DiagnosticSeverity::WARNING) => 1,
+        Some(DiagnosticSeverity::INFORMATION) => 2,
+        Some(DiagnosticSeverity::HINT) => 3,
+        _ => 4,
+    };
+    let mut order: Vec<usize> = (0..diagnostics.len()).collect();
+    order.sort_by_key(|&i| rank(&diagnostics[i]));
+    let mut kept = vec![false; diagnostics.len()];
+    for &i in &order[..max] {
+        kept[i] = true;
+    }
+
+    let suppressed = diagnostics.len() - max;
+    let mut limited: Vec<Diagnostic> = diagnostics
+        .into_iter()
+        .zip(kept)
+        .filter_map(|(diagnostic, kept)| kept.then_some(diagnostic))
+        .collect();
+    limited.push(Diagnostic {
+        range: Range::default(),
+        severity: Some(DiagnosticSeverity::INFORMATION),
+        source: Some("sixu".to_string()),
+        message: format!("{} more diagnostics suppressed", suppressed),
+        ..Default::default()
+    });
+    limited
+}
+
+/// 将 `textDocument/didChange` 的一次修改应用到文档上。带范围的修改只替换该范围，
+/// 范围按 UTF-16 计算列号且超出行尾时截断到行尾；不带范围的修改替换全文
+pub fn apply_content_change(rope: &mut Rope, change: &TextDocumentContentChangeEvent) {
+    let Some(range) = change.range else {
+        *rope = Rope::from_str(&change.text);
+        return;
+    };
+    let start = position_to_char(rope, range.start);
+    let end = position_to_char(rope, range.end).max(start);
+    rope.remove(start..end);
+    rope.insert(start, &change.text);
+}
+
+/// LSP 位置（UTF-16 列号）转换为字符索引，超出文档末尾时返回文档长度
+fn position_to_char(rope: &Rope, position: Position) -> usize {
+    let line = position.line as usize;
+    if line >= rope.len_lines() {
+        return rope.len_chars();
+    }
+    let line_start = rope.line_to_char(line);
+    let line_end = if line + 1 < rope.len_lines() {
+        // 不越过行尾的换行符
+        let next = rope.line_to_char(line + 1);
+        let mut end = next;
+        while end > line_start && matches!(rope.char(end - 1), '\n' | '\r') {
+            end -= 1;
+        }
+        end
+    } else {
+        rope.len_chars()
+    };
+    let start_cu = rope.char_to_utf16_cu(line_start);
+    let end_cu = rope.char_to_utf16_cu(line_end);
+    let cu = (start_cu + position.character as usize).min(end_cu);
+    rope.utf16_cu_to_char(cu)
 }
 
 fn offset_to_position(offset: usize, rope: &Rope) -> (usize, usize) {
@@ -748,22 +1928,21 @@ fn offset_to_position(offset: usize, rope: &Rope) -> (usize, usize) {
     (line, col)
 }
 
-trait Offset {
-    fn offset(&self, second: &str) -> usize;
+/// 创建 LspService 实例（用于 main 和测试共享）
+pub fn create_lsp_service() -> (LspService<Backend>, tower_lsp_server::ClientSocket) {
+    LspService::new(Backend::new)
 }
 
-impl Offset for str {
-    fn offset(&self, second: &str) -> usize {
-        let self_ptr = self.as_ptr() as usize;
-        let second_ptr = second.as_ptr() as usize;
-        if second_ptr < self_ptr || second_ptr > self_ptr + self.len() {
-            return 0;
-        }
-        second_ptr - self_ptr
-    }
+/// 创建使用指定值解析器的 LspService 实例
+pub fn create_lsp_service_with_value_parsers(
+    value_parsers: ValueParsers,
+) -> (LspService<Backend>, tower_lsp_server::ClientSocket) {
+    LspService::new(move |client| Backend::with_value_parsers(client, value_parsers))
 }
 
-/// 创建 LspService 实例（用于 main 和测试共享）
-pub fn create_lsp_service() -> (LspService<Backend>, tower_lsp_server::ClientSocket) {
-    LspService::new(Backend::new)
+/// 创建预置命令 schema 的 LspService 实例
+pub fn create_lsp_service_with_schema(
+    schema: CommandSchema,
+) -> (LspService<Backend>, tower_lsp_server::ClientSocket) {
+    LspService::new(move |client| Backend::new(client).with_schema(schema))
 }
@@ -0,0 +1,76 @@
+//! 代码操作（快速修复）集成测试
+//!
+//! 使用 sample-project/commands.schema.json 中的 changebg 命令，`src` 为字符串类型。
+
+mod helpers;
+use helpers::*;
+use tower_lsp_server::ls_types::*;
+
+const TEXT: &str = concat!(
+    "::test {\n",
+    "    @changebg src=test.jpg\n",
+    "    @changebg(src=123, fadeTime=slow)\n",
+    "    @changebg src=\"ok.jpg\"\n",
+    "}\n",
+);
+
+fn cursor(line: u32, character: u32) -> Range {
+    Range::new(
+        Position::new(line, character),
+        Position::new(line, character),
+    )
+}
+
+/// 取出 action 中对 uri 的唯一修改
+fn single_edit(action: &CodeAction, uri: &Uri) -> TextEdit {
+    let changes = action.edit.as_ref().unwrap().changes.as_ref().unwrap();
+    let edits = &changes[uri];
+    assert_eq!(edits.len(), 1);
+    edits[0].clone()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_quote_unquoted_variable_value() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx.open_document("file:///test/quote.sixu", TEXT).await;
+    let _ = ctx.read_diagnostics().await;
+
+    let actions = ctx.code_actions(&uri, cursor(1, 20), vec![]).await;
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0].title, "Wrap value in quotes");
+    assert_eq!(actions[0].kind, Some(CodeActionKind::QUICKFIX));
+    assert_eq!(actions[0].is_preferred, Some(false));
+    let edit = single_edit(&actions[0], &uri);
+    assert_eq!(edit.new_text, "\"test.jpg\"");
+    assert_eq!(
+        edit.range,
+        Range::new(Position::new(1, 18), Position::new(1, 26))
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_quote_mismatched_literal() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx.open_document("file:///test/quote.sixu", TEXT).await;
+    let diagnostics = ctx.read_diagnostics().await;
+    let mismatch: Vec<Diagnostic> = diagnostics
+        .into_iter()
+        .filter(|d| d.message.starts_with("Type mismatch") && d.range.start.line == 2)
+        .collect();
+    assert_eq!(mismatch.len(), 1);
+
+    // 整行范围：fadeTime 期望数字，不提供修复
+    let line = Range::new(Position::new(2, 0), Position::new(2, 37));
+    let actions = ctx.code_actions(&uri, line, mismatch.clone()).await;
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0].diagnostics, Some(mismatch));
+    assert_eq!(actions[0].is_preferred, Some(true));
+    assert_eq!(single_edit(&actions[0], &uri).new_text, "\"123\"");
+
+    // 已加引号的值没有修复
+    assert!(
+        ctx.code_actions(&uri, cursor(3, 20), vec![])
+            .await
+            .is_empty()
+    );
+}
@@ -133,7 +133,7 @@ async fn test_systemcall_param_exclusion() {
     );
     // story 应该还在
     assert!(
-        labels.iter().any(|l| *l == "story"),
+        labels.contains(&"story"),
         "story 应出现在补全列表中，实际: {:?}",
         labels
     );
@@ -156,12 +156,44 @@ async fn test_systemcall_paren_param_exclusion() {
     let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
     assert!(!labels.contains(&"paragraph"), "paragraph 不应再出现");
     assert!(
-        labels.iter().any(|l| *l == "story"),
+        labels.contains(&"story"),
         "story 应出现, 实际: {:?}",
         labels
     );
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_systemcall_callee_param_defaults() {
+    // 目标段落的参数：可选参数以默认值作为占位符，必选参数为空占位符
+    let mut ctx = TestContext::new().await;
+    let text =
+        "::scene(place, time=\"morning\") {\n}\n::test {\n    #call paragraph=\"scene\" \n}\n";
+    // 第 3 行 `    #call paragraph="scene" `，光标在末尾 col 28
+    let uri = ctx
+        .open_document("file:///test/syscall_callee_params.sixu", text)
+        .await;
+    let _ = ctx.read_diagnostics().await;
+
+    let items = ctx.completion(&uri, 3, 28).await;
+    let items = items.expect("系统调用应返回补全项");
+
+    let time = items
+        .iter()
+        .find(|i| i.label == "time")
+        .expect("应包含可选参数 time");
+    assert_eq!(time.insert_text.as_deref(), Some("time=${1:\"morning\"}"));
+    assert_eq!(
+        time.insert_text_format,
+        Some(tower_lsp_server::ls_types::InsertTextFormat::SNIPPET)
+    );
+
+    let place = items
+        .iter()
+        .find(|i| i.label == "place")
+        .expect("应包含必选参数 place");
+    assert_eq!(place.insert_text.as_deref(), Some("place=$1"));
+}
+
 // ============================================================
 // 上下文验证
 // ============================================================
@@ -227,6 +259,29 @@ async fn test_systemcall_name_completion() {
     );
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_no_name_completion_in_string_or_comment() {
+    // 字符串和注释中的 @、# 不触发命令名补全
+    let mut ctx = TestContext::new().await;
+    let text = "::test {\n    \"mail me @\"\n    // #cal\n}\n";
+    //    line 1: `    "mail me @"`，col 14 在 @ 之后
+    //    line 2: `    // #cal`，col 11 在 #cal 之后
+    let uri = ctx
+        .open_document("file:///test/name_in_string.sixu", text)
+        .await;
+    let _ = ctx.read_diagnostics().await;
+
+    for (line, col) in [(1, 14), (2, 11)] {
+        let items = ctx.completion(&uri, line, col).await;
+        assert!(
+            items.is_none() || items.as_ref().unwrap().is_empty(),
+            "第 {} 行不应触发补全，实际: {:?}",
+            line,
+            items.map(|v| v.iter().map(|i| i.label.clone()).collect::<Vec<_>>())
+        );
+    }
+}
+
 // ============================================================
 // 混合语法测试
 // ============================================================
@@ -145,6 +145,35 @@ async fn test_syntax_error() {
     );
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_syntax_error_lists_expected_tokens() {
+    let mut ctx = TestContext::new().await;
+    ctx.open_document(
+        "file:///test/missing_paren.sixu",
+        "::test {\n    @changebg(src=\"bg.jpg\" fadeTime=600)\n}\n",
+    )
+    .await;
+
+    let diagnostics = ctx.read_diagnostics().await;
+    let syntax = diagnostics
+        .iter()
+        .find(|d| d.source.as_deref() == Some("sixu"))
+        .expect("缺少右括号应产生语法诊断");
+
+    assert!(
+        syntax.message.contains("in command argument list"),
+        "诊断应说明所在上下文，实际: {}",
+        syntax.message
+    );
+    assert!(
+        syntax.message.contains("expected `,` or `)`"),
+        "诊断应列出期望的 token，实际: {}",
+        syntax.message
+    );
+    assert_eq!(syntax.range.start.line, 1);
+    assert_eq!(syntax.range.start.character, 27);
+}
+
 // ============================================================
 // 内联诊断测试（无需 fixture 文件）
 // ============================================================
@@ -338,3 +367,702 @@ async fn test_multiple_errors_in_file() {
     assert!(has_missing_param, "应包含缺少必需参数的诊断");
     assert!(has_unknown_param, "应包含未知参数的诊断");
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unclosed_paren_before_block() {
+    let mut ctx = TestContext::new().await;
+    let text = read_fixture("13_unclosed_paren_before_block.sixu");
+    ctx.open_document("file:///test/13_unclosed_paren_before_block.sixu", &text)
+        .await;
+
+    let diagnostics = ctx.read_diagnostics().await;
+    let tolerant: Vec<_> = diagnostics
+        .iter()
+        .filter(|d| d.source.as_deref() == Some("sixu-syntax"))
+        .collect();
+    assert_eq!(
+        tolerant.len(),
+        1,
+        "应只产生一个针对性的诊断，实际: {:?}",
+        diagnostics.iter().map(|d| &d.message).collect::<Vec<_>>()
+    );
+    assert_eq!(tolerant[0].message, "expected `)` before block");
+    // 诊断位于左括号处
+    assert_eq!(tolerant[0].range.start.line, 2);
+    assert_eq!(tolerant[0].range.start.character, 8);
+
+    // 除 parser 本身的语法错误外，不应有其他错误级别的诊断
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Some(DiagnosticSeverity::ERROR))
+        .count();
+    assert_eq!(errors, 2);
+}
+
+/// 打开 fixture，返回容错解析产生的诊断
+async fn tolerant_diagnostics(name: &str) -> Vec<tower_lsp_server::ls_types::Diagnostic> {
+    let mut ctx = TestContext::new().await;
+    let text = read_fixture(name);
+    ctx.open_document(&format!("file:///test/{}", name), &text)
+        .await;
+
+    ctx.read_diagnostics()
+        .await
+        .into_iter()
+        .filter(|d| d.source.as_deref() == Some("sixu-syntax"))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stray_close_brace_in_paragraph() {
+    let tolerant = tolerant_diagnostics("21_stray_brace_in_paragraph.sixu").await;
+    assert_eq!(
+        tolerant.len(),
+        1,
+        "应只产生一个针对性的诊断，实际: {:?}",
+        tolerant.iter().map(|d| &d.message).collect::<Vec<_>>()
+    );
+    assert_eq!(tolerant[0].message, "unexpected `}`");
+    // 诊断位于多余的 } 处，而不是段落末尾
+    assert_eq!(tolerant[0].range.start.line, 3);
+    assert_eq!(tolerant[0].range.start.character, 4);
+    assert_eq!(tolerant[0].range.end.character, 5);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stray_closers_at_top_level() {
+    let tolerant = tolerant_diagnostics("22_stray_brace_top_level.sixu").await;
+    let found: Vec<_> = tolerant
+        .iter()
+        .map(|d| {
+            (
+                d.message.as_str(),
+                d.range.start.line,
+                d.range.start.character,
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![("unexpected `}`", 4, 0), ("unexpected `)`", 5, 0)]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dangling_attribute_at_eof() {
+    let tolerant = tolerant_diagnostics("29_dangling_attribute.sixu").await;
+    let found: Vec<_> = tolerant
+        .iter()
+        .map(|d| {
+            (
+                d.message.as_str(),
+                d.range.start.line,
+                d.range.start.character,
+                d.range.end.character,
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![("Attribute not attached to a statement", 0, 0, 7)]
+    );
+    assert_eq!(tolerant[0].severity, Some(DiagnosticSeverity::ERROR));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_paragraph_attributes_are_attached() {
+    let mut ctx = TestContext::new().await;
+    ctx.open_document(
+        "file:///test/paragraph_attributes.sixu",
+        "#[once]\n::p {}\n\n#[platform(\"mobile\")]\n::q {\n    hello\n}\n",
+    )
+    .await;
+
+    let diagnostics = ctx.read_diagnostics().await;
+    assert!(
+        diagnostics.is_empty(),
+        "段落上的 once 与 platform 属性不是悬空属性，实际: {:?}",
+        diagnostics.iter().map(|d| &d.message).collect::<Vec<_>>()
+    );
+
+    let mut ctx = TestContext::new().await;
+    ctx.open_document(
+        "file:///test/paragraph_loop.sixu",
+        "#[loop]\n::p {\n    hello\n}\n",
+    )
+    .await;
+    let dangling: Vec<_> = ctx
+        .read_diagnostics()
+        .await
+        .into_iter()
+        .filter(|d| d.message == "Attribute not attached to a statement")
+        .collect();
+    assert_eq!(dangling.len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_attached_attributes() {
+    let mut ctx = TestContext::new().await;
+    ctx.open_document(
+        "file:///test/attached.sixu",
+        "::test {\n    #[loop]\n    #[cond(\"a\")]\n    {\n        #break\n    }\n}\n",
+    )
+    .await;
+
+    let diagnostics = ctx.read_diagnostics().await;
+    assert!(
+        diagnostics.is_empty(),
+        "连续的属性作用于同一条语句，实际: {:?}",
+        diagnostics.iter().map(|d| &d.message).collect::<Vec<_>>()
+    );
+}
+
+/// 以 maxBlockDepth = 2 打开 fixture，返回嵌套深度提示
+async fn block_depth_hints(name: &str) -> Vec<tower_lsp_server::ls_types::Diagnostic> {
+    let mut ctx =
+        TestContext::with_options(workspace_root(), serde_json::json!({ "maxBlockDepth": 2 }))
+            .await;
+    let text = read_fixture(name);
+    ctx.open_document(&format!("file:///test/{}", name), &text)
+        .await;
+
+    ctx.read_diagnostics()
+        .await
+        .into_iter()
+        .filter(|d| d.source.as_deref() == Some("sixu-lint"))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_block_depth_below_and_at_limit() {
+    assert!(
+        block_depth_hints("14_block_depth_below.sixu")
+            .await
+            .is_empty()
+    );
+    assert!(block_depth_hints("15_block_depth_at.sixu").await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_block_depth_above_limit() {
+    let hints = block_depth_hints("16_block_depth_above.sixu").await;
+    assert_eq!(
+        hints.len(),
+        1,
+        "应只在最内层代码块处提示，实际: {:?}",
+        hints.iter().map(|d| &d.message).collect::<Vec<_>>()
+    );
+    assert_eq!(hints[0].severity, Some(DiagnosticSeverity::HINT));
+    assert_eq!(
+        hints[0].message,
+        "Block nested 3 levels deep, more than the maximum of 2"
+    );
+    assert_eq!(hints[0].range.start.line, 4);
+    assert_eq!(hints[0].range.start.character, 12);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_block_depth_off_by_default() {
+    let mut ctx = TestContext::new().await;
+    let text = read_fixture("16_block_depth_above.sixu");
+    ctx.open_document("file:///test/16_block_depth_above.sixu", &text)
+        .await;
+
+    let diagnostics = ctx.read_diagnostics().await;
+    assert!(
+        diagnostics.is_empty(),
+        "默认不检查嵌套深度，实际: {:?}",
+        diagnostics.iter().map(|d| &d.message).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_parameter_type_mismatch() {
+    let mut ctx = TestContext::new().await;
+    let text = read_fixture("17_parameter_type_mismatch.sixu");
+    ctx.open_document("file:///test/17_parameter_type_mismatch.sixu", &text)
+        .await;
+
+    let diagnostics: Vec<_> = ctx
+        .read_diagnostics()
+        .await
+        .into_iter()
+        .filter(|d| d.source.as_deref() == Some("sixu-params"))
+        .collect();
+
+    // 只有第二个调用的两个字面量参数类型不符，变量参数不检查
+    let found: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.range.start.line, d.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (3, "Type mismatch for parameter location. Expected: string"),
+            (3, "Type mismatch for parameter count. Expected: number"),
+        ]
+    );
+    assert!(
+        diagnostics
+            .iter()
+            .all(|d| d.severity == Some(DiagnosticSeverity::WARNING))
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_parameter_types_follow_target_story_edits() {
+    let mut ctx = TestContext::new().await;
+    let target = ctx
+        .open_document(
+            "file:///test/target.sixu",
+            "::scene(count: number) {\n    \"scene\"\n}\n",
+        )
+        .await;
+    ctx.read_diagnostics().await;
+
+    let caller_text = "::entry {\n    #call story=\"target\" paragraph=\"scene\" count=\"x\"\n}\n";
+    let caller = ctx
+        .open_document("file:///test/caller.sixu", caller_text)
+        .await;
+    let mismatches = |diagnostics: Vec<tower_lsp_server::ls_types::Diagnostic>| {
+        diagnostics
+            .into_iter()
+            .filter(|d| d.source.as_deref() == Some("sixu-params"))
+            .count()
+    };
+    assert_eq!(mismatches(ctx.read_diagnostics().await), 1);
+
+    // 修改目标段落的类型标注后，缓存的目标故事失效
+    ctx.change_document(
+        &target,
+        2,
+        serde_json::json!([{ "text": "::scene(count: string) {\n    \"scene\"\n}\n" }]),
+    )
+    .await;
+    ctx.read_diagnostics().await;
+    ctx.change_document(&caller, 2, serde_json::json!([{ "text": caller_text }]))
+        .await;
+    assert_eq!(mismatches(ctx.read_diagnostics().await), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_parameter_types_follow_target_file_on_disk() {
+    let dir = std::env::temp_dir().join(format!("sixu-lsp-target-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("target.sixu");
+    std::fs::write(&target, "::scene(count: number) {\n    \"scene\"\n}\n").unwrap();
+
+    let mut ctx = TestContext::with_workspace(dir.clone()).await;
+    let caller_text = "::entry {\n    #call story=\"target\" paragraph=\"scene\" count=\"x\"\n}\n";
+    let caller_uri =
+        tower_lsp_server::ls_types::Uri::from_file_path(dir.join("caller.sixu")).unwrap();
+    let caller = ctx.open_document(caller_uri.as_str(), caller_text).await;
+    let mismatches = |diagnostics: Vec<tower_lsp_server::ls_types::Diagnostic>| {
+        diagnostics
+            .into_iter()
+            .filter(|d| d.source.as_deref() == Some("sixu-params"))
+            .count()
+    };
+    assert_eq!(mismatches(ctx.read_diagnostics().await), 1);
+
+    // 未打开的目标文件在磁盘上被修改后，缓存按修改时间失效
+    std::fs::write(&target, "::scene(count: string) {\n    \"scene\"\n}\n").unwrap();
+    let later = std::time::SystemTime::now() + std::time::Duration::from_secs(10);
+    std::fs::File::options()
+        .write(true)
+        .open(&target)
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+    ctx.change_document(&caller, 2, serde_json::json!([{ "text": caller_text }]))
+        .await;
+    assert_eq!(mismatches(ctx.read_diagnostics().await), 0);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// 以 checkMixedIndentation 打开 fixture，返回缩进风格警告
+async fn indentation_warnings(name: &str) -> Vec<tower_lsp_server::ls_types::Diagnostic> {
+    let mut ctx = TestContext::with_options(
+        workspace_root(),
+        serde_json::json!({ "checkMixedIndentation": true }),
+    )
+    .await;
+    let text = read_fixture(name);
+    ctx.open_document(&format!("file:///test/{}", name), &text)
+        .await;
+
+    ctx.read_diagnostics()
+        .await
+        .into_iter()
+        .filter(|d| d.source.as_deref() == Some("sixu-lint"))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_consistent_indentation() {
+    assert!(
+        indentation_warnings("18_indent_spaces.sixu")
+            .await
+            .is_empty()
+    );
+    assert!(indentation_warnings("19_indent_tabs.sixu").await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_mixed_indentation() {
+    let warnings = indentation_warnings("20_indent_mixed.sixu").await;
+    assert_eq!(
+        warnings.len(),
+        1,
+        "应只在第一处不一致的行警告，实际: {:?}",
+        warnings.iter().map(|d| &d.message).collect::<Vec<_>>()
+    );
+    assert_eq!(warnings[0].severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(
+        warnings[0].message,
+        "Indentation mixes tabs and spaces, this file is indented with spaces"
+    );
+    // 第 4 行以制表符缩进
+    assert_eq!(warnings[0].range.start.line, 3);
+    assert_eq!(warnings[0].range.end.character, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_mixed_indentation_off_by_default() {
+    let mut ctx = TestContext::new().await;
+    let text = read_fixture("20_indent_mixed.sixu");
+    ctx.open_document("file:///test/20_indent_mixed.sixu", &text)
+        .await;
+
+    let diagnostics = ctx.read_diagnostics().await;
+    assert!(
+        diagnostics.is_empty(),
+        "默认不检查缩进风格，实际: {:?}",
+        diagnostics.iter().map(|d| &d.message).collect::<Vec<_>>()
+    );
+}
+
+async fn call_syntax_hints(name: &str) -> Vec<tower_lsp_server::ls_types::Diagnostic> {
+    let mut ctx = TestContext::with_options(
+        workspace_root(),
+        serde_json::json!({ "checkCallSyntax": true }),
+    )
+    .await;
+    let text = read_fixture(name);
+    ctx.open_document(&format!("file:///test/{}", name), &text)
+        .await;
+
+    ctx.read_diagnostics()
+        .await
+        .into_iter()
+        .filter(|d| d.source.as_deref() == Some("sixu-lint"))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_consistent_call_syntax() {
+    assert!(
+        call_syntax_hints("23_call_syntax_parenthesized.sixu")
+            .await
+            .is_empty()
+    );
+    assert!(
+        call_syntax_hints("24_call_syntax_space.sixu")
+            .await
+            .is_empty()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_mixed_call_syntax() {
+    let hints = call_syntax_hints("25_call_syntax_mixed.sixu").await;
+    // 括号语法的 @changebg 与 #call 是少数写法
+    let lines: Vec<u32> = hints.iter().map(|d| d.range.start.line).collect();
+    assert_eq!(lines, vec![3, 5]);
+    for hint in &hints {
+        assert_eq!(hint.severity, Some(DiagnosticSeverity::HINT));
+        assert_eq!(
+            hint.message,
+            "Call uses parenthesized arguments, most calls in this file are space-separated"
+        );
+    }
+    assert_eq!(hints[0].range.start.character, 4);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_call_syntax_off_by_default() {
+    let mut ctx = TestContext::new().await;
+    let text = read_fixture("25_call_syntax_mixed.sixu");
+    ctx.open_document("file:///test/25_call_syntax_mixed.sixu", &text)
+        .await;
+
+    let hints: Vec<_> = ctx
+        .read_diagnostics()
+        .await
+        .into_iter()
+        .filter(|d| d.source.as_deref() == Some("sixu-lint"))
+        .collect();
+    assert!(
+        hints.is_empty(),
+        "默认不检查参数写法，实际: {:?}",
+        hints.iter().map(|d| &d.message).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_value_parser_format() {
+    let mut parsers = sixu::ValueParsers::new();
+    parsers.register("duration", |value| {
+        let millis = value
+            .as_string()
+            .ok()
+            .and_then(|s| s.strip_suffix("ms"))
+            .and_then(|ms| ms.parse::<i64>().ok());
+        millis
+            .map(sixu::format::Literal::Integer)
+            .ok_or_else(|| format!("`{}` is not a duration", value))
+    });
+
+    let mut ctx = TestContext::with_service(
+        sixu_lsp::create_lsp_service_with_value_parsers(parsers),
+        fixture_dir().join("value_parsers"),
+        serde_json::Value::Null,
+    )
+    .await;
+    let text = "::main {\n    @fade time=\"500ms\"\n    @fade time=\"abc\" color=\"nope\"\n}\n";
+    ctx.open_document("file:///test/value_parsers.sixu", text)
+        .await;
+
+    let diagnostics = ctx.read_diagnostics().await;
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|d| (d.range.start.line, d.message.as_str()))
+            .collect::<Vec<_>>(),
+        // 没有注册 color 的解析器，不做校验
+        vec![(
+            2,
+            "Invalid duration value for time: `abc` is not a duration"
+        )]
+    );
+    assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(diagnostics[0].range.start.character, 15);
+}
+
+/// 用 schema 校验 schema_builder/sample.sixu，返回诊断的行号与消息
+async fn schema_diagnostics(mut ctx: TestContext) -> Vec<(u32, String)> {
+    let path = fixture_dir().join("schema_builder").join("sample.sixu");
+    let text = std::fs::read_to_string(&path).unwrap();
+    ctx.open_document("file:///test/schema_builder.sixu", &text)
+        .await;
+
+    ctx.read_diagnostics()
+        .await
+        .into_iter()
+        .map(|d| (d.range.start.line, d.message))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_schema_builder_matches_json_schema() {
+    let from_json =
+        schema_diagnostics(TestContext::with_workspace(fixture_dir().join("schema_builder")).await)
+            .await;
+
+    let schema = sixu_lsp::SchemaBuilder::new()
+        .command("changebg", |c| {
+            c.description("切换背景")
+                .required("src")
+                .string("src")
+                .description("图片路径")
+                .number("fadeTime")
+                .default(600)
+        })
+        .command("wait", |c| {
+            c.required("time").union("time", &["number", "string"])
+        })
+        .build()
+        .unwrap();
+    // diagnostics 目录中没有 schema 文件，使用预置的 schema
+    let built = schema_diagnostics(
+        TestContext::with_service(
+            sixu_lsp::create_lsp_service_with_schema(schema),
+            fixture_dir().join("diagnostics"),
+            serde_json::Value::Null,
+        )
+        .await,
+    )
+    .await;
+
+    let expected: Vec<(u32, String)> = vec![
+        (3, "Missing required parameter: src".to_string()),
+        (3, "Type mismatch. Expected: [\"number\"]".to_string()),
+        (4, "Unknown parameter: volume".to_string()),
+        (
+            5,
+            "Type mismatch. Expected: [\"number\", \"string\"]".to_string(),
+        ),
+        (6, "Missing required parameter: time".to_string()),
+        (7, "Unknown command: shake".to_string()),
+    ];
+    assert_eq!(from_json, expected);
+    assert_eq!(built, expected);
+}
+
+// ============================================================
+// 诊断数量上限
+// ============================================================
+
+/// 生成含 count 个未知命令（WARNING）和末尾一个缺少必需参数（ERROR）的文件
+fn many_diagnostics_source(count: usize) -> String {
+    let mut text = "::main {\n".to_string();
+    for i in 0..count {
+        text.push_str(&format!("    @unknownCmd{}\n", i));
+    }
+    text.push_str("    @changebg(fadeTime=600)\n}\n");
+    text
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_max_diagnostics_keeps_most_severe() {
+    let mut ctx =
+        TestContext::with_options(workspace_root(), serde_json::json!({ "maxDiagnostics": 3 }))
+            .await;
+    ctx.open_document("file:///test/many.sixu", &many_diagnostics_source(10))
+        .await;
+
+    let diagnostics = ctx.read_diagnostics().await;
+    let summary: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.range.start.line, d.severity.unwrap(), d.message.as_str()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (
+                1,
+                DiagnosticSeverity::WARNING,
+                "Unknown command: unknownCmd0"
+            ),
+            (
+                2,
+                DiagnosticSeverity::WARNING,
+                "Unknown command: unknownCmd1"
+            ),
+            (
+                11,
+                DiagnosticSeverity::ERROR,
+                "Missing required parameter: src"
+            ),
+            (
+                0,
+                DiagnosticSeverity::INFORMATION,
+                "8 more diagnostics suppressed"
+            ),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_max_diagnostics_default() {
+    let mut ctx = TestContext::new().await;
+    ctx.open_document("file:///test/many.sixu", &many_diagnostics_source(1000))
+        .await;
+
+    let diagnostics = ctx.read_diagnostics().await;
+    assert_eq!(
+        diagnostics.len(),
+        sixu_lsp::config::DEFAULT_MAX_DIAGNOSTICS + 1
+    );
+    assert!(
+        diagnostics
+            .iter()
+            .any(|d| d.severity == Some(DiagnosticSeverity::ERROR))
+    );
+    assert_eq!(
+        diagnostics.last().unwrap().message,
+        "1 more diagnostics suppressed"
+    );
+}
+
+/// 打开 fixture，返回所有 "Unknown paragraph" 诊断
+async fn unknown_paragraphs(name: &str) -> Vec<tower_lsp_server::ls_types::Diagnostic> {
+    let mut ctx = TestContext::new().await;
+    let text = read_fixture(name);
+    ctx.open_document(&format!("file:///test/{}", name), &text)
+        .await;
+    ctx.read_diagnostics()
+        .await
+        .into_iter()
+        .filter(|d| d.message.starts_with("Unknown paragraph"))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_goto_existing_paragraph() {
+    assert!(unknown_paragraphs("26_goto_valid.sixu").await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_goto_dangling_paragraph() {
+    let diagnostics = unknown_paragraphs("27_goto_dangling.sixu").await;
+    let found: Vec<_> = diagnostics
+        .iter()
+        .map(|d| {
+            (
+                d.message.as_str(),
+                d.range.start.line,
+                d.range.start.character,
+                d.range.end.character,
+            )
+        })
+        .collect();
+    // 段落名区分大小写；带 story 参数的跨文件跳转不检查
+    assert_eq!(
+        found,
+        vec![
+            ("Unknown paragraph: missing", 2, 20, 29),
+            ("Unknown paragraph: Next", 4, 24, 30),
+        ]
+    );
+    for diagnostic in &diagnostics {
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::WARNING));
+    }
+}
+
+async fn dead_code_hints(
+    options: serde_json::Value,
+) -> Vec<tower_lsp_server::ls_types::Diagnostic> {
+    let mut ctx = TestContext::with_options(workspace_root(), options).await;
+    let text = read_fixture("28_dead_code.sixu");
+    ctx.open_document("file:///test/28_dead_code.sixu", &text)
+        .await;
+    ctx.read_diagnostics()
+        .await
+        .into_iter()
+        .filter(|d| d.message == "Paragraph never referenced")
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unreachable_paragraph() {
+    let hints = dead_code_hints(serde_json::json!({ "checkDeadCode": true })).await;
+    assert_eq!(hints.len(), 1, "只有 orphan 不可达，实际: {:?}", hints);
+    assert_eq!(hints[0].severity, Some(DiagnosticSeverity::HINT));
+    assert_eq!(hints[0].source.as_deref(), Some("sixu-lint"));
+    let range = hints[0].range;
+    assert_eq!(
+        (range.start.line, range.start.character, range.end.character),
+        (8, 2, 8)
+    );
+    assert_eq!(
+        hints[0].tags,
+        Some(vec![tower_lsp_server::ls_types::DiagnosticTag::UNNECESSARY])
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dead_code_off_by_default() {
+    assert!(dead_code_hints(serde_json::json!({})).await.is_empty());
+}
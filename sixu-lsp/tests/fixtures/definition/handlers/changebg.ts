@@ -0,0 +1,5 @@
+import { register } from "./registry";
+
+export function changebg(src: string) {
+  register("changebg", src);
+}
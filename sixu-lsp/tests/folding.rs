@@ -0,0 +1,92 @@
+//! 折叠范围集成测试
+//!
+//! 通过 LspService 进程内测试 textDocument/foldingRange 的区域标记折叠。
+
+mod helpers;
+use helpers::*;
+use serde_json::json;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_region_markers_fold() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx
+        .open_document(
+            "file:///test/regions.sixu",
+            concat!(
+                "// #region 开场\n",
+                "::entry {\n",
+                "    hello\n",
+                "    // #region 对话\n",
+                "    bye\n",
+                "    // #endregion\n",
+                "}\n",
+                "// #endregion\n",
+                "// #endregion\n",
+                "::ending {\n",
+                "    // #region 未闭合\n",
+                "    bye\n",
+                "}\n",
+            ),
+        )
+        .await;
+    let _ = ctx.read_diagnostics().await;
+
+    // 多余的 #endregion 与未闭合的 #region 都被忽略，(1, 6) 与 (9, 12) 为段落折叠
+    let ranges = ctx.folding_ranges(&uri).await;
+    assert_eq!(ranges, vec![(0, 7), (1, 6), (3, 5), (9, 12)]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_region_markers_from_initialization_options() {
+    let mut ctx = TestContext::with_options(
+        workspace_root(),
+        json!({ "regionMarkers": { "start": "region", "end": "endregion" } }),
+    )
+    .await;
+    let uri = ctx
+        .open_document(
+            "file:///test/custom_regions.sixu",
+            concat!(
+                "::entry {\n",
+                "    // region\n",
+                "    hello\n",
+                "    // endregion\n",
+                "    // #region\n",
+                "    // regions are not markers\n",
+                "}\n",
+            ),
+        )
+        .await;
+    let _ = ctx.read_diagnostics().await;
+
+    let ranges = ctx.folding_ranges(&uri).await;
+    assert_eq!(ranges, vec![(0, 6), (1, 3)]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_paragraph_and_block_fold() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx
+        .open_document(
+            "file:///test/blocks.sixu",
+            concat!(
+                "::entry {\n",
+                "    hello\n",
+                "    #[cond(\"ready\")]\n",
+                "    {\n",
+                "        { \"bye\" }\n",
+                "        group \"inner\" {\n",
+                "            bye\n",
+                "        }\n",
+                "    }\n",
+                "}\n",
+                "::short { \"hi\" }\n",
+            ),
+        )
+        .await;
+    let _ = ctx.read_diagnostics().await;
+
+    // 段落从 :: 所在行开始，折叠到右花括号所在行；单行的代码块和段落不折叠
+    let ranges = ctx.folding_ranges(&uri).await;
+    assert_eq!(ranges, vec![(0, 9), (3, 8), (5, 7)]);
+}
@@ -103,6 +103,11 @@ async fn test_format_multi_paragraphs() {
     run_format_test("10_multi_paragraphs").await;
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_format_block_label() {
+    run_format_test("11_block_label").await;
+}
+
 // ============================================================
 // 内联格式化测试
 // ============================================================
@@ -211,3 +216,37 @@ async fn test_format_block_comment_stars_idempotent() {
 
     assert_text_eq(&second, &first, "带 * 多行注释格式化幂等性");
 }
+
+// ============================================================
+// 存在语法错误时拒绝格式化
+// ============================================================
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_format_declines_unterminated_block() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx
+        .open_document(
+            "file:///test/unterminated_block.sixu",
+            "::entry {\n    @changebg src=\"bg.jpg\"\n    {\n        hello\n",
+        )
+        .await;
+    let _ = ctx.read_diagnostics().await;
+
+    let formatted = ctx.format_document(&uri).await;
+    assert!(formatted.is_none(), "未闭合的代码块不应被格式化");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_format_declines_unterminated_string() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx
+        .open_document(
+            "file:///test/unterminated_string.sixu",
+            "::entry {\n    @changebg src=\"bg.jpg\n}\n",
+        )
+        .await;
+    let _ = ctx.read_diagnostics().await;
+
+    let formatted = ctx.format_document(&uri).await;
+    assert!(formatted.is_none(), "未闭合的字符串不应被格式化");
+}
@@ -0,0 +1,115 @@
+//! 跳转定义集成测试
+
+mod helpers;
+use helpers::*;
+use tower_lsp_server::ls_types::*;
+
+const CHAPTER1: &str = "::start {\n    #goto story=\"chapter2\" paragraph=\"ending\"\n}\n";
+const CHAPTER2_ON_DISK: &str = "::ending {\n    @fade\n}\n";
+
+/// 在临时目录中写入磁盘上的 chapter2.sixu，返回目录
+fn story_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("sixu-lsp-goto-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("chapter2.sixu"), CHAPTER2_ON_DISK).unwrap();
+    dir
+}
+
+fn file_uri(path: &std::path::Path) -> String {
+    Uri::from_file_path(path).unwrap().as_str().to_string()
+}
+
+#[tokio::test]
+async fn test_goto_cross_file_reads_disk() {
+    let dir = story_dir("disk");
+    let mut ctx = TestContext::with_workspace(dir.clone()).await;
+    let uri = ctx
+        .open_document(&file_uri(&dir.join("chapter1.sixu")), CHAPTER1)
+        .await;
+
+    // 光标位于 "ending" 上
+    let location = ctx
+        .goto_definition(&uri, 1, 40)
+        .await
+        .expect("应跳转到 chapter2");
+    assert_eq!(location.uri.as_str(), file_uri(&dir.join("chapter2.sixu")));
+    assert_eq!(location.range.start, Position::new(0, 2));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_goto_cross_file_prefers_unsaved_document() {
+    let dir = story_dir("unsaved");
+    let mut ctx = TestContext::with_workspace(dir.clone()).await;
+    let uri = ctx
+        .open_document(&file_uri(&dir.join("chapter1.sixu")), CHAPTER1)
+        .await;
+    // 编辑器中未保存的 chapter2：ending 段落被移到了第 4 行
+    ctx.open_document(
+        &file_uri(&dir.join("chapter2.sixu")),
+        "::intro {\n    @fade\n}\n\n::ending {\n    @fade\n}\n",
+    )
+    .await;
+
+    let location = ctx
+        .goto_definition(&uri, 1, 40)
+        .await
+        .expect("应跳转到 chapter2");
+    assert_eq!(location.uri.as_str(), file_uri(&dir.join("chapter2.sixu")));
+    assert_eq!(location.range.start, Position::new(4, 2));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_goto_command_definition_from_schema() {
+    let dir = fixture_dir().join("definition");
+    let mut ctx = TestContext::with_workspace(dir.clone()).await;
+    let uri = ctx
+        .open_document(
+            "file:///test/definition.sixu",
+            "::start {\n    @changebg src=\"a.jpg\"\n    @wait\n}\n",
+        )
+        .await;
+
+    // 光标位于命令名上，跳转到 definitionPath 的 definitionLine 行
+    let location = ctx
+        .goto_definition(&uri, 1, 8)
+        .await
+        .expect("应跳转到命令实现");
+    assert_eq!(
+        location.uri.as_str(),
+        file_uri(&dir.join("handlers").join("changebg.ts"))
+    );
+    assert_eq!(location.range.start, Position::new(2, 0));
+
+    // 参数上以及未声明 definitionPath 的命令不跳转
+    assert!(ctx.goto_definition(&uri, 1, 16).await.is_none());
+    assert!(ctx.goto_definition(&uri, 2, 6).await.is_none());
+}
+
+#[tokio::test]
+async fn test_goto_cross_file_reads_disk_after_close() {
+    let dir = story_dir("closed");
+    let mut ctx = TestContext::with_workspace(dir.clone()).await;
+    let uri = ctx
+        .open_document(&file_uri(&dir.join("chapter1.sixu")), CHAPTER1)
+        .await;
+    // 未保存就关闭的 chapter2 不再影响跳转
+    let chapter2 = ctx
+        .open_document(
+            &file_uri(&dir.join("chapter2.sixu")),
+            "::intro {\n    @fade\n}\n\n::ending {\n    @fade\n}\n",
+        )
+        .await;
+    ctx.close_document(&chapter2).await;
+
+    let location = ctx
+        .goto_definition(&uri, 1, 40)
+        .await
+        .expect("应跳转到 chapter2");
+    assert_eq!(location.range.start, Position::new(0, 2));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
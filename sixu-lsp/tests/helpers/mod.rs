@@ -32,6 +32,7 @@ pub struct TestContext {
     diagnostics_cursor: usize,
 }
 
+#[allow(dead_code)]
 impl TestContext {
     /// 创建新的测试上下文（已完成 initialize + initialized 握手）
     pub async fn new() -> Self {
@@ -40,7 +41,23 @@ impl TestContext {
 
     /// 使用指定工作区路径创建测试上下文
     pub async fn with_workspace(workspace_path: std::path::PathBuf) -> Self {
-        let (service, socket) = create_lsp_service();
+        Self::with_options(workspace_path, serde_json::Value::Null).await
+    }
+
+    /// 使用指定工作区路径和 initializationOptions 创建测试上下文
+    pub async fn with_options(
+        workspace_path: std::path::PathBuf,
+        options: serde_json::Value,
+    ) -> Self {
+        Self::with_service(create_lsp_service(), workspace_path, options).await
+    }
+
+    /// 使用自行创建的 LspService 创建测试上下文
+    pub async fn with_service(
+        (service, socket): (LspService<Backend>, ClientSocket),
+        workspace_path: std::path::PathBuf,
+        options: serde_json::Value,
+    ) -> Self {
         let diagnostics_store = Arc::new(Mutex::new(Vec::new()));
 
         // 后台任务：持续从 socket 读取通知，将 publishDiagnostics 存入 store
@@ -55,7 +72,7 @@ impl TestContext {
             id_counter: 0,
             diagnostics_cursor: 0,
         };
-        ctx.initialize(&workspace_path).await;
+        ctx.initialize(&workspace_path, options).await;
         ctx
     }
 
@@ -65,13 +82,14 @@ impl TestContext {
     }
 
     /// 发送 initialize 请求 + initialized 通知
-    async fn initialize(&mut self, workspace_path: &Path) {
+    async fn initialize(&mut self, workspace_path: &Path, options: serde_json::Value) {
         let id = self.next_id();
         let workspace_uri = Uri::from_file_path(workspace_path).expect("Invalid workspace path");
 
         let init = Request::build("initialize")
             .params(json!({
                 "capabilities": {},
+                "initializationOptions": options,
                 "workspaceFolders": [{
                     "uri": workspace_uri.as_str(),
                     "name": "test"
@@ -111,6 +129,46 @@ impl TestContext {
         uri
     }
 
+    /// 发送 didChange 通知，`changes` 为 contentChanges 数组
+    pub async fn change_document(&mut self, uri: &Uri, version: i32, changes: serde_json::Value) {
+        let did_change = Request::build("textDocument/didChange")
+            .params(json!({
+                "textDocument": {
+                    "uri": uri.as_str(),
+                    "version": version
+                },
+                "contentChanges": changes
+            }))
+            .finish();
+
+        let _ = self.service.ready().await.unwrap().call(did_change).await;
+    }
+
+    /// 发送 didClose 通知
+    pub async fn close_document(&mut self, uri: &Uri) {
+        let did_close = Request::build("textDocument/didClose")
+            .params(json!({
+                "textDocument": { "uri": uri.as_str() }
+            }))
+            .finish();
+
+        let _ = self.service.ready().await.unwrap().call(did_close).await;
+    }
+
+    /// 发送 workspace/didChangeWatchedFiles 通知，通知 `uri` 被修改
+    pub async fn file_changed(&mut self, uri: &Uri) {
+        let notification = Request::build("workspace/didChangeWatchedFiles")
+            .params(json!({
+                "changes": [{
+                    "uri": uri.as_str(),
+                    "type": 2
+                }]
+            }))
+            .finish();
+
+        let _ = self.service.ready().await.unwrap().call(notification).await;
+    }
+
     /// 读取下一批 publishDiagnostics 通知中的诊断列表
     /// 等待直到有新的诊断到达或超时
     pub async fn read_diagnostics(&mut self) -> Vec<Diagnostic> {
@@ -183,6 +241,404 @@ impl TestContext {
         }
     }
 
+    /// 发送文档符号请求并返回嵌套的符号列表
+    pub async fn document_symbols(&mut self, uri: &Uri) -> Option<Vec<DocumentSymbol>> {
+        let id = self.next_id();
+
+        let request = Request::build("textDocument/documentSymbol")
+            .params(json!({
+                "textDocument": {
+                    "uri": uri.as_str()
+                }
+            }))
+            .id(id)
+            .finish();
+
+        let resp: Result<Option<Response>, _> =
+            self.service.ready().await.unwrap().call(request).await;
+
+        let resp = resp.expect("documentSymbol request failed");
+        let resp = resp.expect("documentSymbol should return a response");
+        let (_, result) = resp.into_parts();
+
+        match result {
+            Ok(value) => {
+                let value: serde_json::Value = value;
+                if value.is_null() {
+                    return None;
+                }
+                serde_json::from_value::<Vec<DocumentSymbol>>(value).ok()
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// 发送工作区符号请求并返回符号列表
+    pub async fn workspace_symbols(&mut self, query: &str) -> Vec<SymbolInformation> {
+        let id = self.next_id();
+
+        let request = Request::build("workspace/symbol")
+            .params(json!({ "query": query }))
+            .id(id)
+            .finish();
+
+        let resp: Result<Option<Response>, _> =
+            self.service.ready().await.unwrap().call(request).await;
+
+        let resp = resp.expect("workspace/symbol request failed");
+        let resp = resp.expect("workspace/symbol should return a response");
+        let (_, result) = resp.into_parts();
+
+        match result {
+            Ok(value) => {
+                let symbols: Option<Vec<SymbolInformation>> =
+                    serde_json::from_value(value).expect("Failed to parse workspace symbols");
+                symbols.unwrap_or_default()
+            }
+            Err(e) => panic!("workspace/symbol returned error: {:?}", e),
+        }
+    }
+
+    /// 发送折叠范围请求并返回 (起始行, 结束行) 列表
+    pub async fn folding_ranges(&mut self, uri: &Uri) -> Vec<(u32, u32)> {
+        let id = self.next_id();
+
+        let request = Request::build("textDocument/foldingRange")
+            .params(json!({
+                "textDocument": {
+                    "uri": uri.as_str()
+                }
+            }))
+            .id(id)
+            .finish();
+
+        let resp: Result<Option<Response>, _> =
+            self.service.ready().await.unwrap().call(request).await;
+
+        let resp = resp.expect("foldingRange request failed");
+        let resp = resp.expect("foldingRange should return a response");
+        let (_, result) = resp.into_parts();
+
+        match result {
+            Ok(value) => {
+                let ranges: Option<Vec<FoldingRange>> =
+                    serde_json::from_value(value).expect("Failed to parse FoldingRange response");
+                ranges
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|r| (r.start_line, r.end_line))
+                    .collect()
+            }
+            Err(e) => panic!("foldingRange returned error: {:?}", e),
+        }
+    }
+
+    /// 发送全文语义 token 请求并返回编码后的 token 列表
+    pub async fn semantic_tokens(&mut self, uri: &Uri) -> Vec<SemanticToken> {
+        let id = self.next_id();
+
+        let request = Request::build("textDocument/semanticTokens/full")
+            .params(json!({
+                "textDocument": {
+                    "uri": uri.as_str()
+                }
+            }))
+            .id(id)
+            .finish();
+
+        let resp: Result<Option<Response>, _> =
+            self.service.ready().await.unwrap().call(request).await;
+
+        let resp = resp.expect("semanticTokens request failed");
+        let resp = resp.expect("semanticTokens should return a response");
+        let (_, result) = resp.into_parts();
+
+        match result {
+            Ok(value) => {
+                let tokens: Option<SemanticTokensResult> =
+                    serde_json::from_value(value).expect("Failed to parse SemanticTokens");
+                match tokens {
+                    Some(SemanticTokensResult::Tokens(tokens)) => tokens.data,
+                    Some(other) => panic!("unexpected semanticTokens response: {:?}", other),
+                    None => Vec::new(),
+                }
+            }
+            Err(e) => panic!("semanticTokens returned error: {:?}", e),
+        }
+    }
+
+    /// 发送签名帮助请求
+    pub async fn signature_help(
+        &mut self,
+        uri: &Uri,
+        line: u32,
+        character: u32,
+    ) -> Option<SignatureHelp> {
+        let id = self.next_id();
+
+        let request = Request::build("textDocument/signatureHelp")
+            .params(json!({
+                "textDocument": {
+                    "uri": uri.as_str()
+                },
+                "position": {
+                    "line": line,
+                    "character": character
+                }
+            }))
+            .id(id)
+            .finish();
+
+        let resp: Result<Option<Response>, _> =
+            self.service.ready().await.unwrap().call(request).await;
+
+        let resp = resp.expect("signatureHelp request failed");
+        let resp = resp.expect("signatureHelp should return a response");
+        let (_, result) = resp.into_parts();
+
+        match result {
+            Ok(value) => serde_json::from_value(value).expect("Failed to parse SignatureHelp"),
+            Err(e) => panic!("signatureHelp returned error: {:?}", e),
+        }
+    }
+
+    /// 发送悬停请求并返回 Markdown 内容
+    pub async fn hover(&mut self, uri: &Uri, line: u32, character: u32) -> Option<String> {
+        let id = self.next_id();
+
+        let request = Request::build("textDocument/hover")
+            .params(json!({
+                "textDocument": {
+                    "uri": uri.as_str()
+                },
+                "position": {
+                    "line": line,
+                    "character": character
+                }
+            }))
+            .id(id)
+            .finish();
+
+        let resp: Result<Option<Response>, _> =
+            self.service.ready().await.unwrap().call(request).await;
+
+        let resp = resp.expect("hover request failed");
+        let resp = resp.expect("hover should return a response");
+        let (_, result) = resp.into_parts();
+
+        match result {
+            Ok(value) => {
+                let value: serde_json::Value = value;
+                if value.is_null() {
+                    return None;
+                }
+                let hover: Hover =
+                    serde_json::from_value(value).expect("Failed to parse Hover response");
+                match hover.contents {
+                    HoverContents::Markup(markup) => Some(markup.value),
+                    other => panic!("unexpected hover contents: {:?}", other),
+                }
+            }
+            Err(e) => panic!("hover returned error: {:?}", e),
+        }
+    }
+
+    /// 发送跳转定义请求并返回目标位置
+    pub async fn goto_definition(
+        &mut self,
+        uri: &Uri,
+        line: u32,
+        character: u32,
+    ) -> Option<Location> {
+        let id = self.next_id();
+
+        let request = Request::build("textDocument/definition")
+            .params(json!({
+                "textDocument": {
+                    "uri": uri.as_str()
+                },
+                "position": {
+                    "line": line,
+                    "character": character
+                }
+            }))
+            .id(id)
+            .finish();
+
+        let resp: Result<Option<Response>, _> =
+            self.service.ready().await.unwrap().call(request).await;
+
+        let resp = resp.expect("definition request failed");
+        let resp = resp.expect("definition should return a response");
+        let (_, result) = resp.into_parts();
+
+        match result {
+            Ok(value) => {
+                let value: serde_json::Value = value;
+                if value.is_null() {
+                    return None;
+                }
+                match serde_json::from_value(value).expect("Failed to parse definition response") {
+                    GotoDefinitionResponse::Scalar(location) => Some(location),
+                    other => panic!("unexpected definition response: {:?}", other),
+                }
+            }
+            Err(e) => panic!("definition returned error: {:?}", e),
+        }
+    }
+
+    /// 发送查找引用请求并返回引用位置
+    pub async fn references(
+        &mut self,
+        uri: &Uri,
+        line: u32,
+        character: u32,
+        include_declaration: bool,
+    ) -> Option<Vec<Location>> {
+        let id = self.next_id();
+
+        let request = Request::build("textDocument/references")
+            .params(json!({
+                "textDocument": {
+                    "uri": uri.as_str()
+                },
+                "position": {
+                    "line": line,
+                    "character": character
+                },
+                "context": {
+                    "includeDeclaration": include_declaration
+                }
+            }))
+            .id(id)
+            .finish();
+
+        let resp: Result<Option<Response>, _> =
+            self.service.ready().await.unwrap().call(request).await;
+
+        let resp = resp.expect("references request failed");
+        let resp = resp.expect("references should return a response");
+        let (_, result) = resp.into_parts();
+
+        match result {
+            Ok(value) => serde_json::from_value(value).expect("Failed to parse references"),
+            Err(e) => panic!("references returned error: {:?}", e),
+        }
+    }
+
+    /// 发送重命名请求，返回 WorkspaceEdit 或错误
+    pub async fn rename(
+        &mut self,
+        uri: &Uri,
+        line: u32,
+        character: u32,
+        new_name: &str,
+    ) -> Result<Option<WorkspaceEdit>, tower_lsp_server::jsonrpc::Error> {
+        let id = self.next_id();
+
+        let request = Request::build("textDocument/rename")
+            .params(json!({
+                "textDocument": {
+                    "uri": uri.as_str()
+                },
+                "position": {
+                    "line": line,
+                    "character": character
+                },
+                "newName": new_name
+            }))
+            .id(id)
+            .finish();
+
+        let resp: Result<Option<Response>, _> =
+            self.service.ready().await.unwrap().call(request).await;
+
+        let resp = resp.expect("rename request failed");
+        let resp = resp.expect("rename should return a response");
+        let (_, result) = resp.into_parts();
+        result.map(|value| serde_json::from_value(value).expect("Failed to parse WorkspaceEdit"))
+    }
+
+    /// 发送内嵌提示请求，范围为整个文档
+    pub async fn inlay_hints(&mut self, uri: &Uri) -> Vec<InlayHint> {
+        let id = self.next_id();
+
+        let request = Request::build("textDocument/inlayHint")
+            .params(json!({
+                "textDocument": {
+                    "uri": uri.as_str()
+                },
+                "range": {
+                    "start": { "line": 0, "character": 0 },
+                    "end": { "line": u32::MAX, "character": 0 }
+                }
+            }))
+            .id(id)
+            .finish();
+
+        let resp: Result<Option<Response>, _> =
+            self.service.ready().await.unwrap().call(request).await;
+
+        let resp = resp.expect("inlayHint request failed");
+        let resp = resp.expect("inlayHint should return a response");
+        let (_, result) = resp.into_parts();
+
+        match result {
+            Ok(value) => {
+                let hints: Option<Vec<InlayHint>> =
+                    serde_json::from_value(value).expect("Failed to parse InlayHint response");
+                hints.unwrap_or_default()
+            }
+            Err(e) => panic!("inlayHint returned error: {:?}", e),
+        }
+    }
+
+    /// 发送代码操作请求并返回其中的 CodeAction 列表
+    pub async fn code_actions(
+        &mut self,
+        uri: &Uri,
+        range: Range,
+        diagnostics: Vec<Diagnostic>,
+    ) -> Vec<CodeAction> {
+        let id = self.next_id();
+
+        let request = Request::build("textDocument/codeAction")
+            .params(json!({
+                "textDocument": {
+                    "uri": uri.as_str()
+                },
+                "range": range,
+                "context": {
+                    "diagnostics": diagnostics
+                }
+            }))
+            .id(id)
+            .finish();
+
+        let resp: Result<Option<Response>, _> =
+            self.service.ready().await.unwrap().call(request).await;
+
+        let resp = resp.expect("codeAction request failed");
+        let resp = resp.expect("codeAction should return a response");
+        let (_, result) = resp.into_parts();
+
+        match result {
+            Ok(value) => {
+                let actions: Option<CodeActionResponse> =
+                    serde_json::from_value(value).expect("Failed to parse CodeAction response");
+                actions
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|action| match action {
+                        CodeActionOrCommand::CodeAction(action) => Some(action),
+                        CodeActionOrCommand::Command(_) => None,
+                    })
+                    .collect()
+            }
+            Err(e) => panic!("codeAction returned error: {:?}", e),
+        }
+    }
+
     /// 发送格式化请求并返回格式化后的文本
     pub async fn format_document(&mut self, uri: &Uri) -> Option<String> {
         let id = self.next_id();
@@ -221,6 +677,31 @@ impl TestContext {
             Err(e) => panic!("formatting returned error: {:?}", e),
         }
     }
+
+    /// 发送 workspace/executeCommand 请求，返回结果或错误
+    pub async fn execute_command(
+        &mut self,
+        command: &str,
+        arguments: serde_json::Value,
+    ) -> Result<serde_json::Value, tower_lsp_server::jsonrpc::Error> {
+        let id = self.next_id();
+
+        let request = Request::build("workspace/executeCommand")
+            .params(json!({
+                "command": command,
+                "arguments": arguments
+            }))
+            .id(id)
+            .finish();
+
+        let resp: Result<Option<Response>, _> =
+            self.service.ready().await.unwrap().call(request).await;
+
+        let resp = resp.expect("executeCommand request failed");
+        let resp = resp.expect("executeCommand should return a response");
+        let (_, result) = resp.into_parts();
+        result
+    }
 }
 
 /// 后台持续从 ClientSocket 读取通知，将 publishDiagnostics 存入 store
@@ -228,10 +709,10 @@ async fn drain_socket(mut socket: ClientSocket, store: Arc<Mutex<Vec<PublishDiag
     while let Some(notification) = socket.next().await {
         if notification.method() == "textDocument/publishDiagnostics" {
             let (_, _, params) = notification.into_parts();
-            if let Some(params) = params {
-                if let Ok(publish) = serde_json::from_value::<PublishDiagnosticsParams>(params) {
-                    store.lock().await.push(publish);
-                }
+            if let Some(params) = params
+                && let Ok(publish) = serde_json::from_value::<PublishDiagnosticsParams>(params)
+            {
+                store.lock().await.push(publish);
             }
         }
         // 其他通知（log_message 等）直接丢弃
@@ -239,6 +720,7 @@ async fn drain_socket(mut socket: ClientSocket, store: Arc<Mutex<Vec<PublishDiag
 }
 
 /// 获取项目根目录（包含 sample-project/ 的路径）
+#[allow(dead_code)]
 pub fn workspace_root() -> std::path::PathBuf {
     let manifest_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     // sixu-lsp/ -> 项目根目录
@@ -249,12 +731,14 @@ pub fn workspace_root() -> std::path::PathBuf {
 }
 
 /// 获取测试 fixture 目录
+#[allow(dead_code)]
 pub fn fixture_dir() -> std::path::PathBuf {
     let manifest_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     manifest_dir.join("tests").join("fixtures")
 }
 
 /// 详细对比两个字符串，输出差异位置（复用自 cst_format.rs 的风格）
+#[allow(dead_code)]
 pub fn assert_text_eq(actual: &str, expected: &str, test_name: &str) {
     let actual_normalized = actual.replace("\r\n", "\n");
     let expected_normalized = expected.replace("\r\n", "\n");
@@ -0,0 +1,39 @@
+//! 悬停提示集成测试
+//!
+//! 通过 LspService 进程内测试 textDocument/hover。
+
+mod helpers;
+use helpers::*;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hover_includes_author_comments() {
+    let mut ctx = TestContext::new().await;
+    let text = "::entry {\n    // 与上一句无关\n\n    // 进入夜晚\n    @changebg(src=\"night.jpg\") // fades the background\n    @changebg src=\"day.jpg\"\n}\n";
+    let uri = ctx.open_document("file:///test/hover.sixu", text).await;
+    let _ = ctx.read_diagnostics().await;
+
+    let hover = ctx.hover(&uri, 4, 6).await.expect("应返回悬停内容");
+    assert_eq!(
+        hover,
+        "Change background command\n\n---\n\n进入夜晚\n\nfades the background"
+    );
+
+    // 没有相邻注释时只显示 schema 描述
+    let hover = ctx.hover(&uri, 5, 6).await.expect("应返回悬停内容");
+    assert_eq!(hover, "Change background command");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hover_comment_without_schema_definition() {
+    let mut ctx = TestContext::new().await;
+    let text = "::entry {\n    @unknownCmd /* 自定义命令 */\n    @other\n}\n";
+    let uri = ctx
+        .open_document("file:///test/hover_unknown.sixu", text)
+        .await;
+    let _ = ctx.read_diagnostics().await;
+
+    let hover = ctx.hover(&uri, 1, 6).await.expect("应返回悬停内容");
+    assert_eq!(hover, "自定义命令");
+
+    assert_eq!(ctx.hover(&uri, 2, 6).await, None);
+}
@@ -0,0 +1,36 @@
+//! 内嵌提示集成测试
+//!
+//! 使用 tests/fixtures/schema_builder 中的 schema：changebg 的 fadeTime 默认值为 600。
+
+mod helpers;
+use helpers::*;
+use tower_lsp_server::ls_types::*;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_default_value_hints() {
+    let mut ctx = TestContext::with_workspace(fixture_dir().join("schema_builder")).await;
+    let uri = ctx
+        .open_document(
+            "file:///test/inlay.sixu",
+            concat!(
+                "::test {\n",
+                "    @changebg src=\"a.jpg\"\n",
+                "    @changebg(src=\"b.jpg\", fadeTime=100)\n",
+                "    @wait time=1\n",
+                "}\n",
+            ),
+        )
+        .await;
+
+    let hints = ctx.inlay_hints(&uri).await;
+    assert_eq!(hints.len(), 1);
+    let hint = &hints[0];
+    match &hint.label {
+        InlayHintLabel::String(label) => assert_eq!(label, "fadeTime=600"),
+        other => panic!("unexpected label: {:?}", other),
+    }
+    assert_eq!(hint.position, Position::new(1, 25));
+    assert_eq!(hint.padding_left, Some(true));
+    // 只作提示，不可插入
+    assert!(hint.text_edits.is_none());
+}
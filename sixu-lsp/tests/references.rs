@@ -0,0 +1,65 @@
+//! 段落引用查找集成测试
+
+mod helpers;
+use helpers::*;
+use tower_lsp_server::ls_types::*;
+
+const TEXT: &str = concat!(
+    "::start {\n",
+    "    #goto paragraph=\"ending\"\n",
+    "    #call(paragraph=\"ending\", mood=\"ending\")\n",
+    "    #goto story=\"other\" paragraph=\"ending\"\n",
+    "}\n",
+    "\n",
+    "::ending {\n",
+    "    #goto paragraph=\"start\"\n",
+    "}\n",
+);
+
+/// 按 (行, 起始列, 结束列) 列出引用位置
+fn positions(locations: &[Location]) -> Vec<(u32, u32, u32)> {
+    let mut positions: Vec<_> = locations
+        .iter()
+        .map(|location| {
+            (
+                location.range.start.line,
+                location.range.start.character,
+                location.range.end.character,
+            )
+        })
+        .collect();
+    positions.sort();
+    positions
+}
+
+#[tokio::test]
+async fn test_references_from_definition() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx.open_document("file:///references.sixu", TEXT).await;
+
+    let locations = ctx.references(&uri, 6, 3, true).await.expect("应返回引用");
+    assert!(locations.iter().all(|location| location.uri == uri));
+    // 范围只覆盖引号内的段落名，story="other" 的调用不计入
+    assert_eq!(
+        positions(&locations),
+        vec![(1, 21, 27), (2, 21, 27), (6, 2, 8)]
+    );
+
+    let locations = ctx.references(&uri, 6, 3, false).await.expect("应返回引用");
+    assert_eq!(positions(&locations), vec![(1, 21, 27), (2, 21, 27)]);
+}
+
+#[tokio::test]
+async fn test_references_from_goto_value() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx.open_document("file:///references.sixu", TEXT).await;
+
+    let locations = ctx
+        .references(&uri, 2, 23, false)
+        .await
+        .expect("应返回引用");
+    assert_eq!(positions(&locations), vec![(1, 21, 27), (2, 21, 27)]);
+
+    // 其他参数中同名的字符串不是段落引用
+    assert!(ctx.references(&uri, 2, 37, false).await.is_none());
+}
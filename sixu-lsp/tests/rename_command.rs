@@ -0,0 +1,91 @@
+//! 命令重命名集成测试
+//!
+//! 通过 workspace/executeCommand 调用 sixu.renameCommand，检查返回的 WorkspaceEdit。
+
+mod helpers;
+use helpers::*;
+use serde_json::json;
+use sixu_lsp::RENAME_COMMAND;
+use tower_lsp_server::ls_types::*;
+
+const OPEN_TEXT: &str = concat!(
+    "::entry {\n",
+    "    @oldCmd a=1\n",
+    "    \"oldCmd 只是文本\"\n",
+    "    #oldCmd\n",
+    "    {\n",
+    "        #[cond(\"ready\")]\n",
+    "        {\n",
+    "            @oldCmd(b=2)\n",
+    "        }\n",
+    "        @other\n",
+    "    }\n",
+    "    @oldCmdExtra\n",
+    "}\n",
+);
+
+async fn rename(ctx: &mut TestContext) -> WorkspaceEdit {
+    let value = ctx
+        .execute_command(RENAME_COMMAND, json!(["oldCmd", "newCmd"]))
+        .await
+        .expect("renameCommand returned error");
+    serde_json::from_value(value).expect("Failed to parse WorkspaceEdit")
+}
+
+/// 按 (行, 起始列, 结束列) 列出编辑位置，并检查替换文本
+fn positions(edits: &[TextEdit]) -> Vec<(u32, u32, u32)> {
+    assert!(edits.iter().all(|edit| edit.new_text == "newCmd"));
+    let mut positions: Vec<_> = edits
+        .iter()
+        .map(|edit| {
+            assert_eq!(edit.range.start.line, edit.range.end.line);
+            (
+                edit.range.start.line,
+                edit.range.start.character,
+                edit.range.end.character,
+            )
+        })
+        .collect();
+    positions.sort();
+    positions
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rename_open_document_including_nested_blocks() {
+    let mut ctx = TestContext::with_workspace(fixture_dir().join("rename")).await;
+    let uri = ctx.open_document("file:///test/open.sixu", OPEN_TEXT).await;
+
+    let edit = rename(&mut ctx).await;
+    let changes = edit.changes.expect("edit should list changes");
+
+    assert_eq!(positions(&changes[&uri]), vec![(1, 5, 11), (7, 13, 19)]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rename_covers_workspace_files() {
+    let mut ctx = TestContext::with_workspace(fixture_dir().join("rename")).await;
+    ctx.open_document("file:///test/open.sixu", OPEN_TEXT).await;
+
+    let edit = rename(&mut ctx).await;
+    let changes = edit.changes.expect("edit should list changes");
+
+    let closed = Uri::from_file_path(fixture_dir().join("rename").join("closed.sixu")).unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(positions(&changes[&closed]), vec![(2, 5, 11)]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rename_rejects_bad_arguments() {
+    let mut ctx = TestContext::with_workspace(fixture_dir().join("rename")).await;
+
+    assert!(
+        ctx.execute_command(RENAME_COMMAND, json!(["oldCmd"]))
+            .await
+            .is_err()
+    );
+    assert!(
+        ctx.execute_command("sixu.unknown", json!(["a", "b"]))
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,138 @@
+//! 段落重命名集成测试
+
+mod helpers;
+use helpers::*;
+use tower_lsp_server::ls_types::*;
+
+const CHAPTER1: &str = concat!(
+    "::start {\n",
+    "    #goto paragraph=\"ending\"\n",
+    "    {\n",
+    "        #call(paragraph='ending', mood=\"ending\")\n",
+    "    }\n",
+    "    #replace story=\"chapter1\" paragraph=\"ending\"\n",
+    "    #goto story=\"chapter2\" paragraph=\"ending\"\n",
+    "    \"ending 只是文本\"\n",
+    "}\n",
+    "\n",
+    "::ending {\n",
+    "    #goto paragraph=\"start\"\n",
+    "}\n",
+);
+const CHAPTER2_ON_DISK: &str = concat!(
+    "::ending {\n",
+    "    #goto story=\"chapter1\" paragraph=\"ending\"\n",
+    "    #goto paragraph=\"ending\"\n",
+    "}\n",
+);
+
+/// 在临时目录中写入磁盘上的 chapter2.sixu，返回目录
+fn story_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("sixu-lsp-rename-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("chapter2.sixu"), CHAPTER2_ON_DISK).unwrap();
+    dir
+}
+
+fn file_uri(path: &std::path::Path) -> Uri {
+    Uri::from_file_path(path).unwrap()
+}
+
+/// 按 (行, 起始列, 结束列) 列出某个文件的编辑位置，并检查替换文本
+fn positions(edit: &WorkspaceEdit, uri: &Uri, new_name: &str) -> Vec<(u32, u32, u32)> {
+    let changes = edit.changes.as_ref().expect("应返回 changes");
+    let Some(edits) = changes.get(uri) else {
+        return Vec::new();
+    };
+    assert!(edits.iter().all(|edit| edit.new_text == new_name));
+    let mut positions: Vec<_> = edits
+        .iter()
+        .map(|edit| {
+            (
+                edit.range.start.line,
+                edit.range.start.character,
+                edit.range.end.character,
+            )
+        })
+        .collect();
+    positions.sort();
+    positions
+}
+
+/// chapter1 中指向 chapter1::ending 的位置
+const CHAPTER1_ENDING: [(u32, u32, u32); 4] = [(1, 21, 27), (3, 25, 31), (5, 41, 47), (10, 2, 8)];
+
+#[tokio::test]
+async fn test_rename_paragraph_from_definition() {
+    let dir = story_dir("definition");
+    let mut ctx = TestContext::with_workspace(dir.clone()).await;
+    let uri = ctx
+        .open_document(file_uri(&dir.join("chapter1.sixu")).as_str(), CHAPTER1)
+        .await;
+
+    let edit = ctx
+        .rename(&uri, 10, 4, "finale")
+        .await
+        .unwrap()
+        .expect("应返回编辑");
+    assert_eq!(positions(&edit, &uri, "finale"), CHAPTER1_ENDING);
+    // chapter2 中只有显式指向 chapter1 的跳转被修改
+    let chapter2 = file_uri(&dir.join("chapter2.sixu"));
+    assert_eq!(positions(&edit, &chapter2, "finale"), vec![(1, 38, 44)]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_rename_paragraph_from_reference() {
+    let dir = story_dir("reference");
+    let mut ctx = TestContext::with_workspace(dir.clone()).await;
+    let uri = ctx
+        .open_document(file_uri(&dir.join("chapter1.sixu")).as_str(), CHAPTER1)
+        .await;
+
+    // 光标位于 #goto paragraph="ending" 的值上
+    let edit = ctx
+        .rename(&uri, 1, 23, "finale")
+        .await
+        .unwrap()
+        .expect("应返回编辑");
+    assert_eq!(positions(&edit, &uri, "finale"), CHAPTER1_ENDING);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_rename_paragraph_in_other_story() {
+    let dir = story_dir("other");
+    let mut ctx = TestContext::with_workspace(dir.clone()).await;
+    let uri = ctx
+        .open_document(file_uri(&dir.join("chapter1.sixu")).as_str(), CHAPTER1)
+        .await;
+
+    // 光标位于 story="chapter2" 的调用上，只修改 chapter2::ending
+    let edit = ctx
+        .rename(&uri, 6, 43, "finale")
+        .await
+        .unwrap()
+        .expect("应返回编辑");
+    assert_eq!(positions(&edit, &uri, "finale"), vec![(6, 38, 44)]);
+    let chapter2 = file_uri(&dir.join("chapter2.sixu"));
+    assert_eq!(
+        positions(&edit, &chapter2, "finale"),
+        vec![(0, 2, 8), (2, 21, 27)]
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_rename_outside_paragraph_reference() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx.open_document("file:///chapter1.sixu", CHAPTER1).await;
+
+    // 其他参数中同名的字符串和文本不能触发重命名
+    assert!(ctx.rename(&uri, 3, 41, "finale").await.unwrap().is_none());
+    assert!(ctx.rename(&uri, 7, 6, "finale").await.unwrap().is_none());
+    assert!(ctx.rename(&uri, 10, 4, "not valid").await.is_err());
+}
@@ -0,0 +1,83 @@
+//! schema 文件变化后重新加载的集成测试
+
+mod helpers;
+use helpers::*;
+use tower_lsp_server::ls_types::*;
+
+const SCHEMA_WITH_FOO: &str = r#"{
+  "oneOf": [
+    { "properties": { "command": { "type": "string", "const": "foo" } } }
+  ]
+}"#;
+
+const SCHEMA_WITH_BAR: &str = r#"{
+  "oneOf": [
+    { "properties": { "command": { "type": "string", "const": "foo" } } },
+    { "properties": { "command": { "type": "string", "const": "bar" } } }
+  ]
+}"#;
+
+const TEXT: &str = "::start {\n    @foo\n    @bar\n}\n";
+
+/// 在临时目录中写入 commands.schema.json，返回目录
+fn schema_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("sixu-lsp-schema-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("commands.schema.json"), SCHEMA_WITH_FOO).unwrap();
+    dir
+}
+
+fn messages(diagnostics: &[Diagnostic]) -> Vec<&str> {
+    diagnostics.iter().map(|d| d.message.as_str()).collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_schema_reloaded_on_change() {
+    let dir = schema_dir("reload");
+    let mut ctx = TestContext::with_workspace(dir.clone()).await;
+    ctx.open_document("file:///test/schema_reload.sixu", TEXT)
+        .await;
+    assert_eq!(
+        messages(&ctx.read_diagnostics().await),
+        vec!["Unknown command: bar"]
+    );
+
+    let schema_path = dir.join("commands.schema.json");
+    std::fs::write(&schema_path, SCHEMA_WITH_BAR).unwrap();
+    ctx.file_changed(&Uri::from_file_path(&schema_path).unwrap())
+        .await;
+
+    // 已打开的文档按新 schema 重新校验
+    assert!(ctx.read_diagnostics().await.is_empty());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_invalid_schema_keeps_previous() {
+    let dir = schema_dir("invalid");
+    let mut ctx = TestContext::with_workspace(dir.clone()).await;
+    let uri = ctx
+        .open_document("file:///test/schema_invalid.sixu", TEXT)
+        .await;
+    let _ = ctx.read_diagnostics().await;
+
+    let schema_path = dir.join("commands.schema.json");
+    std::fs::write(&schema_path, "{ not json").unwrap();
+    ctx.file_changed(&Uri::from_file_path(&schema_path).unwrap())
+        .await;
+
+    // 解析失败时不重新校验，修改文档后仍按原 schema 校验
+    ctx.change_document(
+        &uri,
+        2,
+        serde_json::json!([{ "text": "::start {\n    @baz\n}\n" }]),
+    )
+    .await;
+    assert_eq!(
+        messages(&ctx.read_diagnostics().await),
+        vec!["Unknown command: baz"]
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,72 @@
+//! 语义高亮集成测试
+
+mod helpers;
+use helpers::*;
+use sixu_lsp::SEMANTIC_TOKEN_TYPES;
+use tower_lsp_server::ls_types::*;
+
+const TEXT: &str = concat!(
+    "::start(count = 3) {\n",
+    "    #[cond(\"ready\")]\n",
+    "    @bg src=\"背景.png\" fade=0.5\n",
+    "    #goto paragraph=\"ending\" times=count\n",
+    "    \"只是文本\"\n",
+    "}\n",
+);
+
+/// 将相对编码还原为 (行, 列, 长度, 类型名, 修饰符)
+fn decode(tokens: &[SemanticToken]) -> Vec<(u32, u32, u32, String, u32)> {
+    let (mut line, mut start) = (0, 0);
+    tokens
+        .iter()
+        .map(|token| {
+            if token.delta_line > 0 {
+                start = 0;
+            }
+            line += token.delta_line;
+            start += token.delta_start;
+            let token_type = SEMANTIC_TOKEN_TYPES[token.token_type as usize]
+                .as_str()
+                .to_string();
+            (
+                line,
+                start,
+                token.length,
+                token_type,
+                token.token_modifiers_bitset,
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_semantic_tokens_full() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx.open_document("file:///semantic.sixu", TEXT).await;
+
+    let tokens = ctx.semantic_tokens(&uri).await;
+    assert_eq!(tokens.len(), 15);
+    let expected: Vec<(u32, u32, u32, &str, u32)> = vec![
+        (0, 2, 5, "class", 1),
+        (0, 8, 5, "parameter", 1),
+        (0, 16, 1, "number", 0),
+        (1, 6, 4, "decorator", 0),
+        (1, 11, 7, "string", 0),
+        (2, 5, 2, "function", 0),
+        (2, 8, 3, "parameter", 0),
+        // 列号和长度按 UTF-16 计算
+        (2, 12, 8, "string", 0),
+        (2, 21, 4, "parameter", 0),
+        (2, 26, 3, "number", 0),
+        (3, 5, 4, "macro", 0),
+        (3, 10, 9, "parameter", 0),
+        (3, 20, 8, "string", 0),
+        (3, 29, 5, "parameter", 0),
+        (3, 35, 5, "variable", 0),
+    ];
+    let expected: Vec<_> = expected
+        .into_iter()
+        .map(|(line, start, len, kind, modifiers)| (line, start, len, kind.to_string(), modifiers))
+        .collect();
+    assert_eq!(decode(&tokens), expected);
+}
@@ -0,0 +1,76 @@
+//! 签名帮助集成测试
+//!
+//! 使用 sample-project/commands.schema.json 中的 changebg 命令。
+
+mod helpers;
+use helpers::*;
+use tower_lsp_server::ls_types::*;
+
+const TEXT: &str = concat!(
+    "::test {\n",
+    "    @changebg(src=\"a, b.jpg\", fadeTime=[1, 2], \n",
+    "    @changebg src=\"test.jpg\"\n",
+    "    @unknown(a=1, \n",
+    "    @changebg(src=\"test.jpg\") \"文本\"\n",
+    "    @changebg(src=\"😀\", fadeTime=1, \n",
+    "}\n",
+);
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_signature_help_for_command() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx.open_document("file:///test/signature.sixu", TEXT).await;
+
+    // 光标紧跟在 ( 之后
+    let help = ctx
+        .signature_help(&uri, 1, 14)
+        .await
+        .expect("应返回签名帮助");
+    let signature = &help.signatures[0];
+    assert_eq!(
+        signature.label,
+        "@changebg(src: string, fadeTime?: number, skippable?: boolean)"
+    );
+    assert_eq!(
+        signature.documentation,
+        Some(Documentation::String(
+            "Change background command".to_string()
+        ))
+    );
+    let parameters = signature.parameters.as_ref().unwrap();
+    assert_eq!(parameters.len(), 3);
+    assert_eq!(
+        parameters[0].documentation,
+        Some(Documentation::String("image source".to_string()))
+    );
+    assert_eq!(help.active_parameter, Some(0));
+
+    // 字符串与数组中的逗号不计入
+    let help = ctx.signature_help(&uri, 1, 30).await.unwrap();
+    assert_eq!(help.active_parameter, Some(1));
+    let help = ctx.signature_help(&uri, 1, 46).await.unwrap();
+    assert_eq!(help.active_parameter, Some(2));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_signature_help_outside_argument_list() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx.open_document("file:///test/signature.sixu", TEXT).await;
+
+    // 无括号语法、未知命令、参数列表已闭合
+    assert!(ctx.signature_help(&uri, 2, 20).await.is_none());
+    assert!(ctx.signature_help(&uri, 3, 18).await.is_none());
+    assert!(ctx.signature_help(&uri, 4, 32).await.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_signature_help_utf16_column() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx.open_document("file:///test/signature.sixu", TEXT).await;
+
+    // 😀 占两个 UTF-16 码元：列 22 在逗号之前，列 24 在逗号之后
+    let help = ctx.signature_help(&uri, 5, 22).await.unwrap();
+    assert_eq!(help.active_parameter, Some(0));
+    let help = ctx.signature_help(&uri, 5, 24).await.unwrap();
+    assert_eq!(help.active_parameter, Some(1));
+}
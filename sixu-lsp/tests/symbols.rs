@@ -0,0 +1,69 @@
+//! 文档符号集成测试
+//!
+//! 通过 LspService 进程内测试 textDocument/documentSymbol。
+
+mod helpers;
+use helpers::*;
+use tower_lsp_server::ls_types::SymbolKind;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_paragraph_symbols() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx
+        .open_document(
+            "file:///test/symbols.sixu",
+            "::entry {\n    hello\n}\n\n::ending {\n    bye\n}\n",
+        )
+        .await;
+    let _ = ctx.read_diagnostics().await;
+
+    let symbols = ctx.document_symbols(&uri).await.expect("应返回文档符号");
+    let names: Vec<_> = symbols.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["entry", "ending"]);
+    assert!(symbols.iter().all(|s| s.children.is_none()));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_group_symbols_nested_under_paragraph() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx
+        .open_document(
+            "file:///test/groups.sixu",
+            concat!(
+                "::entry {\n",
+                "    group \"intro\" {\n",
+                "        hello\n",
+                "        group \"inner\" {\n",
+                "            @wait time=1\n",
+                "        }\n",
+                "    }\n",
+                "    {\n",
+                "        group \"hidden\" {\n",
+                "            bye\n",
+                "        }\n",
+                "    }\n",
+                "}\n",
+            ),
+        )
+        .await;
+    let _ = ctx.read_diagnostics().await;
+
+    let symbols = ctx.document_symbols(&uri).await.expect("应返回文档符号");
+    assert_eq!(symbols.len(), 1);
+
+    let groups = symbols[0].children.as_ref().expect("段落下应有 group 符号");
+    let names: Vec<_> = groups.iter().map(|s| s.name.as_str()).collect();
+    // 无标签的块不产生符号，其中的 group 直接挂在段落下
+    assert_eq!(names, vec!["intro", "hidden"]);
+
+    let intro = &groups[0];
+    assert_eq!(intro.kind, SymbolKind::NAMESPACE);
+    assert_eq!(intro.range.start.line, 1);
+    assert_eq!(intro.range.end.line, 6);
+    assert_eq!(intro.selection_range.start.line, 1);
+    assert_eq!(intro.selection_range.start.character, 10);
+
+    let inner = intro.children.as_ref().expect("group 应嵌套子 group");
+    assert_eq!(inner.len(), 1);
+    assert_eq!(inner[0].name, "inner");
+}
@@ -0,0 +1,84 @@
+//! 增量文档同步测试
+
+mod helpers;
+use helpers::*;
+use ropey::Rope;
+use serde_json::json;
+use sixu_lsp::apply_content_change;
+use tower_lsp_server::ls_types::*;
+
+fn change(range: Option<((u32, u32), (u32, u32))>, text: &str) -> TextDocumentContentChangeEvent {
+    TextDocumentContentChangeEvent {
+        range: range.map(|(start, end)| {
+            Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1))
+        }),
+        range_length: None,
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn test_apply_incremental_change() {
+    let mut rope = Rope::from_str("::a {\n    \"你好😀\" #tag\n}\n");
+
+    // 列号按 UTF-16 计算：😀 占两个码元
+    apply_content_change(&mut rope, &change(Some(((1, 7), (1, 9))), "世界"));
+    assert_eq!(rope.to_string(), "::a {\n    \"你好世界\" #tag\n}\n");
+
+    // 跨行替换与插入
+    apply_content_change(&mut rope, &change(Some(((0, 2), (1, 4))), "b {\n    @bg\n"));
+    assert_eq!(rope.to_string(), "::b {\n    @bg\n\"你好世界\" #tag\n}\n");
+
+    // 超出行尾的列号截断到行尾，不会吞掉换行符
+    apply_content_change(&mut rope, &change(Some(((1, 7), (1, 100))), "(a=1)"));
+    assert_eq!(
+        rope.to_string(),
+        "::b {\n    @bg(a=1)\n\"你好世界\" #tag\n}\n"
+    );
+
+    // 不带范围时替换全文
+    apply_content_change(&mut rope, &change(None, "::c {}\n"));
+    assert_eq!(rope.to_string(), "::c {}\n");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_incremental_did_change() {
+    let mut ctx = TestContext::new().await;
+    let uri = ctx
+        .open_document("file:///test/sync.sixu", "::start {\n    \"hello\"\n}\n")
+        .await;
+    assert!(ctx.read_diagnostics().await.is_empty());
+
+    // 依次应用：改段落名，再删除右花括号
+    ctx.change_document(
+        &uri,
+        2,
+        json!([
+            {
+                "range": { "start": { "line": 0, "character": 2 }, "end": { "line": 0, "character": 7 } },
+                "text": "opening"
+            },
+            {
+                "range": { "start": { "line": 2, "character": 0 }, "end": { "line": 2, "character": 1 } },
+                "text": ""
+            }
+        ]),
+    )
+    .await;
+    assert!(!ctx.read_diagnostics().await.is_empty());
+
+    ctx.change_document(
+        &uri,
+        3,
+        json!([{
+            "range": { "start": { "line": 2, "character": 0 }, "end": { "line": 2, "character": 0 } },
+            "text": "}"
+        }]),
+    )
+    .await;
+    assert!(ctx.read_diagnostics().await.is_empty());
+
+    let symbols = ctx.document_symbols(&uri).await.expect("应返回文档符号");
+    let names: Vec<_> = symbols.iter().map(|symbol| symbol.name.as_str()).collect();
+    assert_eq!(names, vec!["opening"]);
+}
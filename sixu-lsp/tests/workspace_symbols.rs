@@ -0,0 +1,86 @@
+//! 工作区符号搜索集成测试
+//!
+//! 使用 tests/fixtures/workspace_symbols 作为工作区根目录。
+
+mod helpers;
+use helpers::*;
+use tower_lsp_server::ls_types::*;
+
+/// 按 (故事名, 段落名, 行号) 列出符号
+fn summary(symbols: &[SymbolInformation]) -> Vec<(String, String, u32)> {
+    symbols
+        .iter()
+        .map(|symbol| {
+            (
+                symbol.container_name.clone().unwrap_or_default(),
+                symbol.name.clone(),
+                symbol.location.range.start.line,
+            )
+        })
+        .collect()
+}
+
+fn owned(expected: &[(&str, &str, u32)]) -> Vec<(String, String, u32)> {
+    expected
+        .iter()
+        .map(|(story, name, line)| (story.to_string(), name.to_string(), *line))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_workspace_symbols_across_files() {
+    let mut ctx = TestContext::with_workspace(fixture_dir().join("workspace_symbols")).await;
+
+    // 忽略大小写的子串匹配，命中两个文件（含子目录）中的段落
+    let symbols = ctx.workspace_symbols("chapter").await;
+    assert_eq!(
+        summary(&symbols),
+        owned(&[
+            ("chapter1", "chapter_end", 6),
+            ("chapter2", "Chapter_Start", 1)
+        ])
+    );
+    let chapter2 = &symbols[1];
+    assert!(
+        chapter2
+            .location
+            .uri
+            .as_str()
+            .ends_with("/nested/chapter2.sixu")
+    );
+    assert_eq!(chapter2.kind, SymbolKind::CLASS);
+    assert_eq!(chapter2.location.range.start.character, 2);
+
+    let symbols = ctx.workspace_symbols("").await;
+    assert_eq!(symbols.len(), 4);
+    let shake = symbols.iter().find(|s| s.name == "shake").unwrap();
+    assert_eq!(shake.kind, SymbolKind::FUNCTION);
+
+    // 再次搜索命中缓存，结果不变
+    assert_eq!(
+        summary(&ctx.workspace_symbols("chapter").await),
+        owned(&[
+            ("chapter1", "chapter_end", 6),
+            ("chapter2", "Chapter_Start", 1)
+        ])
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_workspace_symbols_prefer_open_documents() {
+    let dir = fixture_dir().join("workspace_symbols");
+    let mut ctx = TestContext::with_workspace(dir.clone()).await;
+    let uri = Uri::from_file_path(dir.join("chapter1.sixu")).unwrap();
+    // 编辑器中未保存的 chapter1：chapter_end 被改名
+    ctx.open_document(uri.as_str(), "::opening {}\n\n::chapter_finale {}\n")
+        .await;
+
+    let symbols = ctx.workspace_symbols("chapter").await;
+    assert_eq!(
+        summary(&symbols),
+        owned(&[
+            ("chapter1", "chapter_finale", 2),
+            ("chapter2", "Chapter_Start", 1)
+        ])
+    );
+}
@@ -0,0 +1,28 @@
+//! Parsing time of a story made of a single very long text line, such as a
+//! minified data blob. Throughput should stay flat as the line grows.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sixu::parser::parse;
+
+fn story_with_line(len: usize) -> String {
+    let line = "0123456789abcdef".repeat(len / 16);
+    format!("::entry {{\n{}\n}}\n", line)
+}
+
+fn long_lines(c: &mut Criterion) {
+    let mut group = c.benchmark_group("long_line");
+    group.sample_size(10);
+
+    for len in [256 * 1024, 512 * 1024, 1024 * 1024] {
+        let input = story_with_line(len);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &input, |b, input| {
+            b.iter(|| parse("bench", input).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, long_lines);
+criterion_main!(benches);
@@ -0,0 +1,39 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sixu::cst::node::CstNode;
+use sixu::cst::parse_tolerant;
+
+fuzz_target!(|data: &[u8]| {
+    let input = String::from_utf8_lossy(data);
+    let root = parse_tolerant("fuzz", &input);
+
+    // top-level nodes must stay inside the input, in order and on char boundaries
+    let mut last_end = 0;
+    for node in &root.nodes {
+        check_node(&input, node);
+        let span = node.span();
+        assert!(
+            span.start >= last_end,
+            "overlapping nodes: {:?}",
+            root.nodes
+        );
+        last_end = span.end;
+    }
+});
+
+fn check_node(input: &str, node: &CstNode) {
+    let span = node.span();
+    assert!(span.start <= span.end, "inverted span {:?}", span);
+    assert!(span.end <= input.len(), "span {:?} out of bounds", span);
+    assert!(input.is_char_boundary(span.start) && input.is_char_boundary(span.end));
+
+    let children = match node {
+        CstNode::Paragraph(para) => &para.block.children,
+        CstNode::Block(block) => &block.children,
+        _ => return,
+    };
+    for child in children {
+        check_node(input, child);
+    }
+}
@@ -0,0 +1,69 @@
+//! Attributes used across a story, so embedders can check upfront that their executor
+//! handles every custom attribute.
+
+use std::collections::BTreeSet;
+
+use crate::format::Story;
+
+impl Story {
+    /// Keywords of every attribute in the story, both on paragraphs like
+    /// `#[platform("mobile")]` and on children like `#[cond("x")]`, including those in
+    /// nested blocks. Built-in attributes are reported alongside custom ones.
+    pub fn used_attributes(&self) -> BTreeSet<String> {
+        let mut attributes = BTreeSet::new();
+        for paragraph in &self.paragraphs {
+            let children = paragraph.block.descendants();
+            let child_attributes = children.flat_map(|child| &child.attributes);
+            for attribute in paragraph.attributes.iter().chain(child_attributes) {
+                attributes.insert(attribute.keyword.clone());
+            }
+        }
+        attributes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::parser::parse_story;
+
+    #[test]
+    fn test_collects_nested_and_custom_attributes() {
+        let story = parse_story(
+            "main",
+            r#"
+#[platform("mobile")]
+::entry {
+    #[cond("ready")]
+    @start
+    #[while("count < 3")]
+    {
+        #[fadeIn]
+        {
+            @flash
+        }
+        #[cond("count == 1")]
+        #break
+    }
+}
+
+::other {
+    @plain
+}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            story.used_attributes(),
+            BTreeSet::from(["cond", "fadeIn", "platform", "while"].map(String::from))
+        );
+    }
+
+    #[test]
+    fn test_story_without_attributes() {
+        let story = parse_story("main", "::entry {\n    @start\n}\n").unwrap();
+        assert!(story.used_attributes().is_empty());
+    }
+}
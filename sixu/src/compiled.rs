@@ -0,0 +1,49 @@
+//! Compiled binary form of stories, so games can ship pre-parsed scripts instead of
+//! re-parsing source text at startup.
+//!
+//! A compiled story is a small header, the magic bytes `SIXU` followed by the
+//! [`COMPILED_FORMAT_VERSION`] as a little-endian `u16`, and the story encoded as
+//! MessagePack. The encoding keeps field names, because the serde representation of the
+//! AST (untagged literals, skipped optional fields) needs a self-describing format.
+
+use crate::error::CompiledError;
+use crate::format::Story;
+use crate::parser::parse_story;
+
+/// Magic bytes at the start of every compiled story
+const MAGIC: &[u8; 4] = b"SIXU";
+
+/// Version of the compiled format, bumped whenever the AST changes incompatibly
+pub const COMPILED_FORMAT_VERSION: u16 = 1;
+
+/// Parse `text` as the story `name` and compile it, see [`compile_story`]
+pub fn compile(name: &str, text: &str) -> Result<Vec<u8>, CompiledError> {
+    compile_story(&parse_story(name, text)?)
+}
+
+/// Compile a parsed story into its binary form
+pub fn compile_story(story: &Story) -> Result<Vec<u8>, CompiledError> {
+    let mut bytes = Vec::from(&MAGIC[..]);
+    bytes.extend_from_slice(&COMPILED_FORMAT_VERSION.to_le_bytes());
+    rmp_serde::encode::write_named(&mut bytes, story)?;
+    Ok(bytes)
+}
+
+/// Load a story compiled by [`compile`] or [`compile_story`].
+///
+/// Fails with `VersionMismatch` when the story was compiled by an incompatible version.
+pub fn load_compiled(bytes: &[u8]) -> Result<Story, CompiledError> {
+    let body = bytes
+        .strip_prefix(&MAGIC[..])
+        .filter(|rest| rest.len() >= 2)
+        .ok_or(CompiledError::InvalidHeader)?;
+    let (version, body) = body.split_at(2);
+    let version = u16::from_le_bytes([version[0], version[1]]);
+    if version != COMPILED_FORMAT_VERSION {
+        return Err(CompiledError::VersionMismatch {
+            found: version,
+            expected: COMPILED_FORMAT_VERSION,
+        });
+    }
+    Ok(rmp_serde::from_slice(body)?)
+}
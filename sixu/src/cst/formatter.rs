@@ -3,24 +3,116 @@
 /// This formatter preserves all comments and produces formatted output
 /// with consistent spacing, indentation, and line breaks.
 use crate::cst::node::*;
+use crate::cst::span::{count_line_breaks, is_line_break, split_lines};
+use crate::parser::comment::record_comments;
+
+/// 零参数的命令和系统调用的写法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyCallStyle {
+    /// 去掉空括号：`@cmd()` 格式化为 `@cmd`
+    Bare,
+    /// 总是带空括号：`@cmd` 格式化为 `@cmd()`
+    Parenthesized,
+    /// 保留原文的写法。有的引擎会区分 `@cmd`（标志）与 `@cmd()`（显式调用）
+    #[default]
+    Preserve,
+}
 
-pub struct CstFormatter {
-    indent_size: usize,
+/// 输出使用的换行符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`（Windows 风格）
+    CrLf,
+    /// 单独的 `\r`（旧版 Mac 风格）
+    Cr,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
 }
 
-impl Default for CstFormatter {
+/// 格式化选项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// 每级缩进的空格数
+    pub indent_size: usize,
+    /// 零参数调用的写法
+    pub empty_call_style: EmptyCallStyle,
+    /// 字符串和文本统一使用的引号，`None` 或 `Backtick` 时保留原文的引号
+    pub quote_style: Option<QuoteStyle>,
+    /// 最大行宽（按字符计）。括号语法的调用写成一行超过该宽度时，每个参数独占一行
+    pub max_width: Option<usize>,
+    /// 连续空行最多保留的行数，超出的部分被合并
+    pub max_blank_lines: usize,
+    /// 输出的换行符，原文中的各种换行符统一换成它
+    pub line_ending: LineEnding,
+}
+
+impl Default for FormatOptions {
     fn default() -> Self {
-        Self { indent_size: 4 }
+        Self {
+            indent_size: 4,
+            empty_call_style: EmptyCallStyle::default(),
+            quote_style: None,
+            max_width: None,
+            max_blank_lines: 1,
+            line_ending: LineEnding::default(),
+        }
     }
 }
 
+#[derive(Default)]
+pub struct CstFormatter {
+    options: FormatOptions,
+}
+
 impl CstFormatter {
     pub fn new() -> Self {
         Self::default()
     }
 
     pub fn with_indent(indent_size: usize) -> Self {
-        Self { indent_size }
+        Self::with_options(FormatOptions {
+            indent_size,
+            ..Default::default()
+        })
+    }
+
+    pub fn with_options(options: FormatOptions) -> Self {
+        Self { options }
+    }
+
+    /// 统一字符串和文本的引号，切换引号时按需重新转义
+    pub fn with_quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.options.quote_style = Some(quote_style);
+        self
+    }
+
+    /// 括号语法的调用超过 `max_width` 个字符时，每个参数独占一行
+    pub fn with_max_width(mut self, max_width: usize) -> Self {
+        self.options.max_width = Some(max_width);
+        self
+    }
+
+    /// 连续空行最多保留 `max_blank_lines` 行，为 0 时删除所有空行（段落之间的空行除外）
+    pub fn with_max_blank_lines(mut self, max_blank_lines: usize) -> Self {
+        self.options.max_blank_lines = max_blank_lines;
+        self
+    }
+
+    /// 输出使用 `line_ending` 换行
+    pub fn with_line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.options.line_ending = line_ending;
+        self
     }
 
     /// Format a CST root node into a string
@@ -36,7 +128,28 @@ impl CstFormatter {
             output.push('\n');
         }
 
-        output
+        self.convert_line_endings(output)
+    }
+
+    /// 把输出中的 `\r\n`、`\n` 和单独的 `\r` 统一换成配置的换行符
+    fn convert_line_endings(&self, output: String) -> String {
+        let line_ending = self.options.line_ending.as_str();
+        if line_ending == "\n" && !output.contains('\r') {
+            return output;
+        }
+        let mut converted = String::with_capacity(output.len());
+        let mut chars = output.chars().peekable();
+        while let Some(ch) = chars.next() {
+            if is_line_break(ch) {
+                if ch == '\r' {
+                    chars.next_if_eq(&'\n');
+                }
+                converted.push_str(line_ending);
+            } else {
+                converted.push(ch);
+            }
+        }
+        converted
     }
 
     fn format_node(&self, node: &CstNode, indent_level: usize, output: &mut String) {
@@ -50,8 +163,9 @@ impl CstFormatter {
             CstNode::EmbeddedCode(code) => self.format_embedded_code(code, indent_level, output),
             CstNode::Attribute(attr) => self.format_attribute(attr, indent_level, output),
             CstNode::Error { content, .. } => {
-                // 保留错误节点的原始内容
-                output.push_str(content);
+                // 保留错误节点的原始内容，与其他节点一样缩进，再次格式化时不会变化
+                self.indent(indent_level, output);
+                output.push_str(content.trim_start());
                 output.push('\n');
             }
         }
@@ -59,11 +173,18 @@ impl CstFormatter {
 
     fn format_trivia(&self, trivia: &CstTrivia, indent_level: usize, output: &mut String) {
         match trivia {
-            CstTrivia::Whitespace { content, .. } => {
-                // 处理空行：如果包含2个或以上换行符（表示源码中有空行），输出一个空行
-                let newline_count = content.chars().filter(|&c| c == '\n').count();
-                if newline_count >= 2 {
-                    // 多个换行符，输出一个空行
+            CstTrivia::Whitespace { content, span } => {
+                // 处理空行：n 个换行符表示源码中有 n - 1 个空行，最多保留 max_blank_lines 个。
+                // `##` 代码块与错误节点会吞掉行尾的换行符，其后的空白从行首开始，
+                // 此时一个换行符就意味着空行，否则再次格式化时空行会丢失
+                let newline_count = count_line_breaks(content);
+                let at_line_start = span.start_column == 0 && !output.is_empty();
+                let blank_lines = if at_line_start {
+                    newline_count
+                } else {
+                    newline_count.saturating_sub(1)
+                };
+                for _ in 0..blank_lines.min(self.options.max_blank_lines) {
                     output.push('\n');
                 }
             }
@@ -75,7 +196,7 @@ impl CstFormatter {
             }
             CstTrivia::BlockComment { content, .. } => {
                 // 多行注释需要特殊处理
-                let lines: Vec<&str> = content.lines().collect();
+                let lines = split_lines(content);
 
                 if lines.len() <= 1 {
                     // 单行注释：/* content */
@@ -90,12 +211,12 @@ impl CstFormatter {
                     for line in &lines {
                         let trimmed = line.trim();
                         // 剥除已有的 * 前缀（避免重复格式化时不断叠加 *）
-                        let stripped = if trimmed.starts_with("* ") {
-                            &trimmed[2..]
+                        let stripped = if let Some(rest) = trimmed.strip_prefix("* ") {
+                            rest
                         } else if trimmed == "*" {
                             ""
-                        } else if trimmed.starts_with('*') {
-                            &trimmed[1..]
+                        } else if let Some(rest) = trimmed.strip_prefix('*') {
+                            rest
                         } else {
                             trimmed
                         };
@@ -137,8 +258,11 @@ impl CstFormatter {
             output.push('\n');
         }
 
-        // ::name
+        // ::name 或 ::macro name
         output.push_str("::");
+        if para.macro_keyword.is_some() {
+            output.push_str("macro ");
+        }
         output.push_str(&para.name);
 
         // 参数
@@ -159,6 +283,10 @@ impl CstFormatter {
 
     fn format_parameter(&self, param: &CstParameter, output: &mut String) {
         output.push_str(&param.name);
+        if let Some(ref type_name) = param.type_ {
+            output.push_str(": ");
+            output.push_str(type_name);
+        }
         if let Some(ref default_value) = param.default_value {
             output.push('=');
             self.format_value(default_value, output);
@@ -170,6 +298,11 @@ impl CstFormatter {
         if indent_level > 0 {
             self.indent(indent_level, output);
         }
+        if let Some(label) = &block.label {
+            output.push_str("group ");
+            output.push_str(&label.raw);
+            output.push(' ');
+        }
         output.push_str("{\n");
 
         for child in &block.children {
@@ -185,10 +318,27 @@ impl CstFormatter {
         output.push_str("#[");
         output.push_str(&attr.keyword);
         if let Some(condition) = &attr.condition {
-            output.push_str("(\"");
+            // 条件字符串不支持转义，内容含有首选引号时改用另一种引号
+            let preferred = match self.options.quote_style {
+                Some(QuoteStyle::Single) => '\'',
+                _ => '"',
+            };
+            let quote = if condition.contains(preferred) {
+                if preferred == '"' {
+                    '\''
+                } else {
+                    '"'
+                }
+            } else {
+                preferred
+            };
+            output.push('(');
+            output.push(quote);
             output.push_str(condition);
-            output.push_str("\")");
+            output.push(quote);
+            output.push(')');
         }
+        // 属性作用于下一条语句，单独占一行并紧贴其后的语句
         output.push_str("]\n");
     }
 
@@ -197,30 +347,10 @@ impl CstFormatter {
 
         output.push('@');
         output.push_str(&cmd.command);
-
-        if !cmd.arguments.is_empty() {
-            match cmd.syntax {
-                CommandSyntax::Parenthesized { .. } => {
-                    // 括号语法：@cmd(a=1, b=2)
-                    output.push('(');
-                    for (i, arg) in cmd.arguments.iter().enumerate() {
-                        if i > 0 {
-                            output.push_str(", ");
-                        }
-                        self.format_argument(arg, output);
-                    }
-                    output.push(')');
-                }
-                CommandSyntax::SpaceSeparated => {
-                    // 空格分隔：@cmd a=1 b=2
-                    for arg in &cmd.arguments {
-                        output.push(' ');
-                        self.format_argument(arg, output);
-                    }
-                }
-            }
+        // 宏调用 `@@name` 没有参数列表，加上空括号会变成其后的一行文本
+        if cmd.macro_name().is_none() {
+            self.format_arguments(&cmd.arguments, &cmd.syntax, indent_level, output);
         }
-
         output.push('\n');
     }
 
@@ -229,31 +359,125 @@ impl CstFormatter {
 
         output.push('#');
         output.push_str(&call.command);
+        self.format_arguments(&call.arguments, &call.syntax, indent_level, output);
+        output.push('\n');
+    }
 
-        if !call.arguments.is_empty() {
-            match call.syntax {
-                CommandSyntax::Parenthesized { .. } => {
-                    // 括号语法：#goto(paragraph="main")
-                    output.push('(');
-                    for (i, arg) in call.arguments.iter().enumerate() {
-                        if i > 0 {
-                            output.push_str(", ");
-                        }
-                        self.format_argument(arg, output);
+    fn format_arguments(
+        &self,
+        arguments: &[CstArgument],
+        syntax: &CommandSyntax,
+        indent_level: usize,
+        output: &mut String,
+    ) {
+        if arguments.is_empty() {
+            let parenthesized = match self.options.empty_call_style {
+                EmptyCallStyle::Bare => false,
+                EmptyCallStyle::Parenthesized => true,
+                EmptyCallStyle::Preserve => {
+                    matches!(syntax, CommandSyntax::Parenthesized { .. })
+                }
+            };
+            if parenthesized {
+                output.push_str("()");
+            }
+            return;
+        }
+
+        match syntax {
+            CommandSyntax::Parenthesized { .. } => {
+                // 括号语法：@cmd(a=1, b=2)
+                let mut inline = String::from("(");
+                for (i, arg) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        inline.push_str(", ");
                     }
-                    output.push(')');
+                    self.format_argument(arg, &mut inline);
+                }
+                inline.push(')');
+
+                if Self::has_argument_comments(arguments) || self.exceeds_max_width(output, &inline)
+                {
+                    self.format_arguments_multiline(arguments, indent_level, output);
+                } else {
+                    output.push_str(&inline);
+                }
+            }
+            CommandSyntax::SpaceSeparated => {
+                // 空格分隔：@cmd a=1 b=2
+                for arg in arguments {
+                    output.push(' ');
+                    self.format_argument(arg, output);
+                }
+            }
+        }
+    }
+
+    /// 在当前行末尾接上 `text` 后是否超过最大行宽
+    fn exceeds_max_width(&self, output: &str, text: &str) -> bool {
+        let Some(max_width) = self.options.max_width else {
+            return false;
+        };
+        let line_start = output.rfind('\n').map_or(0, |i| i + 1);
+        let first_line = text.split('\n').next().unwrap_or_default();
+        output[line_start..].chars().count() + first_line.chars().count() > max_width
+    }
+
+    /// 每个参数独占一行，注释跟随所属参数：
+    ///
+    /// ```text
+    /// @cmd(
+    ///     a=1, // 注释
+    ///     b=2
+    /// )
+    /// ```
+    fn format_arguments_multiline(
+        &self,
+        arguments: &[CstArgument],
+        indent_level: usize,
+        output: &mut String,
+    ) {
+        output.push_str("(\n");
+        for (i, arg) in arguments.iter().enumerate() {
+            for trivia in &arg.leading_trivia {
+                if !matches!(trivia, CstTrivia::Whitespace { .. }) {
+                    self.format_trivia(trivia, indent_level + 1, output);
                 }
-                CommandSyntax::SpaceSeparated => {
-                    // 空格分隔：#goto paragraph="main"
-                    for arg in &call.arguments {
-                        output.push(' ');
-                        self.format_argument(arg, output);
+            }
+
+            self.indent(indent_level + 1, output);
+            self.format_argument(arg, output);
+            if i + 1 < arguments.len() {
+                output.push(',');
+            }
+            for trivia in &arg.trailing_trivia {
+                match trivia {
+                    CstTrivia::Whitespace { .. } => {}
+                    CstTrivia::LineComment { content, .. } => {
+                        output.push_str(" //");
+                        output.push_str(content);
+                    }
+                    CstTrivia::BlockComment { content, .. } => {
+                        output.push_str(" /*");
+                        output.push_str(content);
+                        output.push_str("*/");
                     }
                 }
             }
+            output.push('\n');
         }
+        self.indent(indent_level, output);
+        output.push(')');
+    }
 
-        output.push('\n');
+    /// 参数的前导或尾随 trivia 中是否有注释
+    fn has_argument_comments(arguments: &[CstArgument]) -> bool {
+        arguments.iter().any(|arg| {
+            arg.leading_trivia
+                .iter()
+                .chain(&arg.trailing_trivia)
+                .any(|trivia| !matches!(trivia, CstTrivia::Whitespace { .. }))
+        })
     }
 
     fn format_argument(&self, arg: &CstArgument, output: &mut String) {
@@ -266,15 +490,107 @@ impl CstFormatter {
 
     fn format_value(&self, value: &CstValue, output: &mut String) {
         // 数组类型统一规范化为紧缩格式（不含空格），其余类型直接输出原始文本
+        // 含对象的数组保留原文，因为对象的解析结果不保留键的顺序；含注释的数组也保留原文
         if matches!(value.kind, CstValueKind::Array) {
             if let crate::format::RValue::Literal(lit) = &value.parsed {
-                output.push_str(&Self::format_literal_compact(lit));
-                return;
+                if !Self::contains_object(lit) && !Self::contains_comment(&value.raw) {
+                    output.push_str(&Self::format_literal_compact(lit));
+                    return;
+                }
             }
         }
+        // 对象按原顺序输出为单行 `{key: value, ...}`
+        if let CstValueKind::Object { entries } = &value.kind {
+            output.push('{');
+            for (i, entry) in entries.iter().enumerate() {
+                if i > 0 {
+                    output.push_str(", ");
+                }
+                output.push_str(&entry.key_raw);
+                output.push_str(": ");
+                self.format_value(&entry.value, output);
+            }
+            output.push('}');
+            return;
+        }
+        if matches!(value.kind, CstValueKind::String { .. }) {
+            output.push_str(&self.requote(&value.raw));
+            return;
+        }
         output.push_str(&value.raw);
     }
 
+    /// 按 `quote_style` 改写带引号的字符串原文
+    ///
+    /// 内容中同时含有单双引号的字符串无论用哪种引号都需要转义，保留原文以减少改动
+    fn requote<'a>(&self, raw: &'a str) -> std::borrow::Cow<'a, str> {
+        use std::borrow::Cow;
+
+        let target = match self.options.quote_style {
+            Some(QuoteStyle::Double) => '"',
+            Some(QuoteStyle::Single) => '\'',
+            Some(QuoteStyle::Backtick) | None => return Cow::Borrowed(raw),
+        };
+        let Some(quote) = raw.chars().next().filter(|c| matches!(c, '"' | '\'')) else {
+            return Cow::Borrowed(raw);
+        };
+        if quote == target || raw.len() < 2 || !raw.ends_with(quote) {
+            return Cow::Borrowed(raw);
+        }
+
+        let inner = &raw[1..raw.len() - 1];
+        let mut result = String::with_capacity(raw.len() + 2);
+        let mut has_quote = false;
+        let mut has_target = false;
+        result.push(target);
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                match chars.next() {
+                    // 原引号在新引号中无需转义
+                    Some(next) if next == quote => {
+                        has_quote = true;
+                        result.push(next);
+                    }
+                    Some(next) => {
+                        has_target |= next == target;
+                        result.push('\\');
+                        result.push(next);
+                    }
+                    None => result.push('\\'),
+                }
+            } else if c == target {
+                has_target = true;
+                result.push('\\');
+                result.push(c);
+            } else {
+                has_quote |= c == quote;
+                result.push(c);
+            }
+        }
+        result.push(target);
+
+        if has_quote && has_target {
+            Cow::Borrowed(raw)
+        } else {
+            Cow::Owned(result)
+        }
+    }
+
+    /// 数组的原文中是否有注释
+    fn contains_comment(raw: &str) -> bool {
+        let (_, comments) = record_comments(raw, || crate::parser::primitive::array(raw).is_ok());
+        !comments.is_empty()
+    }
+
+    fn contains_object(lit: &crate::format::Literal) -> bool {
+        match lit {
+            crate::format::Literal::Object(_) => true,
+            crate::format::Literal::Array(elements) => elements.iter().any(Self::contains_object),
+            _ => false,
+        }
+    }
+
     /// 将 Literal 格式化为紧缩形式（数组内部无空格）
     fn format_literal_compact(lit: &crate::format::Literal) -> String {
         use crate::format::Literal;
@@ -286,8 +602,8 @@ impl CstFormatter {
                     .collect();
                 format!("[{}]", parts.join(","))
             }
-            Literal::String(s) => format!("\"{}\"", s),
-            other => other.to_string(),
+            // 字符串按需转义，浮点数保留小数点
+            other => crate::source::literal_source(other),
         }
     }
 
@@ -303,6 +619,15 @@ impl CstFormatter {
             self.format_text(main_text, output);
         }
 
+        for segment in &text.segments {
+            output.push(' ');
+            self.format_leading_text(&segment.leading, output);
+            if let Some(ref segment_text) = segment.text {
+                output.push(' ');
+                self.format_text(segment_text, output);
+            }
+        }
+
         if let Some(ref tailing) = text.tailing {
             output.push(' ');
             self.format_tailing_text(tailing, output);
@@ -326,19 +651,43 @@ impl CstFormatter {
 
     fn format_text(&self, text: &CstText, output: &mut String) {
         // raw 字段已经包含了引号等原始文本
-        output.push_str(&text.raw);
+        if matches!(text.kind, CstTextKind::Quoted(_)) {
+            output.push_str(&self.requote(&text.raw));
+        } else {
+            output.push_str(&text.raw);
+        }
     }
 
     fn format_template_literal(&self, tpl: &CstTemplateLiteral, output: &mut String) {
-        for part in &tpl.parts {
+        self.format_template_parts(&tpl.parts, output);
+    }
+
+    fn format_template_parts(&self, parts: &[CstTemplatePart], output: &mut String) {
+        for part in parts {
             match part {
                 CstTemplatePart::Text { content, .. } => {
                     output.push_str(content);
                 }
-                CstTemplatePart::Value { variable, .. } => {
+                CstTemplatePart::Value { value, .. } => {
                     output.push_str("${");
-                    output.push_str(&variable.chain.join("."));
+                    output.push_str(&value.raw);
+                    output.push('}');
+                }
+                CstTemplatePart::Conditional {
+                    condition,
+                    then,
+                    otherwise,
+                    ..
+                } => {
+                    output.push_str("${?");
+                    output.push_str(&condition.raw);
                     output.push('}');
+                    self.format_template_parts(then, output);
+                    if !otherwise.is_empty() {
+                        output.push_str("${:}");
+                        self.format_template_parts(otherwise, output);
+                    }
+                    output.push_str("${/}");
                 }
             }
         }
@@ -358,7 +707,7 @@ impl CstFormatter {
         match code.syntax {
             EmbeddedCodeSyntax::Brace => {
                 let trimmed_code = code.code.trim();
-                if trimmed_code.contains('\n') {
+                if trimmed_code.contains(is_line_break) {
                     // 多行语法：@{ \n code \n }
                     self.indent(indent_level, output);
                     output.push_str("@{\n");
@@ -366,10 +715,7 @@ impl CstFormatter {
                     // 先去除尾部所有空白（包括 } 前的缩进空格），再去除首部换行。
                     // 不能只用 trim_matches(\n|\r)，因为 parser 会把 } 前的缩进空格
                     // 也捕获进 code.code，若不 trim 空格，每轮格式化会多出一行"空行"。
-                    let code_content = code
-                        .code
-                        .trim_end()
-                        .trim_start_matches(|c: char| c == '\n' || c == '\r');
+                    let code_content = code.code.trim_end().trim_start_matches(['\n', '\r']);
                     output.push_str(code_content);
                     output.push('\n');
 
@@ -385,7 +731,7 @@ impl CstFormatter {
             }
             EmbeddedCodeSyntax::Hash => {
                 let trimmed_code = code.code.trim();
-                if trimmed_code.contains('\n') {
+                if trimmed_code.contains(is_line_break) {
                     // 多行语法：开始和结束标记在独立的行上，代码内容保留原样
                     self.indent(indent_level, output);
                     output.push_str("##\n");
@@ -408,7 +754,7 @@ impl CstFormatter {
     }
 
     fn indent(&self, level: usize, output: &mut String) {
-        for _ in 0..(level * self.indent_size) {
+        for _ in 0..(level * self.options.indent_size) {
             output.push(' ');
         }
     }
@@ -429,6 +775,215 @@ mod tests {
         assert!(result.contains("@command(arg=1)"));
     }
 
+    #[test]
+    fn test_format_template_literal_values() {
+        let input = "::p {\n    `count: ${42}, ${1_000}, ${0xff}, ${false}, ${\"s\"}`\n}\n";
+        let formatter = CstFormatter::new();
+        assert_eq!(formatter.format(&parse_tolerant("test", input)), input);
+    }
+
+    #[test]
+    fn test_format_template_variable_chain() {
+        let input = "::p {\n    `${player.name}: ${?a.b.c}yes${:}no${/}`\n}\n";
+        let formatter = CstFormatter::new();
+        assert_eq!(formatter.format(&parse_tolerant("test", input)), input);
+    }
+
+    #[test]
+    fn test_format_empty_call_style() {
+        let input = "::p {\n    @a()\n    @b\n    #leave()\n    #finish\n}\n";
+        let cases = [
+            (
+                EmptyCallStyle::Bare,
+                "::p {\n    @a\n    @b\n    #leave\n    #finish\n}\n",
+            ),
+            (
+                EmptyCallStyle::Parenthesized,
+                "::p {\n    @a()\n    @b()\n    #leave()\n    #finish()\n}\n",
+            ),
+            (EmptyCallStyle::Preserve, input),
+        ];
+
+        let macros = include_str!("../../tests/fixtures/format/source/12_macro.sixu");
+        for (style, expected) in cases {
+            let formatter = CstFormatter::with_options(FormatOptions {
+                empty_call_style: style,
+                ..Default::default()
+            });
+            let result = formatter.format(&parse_tolerant("test", input));
+            assert_eq!(result, expected, "{:?}", style);
+
+            // 再次格式化结果不变
+            let again = formatter.format(&parse_tolerant("test", &result));
+            assert_eq!(again, result, "{:?}", style);
+            assert_eq!(
+                crate::parser::parse_story("test", &result).unwrap(),
+                crate::parser::parse_story("test", input).unwrap()
+            );
+
+            // 宏调用不加空括号
+            let result = formatter.format(&parse_tolerant("test", macros));
+            assert!(!result.contains("@@wait(") && !result.contains("@@fadeOut("));
+            assert_eq!(
+                crate::parser::parse_story("test", &result).unwrap(),
+                crate::parser::parse_story("test", macros).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_number_units() {
+        use crate::cst::parser::parse_tolerant_with_config;
+        use crate::parser::ParserConfig;
+
+        let config = ParserConfig {
+            number_units: true,
+            ..Default::default()
+        };
+        let input = "::p {\n    @fade time=600ms delay=1.5s\n    @move(x=50%, y=-20px)\n}\n";
+        let result =
+            CstFormatter::new().format(&parse_tolerant_with_config("test", input, &config));
+        // 单位后缀原样保留
+        assert_eq!(result, input);
+    }
+
+    #[test]
+    fn test_format_quote_style() {
+        let input = concat!(
+            "::p {\n",
+            "    @show(a='x', b=\"y\", c='say \"hi\"', d='it\\'s', e={k: 'v'})\n",
+            "    @show(mixed='it\\'s \"x\"', esc='a\\nb\\u{4E2D}')\n",
+            "    'single text'\n",
+            "    [bob] \"double text\"\n",
+            "    `template ${'s'}`\n",
+            "}\n",
+        );
+        let cases = [
+            (
+                QuoteStyle::Double,
+                concat!(
+                    "::p {\n",
+                    "    @show(a=\"x\", b=\"y\", c=\"say \\\"hi\\\"\", d=\"it's\", e={k: \"v\"})\n",
+                    "    @show(mixed='it\\'s \"x\"', esc=\"a\\nb\\u{4E2D}\")\n",
+                    "    \"single text\"\n",
+                    "    [bob] \"double text\"\n",
+                    "    `template ${'s'}`\n",
+                    "}\n",
+                ),
+            ),
+            (
+                QuoteStyle::Single,
+                concat!(
+                    "::p {\n",
+                    "    @show(a='x', b='y', c='say \"hi\"', d='it\\'s', e={k: 'v'})\n",
+                    "    @show(mixed='it\\'s \"x\"', esc='a\\nb\\u{4E2D}')\n",
+                    "    'single text'\n",
+                    "    [bob] 'double text'\n",
+                    "    `template ${'s'}`\n",
+                    "}\n",
+                ),
+            ),
+        ];
+
+        for (style, expected) in cases {
+            let formatter = CstFormatter::new().with_quote_style(style);
+            let result = formatter.format(&parse_tolerant("test", input));
+            assert_eq!(result, expected, "{:?}", style);
+
+            let again = formatter.format(&parse_tolerant("test", &result));
+            assert_eq!(again, result, "{:?}", style);
+            assert_eq!(
+                crate::parser::parse_story("test", &result).unwrap(),
+                crate::parser::parse_story("test", input).unwrap()
+            );
+        }
+
+        // 默认保留原文的引号
+        let result = CstFormatter::new().format(&parse_tolerant("test", input));
+        assert_eq!(result, input);
+    }
+
+    #[test]
+    fn test_format_max_width() {
+        let formatter = CstFormatter::new().with_max_width(40);
+
+        // 一行放得下的调用保持原样
+        let fits = "::p {\n    @show(src=\"a.png\", x=1)\n    #goto(paragraph=\"b\")\n}\n";
+        assert_eq!(formatter.format(&parse_tolerant("test", fits)), fits);
+
+        // 超出宽度的括号语法调用每个参数独占一行，空格分隔语法不变
+        let input = concat!(
+            "::p {\n",
+            "    {\n",
+            "        @show(src=\"background.png\", x=100, y=200)\n",
+            "    }\n",
+            "    #goto(paragraph=\"a_long_target\", story=\"chapter\")\n",
+            "    @show src=\"background.png\" x=100 y=200 fade=true\n",
+            "}\n",
+        );
+        let expected = concat!(
+            "::p {\n",
+            "    {\n",
+            "        @show(\n",
+            "            src=\"background.png\",\n",
+            "            x=100,\n",
+            "            y=200\n",
+            "        )\n",
+            "    }\n",
+            "    #goto(\n",
+            "        paragraph=\"a_long_target\",\n",
+            "        story=\"chapter\"\n",
+            "    )\n",
+            "    @show src=\"background.png\" x=100 y=200 fade=true\n",
+            "}\n",
+        );
+        let result = formatter.format(&parse_tolerant("test", input));
+        assert_eq!(result, expected);
+
+        // 换行后的结果再次格式化不变，且语义不变
+        assert_eq!(formatter.format(&parse_tolerant("test", &result)), result);
+        assert_eq!(
+            crate::parser::parse_story("test", &result).unwrap(),
+            crate::parser::parse_story("test", input).unwrap()
+        );
+        // 不限制宽度时换行的调用重新合并为一行
+        assert_eq!(
+            CstFormatter::new().format(&parse_tolerant("test", &result)),
+            input
+        );
+    }
+
+    #[test]
+    fn test_format_max_blank_lines() {
+        let input = "::p {\n    @a\n\n\n\n    @b\n\n    @c\n    @d\n}\n";
+        let format = |max_blank_lines| {
+            let formatter = CstFormatter::new().with_max_blank_lines(max_blank_lines);
+            formatter.format(&parse_tolerant("test", input))
+        };
+
+        // 0：删除所有空行
+        assert_eq!(format(0), "::p {\n    @a\n    @b\n    @c\n    @d\n}\n");
+        // 1：默认行为，连续空行合并为一行
+        assert_eq!(format(1), "::p {\n    @a\n\n    @b\n\n    @c\n    @d\n}\n");
+        assert_eq!(
+            format(1),
+            CstFormatter::new().format(&parse_tolerant("test", input))
+        );
+        // 2：最多保留两行，较少的空行不会被补齐
+        let two = format(2);
+        assert_eq!(two, "::p {\n    @a\n\n\n    @b\n\n    @c\n    @d\n}\n");
+        let formatter = CstFormatter::new().with_max_blank_lines(2);
+        assert_eq!(formatter.format(&parse_tolerant("test", &two)), two);
+
+        // 段落之间始终保留一个空行
+        let paragraphs = "::a {\n}\n::b {\n}\n";
+        let formatter = CstFormatter::new().with_max_blank_lines(0);
+        assert_eq!(
+            formatter.format(&parse_tolerant("test", paragraphs)),
+            "::a {\n}\n\n::b {\n}\n"
+        );
+    }
+
     #[test]
     fn test_format_array_compact() {
         let formatter = CstFormatter::new();
@@ -544,6 +1099,45 @@ mod tests {
         assert!(result.contains("#goto(next)"));
     }
 
+    #[test]
+    fn test_format_attributes() {
+        let input = concat!(
+            "::test {\n",
+            "  #[ cond ( 'a > 1' ) ]\n",
+            "  \"hello\"\n",
+            "    #[loop]\n",
+            "    {\n",
+            "        #break\n",
+            "    }\n",
+            "    #[if('name == \"bob\"')] @bg\n",
+            "}\n",
+        );
+        let expected = concat!(
+            "::test {\n",
+            "    #[cond(\"a > 1\")]\n",
+            "    \"hello\"\n",
+            "    #[loop]\n",
+            "    {\n",
+            "        #break\n",
+            "    }\n",
+            "    #[if('name == \"bob\"')]\n",
+            "    @bg\n",
+            "}\n",
+        );
+        let cst = parse_tolerant("test", input);
+        let result = CstFormatter::new().format(&cst);
+        assert_eq!(result, expected);
+
+        let reformatted = CstFormatter::new().format(&parse_tolerant("test", &result));
+        assert_eq!(reformatted, expected);
+
+        let single = CstFormatter::new()
+            .with_quote_style(QuoteStyle::Single)
+            .format(&cst);
+        assert!(single.contains("#[cond('a > 1')]"));
+        assert!(single.contains("#[if('name == \"bob\"')]"));
+    }
+
     #[test]
     fn test_format_preserves_indent_before_comments() {
         let input = r#"
@@ -630,6 +1224,21 @@ mod tests {
         assert!(result2.contains("@command arg=1 flag"));
     }
 
+    #[test]
+    fn test_format_preserves_empty_argument_syntax() {
+        let formatter = CstFormatter::new();
+
+        // 空括号的命令保持显式调用的写法
+        let input1 = "::test {\n    @pause()\n    #finish()\n}\n";
+        let result1 = formatter.format(&parse_tolerant("test", input1));
+        assert_eq!(result1, input1);
+
+        // 没有括号的命令保持标志的写法
+        let input2 = "::test {\n    @pause\n    #finish\n}\n";
+        let result2 = formatter.format(&parse_tolerant("test", input2));
+        assert_eq!(result2, input2);
+    }
+
     #[test]
     fn test_format_preserves_systemcall_syntax() {
         // 测试括号语法保留
@@ -818,4 +1427,30 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_format_argument_comments_idempotent() {
+        let input =
+            "::main {\n    @cmd(a=1, // 注释 a\n    /* 前置 */ b=[1, 2] // 注释 b\n    )\n}\n";
+        let results = format_n_times(input, 5);
+
+        assert!(results[0].contains("        a=1, // 注释 a\n"));
+        assert!(results[0].contains("        /* 前置 */\n        b=[1,2] // 注释 b\n    )\n"));
+        for (i, result) in results.iter().enumerate().skip(1) {
+            assert_eq!(
+                &results[0],
+                result,
+                "带注释的参数列表格式化不幂等：第 1 次和第 {} 次结果不同\n第 1 次:\n{}\n第 {} 次:\n{}",
+                i + 1,
+                &results[0],
+                i + 1,
+                result
+            );
+        }
+
+        // 换行后的参数列表语义不变
+        let before = crate::parser::parse_story("test", input).unwrap();
+        let after = crate::parser::parse_story("test", &results[0]).unwrap();
+        assert_eq!(before, after);
+    }
 }
@@ -0,0 +1,527 @@
+//! 单个段落的增量重新解析
+//!
+//! 编辑只落在某个段落的花括号内部时，只重新解析这个段落并替换原节点，
+//! 其后所有节点的位置按编辑前后的长度差平移；其余情况退回全文解析。
+
+use std::ops::Range;
+
+use super::node::*;
+use super::parser::{parse_paragraph, parse_tolerant};
+use super::span::{with_line_index, Span, SpanInfo};
+
+impl CstRoot {
+    /// 在编辑前的字节范围 `range` 被替换为 `new_text` 之后，只重新解析受影响的段落
+    ///
+    /// `source` 为编辑后的全文。编辑必须完全位于某个段落的 `{` 与 `}` 之间，
+    /// 并且重新解析后仍是单个完整的段落；跨越段落边界、增删了段落的闭合括号等情况
+    /// 会退回到全文重新解析。返回 `true` 表示使用了增量解析。
+    pub fn reparse_paragraph(&mut self, source: &str, range: Range<usize>, new_text: &str) -> bool {
+        if self.try_reparse_paragraph(source, &range, new_text) {
+            return true;
+        }
+        *self = parse_tolerant(&self.name, source);
+        false
+    }
+
+    fn try_reparse_paragraph(
+        &mut self,
+        source: &str,
+        range: &Range<usize>,
+        new_text: &str,
+    ) -> bool {
+        let Some(index) = self.nodes.iter().position(|node| {
+            matches!(node, CstNode::Paragraph(p)
+                if p.block.open_brace.end <= range.start && range.end <= p.block.close_brace.start)
+        }) else {
+            return false;
+        };
+        let old_span = self.nodes[index].span();
+
+        // 编辑后段落的结束位置
+        let new_end = old_span.end + new_text.len() - range.len();
+        let Some(text) = source.get(old_span.start..new_end) else {
+            return false;
+        };
+
+        let Ok((rest, paragraph)) = with_line_index(text, || parse_paragraph(Span::new(text)))
+        else {
+            return false;
+        };
+        if !rest.fragment().is_empty() {
+            return false;
+        }
+
+        // 新段落是从片段开头解析的，平移到它在全文中的位置
+        let mut node = CstNode::Paragraph(paragraph);
+        let origin = Position {
+            offset: 0,
+            line: 1,
+            column: 0,
+        };
+        let start = Position::start_of(&old_span);
+        node.visit_spans(&mut |span| span.shift(origin, start));
+
+        // 其后的节点按段落结束位置的变化平移
+        let old_end = Position::end_of(&old_span);
+        let new_end = Position::end_of(&node.span());
+        for following in &mut self.nodes[index + 1..] {
+            following.visit_spans(&mut |span| span.shift(old_end, new_end));
+        }
+        if self.span.end >= old_end.offset {
+            self.span.shift_end(old_end, new_end);
+        }
+
+        self.nodes[index] = node;
+        true
+    }
+}
+
+/// 文本中的一个位置
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Position {
+    fn start_of(span: &SpanInfo) -> Self {
+        Self {
+            offset: span.start,
+            line: span.start_line,
+            column: span.start_column,
+        }
+    }
+
+    fn end_of(span: &SpanInfo) -> Self {
+        Self {
+            offset: span.end,
+            line: span.end_line,
+            column: span.end_column,
+        }
+    }
+
+    /// 把位于 `from` 之后的位置平移为相对 `to` 的同一位置
+    fn moved(self, from: Position, to: Position) -> Self {
+        let column = if self.line == from.line {
+            self.column + to.column - from.column
+        } else {
+            self.column
+        };
+        Self {
+            offset: self.offset + to.offset - from.offset,
+            line: self.line + to.line - from.line,
+            column,
+        }
+    }
+}
+
+impl SpanInfo {
+    fn shift(&mut self, from: Position, to: Position) {
+        let start = Position::start_of(self).moved(from, to);
+        self.start = start.offset;
+        self.start_line = start.line;
+        self.start_column = start.column;
+        self.shift_end(from, to);
+    }
+
+    fn shift_end(&mut self, from: Position, to: Position) {
+        let end = Position::end_of(self).moved(from, to);
+        self.end = end.offset;
+        self.end_line = end.line;
+        self.end_column = end.column;
+    }
+}
+
+/// 遍历节点中的所有位置信息
+trait VisitSpans {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo));
+}
+
+impl VisitSpans for SpanInfo {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        f(self)
+    }
+}
+
+impl<T: VisitSpans> VisitSpans for Option<T> {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        if let Some(inner) = self {
+            inner.visit_spans(f);
+        }
+    }
+}
+
+impl<T: VisitSpans> VisitSpans for Vec<T> {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        for item in self {
+            item.visit_spans(f);
+        }
+    }
+}
+
+impl VisitSpans for CstNode {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        match self {
+            CstNode::Trivia(trivia) => trivia.visit_spans(f),
+            CstNode::Paragraph(paragraph) => paragraph.visit_spans(f),
+            CstNode::Command(command) => command.visit_spans(f),
+            CstNode::SystemCall(call) => call.visit_spans(f),
+            CstNode::TextLine(line) => line.visit_spans(f),
+            CstNode::Block(block) => block.visit_spans(f),
+            CstNode::EmbeddedCode(code) => code.span.visit_spans(f),
+            CstNode::Attribute(attribute) => attribute.visit_spans(f),
+            CstNode::Error { span, .. } => span.visit_spans(f),
+        }
+    }
+}
+
+impl VisitSpans for CstTrivia {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        match self {
+            CstTrivia::Whitespace { span, .. }
+            | CstTrivia::LineComment { span, .. }
+            | CstTrivia::BlockComment { span, .. } => span.visit_spans(f),
+        }
+    }
+}
+
+impl VisitSpans for CstParagraph {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        let CstParagraph {
+            name: _,
+            colon_token,
+            macro_keyword,
+            name_span,
+            parameters,
+            open_paren,
+            close_paren,
+            block,
+            span,
+            leading_trivia,
+        } = self;
+        colon_token.visit_spans(f);
+        macro_keyword.visit_spans(f);
+        name_span.visit_spans(f);
+        parameters.visit_spans(f);
+        open_paren.visit_spans(f);
+        close_paren.visit_spans(f);
+        block.visit_spans(f);
+        span.visit_spans(f);
+        leading_trivia.visit_spans(f);
+    }
+}
+
+impl VisitSpans for CstParameter {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        let CstParameter {
+            name: _,
+            name_span,
+            type_: _,
+            type_span,
+            equals_token,
+            default_value,
+            span,
+            leading_trivia,
+            trailing_trivia,
+        } = self;
+        name_span.visit_spans(f);
+        type_span.visit_spans(f);
+        equals_token.visit_spans(f);
+        default_value.visit_spans(f);
+        span.visit_spans(f);
+        leading_trivia.visit_spans(f);
+        trailing_trivia.visit_spans(f);
+    }
+}
+
+impl VisitSpans for CstValue {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        if let CstValueKind::Object { entries } = &mut self.kind {
+            for entry in entries {
+                entry.key_span.visit_spans(f);
+                entry.value.visit_spans(f);
+            }
+        }
+        self.span.visit_spans(f);
+    }
+}
+
+impl VisitSpans for CstBlock {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        let CstBlock {
+            label,
+            open_brace,
+            children,
+            close_brace,
+            span,
+        } = self;
+        if let Some(label) = label {
+            label.keyword_span.visit_spans(f);
+            label.name_span.visit_spans(f);
+        }
+        open_brace.visit_spans(f);
+        children.visit_spans(f);
+        close_brace.visit_spans(f);
+        span.visit_spans(f);
+    }
+}
+
+impl VisitSpans for CommandSyntax {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        if let CommandSyntax::Parenthesized {
+            open_paren,
+            close_paren,
+        } = self
+        {
+            open_paren.visit_spans(f);
+            close_paren.visit_spans(f);
+        }
+    }
+}
+
+impl VisitSpans for CstCommand {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        let CstCommand {
+            command: _,
+            at_token,
+            name_span,
+            arguments,
+            syntax,
+            span,
+            leading_trivia,
+        } = self;
+        at_token.visit_spans(f);
+        name_span.visit_spans(f);
+        arguments.visit_spans(f);
+        syntax.visit_spans(f);
+        span.visit_spans(f);
+        leading_trivia.visit_spans(f);
+    }
+}
+
+impl VisitSpans for CstSystemCall {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        let CstSystemCall {
+            command: _,
+            hash_token,
+            name_span,
+            arguments,
+            syntax,
+            span,
+            leading_trivia,
+        } = self;
+        hash_token.visit_spans(f);
+        name_span.visit_spans(f);
+        arguments.visit_spans(f);
+        syntax.visit_spans(f);
+        span.visit_spans(f);
+        leading_trivia.visit_spans(f);
+    }
+}
+
+impl VisitSpans for CstArgument {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        let CstArgument {
+            name: _,
+            name_span,
+            equals_token,
+            value,
+            span,
+            leading_trivia,
+            trailing_trivia,
+        } = self;
+        name_span.visit_spans(f);
+        equals_token.visit_spans(f);
+        value.visit_spans(f);
+        span.visit_spans(f);
+        leading_trivia.visit_spans(f);
+        trailing_trivia.visit_spans(f);
+    }
+}
+
+impl VisitSpans for CstAttribute {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        let CstAttribute {
+            keyword: _,
+            keyword_span,
+            condition: _,
+            condition_span,
+            open_token,
+            close_token,
+            span,
+            leading_trivia,
+        } = self;
+        keyword_span.visit_spans(f);
+        condition_span.visit_spans(f);
+        open_token.visit_spans(f);
+        close_token.visit_spans(f);
+        span.visit_spans(f);
+        leading_trivia.visit_spans(f);
+    }
+}
+
+impl VisitSpans for CstTextLine {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        let CstTextLine {
+            leading,
+            text,
+            segments,
+            tailing,
+            span,
+            leading_trivia,
+        } = self;
+        leading.visit_spans(f);
+        text.visit_spans(f);
+        segments.visit_spans(f);
+        if let Some(tailing) = tailing {
+            tailing.hash_token.visit_spans(f);
+            tailing.marker_span.visit_spans(f);
+            tailing.span.visit_spans(f);
+        }
+        span.visit_spans(f);
+        leading_trivia.visit_spans(f);
+    }
+}
+
+impl VisitSpans for CstTextSegment {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        self.leading.visit_spans(f);
+        self.text.visit_spans(f);
+        self.span.visit_spans(f);
+    }
+}
+
+impl VisitSpans for CstLeadingText {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        self.open_bracket.visit_spans(f);
+        if let CstLeadingTextContent::Template(template) = &mut self.content {
+            template.visit_spans(f);
+        }
+        self.close_bracket.visit_spans(f);
+        self.span.visit_spans(f);
+    }
+}
+
+impl VisitSpans for CstText {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        if let CstTextKind::Template(template) = &mut self.kind {
+            template.visit_spans(f);
+        }
+        self.span.visit_spans(f);
+    }
+}
+
+impl VisitSpans for CstTemplateLiteral {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        self.parts.visit_spans(f);
+        self.span.visit_spans(f);
+    }
+}
+
+impl VisitSpans for CstTemplatePart {
+    fn visit_spans(&mut self, f: &mut dyn FnMut(&mut SpanInfo)) {
+        match self {
+            CstTemplatePart::Text { span, .. } => span.visit_spans(f),
+            CstTemplatePart::Value {
+                open_token,
+                value,
+                close_token,
+                span,
+            } => {
+                open_token.visit_spans(f);
+                value.visit_spans(f);
+                close_token.visit_spans(f);
+                span.visit_spans(f);
+            }
+            CstTemplatePart::Conditional {
+                condition,
+                then,
+                otherwise,
+                span,
+            } => {
+                condition.visit_spans(f);
+                then.visit_spans(f);
+                otherwise.visit_spans(f);
+                span.visit_spans(f);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 把 `source` 中 `range` 替换为 `new_text`，返回编辑后的全文
+    fn edit(source: &str, range: Range<usize>, new_text: &str) -> String {
+        let mut edited = source.to_string();
+        edited.replace_range(range, new_text);
+        edited
+    }
+
+    const SOURCE: &str = "::first {\n    \"a\"\n}\n\n::second {\n    @cmd x=1\n}\n// 注释\n::third {\n    [b] `c${d}`\n}\n";
+
+    #[test]
+    fn test_reparse_inside_paragraph() {
+        let mut cst = parse_tolerant("test", SOURCE);
+        let first_before = format!("{:?}", cst.nodes[0]);
+
+        // 在第二个段落中插入一行（改变行数和长度）
+        let offset = SOURCE.find("@cmd").unwrap();
+        let new_text = "#goto paragraph=\"third\"\n    ";
+        let edited = edit(SOURCE, offset..offset, new_text);
+
+        assert!(cst.reparse_paragraph(&edited, offset..offset, new_text));
+
+        // 与全文解析的结果一致（包括其后节点平移后的位置）
+        let full = parse_tolerant("test", &edited);
+        assert_eq!(cst.nodes.len(), full.nodes.len());
+        for (node, expected) in cst.nodes.iter().zip(&full.nodes) {
+            assert_eq!(format!("{:?}", node), format!("{:?}", expected));
+        }
+        // 编辑之前的段落没有变化
+        assert_eq!(format!("{:?}", cst.nodes[0]), first_before);
+
+        let story = cst.to_ast().unwrap();
+        assert_eq!(story.paragraphs[1].block.children.len(), 2);
+    }
+
+    #[test]
+    fn test_reparse_shifts_columns_on_same_line() {
+        let source = "::a {\n    \"x\"\n} ::b {\n    \"y\"\n}\n";
+        let mut cst = parse_tolerant("test", source);
+
+        // 删除 "x" 所在的整行，使 `} ::b` 前移一行
+        let start = source.find("    \"x\"").unwrap();
+        let range = start..start + "    \"x\"\n".len();
+        let edited = edit(source, range.clone(), "");
+
+        assert!(cst.reparse_paragraph(&edited, range, ""));
+        let full = parse_tolerant("test", &edited);
+        assert_eq!(format!("{:?}", cst.nodes), format!("{:?}", full.nodes));
+    }
+
+    #[test]
+    fn test_reparse_falls_back_across_paragraphs() {
+        let mut cst = parse_tolerant("test", SOURCE);
+
+        // 编辑跨越第一个段落的闭合括号
+        let start = SOURCE.find("\"a\"").unwrap();
+        let end = SOURCE.find("::second").unwrap();
+        let edited = edit(SOURCE, start..end, "");
+        assert!(!cst.reparse_paragraph(&edited, start..end, ""));
+        let full = parse_tolerant("test", &edited);
+        assert_eq!(format!("{:?}", cst.nodes), format!("{:?}", full.nodes));
+
+        // 在段落内插入闭合括号会拆分段落，同样退回全文解析
+        let mut cst = parse_tolerant("test", SOURCE);
+        let offset = SOURCE.find("@cmd").unwrap();
+        let new_text = "}\n::inserted {\n    ";
+        let edited = edit(SOURCE, offset..offset, new_text);
+        assert!(!cst.reparse_paragraph(&edited, offset..offset, new_text));
+        let story = cst.to_ast().unwrap();
+        let names: Vec<_> = story.paragraphs.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["first", "second", "inserted", "third"]);
+    }
+}
@@ -5,11 +5,12 @@
 //! It is primarily used for LSP features and code formatting.
 
 pub mod formatter;
+mod incremental;
 pub mod node;
 pub mod parser;
 pub mod span;
 
-pub use formatter::CstFormatter;
+pub use formatter::{CstFormatter, EmptyCallStyle, FormatOptions};
 pub use node::*;
 pub use parser::parse_tolerant;
 pub use span::{Span, SpanInfo};
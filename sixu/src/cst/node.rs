@@ -1,6 +1,6 @@
 //! CST node definitions
 
-use super::span::SpanInfo;
+use super::span::{is_line_break, SpanInfo};
 use crate::format;
 
 /// Trivia：不影响语义的语法元素
@@ -42,7 +42,7 @@ impl CstTrivia {
 
     /// 是否包含换行
     pub fn has_newline(&self) -> bool {
-        self.content().contains('\n')
+        self.content().contains(is_line_break)
     }
 }
 
@@ -64,18 +64,74 @@ impl CstRoot {
     /// 转换为 AST Story
     pub fn to_ast(&self) -> crate::error::Result<crate::format::Story> {
         let mut paragraphs = Vec::new();
+        let mut macros = Vec::new();
+        // 紧挨在段落之前（中间只有 trivia）的属性属于该段落
+        let mut attributes = Vec::new();
 
         for node in &self.nodes {
-            if let CstNode::Paragraph(para) = node {
-                paragraphs.push(para.to_ast()?);
+            match node {
+                CstNode::Trivia(_) => {}
+                CstNode::Attribute(attr) => attributes.push(attr.to_ast()),
+                CstNode::Paragraph(para) if para.macro_keyword.is_some() => {
+                    attributes.clear();
+                    macros.push(crate::parser::macros::Macro {
+                        name: para.name.clone(),
+                        block: para.block.to_ast()?,
+                    });
+                }
+                CstNode::Paragraph(para) => {
+                    let mut paragraph = para.to_ast()?;
+                    paragraph.attributes = std::mem::take(&mut attributes);
+                    paragraphs.push(paragraph);
+                }
+                _ => attributes.clear(),
             }
         }
 
+        // 展开宏调用
+        let paragraphs = crate::parser::macros::expand_story(paragraphs, &macros)
+            .map_err(anyhow::Error::from)?;
+
         Ok(crate::format::Story {
             name: self.name.clone(),
             paragraphs,
         })
     }
+
+    /// 查找包含字节偏移 offset 的段落（含宏定义）
+    ///
+    /// 段落范围从 `::` 开始，到右花括号之后结束（不含）；段落之间以及文件首尾的空白、
+    /// 注释处返回 None。
+    pub fn paragraph_at(&self, offset: usize) -> Option<&CstParagraph> {
+        self.nodes.iter().find_map(|node| match node {
+            CstNode::Paragraph(para) if para.span.start <= offset && offset < para.span.end => {
+                Some(para)
+            }
+            _ => None,
+        })
+    }
+
+    /// 按源码顺序遍历所有节点，进入段落与代码块：段落和代码块排在其子节点之前
+    pub fn descendants(&self) -> impl Iterator<Item = &CstNode> {
+        descendants(&self.nodes)
+    }
+}
+
+/// 按源码顺序遍历 `nodes` 及其中段落、代码块的所有子节点
+fn descendants(nodes: &[CstNode]) -> impl Iterator<Item = &CstNode> {
+    let mut stack = vec![nodes.iter()];
+    std::iter::from_fn(move || loop {
+        let Some(node) = stack.last_mut()?.next() else {
+            stack.pop();
+            continue;
+        };
+        match node {
+            CstNode::Paragraph(para) => stack.push(para.block.children.iter()),
+            CstNode::Block(block) => stack.push(block.children.iter()),
+            _ => {}
+        }
+        return Some(node);
+    })
 }
 
 /// CST 节点（所有可能的语法元素）
@@ -189,7 +245,7 @@ pub enum CommandSyntax {
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CstCommand {
-    /// 语义信息（复用 AST）
+    /// 语义信息（复用 AST），宏调用 `@@name` 的命令名保留开头的 @
     pub command: String,
 
     /// @ 符号的位置
@@ -212,12 +268,21 @@ pub struct CstCommand {
 }
 
 impl CstCommand {
+    /// 宏调用 `@@name` 调用的宏名，普通命令为 None
+    pub fn macro_name(&self) -> Option<&str> {
+        self.command.strip_prefix('@')
+    }
+
     /// 转换为 AST CommandLine
-    pub fn to_ast(&self) -> format::CommandLine {
-        format::CommandLine {
+    pub fn to_ast(&self) -> crate::error::Result<format::CommandLine> {
+        Ok(format::CommandLine {
             command: self.command.clone(),
-            arguments: self.arguments.iter().map(|a| a.to_ast()).collect(),
-        }
+            arguments: self
+                .arguments
+                .iter()
+                .map(|a| a.to_ast())
+                .collect::<crate::error::Result<_>>()?,
+        })
     }
 }
 
@@ -249,11 +314,15 @@ pub struct CstSystemCall {
 
 impl CstSystemCall {
     /// 转换为 AST SystemCallLine
-    pub fn to_ast(&self) -> format::SystemCallLine {
-        format::SystemCallLine {
+    pub fn to_ast(&self) -> crate::error::Result<format::SystemCallLine> {
+        Ok(format::SystemCallLine {
             command: self.command.clone(),
-            arguments: self.arguments.iter().map(|a| a.to_ast()).collect(),
-        }
+            arguments: self
+                .arguments
+                .iter()
+                .map(|a| a.to_ast())
+                .collect::<crate::error::Result<_>>()?,
+        })
     }
 }
 
@@ -285,15 +354,18 @@ pub struct CstArgument {
 
 impl CstArgument {
     /// 转换为 AST Argument
-    pub fn to_ast(&self) -> format::Argument {
-        format::Argument {
+    pub fn to_ast(&self) -> crate::error::Result<format::Argument> {
+        Ok(format::Argument {
             name: self.name.clone(),
-            value: self
-                .value
-                .as_ref()
-                .map(|v| v.to_ast())
-                .unwrap_or(format::RValue::Literal(format::Literal::Boolean(true))),
-        }
+            value: match &self.value {
+                Some(v) => v.to_ast()?,
+                None => format::RValue::Literal(format::Literal::Boolean(true)),
+            },
+            span: Some(match &self.value {
+                Some(value) => value.span.start..value.span.end,
+                None => self.name_span.start..self.name_span.end,
+            }),
+        })
     }
 }
 
@@ -333,10 +405,33 @@ pub enum CstValueKind {
 
     /// 数组 [...]
     Array,
+
+    /// 对象 {key: value, ...}
+    Object {
+        /// 各个键值对
+        entries: Vec<CstObjectEntry>,
+    },
+}
+
+/// 对象中的一个键值对 `key: value`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CstObjectEntry {
+    /// 键（已去掉引号）
+    pub key: String,
+
+    /// 键的原始文本（裸标识符或带引号的字符串）
+    pub key_raw: String,
+
+    /// 键的位置
+    pub key_span: SpanInfo,
+
+    /// 值
+    pub value: CstValue,
 }
 
 /// 值节点（字符串、数字、变量等）
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CstValue {
     /// 值的种类
@@ -346,6 +441,8 @@ pub struct CstValue {
     pub raw: String,
 
     /// 解析后的值（用于生成 AST）
+    ///
+    /// 对象中含有变量时无法表示为字面量，此时为 `null`，`to_ast` 会报错
     pub parsed: format::RValue,
 
     /// 值的位置
@@ -354,9 +451,35 @@ pub struct CstValue {
 
 impl CstValue {
     /// 转换为 AST RValue
-    pub fn to_ast(&self) -> format::RValue {
-        self.parsed.clone()
+    pub fn to_ast(&self) -> crate::error::Result<format::RValue> {
+        match &self.kind {
+            CstValueKind::Object { entries } => {
+                Ok(format::RValue::Literal(object_literal(entries)?))
+            }
+            _ => Ok(self.parsed.clone()),
+        }
+    }
+}
+
+/// 由键值对构造对象字面量，值必须都是字面量
+pub(crate) fn object_literal(entries: &[CstObjectEntry]) -> crate::error::Result<format::Literal> {
+    let mut object = std::collections::HashMap::new();
+    for entry in entries {
+        match entry.value.to_ast()? {
+            format::RValue::Literal(literal) => {
+                object.insert(entry.key.clone(), literal);
+            }
+            format::RValue::Variable(variable) => {
+                return Err(anyhow::anyhow!(
+                    "object values must be literals, found variable `{}` for key `{}`",
+                    variable.chain.join("."),
+                    entry.key
+                )
+                .into());
+            }
+        }
     }
+    Ok(format::Literal::Object(object))
 }
 
 // ===== Phase 2-4 的节点（暂时使用占位定义） =====
@@ -371,6 +494,9 @@ pub struct CstParagraph {
     /// :: 符号的位置
     pub colon_token: SpanInfo,
 
+    /// macro 关键字的位置（宏定义 `::macro name { ... }`）
+    pub macro_keyword: Option<SpanInfo>,
+
     /// 段落名的位置
     pub name_span: SpanInfo,
 
@@ -396,8 +522,13 @@ pub struct CstParagraph {
 impl CstParagraph {
     pub fn to_ast(&self) -> crate::error::Result<format::Paragraph> {
         Ok(format::Paragraph {
+            attributes: vec![],
             name: self.name.clone(),
-            parameters: self.parameters.iter().map(|p| p.to_ast()).collect(),
+            parameters: self
+                .parameters
+                .iter()
+                .map(|p| p.to_ast())
+                .collect::<crate::error::Result<_>>()?,
             block: self.block.to_ast()?,
         })
     }
@@ -413,6 +544,12 @@ pub struct CstParameter {
     /// 参数名的位置
     pub name_span: SpanInfo,
 
+    /// 类型标注 `: string` 中的类型名（可选）
+    pub type_: Option<String>,
+
+    /// 类型名的位置
+    pub type_span: Option<SpanInfo>,
+
     /// = 的位置（如果有默认值）
     pub equals_token: Option<SpanInfo>,
 
@@ -430,14 +567,16 @@ pub struct CstParameter {
 }
 
 impl CstParameter {
-    pub fn to_ast(&self) -> format::Parameter {
-        format::Parameter {
+    pub fn to_ast(&self) -> crate::error::Result<format::Parameter> {
+        Ok(format::Parameter {
             name: self.name.clone(),
-            default_value: self.default_value.as_ref().and_then(|v| match &v.parsed {
-                format::RValue::Literal(lit) => Some(lit.clone()),
-                _ => None,
-            }),
-        }
+            type_: self.type_.clone(),
+            default_value: self
+                .default_value
+                .as_ref()
+                .map(|v| v.to_ast())
+                .transpose()?,
+        })
     }
 }
 
@@ -445,13 +584,37 @@ impl CstParameter {
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CstBlock {
+    /// 代码块标签 group "name"（可选）
+    pub label: Option<CstBlockLabel>,
     pub open_brace: SpanInfo,
     pub children: Vec<CstNode>,
     pub close_brace: SpanInfo,
     pub span: SpanInfo,
 }
 
+/// 代码块标签 group "name"，仅用于大纲、折叠等工具功能，不影响运行
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CstBlockLabel {
+    /// 标签名（不含引号）
+    pub name: String,
+
+    /// 标签名原始文本（含引号）
+    pub raw: String,
+
+    /// group 关键字的位置
+    pub keyword_span: SpanInfo,
+
+    /// 标签名的位置（含引号）
+    pub name_span: SpanInfo,
+}
+
 impl CstBlock {
+    /// 按源码顺序遍历代码块的所有子节点，进入嵌套的代码块，见 [`CstRoot::descendants`]
+    pub fn descendants(&self) -> impl Iterator<Item = &CstNode> {
+        descendants(&self.children)
+    }
+
     pub fn to_ast(&self) -> crate::error::Result<format::Block> {
         let mut children = Vec::new();
         let mut pending_attributes: Vec<format::Attribute> = Vec::new();
@@ -471,17 +634,21 @@ impl CstBlock {
                     }
                 }
                 CstNode::Command(cmd) => {
+                    let content = match cmd.macro_name() {
+                        Some(name) => format::ChildContent::MacroInvocation(name.to_string()),
+                        None => format::ChildContent::CommandLine(cmd.to_ast()?),
+                    };
                     children.push(format::Child {
                         marker: pending_marker.take(),
                         attributes: std::mem::take(&mut pending_attributes),
-                        content: format::ChildContent::CommandLine(cmd.to_ast()),
+                        content,
                     });
                 }
                 CstNode::SystemCall(sc) => {
                     children.push(format::Child {
                         marker: pending_marker.take(),
                         attributes: std::mem::take(&mut pending_attributes),
-                        content: format::ChildContent::SystemCallLine(sc.to_ast()),
+                        content: format::ChildContent::SystemCallLine(sc.to_ast()?),
                     });
                 }
                 CstNode::TextLine(tl) => {
@@ -520,7 +687,10 @@ impl CstBlock {
             return Err(anyhow::anyhow!("dangling marker directive at end of block").into());
         }
 
-        Ok(format::Block { children })
+        Ok(format::Block {
+            label: self.label.as_ref().map(|label| label.name.clone()),
+            children,
+        })
     }
 }
 
@@ -547,6 +717,9 @@ pub struct CstTextLine {
     /// 主文本内容
     pub text: Option<CstText>,
 
+    /// 之后的说话人片段（仅在 ParserConfig::multi_speaker 时解析）
+    pub segments: Vec<CstTextSegment>,
+
     /// 后缀标记（如 #wait）
     pub tailing: Option<CstTailingText>,
 
@@ -574,14 +747,42 @@ impl CstTextLine {
             None => format::TailingText::None,
         };
 
+        let content = if self.segments.is_empty() {
+            format::ChildContent::TextLine(leading_ast, text_ast, tailing_ast)
+        } else {
+            let mut segments = vec![(leading_ast, text_ast)];
+            for segment in &self.segments {
+                let text = match &segment.text {
+                    Some(t) => t.to_ast()?,
+                    None => format::Text::None,
+                };
+                segments.push((segment.leading.to_ast(), text));
+            }
+            format::ChildContent::MultiTextLine(segments, tailing_ast)
+        };
+
         Ok(format::Child {
             marker: None,
             attributes: vec![],
-            content: format::ChildContent::TextLine(leading_ast, text_ast, tailing_ast),
+            content,
         })
     }
 }
 
+/// 多说话人行中的后续片段 [speaker] text
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CstTextSegment {
+    /// 说话人
+    pub leading: CstLeadingText,
+
+    /// 片段文本
+    pub text: Option<CstText>,
+
+    /// 整个片段的范围
+    pub span: SpanInfo,
+}
+
 /// 前导文本 [...]
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -704,19 +905,28 @@ impl CstTemplateLiteral {
 pub enum CstTemplatePart {
     /// 文本部分
     Text { content: String, span: SpanInfo },
-    /// 变量插值 ${...}
+    /// 插值 ${...}，可以是变量或字面量
     Value {
         /// ${ 的位置
         open_token: SpanInfo,
-        /// 变量
-        variable: format::Variable,
-        /// 变量的位置
-        variable_span: SpanInfo,
+        /// 插值的值，raw 保留原始写法，span 为值的位置
+        value: CstValue,
         /// } 的位置
         close_token: SpanInfo,
         /// 整个插值的范围
         span: SpanInfo,
     },
+    /// 条件片段 ${?cond}...${:}...${/}
+    Conditional {
+        /// 条件，与插值一样可以是变量或字面量
+        condition: CstValue,
+        /// 条件为真时的部分
+        then: Vec<CstTemplatePart>,
+        /// 条件为假时的部分（没有 ${:} 时为空）
+        otherwise: Vec<CstTemplatePart>,
+        /// 整个条件片段的范围
+        span: SpanInfo,
+    },
 }
 
 impl CstTemplatePart {
@@ -725,8 +935,39 @@ impl CstTemplatePart {
             CstTemplatePart::Text { content, .. } => {
                 format::TemplateLiteralPart::Text(content.clone())
             }
-            CstTemplatePart::Value { variable, .. } => {
-                format::TemplateLiteralPart::Value(format::RValue::Variable(variable.clone()))
+            CstTemplatePart::Value { value, .. } => {
+                format::TemplateLiteralPart::Value(value.parsed.clone())
+            }
+            CstTemplatePart::Conditional {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => format::TemplateLiteralPart::Conditional {
+                condition: condition.parsed.clone(),
+                then: then.iter().map(|p| p.to_ast()).collect(),
+                otherwise: otherwise.iter().map(|p| p.to_ast()).collect(),
+            },
+        }
+    }
+
+    pub fn span(&self) -> SpanInfo {
+        match self {
+            Self::Text { span, .. } => *span,
+            Self::Value { span, .. } => *span,
+            Self::Conditional { span, .. } => *span,
+        }
+    }
+
+    /// 插值变量或条件变量的位置，文本部分和字面量插值没有变量
+    pub fn variable_span(&self) -> Option<SpanInfo> {
+        match self {
+            Self::Text { .. } => None,
+            Self::Value { value, .. } => {
+                matches!(value.kind, CstValueKind::Variable).then_some(value.span)
+            }
+            Self::Conditional { condition, .. } => {
+                matches!(condition.kind, CstValueKind::Variable).then_some(condition.span)
             }
         }
     }
@@ -758,3 +999,32 @@ impl CstEmbeddedCode {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::cst::parser::parse_tolerant;
+
+    #[test]
+    fn test_paragraph_at() {
+        let source = "// 开头\n::first {\n    @a\n}\n\n::second(x) {\n    hello\n}\n";
+        let cst = parse_tolerant("test", source);
+        let name_at = |offset| cst.paragraph_at(offset).map(|p| p.name.as_str());
+
+        // 段落内部
+        assert_eq!(name_at(source.find("@a").unwrap()), Some("first"));
+        assert_eq!(name_at(source.find("hello").unwrap()), Some("second"));
+        assert_eq!(name_at(source.find("x)").unwrap()), Some("second"));
+
+        // 边界：:: 属于段落，右花括号之后不属于
+        let first_start = source.find("::first").unwrap();
+        let first_end = source.find("}\n\n").unwrap() + 1;
+        assert_eq!(name_at(first_start), Some("first"));
+        assert_eq!(name_at(first_end - 1), Some("first"));
+        assert_eq!(name_at(first_end), None);
+
+        // 段落之外：开头的注释、段落之间的空行、文件末尾
+        assert_eq!(name_at(0), None);
+        assert_eq!(name_at(first_end + 1), None);
+        assert_eq!(name_at(source.len()), None);
+    }
+}
@@ -4,27 +4,47 @@ use nom::{
     branch::alt,
     bytes::complete::{tag, take, take_until, take_while, take_while1},
     character::complete::{
-        alpha1, alphanumeric1, char, digit1, multispace1, one_of, space0, space1,
+        alpha1, alphanumeric1, char, digit1, hex_digit1, multispace1, one_of, satisfy, space0,
+        space1,
     },
-    combinator::{opt, recognize, value},
+    combinator::{cut, not, opt, peek, recognize, value},
     multi::{many0, many1, many_till, separated_list0},
-    sequence::{delimited, pair, preceded},
-    IResult, Parser,
+    sequence::{delimited, pair, preceded, terminated},
+    IResult, Input, Parser,
 };
 
 use super::node::*;
-use super::span::{Span, SpanInfo};
+use super::span::{is_line_break, with_line_index, Span, SpanInfo};
 use crate::format;
+use crate::parser::config::multi_speaker;
+use crate::parser::config::{number_units, unknown_escape, with_config, ParserConfig};
+use crate::parser::escape::{simple_escape, template_escape, unknown_escape_text, Escaped};
+use crate::parser::primitive::with_unit;
+use crate::parser::text::speaker_boundary;
 
 type ParseResult<'a, T> = IResult<Span<'a>, T>;
 
+/// 使用指定解析选项的容错解析入口
+pub fn parse_tolerant_with_config(name: &str, input: &str, config: &ParserConfig) -> CstRoot {
+    with_config(config, || parse_tolerant(name, input))
+}
+
 /// 容错解析入口
 pub fn parse_tolerant(name: &str, input: &str) -> CstRoot {
+    with_line_index(input, || parse_root(name, input))
+}
+
+fn parse_root(name: &str, input: &str) -> CstRoot {
     let span = Span::new(input);
     let start_info = SpanInfo::from_span(span);
 
     let mut nodes = Vec::new();
     let mut remaining = span;
+    // 容错跳过的字符中尚未配对的 `{` 与 `(` 数量
+    let mut skipped_braces = 0usize;
+    let mut skipped_parens = 0usize;
+    // 最近解析的段落在 nodes 中的下标、起始位置及其中多余的 `}`
+    let mut last_paragraph: Option<(usize, Span, Vec<usize>)> = None;
 
     while !remaining.fragment().is_empty() {
         // 尝试解析 trivia
@@ -34,15 +54,37 @@ pub fn parse_tolerant(name: &str, input: &str) -> CstRoot {
             continue;
         }
 
+        // 段落之后出现了只能写在段落内的内容，说明段落被多余的 `}` 提前闭合了
+        if let Some((index, start, mut stray)) = last_paragraph.take() {
+            let para = &nodes[index];
+            if let Some((rest, para)) = reparse_early_closed(para, start, &mut stray, remaining) {
+                nodes.truncate(index);
+                nodes.push(CstNode::Paragraph(para));
+                last_paragraph = Some((index, start, stray));
+                remaining = rest;
+                continue;
+            }
+        }
+
         // 尝试解析段落
         if let Ok((rest, para)) = parse_paragraph(remaining) {
+            last_paragraph = Some((nodes.len(), remaining, Vec::new()));
             nodes.push(CstNode::Paragraph(para));
             remaining = rest;
             continue;
         }
 
+        // 尝试解析属性。顶层的属性作用于其后的段落（如 `#[platform("mobile")]`、`#[once]`）
+        if let Ok((rest, attr)) = parse_cst_attribute(remaining) {
+            last_paragraph = None;
+            nodes.push(CstNode::Attribute(attr));
+            remaining = rest;
+            continue;
+        }
+
         // 尝试解析命令
         if let Ok((rest, cmd)) = parse_command(remaining) {
+            last_paragraph = None;
             nodes.push(CstNode::Command(cmd));
             remaining = rest;
             continue;
@@ -50,17 +92,41 @@ pub fn parse_tolerant(name: &str, input: &str) -> CstRoot {
 
         // 尝试解析系统调用
         if let Ok((rest, sc)) = parse_systemcall(remaining) {
+            last_paragraph = None;
             nodes.push(CstNode::SystemCall(sc));
             remaining = rest;
             continue;
         }
 
-        // 容错：跳过一个字符
-        if let Ok((rest, _)) = take::<usize, Span, nom::error::Error<Span>>(1usize)(remaining) {
-            remaining = rest;
-        } else {
+        // 容错：跳过一个字符。没有配对的 `}` 与 `)` 生成 Error 节点
+        let Ok((rest, skipped)) = take::<usize, Span, nom::error::Error<Span>>(1usize)(remaining)
+        else {
             break;
+        };
+        let unpaired = match *skipped.fragment() {
+            "{" => {
+                skipped_braces += 1;
+                None
+            }
+            "(" => {
+                skipped_parens += 1;
+                None
+            }
+            "}" if skipped_braces > 0 => {
+                skipped_braces -= 1;
+                None
+            }
+            ")" if skipped_parens > 0 => {
+                skipped_parens -= 1;
+                None
+            }
+            closer @ ("}" | ")") => Some(closer),
+            _ => None,
+        };
+        if let Some(closer) = unpaired {
+            nodes.push(unexpected_closer(remaining, rest, closer));
         }
+        remaining = rest;
     }
 
     let _end_span = remaining;
@@ -84,6 +150,54 @@ pub fn parse_tolerant(name: &str, input: &str) -> CstRoot {
     }
 }
 
+/// 段落 `node` 之后的 `remaining` 不是段落或属性时，把闭合段落的 `}` 记入 `stray`
+/// 后从 `start` 重新解析段落
+///
+/// `remaining` 以空白或右括号开头时不重试，右括号由顶层的容错报告。重新解析的
+/// 段落不能越过下一个段落的开头，否则保留原来的结果
+fn reparse_early_closed<'a>(
+    node: &CstNode,
+    start: Span<'a>,
+    stray: &mut Vec<usize>,
+    remaining: Span<'a>,
+) -> Option<(Span<'a>, CstParagraph)> {
+    let CstNode::Paragraph(para) = node else {
+        return None;
+    };
+    let fragment = *remaining.fragment();
+    let next = fragment.chars().next()?;
+    let top_level = fragment.starts_with("::") || fragment.starts_with("#[");
+    if top_level || next.is_whitespace() || matches!(next, '}' | ')') {
+        return None;
+    }
+
+    stray.push(para.block.close_brace.start);
+    let (rest, para) = parse_paragraph_with(start, stray).ok()?;
+    let limit = remaining.location_offset() + next_paragraph_start(fragment);
+    (rest.location_offset() <= limit).then_some((rest, para))
+}
+
+/// 下一个以 `::` 开头的行在 `text` 中的位置，没有时为 `text` 的长度
+fn next_paragraph_start(text: &str) -> usize {
+    let mut offset = 0;
+    for line in text.split_inclusive(is_line_break) {
+        if line.trim_start().starts_with("::") {
+            return offset;
+        }
+        offset += line.len();
+    }
+    text.len()
+}
+
+/// 多余的右括号 `closer` 的 Error 节点
+fn unexpected_closer<'a>(start: Span<'a>, end: Span<'a>, closer: &str) -> CstNode {
+    CstNode::Error {
+        content: closer.to_string(),
+        span: SpanInfo::from_range(start, end),
+        message: format!("unexpected `{}`", closer),
+    }
+}
+
 /// 解析 trivia（空白或注释）
 fn parse_trivia(input: Span) -> ParseResult<CstTrivia> {
     alt((parse_line_comment, parse_block_comment, parse_whitespace)).parse(input)
@@ -168,8 +282,14 @@ pub fn parse_command(input: Span) -> ParseResult<CstCommand> {
     let (input, _) = tag("@")(input)?;
     let at_token = SpanInfo::from_span_and_len(at_start, 1);
 
-    // 命令名
-    let (input, (command, name_span)) = parse_identifier(input)?;
+    // 命令名，`@@name` 为宏调用，命令名保留开头的 @
+    let name_start = input;
+    let (input, macro_at) = opt(tag("@")).parse(input)?;
+    let (input, (mut command, mut name_span)) = parse_identifier(input)?;
+    if macro_at.is_some() {
+        command.insert(0, '@');
+        name_span = SpanInfo::from_range(name_start, input);
+    }
 
     // 解析参数（支持两种语法）
     let (input, (arguments, syntax)) = alt((
@@ -232,17 +352,82 @@ pub fn parse_systemcall(input: Span) -> ParseResult<CstSystemCall> {
     ))
 }
 
-/// 解析括号风格的参数 (arg1=val1, arg2=val2)
-fn parse_arguments_parenthesized(input: Span) -> ParseResult<(Vec<CstArgument>, CommandSyntax)> {
+/// 解析括号风格参数中 `)` 之前的部分，返回从左括号开始的输入和参数
+///
+/// 参数之后同一行内的注释（包括逗号后的注释，如 `a=1, // 坐标`）记入该参数的
+/// trailing trivia，最后一个参数之后直到 `)` 前的 trivia 也记入最后一个参数
+fn parse_parenthesized_open(input: Span) -> ParseResult<(Span, Vec<CstArgument>)> {
     let (input, _) = space0(input)?;
     let open_start = input;
     let (input, _) = tag("(")(input)?;
+    let (mut input, _) = space0(input)?;
+
+    let mut arguments = Vec::new();
+    while let Ok((rest, mut arg)) = parse_argument(input) {
+        let (rest, mut trailing) = many0(parse_trivia_inline).parse(rest)?;
+        let (after_comma, comma) = opt(tag(",")).parse(rest)?;
+        let (after_comma, more) = many0(parse_trivia_inline).parse(after_comma)?;
+
+        // 逗号后没有下一个参数时，停在逗号之前，由调用方报告缺少 `)`
+        let has_next = comma.is_some() && parse_argument(after_comma).is_ok();
+        if has_next {
+            trailing.extend(more);
+            input = after_comma;
+        } else {
+            let (rest, more) = many0(parse_trivia).parse(rest)?;
+            trailing.extend(more);
+            input = rest;
+        }
+
+        arg.trailing_trivia = trailing;
+        arguments.push(arg);
+        if !has_next {
+            break;
+        }
+    }
+
+    if arguments.is_empty() {
+        let (rest, _) = many0(parse_trivia).parse(input)?;
+        input = rest;
+    }
+
+    Ok((input, (open_start, arguments)))
+}
+
+/// 解析同一行内的 trivia（空格、制表符、注释），不跨越换行
+fn parse_trivia_inline(input: Span) -> ParseResult<CstTrivia> {
+    alt((parse_line_comment, parse_block_comment, parse_space)).parse(input)
+}
+
+/// 解析不含换行的空白
+fn parse_space(input: Span) -> ParseResult<CstTrivia> {
+    let start_span = input;
+    let (input, ws) = space1(input)?;
+    let end_span = input;
+
+    Ok((
+        input,
+        CstTrivia::Whitespace {
+            content: ws.fragment().to_string(),
+            span: SpanInfo::from_range(start_span, end_span),
+        },
+    ))
+}
+
+/// 解析括号风格的参数 (arg1=val1, arg2=val2)
+///
+/// 括号未闭合就遇到 `{` 时返回指向左括号的 Failure，交由 [`recover_unclosed_paren`] 处理，
+/// 避免回退到空格分隔语法后把后面的块错位解析
+fn parse_arguments_parenthesized(input: Span) -> ParseResult<(Vec<CstArgument>, CommandSyntax)> {
+    let (input, (open_start, arguments)) = parse_parenthesized_open(input)?;
     let open_paren = SpanInfo::from_span_and_len(open_start, 1);
 
-    let (input, _) = space0(input)?;
-    let (input, arguments) =
-        separated_list0(delimited(space0, tag(","), space0), parse_argument).parse(input)?;
-    let (input, _) = space0(input)?;
+    if input.fragment().starts_with('{') {
+        return Err(nom::Err::Failure(nom::error::Error::new(
+            open_start,
+            nom::error::ErrorKind::Char,
+        )));
+    }
 
     let close_start = input;
     let (input, _) = tag(")")(input)?;
@@ -277,11 +462,13 @@ fn parse_argument(input: Span) -> ParseResult<CstArgument> {
     let (input, (name, name_span)) = parse_identifier(input)?;
 
     // 可选的 = 和值
-    let (input, equals_and_value) =
-        opt((preceded(space0, tag("=")), preceded(space0, parse_value))).parse(input)?;
+    let (input, equals_and_value) = opt((
+        preceded(space0, parse_equals_token),
+        preceded(space0, parse_value),
+    ))
+    .parse(input)?;
 
-    let (equals_token, value) = if let Some((eq, val)) = equals_and_value {
-        let eq_span = SpanInfo::from_span_and_len(Span::new(eq.fragment()), 1);
+    let (equals_token, value) = if let Some((eq_span, val)) = equals_and_value {
         (Some(eq_span), Some(val))
     } else {
         (None, None)
@@ -311,6 +498,7 @@ fn parse_value(input: Span) -> ParseResult<CstValue> {
         parse_number_value,
         parse_boolean_value,
         parse_array_value,
+        parse_object_value,
         parse_variable_value,
     ))
     .parse(input)
@@ -320,28 +508,30 @@ fn parse_value(input: Span) -> ParseResult<CstValue> {
 fn parse_string_value(input: Span) -> ParseResult<CstValue> {
     let start_span = input;
 
-    let (input, quote_char) = alt((char('"'), char('\''))).parse(input)?;
-    let quote_style = if quote_char == '"' {
-        QuoteStyle::Double
-    } else {
-        QuoteStyle::Single
+    let quote_style = match input.fragment().chars().next() {
+        Some('"') => QuoteStyle::Double,
+        Some('\'') => QuoteStyle::Single,
+        _ => {
+            return Err(nom::Err::Error(nom::error::Error::new(
+                input,
+                nom::error::ErrorKind::Char,
+            )))
+        }
     };
 
-    // 简化实现：暂不处理转义
-    let (input, content) = take_while(move |c| c != quote_char)(input)?;
-    let (input, _) = char(quote_char)(input)?;
+    // 转义与带引号的文本相同，raw 保留原始写法
+    let (input, content) = parse_quoted_string(input)?;
 
     let end_span = input;
-    let raw = format!("{}{}{}", quote_char, content.fragment(), quote_char);
+    let raw = start_span.fragment()[..end_span.location_offset() - start_span.location_offset()]
+        .to_string();
 
     Ok((
         input,
         CstValue {
             kind: CstValueKind::String { quote: quote_style },
-            raw: raw.clone(),
-            parsed: format::RValue::Literal(format::Literal::String(
-                content.fragment().to_string(),
-            )),
+            raw,
+            parsed: format::RValue::Literal(format::Literal::String(content)),
             span: SpanInfo::from_range(start_span, end_span),
         },
     ))
@@ -372,25 +562,91 @@ fn parse_template_string_value(input: Span) -> ParseResult<CstValue> {
     ))
 }
 
-/// 解析数字值
+/// 解析数字值（支持 `0x` 十六进制和 `_` 数字分隔符，如 `0xFF8800`、`1_000_000`）
 fn parse_number_value(input: Span) -> ParseResult<CstValue> {
     let start_span = input;
 
-    let (input, number_str) =
-        recognize((opt(char('-')), digit1, opt((char('.'), digit1)))).parse(input)?;
+    let digits = |i| recognize((digit1, many0(alt((digit1, tag("_")))))).parse(i);
+    let (input, number_str) = recognize((
+        opt(char('-')),
+        alt((
+            // 0x 之后必须有十六进制数字
+            recognize((
+                alt((tag("0x"), tag("0X"))),
+                cut(hex_digit1),
+                many0(alt((hex_digit1, tag("_")))),
+            )),
+            // 可选的指数部分，e/E 之后必须有数字
+            recognize((
+                digits,
+                opt((char('.'), digits)),
+                opt((one_of("eE"), cut(exponent_digits))),
+            )),
+        )),
+    ))
+    .parse(input)?;
+
+    // 启用 ParserConfig::number_units 时，十进制数字可带单位后缀，如 600ms、50%
+    let (input, unit) = if number_units() && !number_str.fragment().contains(['x', 'X']) {
+        opt(terminated(
+            alt((tag("ms"), tag("s"), tag("px"), tag("%"))),
+            not(satisfy(|c: char| c.is_alphanumeric() || c == '_')),
+        ))
+        .parse(input)?
+    } else {
+        (input, None)
+    };
 
     let end_span = input;
-    let raw = number_str.fragment().to_string();
+    // raw 保留原始写法（含单位），解析时去掉分隔符
+    let raw = format!(
+        "{}{}",
+        number_str.fragment(),
+        unit.map(|u| *u.fragment()).unwrap_or_default()
+    );
+    let normalized = number_str.fragment().replace('_', "");
+    let (negative, unsigned) = match normalized.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, normalized.as_str()),
+    };
 
-    let parsed = if raw.contains('.') {
+    let is_float = !unsigned.starts_with("0x")
+        && !unsigned.starts_with("0X")
+        && unsigned.contains(['.', 'e', 'E']);
+    // 超出范围的数字是错误，而不是静默地变成 0
+    let out_of_range = || {
+        nom::Err::Failure(nom::error::Error::new(
+            start_span,
+            nom::error::ErrorKind::TooLarge,
+        ))
+    };
+    let literal = if let Some(hex) = unsigned
+        .strip_prefix("0x")
+        .or_else(|| unsigned.strip_prefix("0X"))
+    {
+        // 十六进制整数，带上符号以接受 i64::MIN
+        let sign = if negative { "-" } else { "" };
+        let n = i64::from_str_radix(&format!("{}{}", sign, hex), 16).map_err(|_| out_of_range())?;
+        format::Literal::Integer(n)
+    } else if is_float {
         // 浮点数
-        format::RValue::Literal(format::Literal::Float(raw.parse::<f64>().unwrap_or(0.0)))
+        match normalized.parse::<f64>() {
+            Ok(n) if n.is_finite() => format::Literal::Float(n),
+            _ => return Err(out_of_range()),
+        }
     } else {
         // 整数
-        format::RValue::Literal(format::Literal::Integer(raw.parse::<i64>().unwrap_or(0)))
+        format::Literal::Integer(normalized.parse::<i64>().map_err(|_| out_of_range())?)
+    };
+    // 单位换算到基本单位，百分数总是浮点数
+    let literal = match unit {
+        Some(unit) => with_unit(literal, unit.fragment()).ok_or_else(out_of_range)?,
+        None => literal,
     };
+    let is_float = matches!(literal, format::Literal::Float(_));
+    let parsed = format::RValue::Literal(literal);
 
-    let kind = if raw.contains('.') {
+    let kind = if is_float {
         CstValueKind::Float
     } else {
         CstValueKind::Integer
@@ -407,6 +663,24 @@ fn parse_number_value(input: Span) -> ParseResult<CstValue> {
     ))
 }
 
+/// 指数部分 e/E 之后的数字，缺少数字时报 Float 错误以便给出明确的提示
+fn exponent_digits(input: Span) -> ParseResult<Span> {
+    recognize((opt(one_of("+-")), digit1)).parse(input).map_err(
+        |e: nom::Err<nom::error::Error<Span>>| {
+            e.map(|e| nom::error::Error::new(e.input, nom::error::ErrorKind::Float))
+        },
+    )
+}
+
+/// 数字值解析失败时的提示，其他错误返回 None
+fn number_error_message(code: nom::error::ErrorKind) -> Option<&'static str> {
+    match code {
+        nom::error::ErrorKind::TooLarge => Some("number literal out of range"),
+        nom::error::ErrorKind::Float => Some("malformed number literal: expected exponent digits"),
+        _ => None,
+    }
+}
+
 /// 解析布尔值
 fn parse_boolean_value(input: Span) -> ParseResult<CstValue> {
     let start_span = input;
@@ -464,37 +738,20 @@ fn parse_array_value(input: Span) -> ParseResult<CstValue> {
         )));
     }
 
-    // 通过括号深度计数找到匹配的 ']'
-    let mut depth = 0usize;
-    let mut end = None;
-    for (i, ch) in fragment.char_indices() {
-        match ch {
-            '[' => depth += 1,
-            ']' => {
-                depth -= 1;
-                if depth == 0 {
-                    end = Some(i + 1);
-                    break;
-                }
-            }
-            _ => {}
-        }
-    }
-
-    let end = end.ok_or_else(|| {
-        nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag))
+    // 用 AST primitive 解析器找到数组的结尾，与 AST 一样处理字符串转义和注释，
+    // 同时得到结构化的 Literal::Array
+    let (rest, parsed) = crate::parser::primitive::array(fragment).map_err(|_| {
+        nom::Err::Error(nom::error::Error::new(
+            start_span,
+            nom::error::ErrorKind::Tag,
+        ))
     })?;
+    let end = fragment.len() - rest.len();
 
     let raw = fragment[..end].to_string();
-    let (input, _) = take(end)(input)?;
+    let (input, _) = input.take_split(end);
     let end_span = input;
-
-    // 复用 AST primitive 解析器获取结构化的 Literal::Array
-    let parsed = crate::parser::primitive::array(&raw)
-        .map_err(|_| {
-            nom::Err::Error(nom::error::Error::new(start_span, nom::error::ErrorKind::Tag))
-        })
-        .map(|(_, lit)| format::RValue::Literal(lit))?;
+    let parsed = format::RValue::Literal(parsed);
 
     Ok((
         input,
@@ -507,8 +764,69 @@ fn parse_array_value(input: Span) -> ParseResult<CstValue> {
     ))
 }
 
+/// 解析对象值 {key: value, ...}（支持嵌套、末尾逗号和换行）
+///
+/// 值可以是任意值节点；含有变量的对象在 `to_ast` 时报错。
+fn parse_object_value(input: Span) -> ParseResult<CstValue> {
+    let start_span = input;
+    let (mut input, _) = char('{').parse(input)?;
+
+    let mut entries = Vec::new();
+    loop {
+        let (rest, _) = many0(parse_trivia).parse(input)?;
+        input = rest;
+        if input.fragment().starts_with('}') {
+            break;
+        }
+
+        // 键：裸标识符或带引号的字符串
+        let key_start = input;
+        let (rest, key) =
+            alt((parse_identifier.map(|(name, _)| name), parse_quoted_string)).parse(input)?;
+        let key_span = SpanInfo::from_range(key_start, rest);
+        let key_raw = key_start.fragment()[..key_span.end - key_span.start].to_string();
+
+        let (rest, _) = (many0(parse_trivia), char(':'), many0(parse_trivia)).parse(rest)?;
+        let (rest, value) = parse_value(rest)?;
+        entries.push(CstObjectEntry {
+            key,
+            key_raw,
+            key_span,
+            value,
+        });
+
+        // 键值对之间用逗号分隔，最后一个逗号可选
+        let (rest, _) = many0(parse_trivia).parse(rest)?;
+        let (rest, comma) = opt(char(',')).parse(rest)?;
+        input = rest;
+        if comma.is_none() {
+            break;
+        }
+    }
+
+    let (input, _) = char('}').parse(input)?;
+    let span = SpanInfo::from_range(start_span, input);
+    let raw = start_span.fragment()[..span.end - span.start].to_string();
+    let parsed = format::RValue::Literal(object_literal(&entries).unwrap_or(format::Literal::Null));
+
+    Ok((
+        input,
+        CstValue {
+            kind: CstValueKind::Object { entries },
+            raw,
+            parsed,
+            span,
+        },
+    ))
+}
+
 /// 解析段落 ::paragraph_name(param1, param2="default") { ... }
 pub fn parse_paragraph(input: Span) -> ParseResult<CstParagraph> {
+    parse_paragraph_with(input, &[])
+}
+
+/// 解析段落，`stray` 为段落块内多余的 `}` 的位置
+fn parse_paragraph_with<'a>(input: Span<'a>, stray: &[usize]) -> ParseResult<'a, CstParagraph> {
     let start_span = input;
     let (input, leading_trivia) = many0(parse_trivia).parse(input)?;
 
@@ -517,6 +835,13 @@ pub fn parse_paragraph(input: Span) -> ParseResult<CstParagraph> {
     let (input, _) = tag("::").parse(input)?;
     let colon_span = SpanInfo::from_span_and_len(colon_start, 2);
 
+    // 解析可选的 macro 关键字（::macro name { ... }），名为 macro 的普通段落不受影响
+    let macro_start = input;
+    let (input, macro_keyword) =
+        opt(terminated(tag("macro"), (space1, peek(parse_identifier)))).parse(input)?;
+    let macro_keyword = macro_keyword.map(|_| SpanInfo::from_span_and_len(macro_start, 5));
+    let (input, _) = space0(input)?;
+
     // 解析段落名
     let name_start = input;
     let (input, (name, _)) = parse_identifier(input)?;
@@ -534,7 +859,7 @@ pub fn parse_paragraph(input: Span) -> ParseResult<CstParagraph> {
     let (input, _) = space0(input)?;
 
     // 解析块
-    let (input, block) = parse_block(input)?;
+    let (input, block) = parse_block_with(input, stray)?;
 
     let end_span = input;
     let span = SpanInfo::from_range(start_span, end_span);
@@ -544,6 +869,7 @@ pub fn parse_paragraph(input: Span) -> ParseResult<CstParagraph> {
         CstParagraph {
             name: name.clone(),
             colon_token: colon_span,
+            macro_keyword,
             name_span,
             parameters,
             open_paren,
@@ -589,6 +915,14 @@ fn parse_parameter(input: Span) -> ParseResult<CstParameter> {
     let name_end = input;
     let name_span = SpanInfo::from_range(name_start, name_end);
 
+    // 解析可选的类型标注 `: type`，类型名是否合法由 AST parser 检查
+    let (input, opt_type) =
+        opt(preceded((space0, char(':'), space0), parse_identifier)).parse(input)?;
+    let (type_, type_span) = match opt_type {
+        Some((type_name, span)) => (Some(type_name), Some(span)),
+        None => (None, None),
+    };
+
     // 解析可选的默认值
     let (input, opt_default) = opt(preceded(
         space0,
@@ -611,6 +945,8 @@ fn parse_parameter(input: Span) -> ParseResult<CstParameter> {
         CstParameter {
             name: name.clone(),
             name_span,
+            type_,
+            type_span,
             equals_token,
             default_value,
             span,
@@ -714,6 +1050,11 @@ fn parse_cst_attribute(input: Span) -> ParseResult<CstAttribute> {
 
 /// 解析块 { ... }
 pub fn parse_block(input: Span) -> ParseResult<CstBlock> {
+    parse_block_with(input, &[])
+}
+
+/// 解析块，`stray` 为块内多余的 `}` 的位置，见 [`parse_block_children`]
+fn parse_block_with<'a>(input: Span<'a>, stray: &[usize]) -> ParseResult<'a, CstBlock> {
     let start_span = input;
 
     // 解析 {
@@ -722,11 +1063,12 @@ pub fn parse_block(input: Span) -> ParseResult<CstBlock> {
     let open_brace_span = SpanInfo::from_span_and_len(open_brace_start, 1);
 
     // 解析块内容
-    let (input, children) = parse_block_children(input)?;
+    let (input, children) = parse_block_children(input, stray)?;
 
-    // 解析 }
+    // 解析 }。子节点只会停在 } 或输入末尾，缺少 } 说明块一直未闭合到文件末尾，
+    // 外层块同样无法闭合，用 Failure 通知外层直接放弃，避免逐层重试导致指数级回溯
     let close_brace_start = input;
-    let (input, _) = char('}').parse(input)?;
+    let (input, _) = cut(char('}')).parse(input)?;
     let close_brace_span = SpanInfo::from_span_and_len(close_brace_start, 1);
 
     let end_span = input;
@@ -735,6 +1077,7 @@ pub fn parse_block(input: Span) -> ParseResult<CstBlock> {
     Ok((
         input,
         CstBlock {
+            label: None,
             open_brace: open_brace_span,
             children,
             close_brace: close_brace_span,
@@ -743,8 +1086,38 @@ pub fn parse_block(input: Span) -> ParseResult<CstBlock> {
     ))
 }
 
-/// 解析块内的子节点
-fn parse_block_children(input: Span) -> ParseResult<Vec<CstNode>> {
+/// 解析带标签的块 group "name" { ... }，标签与 { 需在同一行
+pub fn parse_labeled_block(input: Span) -> ParseResult<CstBlock> {
+    let start_span = input;
+
+    // 解析 group 关键字
+    let keyword_start = input;
+    let (input, _) = tag("group").parse(input)?;
+    let keyword_span = SpanInfo::from_range(keyword_start, input);
+    let (input, _) = space1(input)?;
+
+    // 解析标签名
+    let name_start = input;
+    let (input, name) = parse_quoted_string(input)?;
+    let name_span = SpanInfo::from_range(name_start, input);
+    let raw = name_start.fragment()[..name_span.end - name_span.start].to_string();
+
+    let (input, _) = space0(input)?;
+    let (input, mut block) = parse_block(input)?;
+
+    block.label = Some(CstBlockLabel {
+        name,
+        raw,
+        keyword_span,
+        name_span,
+    });
+    block.span = SpanInfo::from_range(start_span, input);
+
+    Ok((input, block))
+}
+
+/// 解析块内的子节点，`stray` 中位置上的 `}` 是多余的，不结束块
+fn parse_block_children<'a>(input: Span<'a>, stray: &[usize]) -> ParseResult<'a, Vec<CstNode>> {
     let mut nodes = Vec::new();
     let mut remaining = input;
 
@@ -757,6 +1130,13 @@ fn parse_block_children(input: Span) -> ParseResult<Vec<CstNode>> {
                 nodes.push(CstNode::Trivia(trivia));
                 remaining = rest;
             }
+            // 多余的 } 不结束块，生成 Error 节点后继续
+            if stray.contains(&remaining.location_offset()) {
+                let (rest, _) = remaining.take_split(1);
+                nodes.push(unexpected_closer(remaining, rest, "}"));
+                remaining = rest;
+                continue;
+            }
             break;
         }
 
@@ -775,11 +1155,26 @@ fn parse_block_children(input: Span) -> ParseResult<Vec<CstNode>> {
             continue;
         }
 
+        // 尝试解析带标签的块（在文本行之前，避免 group "name" { 被当作文本）
+        match parse_labeled_block(remaining) {
+            Ok((rest, block)) => {
+                nodes.push(CstNode::Block(block));
+                remaining = rest;
+                continue;
+            }
+            Err(e @ nom::Err::Failure(_)) => return Err(e),
+            Err(_) => {}
+        }
+
         // 尝试解析嵌套块（在命令之前，避免 { 被误判）
-        if let Ok((rest, block)) = parse_block(remaining) {
-            nodes.push(CstNode::Block(block));
-            remaining = rest;
-            continue;
+        match parse_block(remaining) {
+            Ok((rest, block)) => {
+                nodes.push(CstNode::Block(block));
+                remaining = rest;
+                continue;
+            }
+            Err(e @ nom::Err::Failure(_)) => return Err(e),
+            Err(_) => {}
         }
 
         // 检查是否看起来像命令、系统调用或属性
@@ -811,23 +1206,25 @@ fn parse_block_children(input: Span) -> ParseResult<Vec<CstNode>> {
                     continue;
                 }
                 Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+                    if let Some((rest, recovered)) = recover_unclosed_paren(remaining, &e, |i| {
+                        parse_command(i).map(|(rest, cmd)| (rest, CstNode::Command(cmd)))
+                    }) {
+                        nodes.extend(recovered);
+                        remaining = rest;
+                        continue;
+                    }
+
                     // 命令语法错误，创建 Error 节点
                     let start_span = remaining;
                     // 简单地读取到行尾（查找换行符或到字符串末尾）
-                    let content = remaining.fragment();
-                    let line_end = content.find('\n').unwrap_or(content.len());
-                    let line_content = &content[..line_end];
-
-                    // 前进到行尾后（包括换行符）
-                    let bytes_to_skip = line_end + if line_end < content.len() { 1 } else { 0 };
-                    let (rest, _) =
-                        take::<usize, Span, nom::error::Error<Span>>(bytes_to_skip)(remaining)
-                            .unwrap_or((remaining, remaining));
+                    let (rest, line_content) = skip_line(remaining);
 
                     nodes.push(CstNode::Error {
                         content: line_content.to_string(),
                         span: SpanInfo::from_range(start_span, rest),
-                        message: format!("Invalid command syntax: {:?}", e.code),
+                        message: number_error_message(e.code)
+                            .map(str::to_string)
+                            .unwrap_or_else(|| format!("Invalid command syntax: {:?}", e.code)),
                     });
 
                     remaining = rest;
@@ -846,23 +1243,25 @@ fn parse_block_children(input: Span) -> ParseResult<Vec<CstNode>> {
                     continue;
                 }
                 Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+                    if let Some((rest, recovered)) = recover_unclosed_paren(remaining, &e, |i| {
+                        parse_systemcall(i).map(|(rest, sc)| (rest, CstNode::SystemCall(sc)))
+                    }) {
+                        nodes.extend(recovered);
+                        remaining = rest;
+                        continue;
+                    }
+
                     // 系统调用语法错误，创建 Error 节点
                     let start_span = remaining;
                     // 简单地读取到行尾
-                    let content = remaining.fragment();
-                    let line_end = content.find('\n').unwrap_or(content.len());
-                    let line_content = &content[..line_end];
-
-                    // 前进到行尾后（包括换行符）
-                    let bytes_to_skip = line_end + if line_end < content.len() { 1 } else { 0 };
-                    let (rest, _) =
-                        take::<usize, Span, nom::error::Error<Span>>(bytes_to_skip)(remaining)
-                            .unwrap_or((remaining, remaining));
+                    let (rest, line_content) = skip_line(remaining);
 
                     nodes.push(CstNode::Error {
                         content: line_content.to_string(),
                         span: SpanInfo::from_range(start_span, rest),
-                        message: format!("Invalid system call syntax: {:?}", e.code),
+                        message: number_error_message(e.code)
+                            .map(str::to_string)
+                            .unwrap_or_else(|| format!("Invalid system call syntax: {:?}", e.code)),
                     });
 
                     remaining = rest;
@@ -873,10 +1272,42 @@ fn parse_block_children(input: Span) -> ParseResult<Vec<CstNode>> {
         }
 
         // 不像命令或系统调用，尝试解析文本行
-        if let Ok((rest, text_line)) = parse_text_line(remaining) {
-            nodes.push(CstNode::TextLine(text_line));
-            remaining = rest;
-            continue;
+        match parse_text_line(remaining) {
+            Ok((rest, text_line)) => {
+                nodes.push(CstNode::TextLine(text_line));
+                remaining = rest;
+                continue;
+            }
+            Err(nom::Err::Failure(e)) if e.code == nom::error::ErrorKind::Escaped => {
+                // 被 ParserConfig::unknown_escape 拒绝的转义，整行作为 Error 节点
+                let start_span = remaining;
+                let (rest, line_content) = skip_line(remaining);
+
+                nodes.push(CstNode::Error {
+                    content: line_content.to_string(),
+                    span: SpanInfo::from_range(start_span, rest),
+                    message: "unknown escape sequence".to_string(),
+                });
+
+                remaining = rest;
+                continue;
+            }
+            Err(nom::Err::Failure(_)) => {
+                // 模板字符串插值的语法错误（如 `${?}`），整行作为 Error 节点，
+                // 避免逐字符跳过后丢失内容
+                let start_span = remaining;
+                let (rest, line_content) = skip_line(remaining);
+
+                nodes.push(CstNode::Error {
+                    content: line_content.to_string(),
+                    span: SpanInfo::from_range(start_span, rest),
+                    message: "Invalid template literal".to_string(),
+                });
+
+                remaining = rest;
+                continue;
+            }
+            Err(_) => {}
         }
 
         // 容错：跳过一个字符
@@ -890,6 +1321,53 @@ fn parse_block_children(input: Span) -> ParseResult<Vec<CstNode>> {
     Ok((remaining, nodes))
 }
 
+/// 前进到行尾后（包括换行符），返回剩余输入和该行内容（不含换行符）
+///
+/// 按字节切分：nom 的 `take` 按字符计数，不能直接使用字节偏移
+fn skip_line(input: Span<'_>) -> (Span<'_>, &str) {
+    let content = *input.fragment();
+    let line_end = content.find(is_line_break).unwrap_or(content.len());
+    let break_len = if content[line_end..].starts_with("\r\n") {
+        2
+    } else if line_end < content.len() {
+        1
+    } else {
+        0
+    };
+    let bytes_to_skip = line_end + break_len;
+    let (rest, _) = input.take_split(bytes_to_skip);
+    (rest, &content[..line_end])
+}
+
+/// 命令或系统调用的参数括号未闭合就遇到了 `{`（如 `@cmd(a=1 {`）时的恢复：
+/// 括号之前的部分照常解析为节点，从左括号到 `{` 之前生成 Error 节点，
+/// 之后的块照常解析，避免整块内容被错位解析
+fn recover_unclosed_paren<'a>(
+    remaining: Span<'a>,
+    error: &nom::error::Error<Span<'a>>,
+    parse: fn(Span<'a>) -> ParseResult<'a, CstNode>,
+) -> Option<(Span<'a>, [CstNode; 2])> {
+    if error.code != nom::error::ErrorKind::Char || !error.input.fragment().starts_with('(') {
+        return None;
+    }
+
+    let paren = error.input;
+    let (_, before_paren) =
+        remaining.take_split(paren.location_offset() - remaining.location_offset());
+    let (_, node) = parse(before_paren).ok()?;
+    let (rest, _) = parse_parenthesized_open(paren).ok()?;
+    let skipped = &paren.fragment()[..rest.location_offset() - paren.location_offset()];
+    let content = skipped.trim_end();
+    let (content_end, _) = paren.take_split(content.len());
+
+    let error = CstNode::Error {
+        content: content.to_string(),
+        span: SpanInfo::from_range(paren, content_end),
+        message: "expected `)` before block".to_string(),
+    };
+    Some((rest, [node, error]))
+}
+
 /// 解析嵌入代码 @{ ... } 或 ## ... ##
 pub fn parse_embedded_code(input: Span) -> ParseResult<CstEmbeddedCode> {
     alt((parse_embedded_code_brace, parse_embedded_code_hash)).parse(input)
@@ -976,9 +1454,9 @@ fn parse_whitespace_inline(input: Span) -> ParseResult<()> {
     value((), many0(one_of(" \t"))).parse(input)
 }
 
-/// 解析换行符（\n 或 \r\n）
+/// 解析换行符（\n、\r\n 或单独的 \r）
 fn parse_line_ending(input: Span) -> ParseResult<()> {
-    value((), alt((tag("\r\n"), tag("\n")))).parse(input)
+    value((), alt((tag("\r\n"), tag("\n"), tag("\r")))).parse(input)
 }
 
 /// 解析文本行 [leading] text #tailing
@@ -1003,9 +1481,33 @@ pub fn parse_text_line(input: Span) -> ParseResult<CstTextLine> {
     let (input, leading) = opt(parse_leading_text).parse(input)?;
     let (input, _) = space0(input)?;
 
+    // 多说话人模式下，主文本在下一个 [说话人] 前结束
+    let multi = multi_speaker() && leading.is_some();
+
     // 解析主文本（可选）
-    let (input, text) = opt(parse_text).parse(input)?;
-    let (input, _) = space0(input)?;
+    let (input, text) = if multi {
+        opt(parse_segment_text).parse(input)?
+    } else {
+        opt(parse_text).parse(input)?
+    };
+    let (mut input, _) = space0(input)?;
+
+    // 解析后续的说话人片段
+    let mut segments = Vec::new();
+    if multi {
+        while let Ok((rest, leading)) = parse_leading_text(input) {
+            let (rest, _) = space0(rest)?;
+            let (rest, text) = opt(parse_segment_text).parse(rest)?;
+            let span = SpanInfo::from_range(input, rest);
+            let (rest, _) = space0(rest)?;
+            segments.push(CstTextSegment {
+                leading,
+                text,
+                span,
+            });
+            input = rest;
+        }
+    }
 
     // 解析后缀标记（可选）
     let (input, tailing) = opt(parse_tailing_text).parse(input)?;
@@ -1018,6 +1520,7 @@ pub fn parse_text_line(input: Span) -> ParseResult<CstTextLine> {
         CstTextLine {
             leading,
             text,
+            segments,
             tailing,
             span,
             leading_trivia,
@@ -1038,7 +1541,7 @@ fn parse_leading_quoted(i: Span) -> ParseResult<CstLeadingTextContent> {
 
 fn parse_leading_bare(i: Span) -> ParseResult<CstLeadingTextContent> {
     // 裸文本：读取到 ] 为止
-    let (i, text) = take_while(|c| c != ']' && c != '\n').parse(i)?;
+    let (i, text) = take_while(|c| c != ']' && !is_line_break(c)).parse(i)?;
     Ok((
         i,
         CstLeadingTextContent::Text(text.fragment().trim().to_string()),
@@ -1090,7 +1593,11 @@ fn parse_text(input: Span) -> ParseResult<CstText> {
     let start_span = input;
 
     // 尝试模板字符串 `...`
-    if let Ok((i, tpl)) = parse_template_literal(input) {
+    let template = match parse_template_literal(input) {
+        Err(nom::Err::Failure(e)) => return Err(nom::Err::Failure(e)),
+        result => result,
+    };
+    if let Ok((i, tpl)) = template {
         let span = SpanInfo::from_range(start_span, i);
         return Ok((
             i,
@@ -1106,7 +1613,11 @@ fn parse_text(input: Span) -> ParseResult<CstText> {
     // 尝试带引号的字符串 "..." 或 '...'
     if let Some(quote_char) = input.fragment().chars().next() {
         if quote_char == '"' || quote_char == '\'' {
-            if let Ok((i, text)) = parse_quoted_string(input) {
+            let quoted = match parse_quoted_string(input) {
+                Err(nom::Err::Failure(e)) => return Err(nom::Err::Failure(e)),
+                result => result,
+            };
+            if let Ok((i, text)) = quoted {
                 let quote_style = if quote_char == '"' {
                     QuoteStyle::Double
                 } else {
@@ -1144,6 +1655,38 @@ fn parse_text(input: Span) -> ParseResult<CstText> {
     ))
 }
 
+/// 解析多说话人行中一个片段的文本，裸文本在下一个 [说话人] 前结束
+fn parse_segment_text(input: Span) -> ParseResult<CstText> {
+    let fragment = input.fragment();
+    let line = &fragment[..fragment.find(is_line_break).unwrap_or(fragment.len())];
+    let Some(end) = speaker_boundary(line) else {
+        return parse_text(input);
+    };
+    if fragment.starts_with(['"', '\'', '`']) {
+        return parse_text(input);
+    }
+
+    let text_str = line[..end].trim_end().to_string();
+    if text_str.is_empty() {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::TakeWhile1,
+        )));
+    }
+    let (i, _) = take(text_str.len()).parse(input)?;
+    let span = SpanInfo::from_range(input, i);
+
+    Ok((
+        i,
+        CstText {
+            kind: CstTextKind::Bare,
+            raw: text_str.clone(),
+            parsed: text_str,
+            span,
+        },
+    ))
+}
+
 /// 解析后缀标记 #tag
 fn parse_tailing_text(input: Span) -> ParseResult<CstTailingText> {
     let start_span = input;
@@ -1180,12 +1723,28 @@ fn parse_template_literal(input: Span) -> ParseResult<CstTemplateLiteral> {
     // 解析开始的反引号
     let (input, _) = char('`').parse(input)?;
 
-    let mut parts = Vec::new();
+    let (remaining, parts) = parse_template_parts(input)?;
+
+    // 解析结束的反引号（条件片段外的 `${:}` / `${/}` 会在这里报错）
+    let (input, _) = char('`').parse(remaining)?;
+
+    let end_span = input;
+    let span = SpanInfo::from_range(start_span, end_span);
+
+    Ok((input, CstTemplateLiteral { parts, span }))
+}
+
+/// 解析模板字符串的各个部分，遇到结束反引号或条件片段的 `${:}` / `${/}` 时停止
+fn parse_template_parts(input: Span) -> ParseResult<Vec<CstTemplatePart>> {
     let mut remaining = input;
+    let mut parts = Vec::new();
 
     loop {
-        // 检查是否到达结束引号
-        if remaining.fragment().starts_with('`') {
+        // 检查是否到达结束引号或条件片段的分隔符
+        if remaining.fragment().starts_with('`')
+            || remaining.fragment().starts_with("${:}")
+            || remaining.fragment().starts_with("${/}")
+        {
             break;
         }
 
@@ -1197,31 +1756,29 @@ fn parse_template_literal(input: Span) -> ParseResult<CstTemplateLiteral> {
             )));
         }
 
-        // 尝试解析变量插值 ${...}
-        if remaining.fragment().starts_with("${") {
+        // 尝试解析条件片段 ${?cond}...${:}...${/}
+        if remaining.fragment().starts_with("${?") {
+            let (rest, part) = parse_template_conditional(remaining)?;
+            parts.push(part);
+            remaining = rest;
+        } else if remaining.fragment().starts_with("${") {
             let value_start = remaining;
             let (rest, _) = tag("${").parse(remaining)?;
             let open_token = SpanInfo::from_span_and_len(value_start, 2);
 
-            // 解析变量名
-            let var_start = rest;
-            let (rest, (var_name, _)) = parse_identifier(rest)?;
-            let var_end = rest;
-            let variable_span = SpanInfo::from_range(var_start, var_end);
+            // ${ 之后的语法错误不再回退为裸文本
+            let (rest, value) = cut(parse_template_value).parse(rest)?;
 
             // 解析 }
             let close_start = rest;
-            let (rest, _) = char('}').parse(rest)?;
+            let (rest, _) = cut(char('}')).parse(rest)?;
             let close_token = SpanInfo::from_span_and_len(close_start, 1);
 
             let part_span = SpanInfo::from_range(value_start, rest);
 
             parts.push(CstTemplatePart::Value {
                 open_token,
-                variable: format::Variable {
-                    chain: vec![var_name.clone()],
-                },
-                variable_span,
+                value,
                 close_token,
                 span: part_span,
             });
@@ -1243,17 +1800,8 @@ fn parse_template_literal(input: Span) -> ParseResult<CstTemplateLiteral> {
                     let (rest, _) = char('\\').parse(remaining)?;
                     let next_ch = rest.fragment().chars().next().unwrap();
 
-                    let escaped = match next_ch {
-                        'n' => '\n',
-                        't' => '\t',
-                        'r' => '\r',
-                        '\\' => '\\',
-                        '`' => '`',
-                        '$' => '$',
-                        _ => next_ch,
-                    };
-
-                    text.push(escaped);
+                    let escaped = escape_char(remaining, next_ch, template_escape)?;
+                    escaped.push_to(&mut text);
                     let (rest, _) = take(1usize)(rest)?;
                     remaining = rest;
                 } else {
@@ -1275,13 +1823,83 @@ fn parse_template_literal(input: Span) -> ParseResult<CstTemplateLiteral> {
         }
     }
 
-    // 解析结束的反引号
-    let (input, _) = char('`').parse(remaining)?;
+    Ok((remaining, parts))
+}
 
-    let end_span = input;
-    let span = SpanInfo::from_range(start_span, end_span);
+/// 解析模板插值中的变量链 `a.b.c`，`.` 之后必须是标识符
+fn parse_variable_chain(input: Span) -> ParseResult<format::Variable> {
+    let (input, (first, _)) = parse_identifier(input)?;
+    let (input, rest) = many0(preceded(char('.'), cut(parse_identifier))).parse(input)?;
 
-    Ok((input, CstTemplateLiteral { parts, span }))
+    let mut chain = vec![first];
+    chain.extend(rest.into_iter().map(|(name, _)| name));
+    Ok((input, format::Variable { chain }))
+}
+
+/// 解析插值或条件片段中的值，与 AST 的 rvalue 一致：字面量（包括数组和对象）
+/// 或变量链，如 42、"text"、[1, 2]、player.name
+fn parse_template_value(input: Span) -> ParseResult<CstValue> {
+    alt((
+        terminated(
+            alt((
+                parse_string_value,
+                parse_number_value,
+                parse_boolean_value,
+                parse_array_value,
+                parse_object_value,
+            )),
+            peek(char('}')),
+        ),
+        parse_template_variable,
+    ))
+    .parse(input)
+}
+
+/// 解析模板插值中的变量链，作为变量值
+fn parse_template_variable(input: Span) -> ParseResult<CstValue> {
+    let start_span = input;
+    let (input, variable) = parse_variable_chain(input)?;
+    let span = SpanInfo::from_range(start_span, input);
+
+    Ok((
+        input,
+        CstValue {
+            kind: CstValueKind::Variable,
+            raw: variable.chain.join("."),
+            parsed: format::RValue::Variable(variable),
+            span,
+        },
+    ))
+}
+
+/// 解析条件片段 `${?cond}then${:}otherwise${/}`
+fn parse_template_conditional(input: Span) -> ParseResult<CstTemplatePart> {
+    let start_span = input;
+    let (rest, _) = tag("${?").parse(input)?;
+
+    let (rest, condition) = cut(parse_template_value).parse(rest)?;
+    let (rest, _) = cut(char('}')).parse(rest)?;
+
+    let (rest, then) = parse_template_parts(rest)?;
+    let (rest, otherwise) = if rest.fragment().starts_with("${:}") {
+        let (rest, _) = tag("${:}").parse(rest)?;
+        parse_template_parts(rest)?
+    } else {
+        (rest, Vec::new())
+    };
+    let (rest, _) = cut(tag("${/}")).parse(rest)?;
+
+    let span = SpanInfo::from_range(start_span, rest);
+
+    Ok((
+        rest,
+        CstTemplatePart::Conditional {
+            condition,
+            then,
+            otherwise,
+            span,
+        },
+    ))
 }
 
 /// 解析带引号的字符串（支持转义）
@@ -1317,36 +1935,6 @@ fn parse_quoted_string(input: Span) -> ParseResult<String> {
             let next_ch = rest.fragment().chars().next().unwrap();
 
             match next_ch {
-                'n' => {
-                    result.push('\n');
-                    let (rest, _) = take(1usize)(rest)?;
-                    remaining = rest;
-                }
-                't' => {
-                    result.push('\t');
-                    let (rest, _) = take(1usize)(rest)?;
-                    remaining = rest;
-                }
-                'r' => {
-                    result.push('\r');
-                    let (rest, _) = take(1usize)(rest)?;
-                    remaining = rest;
-                }
-                '\\' => {
-                    result.push('\\');
-                    let (rest, _) = take(1usize)(rest)?;
-                    remaining = rest;
-                }
-                '"' => {
-                    result.push('"');
-                    let (rest, _) = take(1usize)(rest)?;
-                    remaining = rest;
-                }
-                '\'' => {
-                    result.push('\'');
-                    let (rest, _) = take(1usize)(rest)?;
-                    remaining = rest;
-                }
                 'u' => {
                     // Unicode 转义 \uXXXX 或 \u{XXXX}
                     let (rest, _) = take(1usize)(rest)?; // 消耗 'u'
@@ -1429,7 +2017,9 @@ fn parse_quoted_string(input: Span) -> ParseResult<String> {
                     }
                 }
                 _ => {
-                    result.push(next_ch);
+                    // 未知转义按 ParserConfig::unknown_escape 处理
+                    let escaped = escape_char(remaining, next_ch, simple_escape)?;
+                    escaped.push_to(&mut result);
                     let (rest, _) = take(1usize)(rest)?;
                     remaining = rest;
                 }
@@ -1448,6 +2038,24 @@ fn parse_quoted_string(input: Span) -> ParseResult<String> {
     )))
 }
 
+/// 解析 `\` 之后的单字符转义，`backslash` 指向 `\`，`known` 为该字符串可用的转义。
+/// 被拒绝的未知转义返回 Failure，避免回退为裸文本
+fn escape_char(
+    backslash: Span,
+    ch: char,
+    known: fn(char) -> Option<char>,
+) -> Result<Escaped, nom::Err<nom::error::Error<Span>>> {
+    match known(ch) {
+        Some(c) => Ok(Escaped::Char(c)),
+        None => unknown_escape_text(ch, unknown_escape()).ok_or_else(|| {
+            nom::Err::Failure(nom::error::Error::new(
+                backslash,
+                nom::error::ErrorKind::Escaped,
+            ))
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1513,7 +2121,7 @@ mod tests {
         let cst = parse_tolerant("test", input);
 
         // 应该包含空白、command1、空白、注释、空白、command2、空白
-        assert!(cst.nodes.len() > 0);
+        assert!(!cst.nodes.is_empty());
 
         // 统计命令数量
         let cmd_count = cst
@@ -1541,6 +2149,93 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_number_units() {
+        use format::Literal;
+
+        let config = ParserConfig {
+            number_units: true,
+            ..Default::default()
+        };
+        let tests = vec![
+            ("600ms", Literal::Integer(600), CstValueKind::Integer),
+            ("1.5s", Literal::Float(1500.0), CstValueKind::Float),
+            ("1_000px", Literal::Integer(1000), CstValueKind::Integer),
+            ("50%", Literal::Float(0.5), CstValueKind::Float),
+            ("42", Literal::Integer(42), CstValueKind::Integer),
+        ];
+
+        for (input, expected, kind) in tests {
+            let (rest, value) =
+                with_config(&config, || parse_number_value(Span::new(input))).unwrap();
+            assert!(rest.fragment().is_empty(), "未完整解析: {}", input);
+            // raw 保留单位后缀
+            assert_eq!(value.raw, input);
+            assert_eq!(value.kind, kind);
+            assert_eq!(value.to_ast().unwrap(), format::RValue::Literal(expected));
+        }
+
+        // 未启用时不解析单位
+        let (rest, value) = parse_number_value(Span::new("600ms")).unwrap();
+        assert_eq!(*rest.fragment(), "ms");
+        assert_eq!(value.raw, "600");
+    }
+
+    #[test]
+    fn test_parse_hex_and_separated_numbers() {
+        use format::Literal;
+
+        let tests = vec![
+            ("0xFF8800", Literal::Integer(0xFF8800)),
+            ("0Xff", Literal::Integer(0xff)),
+            ("-0x10", Literal::Integer(-16)),
+            ("0xAB_CD", Literal::Integer(0xABCD)),
+            ("1_000_000", Literal::Integer(1_000_000)),
+            ("-1_000", Literal::Integer(-1000)),
+            ("1_000.5", Literal::Float(1000.5)),
+            ("1.5e-3", Literal::Float(1.5e-3)),
+            ("2E6", Literal::Float(2e6)),
+            ("-2.5e+2", Literal::Float(-250.0)),
+        ];
+
+        for (input, expected) in tests {
+            let (rest, value) = parse_number_value(Span::new(input)).unwrap();
+            assert!(rest.fragment().is_empty(), "未完整解析: {}", input);
+            // raw 保留原始写法
+            assert_eq!(value.raw, input);
+            assert_eq!(value.to_ast().unwrap(), format::RValue::Literal(expected));
+        }
+
+        // 指数标记之后没有数字时直接失败
+        assert!(matches!(
+            parse_number_value(Span::new("1e")),
+            Err(nom::Err::Failure(_))
+        ));
+        assert!(matches!(
+            parse_number_value(Span::new("1.5e+")),
+            Err(nom::Err::Failure(_))
+        ));
+
+        // 0x 之后没有数字时直接失败，而不是解析为 0 或变量
+        assert!(matches!(
+            parse_number_value(Span::new("0x")),
+            Err(nom::Err::Failure(_))
+        ));
+        assert!(matches!(
+            parse_value(Span::new("0xzz")),
+            Err(nom::Err::Failure(_))
+        ));
+
+        // 格式化后保持原始写法
+        let cst = parse_tolerant("test", "@tint color=0xFF8800 count=1_000_000\n");
+        let result = crate::cst::formatter::CstFormatter::new().format(&cst);
+        assert!(
+            result.contains("@tint color=0xFF8800 count=1_000_000"),
+            "got: {}",
+            result
+        );
+    }
+
     #[test]
     fn test_parse_array_value() {
         // 基本整数数组
@@ -1562,6 +2257,21 @@ mod tests {
         let (_, v) = parse_array_value(Span::new(r#"[1, "hello", true]"#)).unwrap();
         assert!(matches!(v.kind, CstValueKind::Array));
 
+        // 字符串内的括号不影响匹配，数组内可以换行
+        let (rest, v) = parse_array_value(Span::new("[\"a]\",\n  ['b[', 2],\n] x")).unwrap();
+        assert_eq!(*rest.fragment(), " x");
+        assert_eq!(v.span.end_line, 3);
+        assert_eq!(
+            v.to_ast().unwrap(),
+            format::RValue::Literal(format::Literal::Array(vec![
+                format::Literal::String("a]".to_string()),
+                format::Literal::Array(vec![
+                    format::Literal::String("b[".to_string()),
+                    format::Literal::Integer(2),
+                ]),
+            ]))
+        );
+
         // 作为命令参数：整条行格式化后应完整保留（紧缩格式）
         let cst = parse_tolerant("test", "@cmd x=[0,0] y=false\n");
         let formatter = crate::cst::formatter::CstFormatter::new();
@@ -1577,6 +2287,325 @@ mod tests {
         let cst = parse_tolerant("test", "@cmd pts=[[1, 2], [3, 4]]\n");
         let result = formatter.format(&cst);
         assert!(result.contains("@cmd pts=[[1,2],[3,4]]"), "got: {}", result);
+
+        // 字符串内的转义引号与注释中的括号不影响匹配
+        let input = r#"@cmd x=["a\"]", 1] y=2"#;
+        let cst = parse_tolerant("test", input);
+        assert_eq!(formatter.format(&cst), "@cmd x=[\"a\\\"]\",1] y=2\n");
+        let input = "::entry {\n    @cmd x=[1, // ]\n        2] y=2\n}\n";
+        let cst = parse_tolerant("test", input);
+        assert_eq!(collect_error_nodes(&cst.nodes), vec![]);
+        assert_eq!(
+            cst.to_ast().unwrap(),
+            crate::parser::parse_story("test", input).unwrap()
+        );
+        // 含注释的数组保留原文
+        assert_eq!(formatter.format(&cst), input);
+
+        // 多字节字符：span 按字节计算，不能越过数组末尾
+        let input = r#"["你好", "世界"] rest"#;
+        let (rest, v) = parse_array_value(Span::new(input)).unwrap();
+        assert_eq!(v.raw, r#"["你好", "世界"]"#);
+        assert_eq!(*rest.fragment(), " rest");
+        assert_eq!(v.span.end, v.raw.len());
+    }
+
+    #[test]
+    fn test_parse_object_value() {
+        let input = "{retries: 3, 'label': \"ok\",\n  nested: {list: [1, 2]},\n} rest";
+        let (rest, v) = parse_object_value(Span::new(input)).unwrap();
+        assert_eq!(*rest.fragment(), " rest");
+        assert_eq!(v.raw, &input[..input.len() - 5]);
+
+        let CstValueKind::Object { entries } = &v.kind else {
+            panic!("Expected object value");
+        };
+        let keys: Vec<_> = entries.iter().map(|e| e.key_raw.as_str()).collect();
+        assert_eq!(keys, vec!["retries", "'label'", "nested"]);
+        assert_eq!(entries[1].key, "label");
+        assert!(matches!(entries[2].value.kind, CstValueKind::Object { .. }));
+
+        let format::RValue::Literal(format::Literal::Object(object)) = v.to_ast().unwrap() else {
+            panic!("Expected object literal");
+        };
+        assert_eq!(object["retries"], format::Literal::Integer(3));
+        assert_eq!(object["label"], format::Literal::String("ok".to_string()));
+        assert_eq!(
+            object["nested"],
+            format::Literal::Object(
+                [(
+                    "list".to_string(),
+                    format::Literal::Array(vec![
+                        format::Literal::Integer(1),
+                        format::Literal::Integer(2),
+                    ]),
+                )]
+                .into_iter()
+                .collect()
+            )
+        );
+
+        // 空对象
+        let (_, v) = parse_object_value(Span::new("{}")).unwrap();
+        assert_eq!(
+            v.to_ast().unwrap(),
+            format::RValue::Literal(format::Literal::Object(Default::default()))
+        );
+
+        // 对象的值不能是变量
+        let (_, v) = parse_object_value(Span::new("{name: player.name}")).unwrap();
+        let err = v.to_ast().unwrap_err();
+        assert!(
+            err.to_string().contains("object values must be literals"),
+            "got: {}",
+            err
+        );
+
+        // 作为命令参数：格式化后保持键的顺序
+        let cst = parse_tolerant("test", "@configure opts={retries:3,label:\"ok\"} x=1\n");
+        let result = crate::cst::formatter::CstFormatter::new().format(&cst);
+        assert!(
+            result.contains("@configure opts={retries: 3, label: \"ok\"} x=1"),
+            "got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_parse_tolerant_deeply_nested_unclosed_blocks() {
+        // 未闭合的嵌套块不能逐层重试，否则耗时随深度指数增长
+        let input = format!("::entry {{\n{}@cmd\n", "group \"g\" {\n{\n".repeat(16));
+        let cst = parse_tolerant("test", &input);
+
+        assert!(!cst.nodes.iter().any(|n| matches!(n, CstNode::Paragraph(_))));
+        assert!(cst
+            .nodes
+            .iter()
+            .any(|n| matches!(n, CstNode::Command(c) if c.command == "cmd")));
+    }
+
+    #[test]
+    fn test_parse_tolerant_invalid_command_with_multibyte_chars() {
+        // 错误恢复按字节跳过整行，多字节字符不能导致停滞
+        let input = "::entry {\n@\u{FFFD}\u{FFFD}命令\n@next\n}\n";
+        let cst = parse_tolerant("test", input);
+
+        let para = match &cst.nodes[0] {
+            CstNode::Paragraph(p) => p,
+            other => panic!("期望段落，实际为 {:?}", other),
+        };
+        let errors: Vec<_> = para
+            .block
+            .children
+            .iter()
+            .filter_map(|n| match n {
+                CstNode::Error { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(errors, vec!["@\u{FFFD}\u{FFFD}命令"]);
+        assert!(para
+            .block
+            .children
+            .iter()
+            .any(|n| matches!(n, CstNode::Command(c) if c.command == "next")));
+    }
+
+    #[test]
+    fn test_parse_tolerant_unclosed_paren_before_block() {
+        let input = "::entry {\n    @cmd(a=1 {\n        hello\n    }\n    #goto(paragraph=\"end\" {\n    }\n    @next\n}\n";
+        let cst = parse_tolerant("test", input);
+        assert_eq!(cst.nodes.len(), 2, "段落不应被提前闭合: {:?}", cst.nodes);
+
+        let para = match &cst.nodes[0] {
+            CstNode::Paragraph(p) => p,
+            other => panic!("期望段落，实际为 {:?}", other),
+        };
+        let children: Vec<_> = para
+            .block
+            .children
+            .iter()
+            .filter(|n| !matches!(n, CstNode::Trivia(_)))
+            .collect();
+        assert_eq!(children.len(), 7, "{:?}", children);
+
+        match children[0] {
+            CstNode::Command(c) => {
+                assert_eq!(c.command, "cmd");
+                assert!(c.arguments.is_empty());
+            }
+            other => panic!("期望命令，实际为 {:?}", other),
+        }
+        match children[1] {
+            CstNode::Error {
+                content,
+                span,
+                message,
+            } => {
+                assert_eq!(content, "(a=1");
+                assert_eq!(message, "expected `)` before block");
+                // 错误位于左括号处
+                assert_eq!((span.start_line, span.start_column), (2, 8));
+                assert_eq!(span.end_column, 12);
+            }
+            other => panic!("期望错误节点，实际为 {:?}", other),
+        }
+        assert!(matches!(children[2], CstNode::Block(b) if b.children.len() == 3));
+        assert!(matches!(children[3], CstNode::SystemCall(s) if s.command == "goto"));
+        assert!(
+            matches!(children[4], CstNode::Error { message, .. } if message == "expected `)` before block")
+        );
+        assert!(matches!(children[5], CstNode::Block(_)));
+        assert!(matches!(children[6], CstNode::Command(c) if c.command == "next"));
+    }
+
+    /// 所有 Error 节点（含段落与块内）的内容、消息与起始位置
+    fn collect_error_nodes(nodes: &[CstNode]) -> Vec<(String, String, usize, usize)> {
+        let mut errors = Vec::new();
+        for node in nodes {
+            match node {
+                CstNode::Error {
+                    content,
+                    message,
+                    span,
+                } => errors.push((
+                    content.clone(),
+                    message.clone(),
+                    span.start_line,
+                    span.start_column,
+                )),
+                CstNode::Paragraph(p) => errors.extend(collect_error_nodes(&p.block.children)),
+                CstNode::Block(b) => errors.extend(collect_error_nodes(&b.children)),
+                _ => {}
+            }
+        }
+        errors
+    }
+
+    #[test]
+    fn test_parse_tolerant_stray_close_brace_in_block() {
+        let input = concat!(
+            "::entry {\n    @a\n    }\n    @b\n",
+            "    {\n        \"x\"\n    }\n}\n",
+            "::next {\n    @c\n}\n",
+        );
+        let cst = parse_tolerant("test", input);
+        assert_eq!(
+            collect_error_nodes(&cst.nodes),
+            vec![("}".to_string(), "unexpected `}`".to_string(), 3, 4)]
+        );
+
+        // 多余的 } 不会提前结束段落
+        let para = match &cst.nodes[0] {
+            CstNode::Paragraph(p) => p,
+            other => panic!("期望段落，实际为 {:?}", other),
+        };
+        assert!(para
+            .block
+            .children
+            .iter()
+            .any(|n| matches!(n, CstNode::Command(c) if c.command == "b")));
+        assert!(matches!(&cst.nodes[2], CstNode::Paragraph(p) if p.name == "next"));
+    }
+
+    #[test]
+    fn test_parse_tolerant_stray_closers_at_top_level() {
+        let input = "::entry {\n    { @a }\n}\n}\n)\n::next {\n    \"it's {\"\n}\n";
+        let cst = parse_tolerant("test", input);
+        assert_eq!(
+            collect_error_nodes(&cst.nodes),
+            vec![
+                ("}".to_string(), "unexpected `}`".to_string(), 4, 0),
+                (")".to_string(), "unexpected `)`".to_string(), 5, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_tolerant_brace_in_text_is_not_stray() {
+        let input = "::entry {\n    {\n        \"a\"\n    }\n    hi there } ok\n}\n";
+        assert!(crate::parser::parse_story("test", input).is_ok());
+
+        let cst = parse_tolerant("test", input);
+        assert_eq!(collect_error_nodes(&cst.nodes), vec![]);
+        assert_eq!(cst.to_ast().unwrap().paragraphs.len(), 1);
+        let output = crate::cst::formatter::CstFormatter::new().format(&cst);
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_parse_tolerant_top_level_attribute() {
+        let cst = parse_tolerant("test", "#[loop]");
+        assert_eq!(cst.nodes.len(), 1);
+        match &cst.nodes[0] {
+            CstNode::Attribute(attr) => {
+                assert_eq!(attr.keyword, "loop");
+                assert_eq!(attr.condition, None);
+                assert_eq!((attr.span.start, attr.span.end), (0, 7));
+            }
+            other => panic!("期望属性，实际为 {:?}", other),
+        }
+
+        let cst = parse_tolerant("test", "#[cond(\"a\")]\n::entry {\n    @a\n}\n");
+        assert!(matches!(&cst.nodes[0], CstNode::Attribute(a) if a.keyword == "cond"));
+        assert!(matches!(&cst.nodes[2], CstNode::Paragraph(p) if p.name == "entry"));
+    }
+
+    #[test]
+    fn test_parse_tolerant_balanced_braces_have_no_errors() {
+        let input = concat!(
+            "::entry {\n",
+            "    @{ if (a) { b() } }\n",
+            "    // }\n",
+            "    { \"}\" /* } */ }\n",
+            "    `}`\n",
+            "}\n",
+        );
+        let cst = parse_tolerant("test", input);
+        assert_eq!(collect_error_nodes(&cst.nodes), vec![]);
+    }
+
+    #[test]
+    fn test_parse_invalid_numbers_as_error_nodes() {
+        let input = "::main {\n@wait time=99999999999999999999\n#fade alpha=1.5e\n@next\n}\n";
+        let cst = parse_tolerant("test", input);
+
+        let para = match &cst.nodes[0] {
+            CstNode::Paragraph(p) => p,
+            other => panic!("期望段落，实际为 {:?}", other),
+        };
+        let children: Vec<_> = para
+            .block
+            .children
+            .iter()
+            .filter(|n| !matches!(n, CstNode::Trivia(_)))
+            .collect();
+        assert_eq!(children.len(), 3, "{:?}", children);
+
+        // 溢出 i64 的整数不会静默变成 0
+        assert!(matches!(
+            children[0],
+            CstNode::Error { content, message, .. }
+                if content == "@wait time=99999999999999999999"
+                    && message == "number literal out of range"
+        ));
+        // 指数部分缺少数字
+        assert!(matches!(
+            children[1],
+            CstNode::Error { message, .. }
+                if message == "malformed number literal: expected exponent digits"
+        ));
+        assert!(matches!(children[2], CstNode::Command(c) if c.command == "next"));
+
+        assert!(matches!(
+            parse_number_value(Span::new("1e999")),
+            Err(nom::Err::Failure(e)) if e.code == nom::error::ErrorKind::TooLarge
+        ));
+        let (_, value) = parse_number_value(Span::new("-9223372036854775808")).unwrap();
+        assert_eq!(
+            value.parsed,
+            format::RValue::Literal(format::Literal::Integer(i64::MIN))
+        );
     }
 
     #[test]
@@ -1584,7 +2613,7 @@ mod tests {
         let input = r#"@changebg src="test.jpg" fadeTime=600"#;
         let (_, cmd) = parse_command(Span::new(input)).unwrap();
 
-        let ast_cmd = cmd.to_ast();
+        let ast_cmd = cmd.to_ast().unwrap();
         assert_eq!(ast_cmd.command, "changebg");
         assert_eq!(ast_cmd.arguments.len(), 2);
     }
@@ -1613,6 +2642,18 @@ mod tests {
         assert!(param.equals_token.is_some());
     }
 
+    #[test]
+    fn test_parse_parameter_with_type() {
+        let input = "count : number = 0";
+        let (_, param) = parse_parameter(Span::new(input)).unwrap();
+        assert_eq!(param.name, "count");
+        assert_eq!(param.type_.as_deref(), Some("number"));
+        let type_span = param.type_span.unwrap();
+        assert_eq!(&input[type_span.start..type_span.end], "number");
+        assert!(param.default_value.is_some());
+        assert_eq!(param.to_ast().unwrap().type_.as_deref(), Some("number"));
+    }
+
     #[test]
     fn test_parse_parameters() {
         let input = r#"(param1, param2="default", param3=123)"#;
@@ -1677,6 +2718,81 @@ mod tests {
         assert!(has_nested_block);
     }
 
+    #[test]
+    fn test_parse_block_labeled() {
+        let input = "{\n    group \"intro\" {\n        @command1\n    }\n    {\n    }\n}";
+        let (_, block) = parse_block(Span::new(input)).unwrap();
+
+        let blocks: Vec<_> = block
+            .children
+            .iter()
+            .filter_map(|n| match n {
+                CstNode::Block(b) => Some(b),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(blocks.len(), 2);
+
+        let label = blocks[0].label.as_ref().expect("第一个块应带标签");
+        assert_eq!(label.name, "intro");
+        assert_eq!(label.raw, "\"intro\"");
+        assert_eq!(
+            &input[label.name_span.start..label.name_span.end],
+            "\"intro\""
+        );
+        assert_eq!(
+            &input[blocks[0].span.start..blocks[0].span.start + 5],
+            "group"
+        );
+        assert!(blocks[1].label.is_none());
+
+        let ast = block.to_ast().unwrap();
+        match &ast.children[0].content {
+            format::ChildContent::Block(b) => assert_eq!(b.label.as_deref(), Some("intro")),
+            other => panic!("期望代码块，实际为 {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_macro_definition_and_invocation() {
+        let input =
+            "::macro fadeOut {\n    @fade\n}\n\n::macro {\n}\n\n::entry {\n    @@fadeOut\n}\n";
+        let cst = parse_tolerant("test", input);
+
+        let paragraphs: Vec<_> = cst
+            .nodes
+            .iter()
+            .filter_map(|n| match n {
+                CstNode::Paragraph(p) => Some(p),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(paragraphs.len(), 3);
+
+        let keyword = paragraphs[0].macro_keyword.as_ref().expect("应为宏定义");
+        assert_eq!(&input[keyword.start..keyword.end], "macro");
+        assert_eq!(paragraphs[0].name, "fadeOut");
+        // 名为 macro 的普通段落
+        assert!(paragraphs[1].macro_keyword.is_none());
+        assert_eq!(paragraphs[1].name, "macro");
+
+        match &paragraphs[2].block.children[1] {
+            CstNode::Command(cmd) => {
+                assert_eq!(cmd.command, "@fadeOut");
+                assert_eq!(&input[cmd.name_span.start..cmd.name_span.end], "@fadeOut");
+            }
+            other => panic!("期望命令，实际为 {:?}", other),
+        }
+
+        let story = cst.to_ast().unwrap();
+        assert_eq!(story.paragraphs.len(), 2);
+        let entry = &story.paragraphs[1];
+        match &entry.block.children[0].content {
+            format::ChildContent::CommandLine(c) => assert_eq!(c.command, "fade"),
+            other => panic!("期望命令，实际为 {:?}", other),
+        }
+    }
+
     #[test]
     fn test_parse_paragraph_simple() {
         let input = r#"::main {
@@ -1786,6 +2902,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cst_to_ast_paragraph_attributes() {
+        let input = concat!(
+            "#[platform(\"mobile\")]\n",
+            "#[once]\n",
+            "::main {\n    @wait time=500\n}\n\n",
+            "// 注释不影响属性的归属\n",
+            "#[once]\n",
+            "// 注释\n",
+            "::next {\n    hello\n}\n",
+            "::plain {\n}\n",
+        );
+
+        let cst_ast = parse_tolerant("test", input).to_ast().unwrap();
+        let (_, ast) = crate::parser::parse("test", input).unwrap();
+        let attributes = |story: &crate::format::Story| {
+            story
+                .paragraphs
+                .iter()
+                .map(|p| p.attributes.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(attributes(&cst_ast), attributes(&ast));
+        assert_eq!(cst_ast.paragraphs[0].attributes.len(), 2);
+        assert_eq!(cst_ast.paragraphs[0].attributes[0].keyword, "platform");
+        assert_eq!(cst_ast.paragraphs[1].attributes[0].keyword, "once");
+        assert!(cst_ast.paragraphs[2].attributes.is_empty());
+    }
+
     #[test]
     fn test_parse_quoted_string_double() {
         let input = r#""hello world""#;
@@ -1836,6 +2981,37 @@ mod tests {
         assert_eq!(text, "hello\nworld\t!");
     }
 
+    #[test]
+    fn test_parse_string_value_with_escapes() {
+        let tests = vec![
+            (r#""hello\nworld\t!""#, "hello\nworld\t!"),
+            (r#""she said \"hi\"""#, "she said \"hi\""),
+            (r"'it\'s'", "it's"),
+            (r#""a\\b\rc""#, "a\\b\rc"),
+            (r#""\u0041\u{1F600}""#, "A\u{1F600}"),
+            (r#""""#, ""),
+        ];
+
+        for (input, expected) in tests {
+            let (rest, value) = parse_string_value(Span::new(input)).unwrap();
+            assert!(rest.fragment().is_empty(), "未完整解析: {}", input);
+            // raw 保留原始写法，parsed 为转义后的值
+            assert_eq!(value.raw, input);
+            assert_eq!(
+                value.parsed,
+                format::RValue::Literal(format::Literal::String(expected.to_string()))
+            );
+        }
+
+        // 与 AST 解析器的结果一致
+        let input = "::main {\n    @say text=\"she said \\\"hi\\\"\" next=1\n}\n";
+        let cst = parse_tolerant("test", input);
+        assert_eq!(
+            cst.to_ast().unwrap(),
+            crate::parser::parse_story("test", input).unwrap()
+        );
+    }
+
     #[test]
     fn test_parse_template_literal_simple() {
         let input = "`hello world`";
@@ -1867,13 +3043,153 @@ mod tests {
             panic!("Expected text part");
         }
 
-        if let CstTemplatePart::Value { variable, .. } = &tpl.parts[1] {
-            assert_eq!(variable.chain, vec!["name".to_string()]);
+        if let CstTemplatePart::Value { value, .. } = &tpl.parts[1] {
+            assert_eq!(
+                value.parsed,
+                format::RValue::Variable(format::Variable {
+                    chain: vec!["name".to_string()]
+                })
+            );
         } else {
             panic!("Expected value part");
         }
     }
 
+    #[test]
+    fn test_parse_template_literal_variable_chain() {
+        let input = "`${player.name} has ${?player.inventory.key}a key${/}`";
+        let (rest, tpl) = parse_template_literal(Span::new(input)).unwrap();
+        assert!(rest.fragment().is_empty());
+        assert_eq!(tpl.parts.len(), 3);
+
+        // 变量的位置覆盖整个变量链
+        match &tpl.parts[0] {
+            CstTemplatePart::Value { value, .. } => {
+                assert_eq!(value.raw, "player.name");
+                let span = tpl.parts[0].variable_span().unwrap();
+                assert_eq!(&input[span.start..span.end], "player.name");
+            }
+            other => panic!("Expected value part, got {:?}", other),
+        }
+        match &tpl.parts[2] {
+            CstTemplatePart::Conditional { condition, .. } => {
+                assert_eq!(condition.raw, "player.inventory.key");
+                let span = tpl.parts[2].variable_span().unwrap();
+                assert_eq!(&input[span.start..span.end], "player.inventory.key");
+            }
+            other => panic!("Expected conditional part, got {:?}", other),
+        }
+
+        // 与 AST 解析器的结果一致
+        let source = format!("::main {{\n    {}\n}}\n", input);
+        assert_eq!(
+            parse_tolerant("test", &source).to_ast().unwrap(),
+            crate::parser::parse_story("test", &source).unwrap()
+        );
+
+        // `.` 之后缺少标识符时失败
+        assert!(parse_template_literal(Span::new("`${player.}`")).is_err());
+    }
+
+    #[test]
+    fn test_parse_template_literal_literal_values() {
+        use format::{Literal, RValue};
+
+        let input = "`count: ${42}, ${-1.5}, ${0xFF}, ${true}, ${'a b'}, ${trueish}`";
+        let (rest, tpl) = parse_template_literal(Span::new(input)).unwrap();
+        assert!(rest.fragment().is_empty());
+
+        let values: Vec<_> = tpl
+            .parts
+            .iter()
+            .filter_map(|part| match part {
+                CstTemplatePart::Value { value, .. } => Some((value.raw.as_str(), &value.parsed)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("42", &RValue::Literal(Literal::Integer(42))),
+                ("-1.5", &RValue::Literal(Literal::Float(-1.5))),
+                ("0xFF", &RValue::Literal(Literal::Integer(255))),
+                ("true", &RValue::Literal(Literal::Boolean(true))),
+                (
+                    "'a b'",
+                    &RValue::Literal(Literal::String("a b".to_string()))
+                ),
+                (
+                    "trueish",
+                    &RValue::Variable(format::Variable {
+                        chain: vec!["trueish".to_string()]
+                    })
+                ),
+            ]
+        );
+        // 字面量插值没有变量
+        assert!(tpl.parts[1].variable_span().is_none());
+
+        // 与 AST 解析器的结果一致
+        let source = "::main {\n    `count: ${42}`\n}\n";
+        assert_eq!(
+            parse_tolerant("test", source).to_ast().unwrap(),
+            crate::parser::parse_story("test", source).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_template_literal_rvalues_match_ast() {
+        let source = "::entry {\n    `v: ${?1}x${/}! ${[1, 2]} ${?{a: 1}}y${/}`\n}\n";
+        let cst = parse_tolerant("test", source);
+        assert_eq!(collect_error_nodes(&cst.nodes), vec![]);
+        assert_eq!(
+            cst.to_ast().unwrap(),
+            crate::parser::parse_story("test", source).unwrap()
+        );
+        let output = crate::cst::formatter::CstFormatter::new().format(&cst);
+        assert_eq!(output, source);
+    }
+
+    #[test]
+    fn test_parse_invalid_template_keeps_paragraph() {
+        let source = "::entry {\n    `v: ${?}x${/}`\n    @a\n}\n";
+        assert!(crate::parser::parse_story("test", source).is_err());
+        let cst = parse_tolerant("test", source);
+        assert!(!collect_error_nodes(&cst.nodes).is_empty());
+        assert!(matches!(&cst.nodes[0], CstNode::Paragraph(p) if p.name == "entry"));
+    }
+
+    #[test]
+    fn test_parse_template_literal_conditional() {
+        let input = "`Hi${?met} again${:}, ${name}${/}!`";
+        let (rest, tpl) = parse_template_literal(Span::new(input)).unwrap();
+        assert!(rest.fragment().is_empty());
+        assert_eq!(tpl.parts.len(), 3); // "Hi", 条件片段, "!"
+
+        if let CstTemplatePart::Conditional {
+            condition,
+            then,
+            otherwise,
+            span,
+        } = &tpl.parts[1]
+        {
+            assert_eq!(condition.raw, "met");
+            assert_eq!(condition.span.start_column, 6);
+            assert_eq!(then.len(), 1);
+            assert_eq!(otherwise.len(), 2); // ", ", ${name}
+            assert_eq!(span.start_column, 3);
+            assert_eq!(span.end_column, 33);
+        } else {
+            panic!("Expected conditional part");
+        }
+
+        let ast = tpl.to_ast();
+        assert_eq!(ast.get_strings(), vec!["Hi", "!"]);
+
+        // 条件片段外的分隔符是错误
+        assert!(parse_template_literal(Span::new("`again${/}`")).is_err());
+    }
+
     #[test]
     fn test_parse_leading_text_simple() {
         let input = "[角色名]";
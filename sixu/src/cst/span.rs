@@ -1,5 +1,7 @@
 //! Span and position utilities for CST
 
+use std::cell::RefCell;
+
 use nom_locate::LocatedSpan;
 
 /// CST 使用的输入类型
@@ -23,12 +25,86 @@ pub struct SpanInfo {
     pub end_column: usize,
 }
 
+thread_local! {
+    /// 正在解析的输入中每一行的起始字节偏移
+    static LINE_STARTS: RefCell<Option<Vec<usize>>> = const { RefCell::new(None) };
+}
+
+/// 换行符是否为 `\r\n`、`\n` 或单独的 `\r`（旧版 Mac 风格）
+pub fn is_line_break(c: char) -> bool {
+    c == '\n' || c == '\r'
+}
+
+/// 统计文本中的换行数，`\r\n` 计为一次
+pub fn count_line_breaks(text: &str) -> usize {
+    line_starts(text).len() - 1
+}
+
+/// 按 `\r\n`、`\n` 或单独的 `\r` 拆分行，行内容不含换行符
+pub fn split_lines(text: &str) -> Vec<&str> {
+    let starts = line_starts(text);
+    let mut lines = Vec::with_capacity(starts.len());
+    for (i, &start) in starts.iter().enumerate() {
+        let end = match starts.get(i + 1) {
+            Some(&next) => {
+                let line = &text[start..next];
+                next - if line.ends_with("\r\n") { 2 } else { 1 }
+            }
+            None => text.len(),
+        };
+        lines.push(&text[start..end]);
+    }
+    // 与 str::lines 一致：末尾换行后不再产生空行
+    if lines.len() > 1 && lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines
+}
+
+fn line_starts(text: &str) -> Vec<usize> {
+    let bytes = text.as_bytes();
+    let mut starts = vec![0];
+    for (i, &b) in bytes.iter().enumerate() {
+        let is_break = b == b'\n' || (b == b'\r' && bytes.get(i + 1) != Some(&b'\n'));
+        if is_break {
+            starts.push(i + 1);
+        }
+    }
+    starts
+}
+
+/// 在解析 `input` 期间按 `\r\n`、`\n` 和单独的 `\r` 计算行号
+///
+/// nom_locate 只按 `\n` 计行，旧版 Mac 风格的文件会全部落在第一行。
+pub(crate) fn with_line_index<T>(input: &str, parse: impl FnOnce() -> T) -> T {
+    let previous = LINE_STARTS.replace(Some(line_starts(input)));
+    let result = parse();
+    LINE_STARTS.set(previous);
+    result
+}
+
+/// 返回 span 起点的行号（1-based）与列号（0-based，按字节）
+fn position(span: Span) -> (usize, usize) {
+    let offset = span.location_offset();
+    LINE_STARTS
+        .with_borrow(|starts| {
+            let starts = starts.as_ref()?;
+            let line = starts.partition_point(|&start| start <= offset);
+            Some((line, offset - starts[line - 1]))
+        })
+        .unwrap_or_else(|| {
+            (
+                span.location_line() as usize,
+                span.get_column().saturating_sub(1), // 转换为 0-based
+            )
+        })
+}
+
 impl SpanInfo {
     /// 从单个 nom_locate::Span 创建（起始和结束相同）
     pub fn from_span(span: Span) -> Self {
         let offset = span.location_offset();
-        let line = span.location_line() as usize;
-        let column = span.get_column().saturating_sub(1); // 转换为 0-based
+        let (line, column) = position(span);
 
         Self {
             start: offset,
@@ -44,10 +120,8 @@ impl SpanInfo {
     pub fn from_range(start_span: Span, end_span: Span) -> Self {
         let start_offset = start_span.location_offset();
         let end_offset = end_span.location_offset();
-        let start_line = start_span.location_line() as usize;
-        let start_column = start_span.get_column().saturating_sub(1);
-        let end_line = end_span.location_line() as usize;
-        let end_column = end_span.get_column().saturating_sub(1);
+        let (start_line, start_column) = position(start_span);
+        let (end_line, end_column) = position(end_span);
 
         Self {
             start: start_offset,
@@ -63,8 +137,7 @@ impl SpanInfo {
     pub fn from_span_and_len(start_span: Span, content_len: usize) -> Self {
         let start_offset = start_span.location_offset();
         let end_offset = start_offset + content_len;
-        let start_line = start_span.location_line() as usize;
-        let start_column = start_span.get_column().saturating_sub(1);
+        let (start_line, start_column) = position(start_span);
 
         // 简化处理：假设内容在同一行（对于单 token 通常如此）
         // 更精确的实现需要扫描换行符
@@ -0,0 +1,149 @@
+//! Comparison of two sources by meaning rather than by text, e.g. to check that
+//! formatting a file did not change what it does.
+
+use crate::format::{Argument, Block, ChildContent, Story};
+use crate::parser::parse_story;
+
+/// Options of [`semantically_equal_with`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EqualityOptions {
+    /// Compare the arguments of each command and system call regardless of their
+    /// order. Arguments sharing a name keep their relative order.
+    pub ignore_argument_order: bool,
+}
+
+/// Whether sources `a` and `b` parse to the same story. A source that fails to parse
+/// is not equal to anything.
+///
+/// Both sources are parsed with macros expanded and the resulting stories compared, so
+/// everything the AST does not keep is ignored:
+///
+/// - whitespace, line breaks and comments, except `//#marker` directives
+/// - quote styles, and whether arguments are written `@cmd a=1` or `@cmd(a=1)`
+/// - the spelling of equal literals, e.g. `1.0` and `1.00`
+/// - source positions of arguments
+///
+/// On top of that, embedded code is compared with surrounding whitespace trimmed and
+/// line endings normalized, and [`EqualityOptions::ignore_argument_order`] may
+/// disregard the order of arguments. Everything else, including the order of
+/// paragraphs, children and attributes, is significant.
+pub fn semantically_equal(a: &str, b: &str) -> bool {
+    semantically_equal_with(a, b, &EqualityOptions::default())
+}
+
+/// [`semantically_equal`] with `options`
+pub fn semantically_equal_with(a: &str, b: &str, options: &EqualityOptions) -> bool {
+    match (canonical(a, options), canonical(b, options)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn canonical(source: &str, options: &EqualityOptions) -> Option<Story> {
+    // the name is not part of the source, use the same one for both sides
+    let mut story = parse_story("", source).ok()?;
+    for paragraph in &mut story.paragraphs {
+        canonicalize_block(&mut paragraph.block, options);
+    }
+    Some(story)
+}
+
+fn canonicalize_block(block: &mut Block, options: &EqualityOptions) {
+    for child in &mut block.children {
+        match &mut child.content {
+            ChildContent::Block(block) => canonicalize_block(block, options),
+            ChildContent::CommandLine(command) => {
+                canonicalize_arguments(&mut command.arguments, options)
+            }
+            ChildContent::SystemCallLine(call) => {
+                canonicalize_arguments(&mut call.arguments, options)
+            }
+            ChildContent::EmbeddedCode(code) => {
+                *code = code
+                    .replace("\r\n", "\n")
+                    .replace('\r', "\n")
+                    .trim()
+                    .to_string();
+            }
+            ChildContent::TextLine(..)
+            | ChildContent::MultiTextLine(..)
+            | ChildContent::MacroInvocation(_) => {}
+        }
+    }
+}
+
+fn canonicalize_arguments(arguments: &mut [Argument], options: &EqualityOptions) {
+    if options.ignore_argument_order {
+        arguments.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"
+::entry(name, count=1) {
+    @bg src="school.png" fade=1.0
+    #[cond("ready")]
+    [hero] "Hello, world"
+    ## let a = 1; ##
+    #goto paragraph="next"
+}
+"#;
+
+    #[test]
+    fn test_reformatted_source_is_equal() {
+        let reformatted = r#"// a comment that changes nothing
+::entry( name , count = 1 ){
+  @bg( src = 'school.png', /* inline */ fade = 1.00 )
+  #[cond("ready")] [hero] 'Hello, world'
+  ##
+  let a = 1;
+  ##
+  #goto(paragraph="next") // trailing
+}"#;
+        assert!(semantically_equal(SOURCE, reformatted));
+    }
+
+    #[test]
+    fn test_meaning_changes_are_unequal() {
+        assert!(!semantically_equal(
+            SOURCE,
+            &SOURCE.replace("school.png", "home.png")
+        ));
+        assert!(!semantically_equal(
+            SOURCE,
+            &SOURCE.replace("ready", "done")
+        ));
+        assert!(!semantically_equal(
+            SOURCE,
+            &SOURCE.replace("count=1", "count")
+        ));
+        assert!(!semantically_equal(
+            SOURCE,
+            &SOURCE.replace("a = 1", "a = 2")
+        ));
+        // both unparsable sources are not equal either
+        assert!(!semantically_equal("::entry {", "::entry {"));
+    }
+
+    #[test]
+    fn test_argument_order() {
+        let swapped = SOURCE.replace(
+            r#"src="school.png" fade=1.0"#,
+            r#"fade=1.0 src="school.png""#,
+        );
+        assert!(!semantically_equal(SOURCE, &swapped));
+
+        let options = EqualityOptions {
+            ignore_argument_order: true,
+        };
+        assert!(semantically_equal_with(SOURCE, &swapped, &options));
+        assert!(!semantically_equal_with(
+            SOURCE,
+            &swapped.replace("fade=1.0", "fade=2.0"),
+            &options
+        ));
+    }
+}
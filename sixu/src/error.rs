@@ -1,4 +1,4 @@
-use nom_language::error::VerboseError;
+use nom_language::error::{VerboseError, VerboseErrorKind};
 use thiserror::Error;
 
 pub type Result<T, E = RuntimeError> = std::result::Result<T, E>;
@@ -21,9 +21,30 @@ pub enum RuntimeError {
     WrongArgumentSystemCallLine(String),
     #[error("Wrong argument(s) provided to command line: {0}")]
     WrongArgumentCommandLine(String),
+    #[error("Default value of parameter {parameter} refers to parameter {referenced}, which is not bound yet")]
+    ForwardParameterReference {
+        parameter: String,
+        referenced: String,
+    },
+    #[error("Paragraph {paragraph} has no parameter {argument}")]
+    UnknownParagraphArgument { paragraph: String, argument: String },
+
+    #[error("Template interpolation nested deeper than {0} levels")]
+    TemplateRecursionLimit(usize),
+    #[error("Step ran more than {0} iterations without pausing")]
+    StepLimitExceeded(usize),
+    #[error("Cannot rewind further than the {0} recorded steps")]
+    NotEnoughHistory(usize),
+    #[error("Paragraph {0} is defined more than once")]
+    DuplicateParagraph(String),
+    #[error("Variable {0} not found")]
+    VariableNotFound(String),
 
     #[error("Parse error: {0}")]
-    ParseError(#[from] VerboseError<&'static str>),
+    ParseError(#[from] ParseError),
+
+    #[error("Read error: {0}")]
+    ReadError(#[from] ReadError),
 
     #[error("Not a string")]
     NotAString,
@@ -43,3 +64,260 @@ pub enum RuntimeError {
     #[error("Other error: {0}")]
     Anyhow(#[from] anyhow::Error),
 }
+
+/// Failure while reading a story from a byte stream with
+/// [`parse_story_from_reader`](crate::parser::parse_story_from_reader)
+#[derive(Debug, Error)]
+pub enum ReadError {
+    #[error("Failed to read story: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid UTF-8 at byte {offset} (line {line})")]
+    InvalidUtf8 {
+        /// Byte offset of the first invalid byte
+        offset: usize,
+        /// Line of the first invalid byte (1-based)
+        line: usize,
+    },
+    #[error("Failed to parse story: {0}")]
+    Parse(#[from] ParseError),
+}
+
+/// Failure while compiling a story into, or loading it from, its binary form
+#[cfg(feature = "compiled")]
+#[derive(Debug, Error)]
+pub enum CompiledError {
+    #[error("Not a compiled story")]
+    InvalidHeader,
+    #[error("Compiled story has format version {found}, expected {expected}")]
+    VersionMismatch { found: u16, expected: u16 },
+    #[error("Failed to parse story: {0}")]
+    Parse(#[from] ParseError),
+    #[error("Failed to encode story: {0}")]
+    Encode(#[from] rmp_serde::encode::Error),
+    #[error("Failed to decode story: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+}
+
+/// Failure while bundling several stories into one with [`StorySet`](crate::merge::StorySet)
+#[derive(Debug, Error)]
+pub enum MergeError {
+    #[error("Failed to read {path}: {source}")]
+    Io {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse {path}: {source}")]
+    Parse {
+        path: std::path::PathBuf,
+        source: Box<ParseError>,
+    },
+    #[error("Story {0} is included more than once")]
+    DuplicateStory(String),
+    #[error("Paragraph {paragraph} from story {second} collides with the one from story {first}")]
+    ParagraphCollision {
+        paragraph: String,
+        first: String,
+        second: String,
+    },
+    #[error(
+        "Cannot rewrite #{command} in {story}::{paragraph}, its target is not a string literal"
+    )]
+    DynamicTarget {
+        command: String,
+        story: String,
+        paragraph: String,
+    },
+}
+
+/// Misuse of a [`SchemaBuilder`](crate::schema::SchemaBuilder), reported by its `build`
+#[cfg(feature = "schema")]
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum SchemaError {
+    #[error("`{method}` of command {command} must follow a parameter declaration")]
+    MissingParameter {
+        command: String,
+        method: &'static str,
+    },
+}
+
+/// A parse failure resolved against the source it came from, carrying the failing
+/// position, the parser contexts it happened in and the tokens that were expected there.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("line {line}, column {column}: {}", self.message())]
+pub struct ParseError {
+    /// Byte offset of the failing position
+    pub offset: usize,
+    /// Line of the failing position (1-based)
+    pub line: usize,
+    /// Column of the failing position in characters (1-based)
+    pub column: usize,
+    /// Parser contexts the failure happened in, innermost first
+    pub contexts: Vec<&'static str>,
+    /// Tokens that would have been accepted at the failing position
+    pub expected: Vec<String>,
+    /// Character found at the failing position, `None` at end of input
+    pub found: Option<char>,
+    /// Why the input was rejected, when it is well-formed but semantically invalid
+    pub reason: Option<String>,
+}
+
+impl ParseError {
+    /// Build a `ParseError` from the nom error produced while parsing `input`.
+    pub fn from_verbose(input: &str, err: VerboseError<&str>) -> Self {
+        let remaining = err.errors.first().map(|(rest, _)| *rest).unwrap_or("");
+        let offset = input.len().saturating_sub(remaining.len());
+        let (line, column) = line_column(input, offset);
+
+        let mut contexts = Vec::new();
+        let mut expected = Vec::new();
+        for (rest, kind) in &err.errors {
+            match kind {
+                VerboseErrorKind::Context(ctx) => contexts.push(*ctx),
+                VerboseErrorKind::Char(ch) if rest.len() == remaining.len() => {
+                    let token = format!("`{}`", ch);
+                    if !expected.contains(&token) {
+                        expected.push(token);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // a failed macro expansion points at the name of the macro, see `parser::parse`
+        let reason = contexts
+            .first()
+            .filter(|ctx| crate::parser::macros::ERROR_CONTEXTS.contains(ctx))
+            .map(|ctx| {
+                let name = remaining
+                    .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .next()
+                    .unwrap_or_default();
+                format!("{} `{}`", ctx, name)
+            });
+
+        Self {
+            offset,
+            line,
+            column,
+            contexts,
+            expected,
+            found: remaining.chars().next(),
+            reason,
+        }
+    }
+
+    /// Build a `ParseError` for input rejected at `offset` for `reason`.
+    pub fn rejected(input: &str, offset: usize, context: &'static str, reason: String) -> Self {
+        let (line, column) = line_column(input, offset);
+        Self {
+            offset,
+            line,
+            column,
+            contexts: vec![context],
+            expected: Vec::new(),
+            found: input[offset..].chars().next(),
+            reason: Some(reason),
+        }
+    }
+
+    /// Human readable description of the failure, without the position prefix
+    pub fn message(&self) -> String {
+        let mut message = String::new();
+        if let Some(ctx) = self.contexts.first() {
+            message.push_str("in ");
+            message.push_str(ctx);
+            message.push_str(", ");
+        }
+
+        if let Some(reason) = &self.reason {
+            message.push_str(reason);
+        } else if !self.expected.is_empty() {
+            message.push_str("expected ");
+            message.push_str(&join_alternatives(&self.expected));
+            match self.found {
+                Some(ch) => message.push_str(&format!(", found `{}`", ch.escape_debug())),
+                None => message.push_str(", found end of input"),
+            }
+        } else {
+            match self.found {
+                Some(ch) => message.push_str(&format!("unexpected `{}`", ch.escape_debug())),
+                None => message.push_str("unexpected end of input"),
+            }
+        }
+
+        message
+    }
+}
+
+/// What a [`SyntaxError`] reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxErrorKind {
+    /// The source does not parse at all
+    Parse,
+    /// A value that is meant as a string but is not quoted, e.g. `src=bg-01.png`
+    UnquotedValue,
+    /// Content after a parenthesized argument list on the same line
+    TrailingContent,
+    /// A bare identifier used as a value that looks like a misspelled literal
+    SuspiciousIdentifier,
+}
+
+/// A syntax error or a construct rejected by [`crate::parse_strict`], with its position
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("line {line}, column {column}: {message}")]
+pub struct SyntaxError {
+    /// Byte offset of the offending position
+    pub offset: usize,
+    /// Line of the offending position (1-based)
+    pub line: usize,
+    /// Column of the offending position in characters (1-based)
+    pub column: usize,
+    pub kind: SyntaxErrorKind,
+    pub message: String,
+}
+
+impl SyntaxError {
+    pub(crate) fn new(input: &str, offset: usize, kind: SyntaxErrorKind, message: String) -> Self {
+        let (line, column) = line_column(input, offset);
+        Self {
+            offset,
+            line,
+            column,
+            kind,
+            message,
+        }
+    }
+}
+
+impl From<ParseError> for SyntaxError {
+    fn from(err: ParseError) -> Self {
+        Self {
+            offset: err.offset,
+            line: err.line,
+            column: err.column,
+            kind: SyntaxErrorKind::Parse,
+            message: err.message(),
+        }
+    }
+}
+
+pub(crate) fn line_column(input: &str, offset: usize) -> (usize, usize) {
+    let consumed = &input[..offset];
+    // a lone `\r` (classic Mac OS) ends a line as well, `\r\n` counts once
+    let lone_cr = consumed
+        .match_indices('\r')
+        .filter(|(i, _)| input.as_bytes().get(i + 1) != Some(&b'\n'))
+        .count();
+    let line = consumed.matches('\n').count() + lone_cr + 1;
+    let line_start = consumed.rfind(['\n', '\r']).map(|i| i + 1).unwrap_or(0);
+    let column = consumed[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+fn join_alternatives(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.clone(),
+        [init @ .., last] => format!("{} or {}", init.join(", "), last),
+    }
+}
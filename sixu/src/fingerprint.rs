@@ -29,6 +29,8 @@ enum Tag {
     ChildContentCommandLine = 0x22,
     ChildContentSystemCallLine = 0x23,
     ChildContentEmbeddedCode = 0x24,
+    ChildContentMultiTextLine = 0x25,
+    ChildContentMacroInvocation = 0x26,
 
     LeadingTextNone = 0x30,
     LeadingTextText = 0x31,
@@ -43,6 +45,7 @@ enum Tag {
 
     TemplateLiteralPartText = 0x60,
     TemplateLiteralPartValue = 0x61,
+    TemplateLiteralPartConditional = 0x62,
 
     RValueLiteral = 0x70,
     RValueVariable = 0x71,
@@ -264,6 +267,15 @@ impl FingerprintEncode for ChildContent {
                 text.encode(writer);
                 tailing.encode(writer);
             }
+            Self::MultiTextLine(segments, tailing) => {
+                writer.write_tag(Tag::ChildContentMultiTextLine);
+                writer.write_len(segments.len());
+                for (leading, text) in segments {
+                    leading.encode(writer);
+                    text.encode(writer);
+                }
+                tailing.encode(writer);
+            }
             Self::CommandLine(command_line) => {
                 writer.write_tag(Tag::ChildContentCommandLine);
                 command_line.encode(writer);
@@ -276,6 +288,10 @@ impl FingerprintEncode for ChildContent {
                 writer.write_tag(Tag::ChildContentEmbeddedCode);
                 writer.write_str(&normalize_embedded_code(code));
             }
+            Self::MacroInvocation(name) => {
+                writer.write_tag(Tag::ChildContentMacroInvocation);
+                writer.write_str(name);
+            }
         }
     }
 }
@@ -346,6 +362,20 @@ impl FingerprintEncode for TemplateLiteralPart {
                 writer.write_tag(Tag::TemplateLiteralPartValue);
                 value.encode(writer);
             }
+            Self::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => {
+                writer.write_tag(Tag::TemplateLiteralPartConditional);
+                condition.encode(writer);
+                for parts in [then, otherwise] {
+                    writer.write_len(parts.len());
+                    for part in parts {
+                        part.encode(writer);
+                    }
+                }
+            }
         }
     }
 }
@@ -516,6 +546,7 @@ mod tests {
                     .map(|(name, value)| Argument {
                         name: name.to_string(),
                         value,
+                        span: None,
                     })
                     .collect(),
             }),
@@ -525,9 +556,11 @@ mod tests {
     #[test]
     fn fingerprint_changes_when_children_order_changes() {
         let first = Block {
+            label: None,
             children: vec![text_child("first"), text_child("second")],
         };
         let second = Block {
+            label: None,
             children: vec![text_child("second"), text_child("first")],
         };
 
@@ -537,6 +570,7 @@ mod tests {
     #[test]
     fn fingerprint_ignores_attribute_order() {
         let first = Block {
+            label: None,
             children: vec![Child {
                 marker: None,
                 attributes: vec![
@@ -549,10 +583,14 @@ mod tests {
                         condition: Some("b".to_string()),
                     },
                 ],
-                content: ChildContent::Block(Block { children: vec![] }),
+                content: ChildContent::Block(Block {
+                    label: None,
+                    children: vec![],
+                }),
             }],
         };
         let second = Block {
+            label: None,
             children: vec![Child {
                 marker: None,
                 attributes: vec![
@@ -565,7 +603,10 @@ mod tests {
                         condition: Some("a".to_string()),
                     },
                 ],
-                content: ChildContent::Block(Block { children: vec![] }),
+                content: ChildContent::Block(Block {
+                    label: None,
+                    children: vec![],
+                }),
             }],
         };
 
@@ -575,6 +616,7 @@ mod tests {
     #[test]
     fn fingerprint_ignores_argument_order() {
         let first = Block {
+            label: None,
             children: vec![command_child(
                 "say",
                 vec![
@@ -584,6 +626,7 @@ mod tests {
             )],
         };
         let second = Block {
+            label: None,
             children: vec![command_child(
                 "say",
                 vec![
@@ -599,6 +642,7 @@ mod tests {
     #[test]
     fn fingerprint_ignores_object_insertion_order() {
         let first = Block {
+            label: None,
             children: vec![command_child(
                 "config",
                 vec![(
@@ -611,6 +655,7 @@ mod tests {
             )],
         };
         let second = Block {
+            label: None,
             children: vec![command_child(
                 "config",
                 vec![(
@@ -629,6 +674,7 @@ mod tests {
     #[test]
     fn fingerprint_normalizes_embedded_code_text() {
         let first = Block {
+            label: None,
             children: vec![Child {
                 marker: None,
                 attributes: Vec::new(),
@@ -636,6 +682,7 @@ mod tests {
             }],
         };
         let second = Block {
+            label: None,
             children: vec![Child {
                 marker: None,
                 attributes: Vec::new(),
@@ -649,18 +696,21 @@ mod tests {
     #[test]
     fn fingerprint_normalizes_negative_zero_and_nan() {
         let zero = Block {
+            label: None,
             children: vec![command_child(
                 "set",
                 vec![("value", RValue::Literal(Literal::Float(0.0)))],
             )],
         };
         let negative_zero = Block {
+            label: None,
             children: vec![command_child(
                 "set",
                 vec![("value", RValue::Literal(Literal::Float(-0.0)))],
             )],
         };
         let nan_a = Block {
+            label: None,
             children: vec![command_child(
                 "set",
                 vec![(
@@ -670,6 +720,7 @@ mod tests {
             )],
         };
         let nan_b = Block {
+            label: None,
             children: vec![command_child(
                 "set",
                 vec![(
@@ -687,6 +738,7 @@ mod tests {
     #[test]
     fn fingerprint_serde_round_trip_uses_lowercase_hex() {
         let fingerprint = Block {
+            label: None,
             children: vec![text_child("hello")],
         }
         .fingerprint();
@@ -19,6 +19,12 @@ pub struct Story {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct Paragraph {
+    /// Attributes written before `::name`, e.g. `#[platform("mobile")]`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub attributes: Vec<Attribute>,
     pub name: String,
     pub parameters: Vec<Parameter>,
     /// root block
@@ -30,24 +36,78 @@ pub struct Paragraph {
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct Parameter {
     pub name: String,
-    pub default_value: Option<Literal>,
+    /// Declared type like `string` in `::scene(location: string)`, one of
+    /// [`PARAMETER_TYPES`]. Untyped parameters accept any value.
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "type", default, skip_serializing_if = "Option::is_none")
+    )]
+    pub type_: Option<String>,
+    /// Default value used when the argument is omitted. Variables may refer to
+    /// parameters declared earlier in the same list.
+    pub default_value: Option<RValue>,
+}
+
+/// Type names accepted in parameter annotations
+pub const PARAMETER_TYPES: &[&str] = &["string", "number", "boolean", "array", "object", "any"];
+
+impl Parameter {
+    /// Whether `value` matches the declared type. `null` only matches `any`, and
+    /// untyped parameters accept everything.
+    pub fn accepts(&self, value: &Literal) -> bool {
+        match self.type_.as_deref() {
+            None | Some("any") => true,
+            Some("string") => value.is_string(),
+            Some("number") => matches!(value, Literal::Integer(_) | Literal::Float(_)),
+            Some("boolean") => matches!(value, Literal::Boolean(_)),
+            Some("array") => matches!(value, Literal::Array(_)),
+            Some("object") => matches!(value, Literal::Object(_)),
+            Some(_) => false,
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct Argument {
     pub name: String,
     pub value: RValue,
+    /// Byte range of the value in the source, or of the name for a flag without value.
+    /// Only stories converted from a CST carry spans, and they are ignored by `==`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub span: Option<std::ops::Range<usize>>,
+}
+
+impl PartialEq for Argument {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.value == other.value
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 #[cfg_attr(feature = "ts", derive(ts_rs::TS))]
 pub struct ResolvedArgument {
     pub name: String,
     pub value: Literal,
+    /// Source span of the argument it was resolved from, see [`Argument::span`]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub span: Option<std::ops::Range<usize>>,
+}
+
+impl PartialEq for ResolvedArgument {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.value == other.value
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -133,6 +193,20 @@ impl Literal {
         }
     }
 
+    /// Truthiness used by conditional template spans: `null`, `false`, zero, and empty
+    /// strings, arrays and objects are false, everything else is true
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Literal::Null => false,
+            Literal::Boolean(b) => *b,
+            Literal::Integer(i) => *i != 0,
+            Literal::Float(f) => *f != 0.0,
+            Literal::String(s) => !s.is_empty(),
+            Literal::Array(a) => !a.is_empty(),
+            Literal::Object(o) => !o.is_empty(),
+        }
+    }
+
     pub fn as_array(&self) -> Result<&Vec<Literal>> {
         if let Literal::Array(ref a) = self {
             Ok(a)
@@ -195,26 +269,36 @@ impl Literal {
             Err(RuntimeError::NotAObject)
         }
     }
+
+    /// Get a nested value by a dotted path, where each segment is an object key or
+    /// an array index, e.g. `pos.0`. Returns None if a segment is missing or the
+    /// value at that point is neither an object nor an array.
+    pub fn get_path(&self, path: &str) -> Option<&Literal> {
+        path.split('.')
+            .try_fold(self, |value, segment| match value {
+                Literal::Object(o) => o.get(segment),
+                Literal::Array(a) => segment.parse::<usize>().ok().and_then(|i| a.get(i)),
+                _ => None,
+            })
+    }
 }
 
-impl ToString for Literal {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Literal::Null => "null".to_string(),
-            Literal::String(s) => s.clone(),
-            Literal::Integer(i) => i.to_string(),
-            Literal::Float(f) => f.to_string(),
-            Literal::Boolean(b) => b.to_string(),
+            Literal::Null => write!(f, "null"),
+            Literal::String(s) => write!(f, "{}", s),
+            Literal::Integer(i) => write!(f, "{}", i),
+            Literal::Float(fl) => write!(f, "{}", fl),
+            Literal::Boolean(b) => write!(f, "{}", b),
             Literal::Array(a) => {
                 let elements: Vec<String> = a.iter().map(|e| e.to_string()).collect();
-                format!("[{}]", elements.join(", "))
+                write!(f, "[{}]", elements.join(", "))
             }
             Literal::Object(o) => {
-                let entries: Vec<String> = o
-                    .iter()
-                    .map(|(k, v)| format!("\"{}\": {}", k, v.to_string()))
-                    .collect();
-                format!("{{{}}}", entries.join(", "))
+                let entries: Vec<String> =
+                    o.iter().map(|(k, v)| format!("\"{}\": {}", k, v)).collect();
+                write!(f, "{{{}}}", entries.join(", "))
             }
         }
     }
@@ -278,13 +362,165 @@ pub enum RValue {
     Variable(Variable),
 }
 
+impl RValue {
+    /// Get a nested value of a literal by a dotted path, see [`Literal::get_path`].
+    /// Variables are not resolved here and always return None.
+    pub fn get_path(&self, path: &str) -> Option<&Literal> {
+        match self {
+            RValue::Literal(literal) => literal.get_path(path),
+            RValue::Variable(_) => None,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct Block {
+    /// Label of a `group "name" { ... }` block. It only serves tooling such as
+    /// outlines and folding and has no effect on execution.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub label: Option<String>,
     pub children: Vec<Child>,
 }
 
+impl Block {
+    /// Direct children that are command lines, together with the child carrying
+    /// their marker and attributes. Nested blocks are not entered.
+    ///
+    /// ```
+    /// let source = r#"
+    /// ::entry {
+    ///     @bg src="a.png"
+    ///     "hello"
+    ///     #[cond("x")]
+    ///     @bgm
+    /// }
+    /// "#;
+    /// let story = sixu::parser::parse_story("demo", source).unwrap();
+    /// let block = &story.paragraphs[0].block;
+    ///
+    /// let names: Vec<&str> = block.commands().map(|(_, cmd)| cmd.command.as_str()).collect();
+    /// assert_eq!(names, ["bg", "bgm"]);
+    /// let conditional = block.commands().filter(|(child, _)| !child.attributes.is_empty());
+    /// assert_eq!(conditional.count(), 1);
+    /// ```
+    pub fn commands(&self) -> impl Iterator<Item = (&Child, &CommandLine)> {
+        self.children
+            .iter()
+            .filter_map(|child| match &child.content {
+                ChildContent::CommandLine(command) => Some((child, command)),
+                _ => None,
+            })
+    }
+
+    /// Direct children that are system call lines, together with the child carrying
+    /// their marker and attributes. Nested blocks are not entered.
+    pub fn system_calls(&self) -> impl Iterator<Item = (&Child, &SystemCallLine)> {
+        self.children
+            .iter()
+            .filter_map(|child| match &child.content {
+                ChildContent::SystemCallLine(call) => Some((child, call)),
+                _ => None,
+            })
+    }
+
+    /// Direct children that are text lines, including lines of several speaker
+    /// segments. Nested blocks are not entered.
+    ///
+    /// ```
+    /// let source = r#"
+    /// ::entry {
+    ///     "one"
+    ///     @wait
+    ///     [Alice] "two"
+    ///     {
+    ///         "three"
+    ///     }
+    /// }
+    /// "#;
+    /// let story = sixu::parser::parse_story("demo", source).unwrap();
+    ///
+    /// assert_eq!(story.paragraphs[0].block.text_lines().count(), 2);
+    /// ```
+    pub fn text_lines(&self) -> impl Iterator<Item = &Child> {
+        self.children.iter().filter(|child| {
+            matches!(
+                child.content,
+                ChildContent::TextLine(..) | ChildContent::MultiTextLine(..)
+            )
+        })
+    }
+
+    /// Direct children that are nested blocks, together with the child carrying
+    /// their marker and attributes. The nested blocks themselves are not entered.
+    pub fn blocks(&self) -> impl Iterator<Item = (&Child, &Block)> {
+        self.children
+            .iter()
+            .filter_map(|child| match &child.content {
+                ChildContent::Block(block) => Some((child, block)),
+                _ => None,
+            })
+    }
+
+    /// Every child of the block in source order, entering nested blocks: a child holding
+    /// a nested block comes right before the children of that block.
+    ///
+    /// ```
+    /// use sixu::format::ChildContent;
+    ///
+    /// let source = r#"
+    /// ::entry {
+    ///     "one"
+    ///     {
+    ///         #goto paragraph="next"
+    ///     }
+    ///     @wait
+    /// }
+    /// "#;
+    /// let story = sixu::parser::parse_story("demo", source).unwrap();
+    /// let block = &story.paragraphs[0].block;
+    ///
+    /// assert_eq!(block.descendants().count(), 4);
+    /// let jumps = block
+    ///     .descendants()
+    ///     .filter(|child| matches!(child.content, ChildContent::SystemCallLine(_)));
+    /// assert_eq!(jumps.count(), 1);
+    /// ```
+    pub fn descendants(&self) -> impl Iterator<Item = &Child> {
+        let mut stack = vec![self.children.iter()];
+        std::iter::from_fn(move || loop {
+            let Some(child) = stack.last_mut()?.next() else {
+                stack.pop();
+                continue;
+            };
+            if let ChildContent::Block(block) = &child.content {
+                stack.push(block.children.iter());
+            }
+            return Some(child);
+        })
+    }
+
+    /// Call `f` on every child of the block in the order of
+    /// [`descendants`](Block::descendants), stopping at the first error. A nested block
+    /// is entered after `f` ran on the child holding it.
+    pub fn try_for_each_descendant_mut<E>(
+        &mut self,
+        f: &mut impl FnMut(&mut Child) -> Result<(), E>,
+    ) -> Result<(), E> {
+        for child in &mut self.children {
+            f(child)?;
+            if let ChildContent::Block(block) = &mut child.content {
+                block.try_for_each_descendant_mut(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
@@ -304,6 +540,29 @@ impl LineMarker {
     }
 }
 
+/// A source comment, as collected by `parser::parse_story_with_comments`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Comment {
+    pub kind: CommentKind,
+    /// Content without the `//`, `/*` and `*/` delimiters
+    pub text: String,
+}
+
+/// Comments of a file with their byte ranges, in source order
+pub type CommentList = Vec<(std::ops::Range<usize>, Comment)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum CommentKind {
+    /// `// ...` up to the end of the line
+    Line,
+    /// `/* ... */`, possibly spanning several lines
+    Block,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
@@ -319,9 +578,15 @@ pub struct Child {
 pub enum ChildContent {
     Block(Block),
     TextLine(LeadingText, Text, TailingText),
+    /// Several `[speaker] text` segments on one line, only parsed with
+    /// `ParserConfig::multi_speaker`. The tailing text belongs to the last segment.
+    MultiTextLine(Vec<(LeadingText, Text)>, TailingText),
     CommandLine(CommandLine),
     SystemCallLine(SystemCallLine),
     EmbeddedCode(String),
+    /// `@@name` invoking a macro. Macros are expanded when a story is parsed, so
+    /// parsed stories never hold one.
+    MacroInvocation(String),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -358,23 +623,38 @@ pub struct TemplateLiteral {
 }
 
 impl TemplateLiteral {
+    /// Text parts outside of conditional spans
     pub fn get_strings(&self) -> Vec<String> {
         self.parts
             .iter()
             .filter_map(|part| match part {
                 TemplateLiteralPart::Text(text) => Some(text.clone()),
-                TemplateLiteralPart::Value(_) => None,
+                _ => None,
             })
             .collect()
     }
+    /// Interpolated values, including the conditions and values of conditional spans
     pub fn get_values(&self) -> Vec<RValue> {
-        self.parts
-            .iter()
-            .filter_map(|part| match part {
-                TemplateLiteralPart::Text(_) => None,
-                TemplateLiteralPart::Value(value) => Some(value.clone()),
-            })
-            .collect()
+        fn collect(parts: &[TemplateLiteralPart], values: &mut Vec<RValue>) {
+            for part in parts {
+                match part {
+                    TemplateLiteralPart::Text(_) => {}
+                    TemplateLiteralPart::Value(value) => values.push(value.clone()),
+                    TemplateLiteralPart::Conditional {
+                        condition,
+                        then,
+                        otherwise,
+                    } => {
+                        values.push(condition.clone());
+                        collect(then, values);
+                        collect(otherwise, values);
+                    }
+                }
+            }
+        }
+        let mut values = Vec::new();
+        collect(&self.parts, &mut values);
+        values
     }
 }
 
@@ -384,6 +664,13 @@ impl TemplateLiteral {
 pub enum TemplateLiteralPart {
     Text(String),
     Value(RValue),
+    /// `${?condition}then${:}otherwise${/}`, renders `then` when the condition is
+    /// truthy (see `Literal::is_truthy`) and `otherwise` if not
+    Conditional {
+        condition: RValue,
+        then: Vec<TemplateLiteralPart>,
+        otherwise: Vec<TemplateLiteralPart>,
+    },
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -419,6 +706,23 @@ impl ResolvedCommandLine {
             .find(|arg| arg.name == name)
             .map(|arg| &arg.value)
     }
+
+    /// Get a nested argument value by a dotted path starting with the argument name,
+    /// e.g. `entity.pos.0`
+    pub fn get_argument_path(&self, path: &str) -> Option<&Literal> {
+        match path.split_once('.') {
+            Some((name, rest)) => self.get_argument(name)?.get_path(rest),
+            None => self.get_argument(path),
+        }
+    }
+
+    /// Source span of the value of argument `name`, when the story came from a CST
+    pub fn get_argument_span(&self, name: &str) -> Option<std::ops::Range<usize>> {
+        self.arguments
+            .iter()
+            .find(|arg| arg.name == name)
+            .and_then(|arg| arg.span.clone())
+    }
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -454,6 +758,14 @@ impl ResolvedSystemCallLine {
             .find(|arg| arg.name == name)
             .map(|arg| &arg.value)
     }
+
+    /// Source span of the value of argument `name`, when the story came from a CST
+    pub fn get_argument_span(&self, name: &str) -> Option<std::ops::Range<usize>> {
+        self.arguments
+            .iter()
+            .find(|arg| arg.name == name)
+            .and_then(|arg| arg.span.clone())
+    }
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -463,3 +775,90 @@ pub struct Attribute {
     pub keyword: String,
     pub condition: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity() -> Literal {
+        Literal::Object(HashMap::from([
+            (
+                "pos".to_string(),
+                Literal::Array(vec![Literal::Integer(1), Literal::Integer(2)]),
+            ),
+            (
+                "tags".to_string(),
+                Literal::Array(vec![Literal::Object(HashMap::from([(
+                    "name".to_string(),
+                    Literal::String("hero".to_string()),
+                )]))]),
+            ),
+        ]))
+    }
+
+    #[test]
+    fn test_get_path() {
+        let entity = entity();
+        assert_eq!(entity.get_path("pos.0"), Some(&Literal::Integer(1)));
+        assert_eq!(entity.get_path("pos.1"), Some(&Literal::Integer(2)));
+        assert_eq!(
+            entity.get_path("tags.0.name"),
+            Some(&Literal::String("hero".to_string()))
+        );
+        assert!(entity.get_path("pos").unwrap().is_array());
+    }
+
+    #[test]
+    fn test_get_path_missing_or_mismatched() {
+        let entity = entity();
+        // missing key and out of range index
+        assert_eq!(entity.get_path("size"), None);
+        assert_eq!(entity.get_path("pos.2"), None);
+        // arrays only accept numeric indices, scalars have no children
+        assert_eq!(entity.get_path("pos.x"), None);
+        assert_eq!(entity.get_path("pos.-1"), None);
+        assert_eq!(entity.get_path("pos.0.x"), None);
+        assert_eq!(entity.get_path("pos..0"), None);
+    }
+
+    #[test]
+    fn test_rvalue_and_argument_path() {
+        let value = RValue::Literal(entity());
+        assert_eq!(value.get_path("pos.1"), Some(&Literal::Integer(2)));
+        let variable = RValue::Variable(Variable {
+            chain: vec!["entity".to_string()],
+        });
+        assert_eq!(variable.get_path("pos.1"), None);
+
+        let command = ResolvedCommandLine {
+            command: "spawn".to_string(),
+            arguments: vec![ResolvedArgument {
+                name: "entity".to_string(),
+                value: entity(),
+                span: None,
+            }],
+        };
+        assert_eq!(
+            command.get_argument_path("entity.pos.0"),
+            Some(&Literal::Integer(1))
+        );
+        assert!(command.get_argument_path("entity").unwrap().is_object());
+        assert_eq!(command.get_argument_path("missing.pos"), None);
+    }
+
+    #[test]
+    fn test_parameter_accepts() {
+        let typed = |type_: &str| Parameter {
+            name: "p".to_string(),
+            type_: Some(type_.to_string()),
+            default_value: None,
+        };
+        assert!(typed("number").accepts(&Literal::Integer(1)));
+        assert!(typed("number").accepts(&Literal::Float(1.5)));
+        assert!(!typed("number").accepts(&Literal::String("1".to_string())));
+        assert!(typed("object").accepts(&entity()));
+        assert!(!typed("string").accepts(&Literal::Null));
+        assert!(typed("any").accepts(&Literal::Null));
+        assert!(Parameter::default().accepts(&Literal::Boolean(true)));
+    }
+}
@@ -0,0 +1,191 @@
+//! Graphviz export of the paragraph transitions of a story, for design review.
+
+use std::fmt::Write;
+
+use crate::format::{Block, ChildContent, Literal, RValue, Story};
+
+/// Node standing for every target given by a variable
+const DYNAMIC_NODE: &str = "<dynamic>";
+
+impl Story {
+    /// Render the paragraph graph of this story as a Graphviz `digraph`.
+    ///
+    /// Nodes are the paragraphs, in source order. Edges are the literal targets of
+    /// `#goto` (solid), `#call` (bold) and `#replace` (hollow arrowhead), labelled with
+    /// the system call, plus dotted `fall-through` edges to the next paragraph when a
+    /// paragraph can run to its end. Targets in other stories are boxes named
+    /// `story::paragraph`, and targets given by a variable are dashed `dynamic` edges to
+    /// a shared `<dynamic>` node. Jumps under attributes are included as if their
+    /// conditions held.
+    pub fn to_dot(&self) -> String {
+        let mut nodes: Vec<String> = Vec::new();
+        let mut external: Vec<String> = Vec::new();
+        let mut edges: Vec<(String, String, &'static str)> = Vec::new();
+
+        for (index, paragraph) in self.paragraphs.iter().enumerate() {
+            nodes.push(paragraph.name.clone());
+            let jumps = paragraph
+                .block
+                .descendants()
+                .filter_map(|child| match &child.content {
+                    ChildContent::SystemCallLine(call)
+                        if matches!(call.command.as_str(), "goto" | "call" | "replace") =>
+                    {
+                        Some(call)
+                    }
+                    _ => None,
+                });
+            for call in jumps {
+                let target = match (call.get_argument("story"), call.get_argument("paragraph")) {
+                    (None, Some(RValue::Literal(Literal::String(paragraph)))) => paragraph.clone(),
+                    (
+                        Some(RValue::Literal(Literal::String(story))),
+                        Some(RValue::Literal(Literal::String(paragraph))),
+                    ) if *story == self.name => paragraph.clone(),
+                    (
+                        Some(RValue::Literal(Literal::String(story))),
+                        Some(RValue::Literal(Literal::String(paragraph))),
+                    ) => {
+                        let target = format!("{}::{}", story, paragraph);
+                        if !external.contains(&target) {
+                            external.push(target.clone());
+                        }
+                        target
+                    }
+                    _ => {
+                        push_edge(&mut edges, &paragraph.name, DYNAMIC_NODE, "dynamic");
+                        continue;
+                    }
+                };
+                let kind = match call.command.as_str() {
+                    "call" => "call",
+                    "replace" => "replace",
+                    _ => "goto",
+                };
+                push_edge(&mut edges, &paragraph.name, &target, kind);
+            }
+
+            if falls_through(&paragraph.block) {
+                if let Some(next) = self.paragraphs.get(index + 1) {
+                    push_edge(&mut edges, &paragraph.name, &next.name, "fall-through");
+                }
+            }
+        }
+
+        let mut dot = String::new();
+        let _ = writeln!(dot, "digraph {} {{", quote(&self.name));
+        for node in &nodes {
+            let _ = writeln!(dot, "    {};", quote(node));
+        }
+        for node in &external {
+            let _ = writeln!(dot, "    {} [shape=box];", quote(node));
+        }
+        if edges.iter().any(|(_, to, _)| to == DYNAMIC_NODE) {
+            let _ = writeln!(dot, "    {} [shape=diamond];", quote(DYNAMIC_NODE));
+        }
+        for (from, to, kind) in &edges {
+            let style = match *kind {
+                "call" => ", style=bold",
+                "replace" => ", arrowhead=empty",
+                "fall-through" => ", style=dotted",
+                "dynamic" => ", style=dashed",
+                _ => "",
+            };
+            let _ = writeln!(
+                dot,
+                "    {} -> {} [label={}{}];",
+                quote(from),
+                quote(to),
+                quote(kind),
+                style
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn push_edge(
+    edges: &mut Vec<(String, String, &'static str)>,
+    from: &str,
+    to: &str,
+    kind: &'static str,
+) {
+    if !edges
+        .iter()
+        .any(|(f, t, k)| f == from && t == to && *k == kind)
+    {
+        edges.push((from.to_string(), to.to_string(), kind));
+    }
+}
+
+/// Whether a paragraph may run to its end, i.e. it has no unconditional exit
+fn falls_through(block: &Block) -> bool {
+    block
+        .children
+        .iter()
+        .filter(|child| child.attributes.is_empty())
+        .all(|child| match &child.content {
+            ChildContent::Block(block) => falls_through(block),
+            ChildContent::SystemCallLine(call) => !matches!(
+                call.command.as_str(),
+                "goto" | "replace" | "finish" | "return"
+            ),
+            _ => true,
+        })
+}
+
+/// Quote an identifier for DOT
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_story;
+
+    #[test]
+    fn test_to_dot() {
+        let story = parse_story(
+            "main",
+            r#"
+::entry {
+    "hello"
+    #call paragraph="helper"
+    #[cond("lucky")]
+    #goto paragraph="bonus"
+}
+::helper {
+    #goto paragraph="ending" story="chapter2"
+}
+::bonus {
+    #[cond("again")]
+    #replace paragraph="entry"
+    #goto paragraph=next
+}
+"#,
+        )
+        .unwrap();
+
+        let dot = story.to_dot();
+        assert!(dot.starts_with("digraph \"main\" {\n"), "got:\n{}", dot);
+        for line in [
+            "    \"entry\";",
+            "    \"helper\";",
+            "    \"bonus\";",
+            "    \"chapter2::ending\" [shape=box];",
+            "    \"<dynamic>\" [shape=diamond];",
+            "    \"entry\" -> \"helper\" [label=\"call\", style=bold];",
+            "    \"entry\" -> \"bonus\" [label=\"goto\"];",
+            // the goto is conditional, so entry may still fall through
+            "    \"entry\" -> \"helper\" [label=\"fall-through\", style=dotted];",
+            "    \"helper\" -> \"chapter2::ending\" [label=\"goto\"];",
+            "    \"bonus\" -> \"entry\" [label=\"replace\", arrowhead=empty];",
+            "    \"bonus\" -> \"<dynamic>\" [label=\"dynamic\", style=dashed];",
+        ] {
+            assert!(dot.contains(line), "missing `{}` in:\n{}", line, dot);
+        }
+        // helper always leaves by its goto
+        assert!(!dot.contains("\"helper\" -> \"bonus\""), "got:\n{}", dot);
+    }
+}
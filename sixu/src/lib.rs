@@ -1,11 +1,36 @@
+mod attributes;
+#[cfg(feature = "compiled")]
+mod compiled;
 pub mod error;
+mod equality;
 pub mod format;
 mod fingerprint;
+mod graph;
+pub mod lint;
+pub mod merge;
 pub mod parser;
 pub mod result;
 pub mod runtime;
+mod preview;
+mod references;
+mod rename;
+mod simulate;
+mod source;
+mod usage;
+pub mod value_parser;
 
 #[cfg(feature = "cst")]
 pub mod cst;
+#[cfg(feature = "schema")]
+pub mod schema;
 
+#[cfg(feature = "compiled")]
+pub use compiled::{compile, compile_story, load_compiled, COMPILED_FORMAT_VERSION};
+pub use equality::{semantically_equal, semantically_equal_with, EqualityOptions};
 pub use fingerprint::BlockFingerprint;
+pub use lint::{lint, lint_line};
+pub use merge::StorySet;
+pub use parser::parse_strict;
+pub use simulate::{SimEvent, SIMULATION_STEP_LIMIT};
+pub use usage::VariableUsage;
+pub use value_parser::ValueParsers;
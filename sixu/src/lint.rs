@@ -0,0 +1,188 @@
+//! Source level checks producing editor friendly diagnostics.
+
+use std::ops::Range as LineRange;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::ParseError;
+use crate::parser;
+
+/// A position in a source text, both fields are 0-based and `column` counts characters.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: Severity,
+    /// Name of the checker that produced this diagnostic, e.g. `sixu`
+    pub source: String,
+    pub message: String,
+    /// Stable identifier of the check, if any
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub code: Option<String>,
+}
+
+/// Lint a whole document, reporting the first syntax error if it does not parse.
+pub fn lint(text: &str) -> Vec<Diagnostic> {
+    match parser::parse_story("lint", text) {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![syntax_error(&e, 0)],
+    }
+}
+
+/// Lint the statement on the lines `line_range` (0-based, end exclusive) of `text`
+/// in isolation, without parsing the rest of the document.
+///
+/// Only commands, system calls and text lines (with their attributes) are checked.
+/// Lines that only make sense as part of the surrounding structure, i.e. paragraph
+/// headers, block braces and embedded code, as well as blank and comment lines, yield
+/// no diagnostics. Checks that need the whole document, such as unclosed blocks,
+/// duplicate paragraphs or `#goto` targets, are never performed in this mode.
+pub fn lint_line(text: &str, line_range: LineRange<usize>) -> Vec<Diagnostic> {
+    let start = line_offset(text, line_range.start);
+    let end = line_offset(text, line_range.end.max(line_range.start));
+    let source = &text[start..end];
+
+    if !is_standalone_statement(source) {
+        return Vec::new();
+    }
+
+    match parser::parse_statement(source) {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![syntax_error(&e, line_range.start)],
+    }
+}
+
+/// Diagnostic for a parse error of a source that starts on line `first_line`
+fn syntax_error(e: &ParseError, first_line: usize) -> Diagnostic {
+    let start = Position {
+        line: first_line + e.line - 1,
+        column: e.column - 1,
+    };
+    let end = Position {
+        column: start.column + 1,
+        ..start
+    };
+    Diagnostic {
+        range: Range { start, end },
+        severity: Severity::Error,
+        source: "sixu".to_string(),
+        message: format!("Syntax error: {}", e.message()),
+        code: Some("syntax-error".to_string()),
+    }
+}
+
+/// Byte offset of the beginning of line `line`, or the end of `text` if there are fewer lines
+fn line_offset(text: &str, line: usize) -> usize {
+    if line == 0 {
+        return 0;
+    }
+    text.match_indices('\n')
+        .nth(line - 1)
+        .map(|(i, _)| i + 1)
+        .unwrap_or(text.len())
+}
+
+/// Whether `source` holds something that can be checked without its surroundings
+fn is_standalone_statement(source: &str) -> bool {
+    let trimmed = source.trim();
+    !(trimmed.is_empty()
+        || trimmed.starts_with("//")
+        || trimmed.starts_with("/*")
+        || trimmed.starts_with("::")
+        || trimmed.starts_with('{')
+        || trimmed.starts_with('}')
+        || trimmed.starts_with("@{")
+        || trimmed.starts_with("##"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str =
+        "::entry {\n    @changebg src=\"bg.jpg\"\n    @show(a=1 b=2)\n    hello\n}\n";
+
+    #[test]
+    fn test_lint_line_valid_statements() {
+        assert!(lint_line(DOCUMENT, 1..2).is_empty());
+        assert!(lint_line(DOCUMENT, 3..4).is_empty());
+        assert!(lint_line("#[cond(\"a > 1\")] #goto paragraph=\"x\"", 0..1).is_empty());
+    }
+
+    #[test]
+    fn test_lint_line_malformed_command() {
+        let diagnostics = lint_line(DOCUMENT, 2..3);
+
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(
+            diagnostic.range.start,
+            Position {
+                line: 2,
+                column: 14
+            }
+        );
+        assert!(
+            diagnostic.message.contains("expected `,` or `)`"),
+            "unexpected message: {}",
+            diagnostic.message
+        );
+    }
+
+    #[test]
+    fn test_lint_line_ignores_structural_lines() {
+        // the paragraph header and closing brace are only valid with their
+        // counterparts, which single line mode never looks at
+        assert!(lint_line(DOCUMENT, 0..1).is_empty());
+        assert!(lint_line(DOCUMENT, 4..5).is_empty());
+        assert!(lint_line("// comment\n\n", 0..2).is_empty());
+    }
+
+    #[test]
+    fn test_lint_document() {
+        assert!(lint(DOCUMENT.replace("(a=1 b=2)", "(a=1, b=2)").as_str()).is_empty());
+
+        let diagnostics = lint(DOCUMENT);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].range.start,
+            Position {
+                line: 2,
+                column: 14
+            }
+        );
+        assert_eq!(diagnostics[0].code.as_deref(), Some("syntax-error"));
+    }
+
+    #[test]
+    fn test_lint_line_out_of_range() {
+        assert!(lint_line(DOCUMENT, 10..12).is_empty());
+    }
+}
@@ -0,0 +1,332 @@
+//! Merging several stories into a single one, so that a game can be deployed as
+//! one bundled file.
+//!
+//! Every paragraph is renamed to `<story>.<paragraph>` after the story it comes
+//! from, and the targets of `#goto`, `#replace` and `#call` are rewritten to the
+//! new names: jumps inside a file or to another file of the bundle end up in the
+//! merged story, jumps to stories outside of the bundle are left untouched.
+//!
+//! Each story is followed by a [`story_end`] paragraph running `#finish`, so that
+//! running off the end of a story finishes it as it would unmerged, instead of
+//! falling through into the next story. The merged story starts with an `entry`
+//! paragraph jumping to the `entry` of the first story which has one, so it can be
+//! started with `Runtime::start(name, None)`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::MergeError;
+use crate::format::{
+    Argument, Block, Child, ChildContent, Literal, Paragraph, RValue, Story, SystemCallLine,
+};
+use crate::parser::parse_story;
+
+/// System calls whose `story` and `paragraph` arguments name a jump target
+const JUMP_COMMANDS: &[&str] = &["goto", "replace", "call"];
+
+/// Name of paragraph `paragraph` of story `story` once merged
+pub fn namespaced(story: &str, paragraph: &str) -> String {
+    format!("{}.{}", story, paragraph)
+}
+
+/// Name of the paragraph closing story `story` once merged. Paragraph names can't
+/// contain `#`, so it never collides with a paragraph of the story.
+pub fn story_end(story: &str) -> String {
+    namespaced(story, "#end")
+}
+
+/// Paragraph `name` made of a single system call
+fn system_call_paragraph(name: String, command: &str, arguments: Vec<Argument>) -> Paragraph {
+    Paragraph {
+        attributes: Vec::new(),
+        name,
+        parameters: Vec::new(),
+        block: Block {
+            label: None,
+            children: vec![Child {
+                marker: None,
+                attributes: Vec::new(),
+                content: ChildContent::SystemCallLine(SystemCallLine {
+                    command: command.to_string(),
+                    arguments,
+                }),
+            }],
+        },
+    }
+}
+
+/// A set of stories to be merged into a single one
+#[derive(Debug, Default, Clone)]
+pub struct StorySet {
+    stories: Vec<Story>,
+}
+
+impl StorySet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read and parse story files, each story is named after its file stem.
+    pub fn from_files<P: AsRef<Path>>(
+        paths: impl IntoIterator<Item = P>,
+    ) -> Result<Self, MergeError> {
+        let mut set = Self::new();
+        for path in paths {
+            let path = path.as_ref();
+            let source = std::fs::read_to_string(path).map_err(|source| MergeError::Io {
+                path: path.to_path_buf(),
+                source,
+            })?;
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let story = parse_story(&name, &source).map_err(|source| MergeError::Parse {
+                path: path.to_path_buf(),
+                source: Box::new(source),
+            })?;
+            set.add(story);
+        }
+        Ok(set)
+    }
+
+    pub fn add(&mut self, story: Story) {
+        self.stories.push(story);
+    }
+
+    pub fn stories(&self) -> &[Story] {
+        &self.stories
+    }
+
+    /// Merge every story of the set into a single story called `name`
+    pub fn merge(self, name: &str) -> Result<Story, MergeError> {
+        merge(name, self.stories)
+    }
+}
+
+/// Merge `stories` into a single story called `name`, see the [module](self) documentation.
+pub fn merge(name: &str, stories: Vec<Story>) -> Result<Story, MergeError> {
+    for (index, story) in stories.iter().enumerate() {
+        if stories[..index]
+            .iter()
+            .any(|other| other.name == story.name)
+        {
+            return Err(MergeError::DuplicateStory(story.name.clone()));
+        }
+    }
+
+    let bundled = stories.iter().map(|s| s.name.clone()).collect::<Vec<_>>();
+    let mut owners: HashMap<String, String> = HashMap::new();
+    let mut paragraphs = Vec::new();
+
+    let entry = stories
+        .iter()
+        .find(|story| story.paragraphs.iter().any(|p| p.name == "entry"))
+        .map(|story| namespaced(&story.name, "entry"));
+    if let Some(entry) = entry {
+        let target = Argument {
+            name: "paragraph".to_string(),
+            value: RValue::Literal(Literal::String(entry)),
+            span: None,
+        };
+        paragraphs.push(system_call_paragraph(
+            "entry".to_string(),
+            "goto",
+            vec![target],
+        ));
+    }
+
+    for story in stories {
+        for mut paragraph in story.paragraphs {
+            let merged_name = namespaced(&story.name, &paragraph.name);
+            if let Some(first) = owners.insert(merged_name.clone(), story.name.clone()) {
+                return Err(MergeError::ParagraphCollision {
+                    paragraph: merged_name,
+                    first,
+                    second: story.name,
+                });
+            }
+
+            let target = Target {
+                merged: name,
+                bundled: &bundled,
+                story: &story.name,
+                paragraph: &paragraph.name,
+            };
+            target.rewrite_block(&mut paragraph.block)?;
+            paragraph.name = merged_name;
+            paragraphs.push(paragraph);
+        }
+        paragraphs.push(system_call_paragraph(
+            story_end(&story.name),
+            "finish",
+            Vec::new(),
+        ));
+    }
+
+    Ok(Story {
+        name: name.to_string(),
+        paragraphs,
+    })
+}
+
+/// The paragraph being rewritten and the stories taking part in the merge
+struct Target<'a> {
+    merged: &'a str,
+    bundled: &'a [String],
+    story: &'a str,
+    paragraph: &'a str,
+}
+
+impl Target<'_> {
+    fn rewrite_block(&self, block: &mut Block) -> Result<(), MergeError> {
+        block.try_for_each_descendant_mut(&mut |child| match &mut child.content {
+            ChildContent::SystemCallLine(call)
+                if JUMP_COMMANDS.contains(&call.command.as_str()) =>
+            {
+                self.rewrite_jump(&call.command, &mut call.arguments)
+            }
+            _ => Ok(()),
+        })
+    }
+
+    fn rewrite_jump(&self, command: &str, arguments: &mut [Argument]) -> Result<(), MergeError> {
+        let dynamic = || MergeError::DynamicTarget {
+            command: command.to_string(),
+            story: self.story.to_string(),
+            paragraph: self.paragraph.to_string(),
+        };
+
+        let target_story = match arguments.iter().find(|arg| arg.name == "story") {
+            None => self.story.to_string(),
+            Some(arg) => string_literal(&arg.value).ok_or_else(dynamic)?.to_string(),
+        };
+        if !self.bundled.contains(&target_story) {
+            return Ok(());
+        }
+
+        for arg in arguments.iter_mut() {
+            match arg.name.as_str() {
+                "story" => arg.value = RValue::Literal(Literal::String(self.merged.to_string())),
+                "paragraph" => {
+                    let paragraph = string_literal(&arg.value).ok_or_else(dynamic)?;
+                    arg.value =
+                        RValue::Literal(Literal::String(namespaced(&target_story, paragraph)));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn string_literal(value: &RValue) -> Option<&str> {
+    match value {
+        RValue::Literal(Literal::String(s)) => Some(s),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn story(name: &str, source: &str) -> Story {
+        parse_story(name, source).unwrap()
+    }
+
+    fn jump_arguments(story: &Story, paragraph: &str) -> Vec<(String, RValue)> {
+        let paragraph = story
+            .paragraphs
+            .iter()
+            .find(|p| p.name == paragraph)
+            .unwrap();
+        match &paragraph.block.children[0].content {
+            ChildContent::SystemCallLine(call) => call
+                .arguments
+                .iter()
+                .map(|arg| (arg.name.clone(), arg.value.clone()))
+                .collect(),
+            other => panic!("expected a system call, got {:?}", other),
+        }
+    }
+
+    fn string(s: &str) -> RValue {
+        RValue::Literal(Literal::String(s.to_string()))
+    }
+
+    #[test]
+    fn test_jump_targets_are_rewritten() {
+        let merged = merge(
+            "bundle",
+            vec![
+                story(
+                    "a",
+                    "::local {\n#goto paragraph=\"other\"\n}\n::cross {\n#call story=\"b\" paragraph=\"entry\" x=1\n}\n::external {\n#replace story=\"dlc\" paragraph=\"entry\"\n}\n",
+                ),
+                story("b", "::entry {\n}\n"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(merged.name, "bundle");
+        assert_eq!(
+            jump_arguments(&merged, "a.local"),
+            vec![("paragraph".to_string(), string("a.other"))]
+        );
+        assert_eq!(
+            jump_arguments(&merged, "a.cross"),
+            vec![
+                ("story".to_string(), string("bundle")),
+                ("paragraph".to_string(), string("b.entry")),
+                ("x".to_string(), RValue::Literal(Literal::Integer(1))),
+            ]
+        );
+        // stories outside of the bundle are left alone
+        assert_eq!(
+            jump_arguments(&merged, "a.external"),
+            vec![
+                ("story".to_string(), string("dlc")),
+                ("paragraph".to_string(), string("entry")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collisions_are_reported() {
+        let err = merge(
+            "bundle",
+            vec![story("a", "::x {\n}\n"), story("a", "::y {\n}\n")],
+        )
+        .unwrap_err();
+        assert!(matches!(err, MergeError::DuplicateStory(name) if name == "a"));
+
+        // paragraph names can't contain dots, so merged names only collide when a
+        // story defines the same paragraph twice
+        let err = merge("bundle", vec![story("a", "::x {\n}\n::x {\n}\n")]).unwrap_err();
+        match err {
+            MergeError::ParagraphCollision {
+                paragraph,
+                first,
+                second,
+            } => {
+                assert_eq!(paragraph, "a.x");
+                assert_eq!((first.as_str(), second.as_str()), ("a", "a"));
+            }
+            other => panic!("expected a collision, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_dynamic_target_is_an_error() {
+        let err = merge(
+            "bundle",
+            vec![story("a", "::entry {\n#goto paragraph=next\n}\n")],
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot rewrite #goto in a::entry, its target is not a string literal"
+        );
+    }
+}
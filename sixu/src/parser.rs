@@ -2,38 +2,225 @@ mod argument;
 mod attribute;
 mod block;
 mod command_line;
-mod comment;
+pub(crate) mod comment;
+pub(crate) mod config;
+pub(crate) mod escape;
 mod identifier;
+pub(crate) mod macros;
 mod paragraph;
 mod parameter;
 pub(crate) mod primitive;
+mod reader;
+mod recorder;
 mod rvalue;
+mod strict;
 mod systemcall_line;
 mod template;
-mod text;
+pub(crate) mod text;
 mod variable;
 
-use nom::combinator::all_consuming;
+use nom::branch::alt;
+use nom::combinator::{all_consuming, consumed, map};
+use nom::error::context;
 use nom::multi::*;
 use nom::sequence::*;
-use nom::Parser;
+use nom::{Finish, Parser};
+use nom_language::error::{VerboseError, VerboseErrorKind};
 
+use crate::error::{ParseError, ReadError, SyntaxError};
 use crate::format::*;
 use crate::result::ParseResult;
 
+pub use self::config::{ParserConfig, UnknownEscape};
+
 use self::comment::span0;
+use self::macros::{expand_items, macro_definition, Item};
 use self::paragraph::paragraph;
 
-/// parse a story file which is a sequence of paragraphs
+/// parse a story file which is a sequence of paragraphs and macro definitions,
+/// expanding every macro invocation
 pub fn parse<'a>(name: &'a str, input: &'a str) -> ParseResult<&'a str, Story> {
-    let (input, paragraphs) =
-        all_consuming(terminated(many0(preceded(span0, paragraph)), span0)).parse(input)?;
+    let (rest, items) = items(input)?;
+    let paragraphs = expand_items(input, items).map_err(|(offset, e)| {
+        let name = &input[offset + macros::name_offset(&input[offset..])..];
+        nom::Err::Failure(VerboseError {
+            errors: vec![
+                (name, VerboseErrorKind::Context(e.context())),
+                (name, VerboseErrorKind::Context("macro expansion")),
+            ],
+        })
+    })?;
 
     Ok((
-        input,
+        rest,
         Story {
             name: name.to_string(),
             paragraphs,
         },
     ))
 }
+
+/// parse a story file, resolving failures into a readable [`ParseError`]
+pub fn parse_story(name: &str, input: &str) -> Result<Story, ParseError> {
+    let (_, items) = items(input)
+        .finish()
+        .map_err(|e| ParseError::from_verbose(input, e))?;
+    let paragraphs = expand_items(input, items).map_err(|(offset, e)| {
+        ParseError::rejected(input, offset, "macro expansion", e.to_string())
+    })?;
+
+    Ok(Story {
+        name: name.to_string(),
+        paragraphs,
+    })
+}
+
+fn items(input: &str) -> ParseResult<&str, Vec<Item<'_>>> {
+    all_consuming(terminated(
+        many0(preceded(
+            span0,
+            alt((
+                map(
+                    consumed(context("macro", macro_definition)),
+                    |(source, m)| Item::Macro(m, source),
+                ),
+                map(context("paragraph", paragraph), Item::Paragraph),
+            )),
+        )),
+        span0,
+    ))
+    .parse(input)
+}
+
+/// parse a story file like [`parse_story`], additionally rejecting constructs that
+/// parse but are likely mistakes, for gating content in CI:
+///
+/// - unquoted values meant as strings, e.g. `src=bg-01.png` which ends at `-` or
+///   `src=test.jpg` which is read as a variable
+/// - content after a parenthesized argument list on the same line, e.g.
+///   `@show(a=1) extra`
+/// - bare identifiers used as values that look like misspelled literals, e.g. `ture`
+///
+/// Every violation is reported, or the syntax error if the file does not parse.
+pub fn parse_strict(name: &str, input: &str) -> Result<Story, Vec<SyntaxError>> {
+    let (story, mut errors) = strict::record_violations(input, || parse_story(name, input));
+    match story {
+        Ok(story) if errors.is_empty() => Ok(story),
+        Ok(_) => Err(errors),
+        Err(e) => {
+            let e = SyntaxError::from(e);
+            errors.retain(|violation| violation.offset < e.offset);
+            errors.push(e);
+            Err(errors)
+        }
+    }
+}
+
+/// parse a story file like [`parse_story`] with the options in `config`
+pub fn parse_story_with_config(
+    name: &str,
+    input: &str,
+    config: &ParserConfig,
+) -> Result<Story, ParseError> {
+    config::with_config(config, || parse_story(name, input))
+}
+
+/// parse a story file like [`parse_story`] from a byte stream such as a file or a
+/// network response
+///
+/// The parser needs the whole source, so the stream is read to its end first while
+/// validating UTF-8 chunk by chunk; see [`ReadError`] for the failures.
+pub fn parse_story_from_reader(name: &str, reader: impl std::io::Read) -> Result<Story, ReadError> {
+    let text = reader::read_to_string(reader)?;
+    Ok(parse_story(name, &text)?)
+}
+
+/// parse a story file like [`parse_story`], also collecting every comment of the file
+/// with its byte range, for tools that need comments but not a full CST
+pub fn parse_story_with_comments(
+    name: &str,
+    input: &str,
+) -> Result<(Story, CommentList), ParseError> {
+    let (story, comments) = comment::record_comments(input, || parse_story(name, input));
+    Ok((story?, comments))
+}
+
+/// parse a single statement (a command, system call or text line together with its
+/// attributes) which must make up the whole input
+pub fn parse_statement(input: &str) -> Result<Child, ParseError> {
+    all_consuming(terminated(
+        context("statement", block::child),
+        comment::span0,
+    ))
+    .parse(input)
+    .finish()
+    .map(|(_, child)| child)
+    .map_err(|e| ParseError::from_verbose(input, e))
+}
+
+/// match the closing character `ch`, reporting every token in `expected` as
+/// acceptable on failure so that error messages can list the alternatives
+pub(crate) fn closing_char<'a>(
+    ch: char,
+    expected: &'static [char],
+) -> impl Fn(&'a str) -> ParseResult<&'a str, char> {
+    move |input: &'a str| match input.chars().next() {
+        Some(c) if c == ch => Ok((&input[c.len_utf8()..], c)),
+        _ => Err(nom::Err::Error(VerboseError {
+            errors: expected
+                .iter()
+                .map(|c| (input, VerboseErrorKind::Char(*c)))
+                .collect(),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_missing_close_paren() {
+        let input = "::entry {\n@cmd(a=1 b=2)\n}\n";
+        let err = parse_story("test", input).unwrap_err();
+
+        assert_eq!(err.line, 2);
+        assert_eq!(err.column, 10);
+        assert_eq!(err.expected, vec!["`,`", "`)`"]);
+        assert_eq!(err.contexts.first(), Some(&"command argument list"));
+        assert_eq!(
+            err.to_string(),
+            "line 2, column 10: in command argument list, expected `,` or `)`, found `b`"
+        );
+    }
+
+    #[test]
+    fn test_parse_error_number_out_of_range() {
+        let input = "::entry {\n@wait time=99999999999999999999\n}\n";
+        let err = parse_story("test", input).unwrap_err();
+
+        assert_eq!((err.line, err.column), (2, 12));
+        assert_eq!(err.contexts.first(), Some(&"integer out of range"));
+    }
+
+    #[test]
+    fn test_parse_error_unclosed_parameter_list() {
+        let input = "::entry(a, b {\n}\n";
+        let err = parse_story("test", input).unwrap_err();
+
+        assert_eq!(err.contexts.first(), Some(&"paragraph parameter list"));
+        assert_eq!(err.expected, vec!["`,`", "`)`"]);
+        assert!(err.contexts.contains(&"paragraph"));
+    }
+
+    #[test]
+    fn test_parse_error_unclosed_block_at_end_of_input() {
+        let input = "::entry {\n    {\n        hello\n";
+        let err = parse_story("test", input).unwrap_err();
+
+        assert_eq!((err.line, err.column), (4, 1));
+        assert_eq!(err.contexts.first(), Some(&"block"));
+        assert_eq!(err.expected, vec!["`}`"]);
+        assert_eq!(err.found, None);
+    }
+}
@@ -1,6 +1,7 @@
 use nom::branch::alt;
 use nom::bytes::complete::*;
 use nom::combinator::*;
+use nom::error::context;
 use nom::multi::{many0, separated_list0};
 use nom::sequence::*;
 use nom::Parser;
@@ -11,22 +12,30 @@ use crate::result::ParseResult;
 use super::comment::{span0, span0_inline};
 use super::identifier::identifier;
 use super::rvalue::rvalue;
-use super::Argument;
+use super::strict;
+use super::{closing_char, Argument};
 
 pub fn arguments(input: &str) -> ParseResult<&str, Vec<Argument>> {
     let (input, _) = span0_inline.parse(input)?;
+    let parenthesized = input.starts_with('(');
     let (input, arguments) = cut(alt((arguments_type_a, arguments_type_b))).parse(input)?;
+    if parenthesized {
+        strict::check_after_arguments(input);
+    }
     Ok((input, arguments))
 }
 
 pub fn arguments_type_a(input: &str) -> ParseResult<&str, Vec<Argument>> {
-    let (input, _) = tag("(").parse(input)?;
-    let (input, _) = span0.parse(input)?;
-    let (input, arguments) =
-        separated_list0(delimited(span0, tag(","), span0), argument).parse(input)?;
-    let (input, _) = span0.parse(input)?;
-    let (input, _) = tag(")").parse(input)?;
-    Ok((input, arguments))
+    context("command argument list", |input| {
+        let (input, _) = tag("(").parse(input)?;
+        let (input, _) = span0.parse(input)?;
+        let (input, arguments) =
+            separated_list0(delimited(span0, tag(","), span0), argument).parse(input)?;
+        let (input, _) = span0.parse(input)?;
+        let (input, _) = cut(closing_char(')', &[',', ')'])).parse(input)?;
+        Ok((input, arguments))
+    })
+    .parse(input)
 }
 
 pub fn arguments_type_b(input: &str) -> ParseResult<&str, Vec<Argument>> {
@@ -36,12 +45,21 @@ pub fn arguments_type_b(input: &str) -> ParseResult<&str, Vec<Argument>> {
 pub fn argument(input: &str) -> ParseResult<&str, Argument> {
     let (input, name) = identifier.parse(input)?;
     let (input, _) = span0.parse(input)?;
-    let (input, value) = cut(opt(preceded(tag("="), preceded(span0, cut(rvalue))))).parse(input)?;
+    let (input, value) = cut(opt(preceded(
+        tag("="),
+        preceded(span0, cut(consumed(rvalue))),
+    )))
+    .parse(input)?;
+    let value = value.map(|(source, value)| {
+        strict::check_value(source, &value, input);
+        value
+    });
     Ok((
         input,
         Argument {
             name: name.to_string(),
             value: value.unwrap_or(RValue::Literal(Literal::Boolean(true))),
+            span: None,
         },
     ))
 }
@@ -61,6 +79,7 @@ mod tests {
                 Argument {
                     name: "a".to_string(),
                     value: RValue::Literal(Literal::Boolean(true)),
+                    span: None,
                 }
             ))
         );
@@ -71,6 +90,7 @@ mod tests {
                 Argument {
                     name: "a".to_string(),
                     value: RValue::Literal(Literal::Integer(1)),
+                    span: None,
                 }
             ))
         );
@@ -81,6 +101,7 @@ mod tests {
                 Argument {
                     name: "a".to_string(),
                     value: RValue::Literal(Literal::Integer(1)),
+                    span: None,
                 }
             ))
         );
@@ -91,6 +112,7 @@ mod tests {
                 Argument {
                     name: "foo".to_string(),
                     value: RValue::Literal(Literal::String("bar".to_string())),
+                    span: None,
                 }
             ))
         );
@@ -103,6 +125,7 @@ mod tests {
                     value: RValue::Variable(Variable {
                         chain: vec!["foo".to_string(), "bar".to_string()],
                     }),
+                    span: None,
                 }
             ))
         );
@@ -116,6 +139,7 @@ mod tests {
                 vec![Argument {
                     name: "a".to_string(),
                     value: RValue::Literal(Literal::Integer(1)),
+                    span: None,
                 }]
             ))
         );
@@ -127,10 +151,12 @@ mod tests {
                     Argument {
                         name: "a".to_string(),
                         value: RValue::Literal(Literal::Integer(1)),
+                        span: None,
                     },
                     Argument {
                         name: "b".to_string(),
                         value: RValue::Literal(Literal::String("aa".to_string())),
+                        span: None,
                     }
                 ]
             ))
@@ -145,6 +171,7 @@ mod tests {
                 vec![Argument {
                     name: "a".to_string(),
                     value: RValue::Literal(Literal::Integer(1)),
+                    span: None,
                 }]
             ))
         );
@@ -156,10 +183,12 @@ mod tests {
                     Argument {
                         name: "a".to_string(),
                         value: RValue::Literal(Literal::Integer(1)),
+                        span: None,
                     },
                     Argument {
                         name: "b".to_string(),
                         value: RValue::Literal(Literal::String("aa".to_string())),
+                        span: None,
                     }
                 ]
             ))
@@ -1,28 +1,39 @@
 use nom::branch::alt;
 use nom::bytes::complete::*;
-use nom::character::complete::{anychar, line_ending, multispace1};
-use nom::combinator::{cut, opt};
-use nom::error::ParseError;
+use nom::character::complete::{anychar, multispace1, space0, space1};
+use nom::combinator::{cut, opt, peek};
+use nom::error::{context, ParseError};
 use nom::multi::{many0, many_till};
 use nom::sequence::*;
 use nom::Parser;
 use nom_language::error::VerboseError;
 
-use crate::format::{Child, ChildContent, LineMarker};
+use crate::format::{Child, ChildContent, LineMarker, Literal};
 use crate::result::ParseResult;
 
 use super::attribute::{attribute, balanced_delimiters};
 use super::command_line::command_line;
-use super::comment::{comment, marker_directive_comment, span0, span0_inline};
+use super::comment::{comment, line_break, marker_directive_comment, span0, span0_inline};
+use super::macros::macro_invocation;
+use super::primitive::string;
 use super::systemcall_line::systemcall_line;
 use super::text::text_line;
-use super::Block;
+use super::{closing_char, Block};
 
 pub fn block(input: &str) -> ParseResult<&str, Block> {
-    let (input, _) = tag("{").parse(input)?;
-    let (input, children) = cut(block_children).parse(input)?;
-    let (input, _) = preceded(span0, tag("}")).parse(input)?;
-    Ok((input, Block { children }))
+    context("block", |input| {
+        let (input, _) = tag("{").parse(input)?;
+        let (input, children) = cut(block_children).parse(input)?;
+        let (input, _) = preceded(span0, tag("}")).parse(input)?;
+        Ok((
+            input,
+            Block {
+                label: None,
+                children,
+            },
+        ))
+    })
+    .parse(input)
 }
 
 fn block_children(mut input: &str) -> ParseResult<&str, Vec<Child>> {
@@ -41,7 +52,19 @@ fn block_children(mut input: &str) -> ParseResult<&str, Vec<Child>> {
             return Ok((next_input, children));
         }
 
+        // Reaching the end of input means the block is never closed
+        if next_input.is_empty() {
+            closing_char('}', &['}']).parse(next_input)?;
+        }
+
         let (after_child, mut child) = child(next_input)?;
+        // A child that consumes nothing would make the loop spin forever
+        if after_child.len() == next_input.len() {
+            return Err(nom::Err::Error(VerboseError::from_error_kind(
+                next_input,
+                nom::error::ErrorKind::Many0,
+            )));
+        }
         child.marker = marker;
         children.push(child);
         input = after_child;
@@ -49,10 +72,28 @@ fn block_children(mut input: &str) -> ParseResult<&str, Vec<Child>> {
 }
 
 pub fn block_child(input: &str) -> ParseResult<&str, ChildContent> {
-    let (input, block) = block.parse(input)?;
+    let (input, label) = opt(block_label).parse(input)?;
+    let (input, mut block) = block.parse(input)?;
+    block.label = label;
     Ok((input, ChildContent::Block(block)))
 }
 
+/// parse the label of a labeled block like `group "intro" { ... }`, which must be
+/// on the same line as the opening brace
+fn block_label(input: &str) -> ParseResult<&str, String> {
+    let (input, _) = tag("group").parse(input)?;
+    let (input, _) = space1.parse(input)?;
+    let (input, label) = string.parse(input)?;
+    let (input, _) = space0.parse(input)?;
+    // only a label when a block follows, otherwise this is a text line
+    peek(tag("{")).parse(input)?;
+    let label = match label {
+        Literal::String(s) => s,
+        _ => unreachable!("string parser always yields a string literal"),
+    };
+    Ok((input, label))
+}
+
 pub fn child(input: &str) -> ParseResult<&str, Child> {
     let (input, _) = span0.parse(input)?;
     let (input, attributes) = many0(attribute).parse(input)?;
@@ -60,6 +101,7 @@ pub fn child(input: &str) -> ParseResult<&str, Child> {
     let (input, child) = alt((
         embedded_code,
         block_child,
+        macro_invocation,
         command_line,
         systemcall_line,
         text_line,
@@ -119,9 +161,9 @@ pub fn embedded_code_brace(input: &str) -> ParseResult<&str, ChildContent> {
 
 /// Parse embedded code using ##...## syntax (legacy support)
 pub fn embedded_code_hash(input: &str) -> ParseResult<&str, ChildContent> {
-    let (input, _) = (tag("##"), span0_inline, opt(line_ending)).parse(input)?;
+    let (input, _) = (tag("##"), span0_inline, opt(line_break)).parse(input)?;
     let (input, (content, _)) =
-        cut(many_till(anychar, (tag("##"), span0_inline, line_ending))).parse(input)?;
+        cut(many_till(anychar, (tag("##"), span0_inline, line_break))).parse(input)?;
     Ok((
         input,
         ChildContent::EmbeddedCode(content.into_iter().collect::<String>()),
@@ -139,13 +181,32 @@ mod tests {
 
     #[test]
     fn test_block() {
-        assert_eq!(block("{}"), Ok(("", Block { children: vec![] })));
-        assert_eq!(block("{\n}"), Ok(("", Block { children: vec![] })));
+        assert_eq!(
+            block("{}"),
+            Ok((
+                "",
+                Block {
+                    label: None,
+                    children: vec![]
+                }
+            ))
+        );
+        assert_eq!(
+            block("{\n}"),
+            Ok((
+                "",
+                Block {
+                    label: None,
+                    children: vec![]
+                }
+            ))
+        );
         assert_eq!(
             block("{\n@command foo=false}"),
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![Child {
                         marker: None,
                         attributes: vec![],
@@ -154,6 +215,7 @@ mod tests {
                             arguments: vec![Argument {
                                 name: "foo".to_string(),
                                 value: RValue::Literal(Literal::Boolean(false)),
+                                span: None,
                             }],
                         }),
                     }],
@@ -165,6 +227,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![
                         Child {
                             marker: None,
@@ -174,6 +237,7 @@ mod tests {
                                 arguments: vec![Argument {
                                     name: "foo".to_string(),
                                     value: RValue::Literal(Literal::Boolean(false)),
+                                    span: None,
                                 }],
                             }),
                         },
@@ -195,6 +259,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![
                         Child {
                             marker: None,
@@ -204,6 +269,7 @@ mod tests {
                                 arguments: vec![Argument {
                                     name: "foo".to_string(),
                                     value: RValue::Literal(Literal::Boolean(false)),
+                                    span: None,
                                 }],
                             }),
                         },
@@ -226,6 +292,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![
                         Child {
                             marker: None,
@@ -235,6 +302,7 @@ mod tests {
                                 arguments: vec![Argument {
                                     name: "foo".to_string(),
                                     value: RValue::Literal(Literal::Boolean(false)),
+                                    span: None,
                                 }],
                             }),
                         },
@@ -251,6 +319,7 @@ mod tests {
                             marker: None,
                             attributes: vec![],
                             content: ChildContent::Block(Block {
+                                label: None,
                                 children: vec![Child {
                                     marker: None,
                                     attributes: vec![],
@@ -259,6 +328,7 @@ mod tests {
                                         arguments: vec![Argument {
                                             name: "bar".to_string(),
                                             value: RValue::Literal(Literal::Boolean(true)),
+                                            span: None,
                                         }],
                                     }),
                                 }],
@@ -277,6 +347,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![Child {
                         marker: Some(LineMarker {
                             id: "Labc123".to_string(),
@@ -287,6 +358,7 @@ mod tests {
                             arguments: vec![Argument {
                                 name: "foo".to_string(),
                                 value: RValue::Literal(Literal::Boolean(false)),
+                                span: None,
                             }],
                         }),
                     }],
@@ -302,6 +374,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![Child {
                         marker: Some(LineMarker {
                             id: "Labc123".to_string(),
@@ -475,6 +548,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![
                         Child {
                             marker: None,
@@ -502,6 +576,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![Child {
                         marker: None,
                         attributes: vec![Attribute {
@@ -524,6 +599,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![
                         Child {
                             marker: None,
@@ -554,6 +630,7 @@ mod tests {
                                 arguments: vec![Argument {
                                     name: "foo".to_string(),
                                     value: RValue::Literal(Literal::Boolean(false)),
+                                    span: None,
                                 }],
                             }),
                         }
@@ -572,6 +649,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![Child {
                         marker: None,
                         attributes: vec![Attribute {
@@ -599,6 +677,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![Child {
                         marker: None,
                         attributes: vec![
@@ -630,6 +709,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![Child {
                         marker: None,
                         attributes: vec![Attribute {
@@ -637,6 +717,7 @@ mod tests {
                             condition: Some("x > 0".to_string()),
                         }],
                         content: ChildContent::Block(Block {
+                            label: None,
                             children: vec![Child {
                                 marker: None,
                                 attributes: vec![],
@@ -661,6 +742,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![Child {
                         marker: None,
                         attributes: vec![Attribute {
@@ -686,6 +768,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![Child {
                         marker: None,
                         attributes: vec![Attribute {
@@ -693,6 +776,7 @@ mod tests {
                             condition: Some("counter < 3".to_string()),
                         }],
                         content: ChildContent::Block(Block {
+                            label: None,
                             children: vec![Child {
                                 marker: None,
                                 attributes: vec![],
@@ -701,6 +785,7 @@ mod tests {
                                     arguments: vec![Argument {
                                         name: "arg".to_string(),
                                         value: RValue::Literal(Literal::Integer(1)),
+                                        span: None,
                                     }],
                                 }),
                             }],
@@ -719,6 +804,7 @@ mod tests {
             Ok((
                 "",
                 Block {
+                    label: None,
                     children: vec![Child {
                         marker: None,
                         attributes: vec![Attribute {
@@ -726,6 +812,7 @@ mod tests {
                             condition: None,
                         }],
                         content: ChildContent::Block(Block {
+                            label: None,
                             children: vec![
                                 Child {
                                     marker: None,
@@ -735,6 +822,7 @@ mod tests {
                                         arguments: vec![Argument {
                                             name: "arg".to_string(),
                                             value: RValue::Literal(Literal::Integer(1)),
+                                            span: None,
                                         }],
                                     }),
                                 },
@@ -781,4 +869,23 @@ mod tests {
         assert_eq!(child.attributes[2].keyword, "if");
         assert!(matches!(child.content, ChildContent::Block(_)));
     }
+
+    #[test]
+    fn test_labeled_block() {
+        let (_, parsed) = block("{\ngroup \"intro\" {\n@cmd\n}\ngroup of friends\n}").unwrap();
+        assert_eq!(parsed.children.len(), 2);
+
+        match &parsed.children[0].content {
+            ChildContent::Block(inner) => {
+                assert_eq!(inner.label.as_deref(), Some("intro"));
+                assert_eq!(inner.children.len(), 1);
+            }
+            other => panic!("expected a labeled block, got {:?}", other),
+        }
+        // `group` without a following block is just text
+        assert!(matches!(
+            parsed.children[1].content,
+            ChildContent::TextLine(..)
+        ));
+    }
 }
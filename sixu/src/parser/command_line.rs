@@ -1,5 +1,6 @@
 use nom::character::complete::char;
 use nom::combinator::cut;
+use nom::error::context;
 use nom::sequence::*;
 use nom::Parser;
 
@@ -12,8 +13,11 @@ use super::ChildContent;
 use super::CommandLine;
 
 pub fn command_line(input: &str) -> ParseResult<&str, ChildContent> {
-    let (input, (command, arguments)) =
-        preceded(span0, (preceded(char('@'), cut(identifier)), arguments)).parse(input)?;
+    let (input, (command, arguments)) = context(
+        "command",
+        preceded(span0, (preceded(char('@'), cut(identifier)), arguments)),
+    )
+    .parse(input)?;
 
     Ok((
         input,
@@ -51,6 +55,7 @@ mod tests {
                     arguments: vec![Argument {
                         name: "a".to_string(),
                         value: RValue::Literal(Literal::Boolean(true)),
+                        span: None,
                     }],
                 })
             ))
@@ -64,6 +69,7 @@ mod tests {
                     arguments: vec![Argument {
                         name: "a".to_string(),
                         value: RValue::Literal(Literal::Integer(1)),
+                        span: None,
                     }],
                 })
             ))
@@ -78,10 +84,12 @@ mod tests {
                         Argument {
                             name: "a".to_string(),
                             value: RValue::Literal(Literal::Integer(1)),
+                            span: None,
                         },
                         Argument {
                             name: "b".to_string(),
                             value: RValue::Literal(Literal::Boolean(true)),
+                            span: None,
                         }
                     ],
                 })
@@ -97,10 +105,12 @@ mod tests {
                         Argument {
                             name: "a".to_string(),
                             value: RValue::Literal(Literal::Integer(1)),
+                            span: None,
                         },
                         Argument {
                             name: "b".to_string(),
                             value: RValue::Literal(Literal::Integer(2)),
+                            span: None,
                         },
                     ],
                 })
@@ -116,14 +126,17 @@ mod tests {
                         Argument {
                             name: "a".to_string(),
                             value: RValue::Literal(Literal::Integer(1)),
+                            span: None,
                         },
                         Argument {
                             name: "b".to_string(),
                             value: RValue::Literal(Literal::Integer(2)),
+                            span: None,
                         },
                         Argument {
                             name: "c".to_string(),
                             value: RValue::Literal(Literal::Boolean(true)),
+                            span: None,
                         }
                     ],
                 })
@@ -139,14 +152,17 @@ mod tests {
                         Argument {
                             name: "a".to_string(),
                             value: RValue::Literal(Literal::Integer(1)),
+                            span: None,
                         },
                         Argument {
                             name: "b".to_string(),
                             value: RValue::Literal(Literal::Integer(2)),
+                            span: None,
                         },
                         Argument {
                             name: "c".to_string(),
                             value: RValue::Literal(Literal::Boolean(true)),
+                            span: None,
                         }
                     ],
                 })
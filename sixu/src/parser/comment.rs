@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+
 use nom::branch::*;
 use nom::bytes::complete::*;
 use nom::character::complete::*;
@@ -7,9 +9,44 @@ use nom::multi::*;
 use nom::sequence::*;
 use nom::Parser;
 
-use crate::format::LineMarker;
+use crate::format::{Comment, CommentKind, CommentList, LineMarker};
 use crate::result::ParseResult;
 
+use super::recorder::{Recorder, Slot};
+
+thread_local! {
+    /// Comments matched while `record_comments` runs
+    static MATCHED: Slot<CommentKind> = const { RefCell::new(None) };
+}
+
+static COMMENTS: Recorder<CommentKind> = Recorder(&MATCHED);
+
+/// Run `parse` over `input` and collect every comment it matches, as byte ranges of
+/// `input` in source order. Comments matched more than once due to backtracking are
+/// reported once, and those matched in other strings (e.g. expanded macro bodies)
+/// are dropped.
+pub fn record_comments<T>(input: &str, parse: impl FnOnce() -> T) -> (T, CommentList) {
+    let (result, matched) = COMMENTS.collect(input, parse);
+    let mut comments: CommentList = matched
+        .into_iter()
+        .map(|(range, kind)| {
+            let source = &input[range.clone()];
+            let text = match kind {
+                CommentKind::Line => &source[2..],
+                CommentKind::Block => &source[2..source.len() - 2],
+            };
+            let comment = Comment {
+                kind,
+                text: text.to_string(),
+            };
+            (range, comment)
+        })
+        .collect();
+    comments.dedup_by_key(|(range, _)| range.start);
+
+    (result, comments)
+}
+
 /// parse comment like `// C++/EOL-style comments`
 pub fn comment(input: &str) -> ParseResult<&str, &str> {
     alt((comment_single, comment_multi)).parse(input)
@@ -17,13 +54,15 @@ pub fn comment(input: &str) -> ParseResult<&str, &str> {
 
 /// parse comment like `// C++/EOL-style comments`
 pub fn comment_single(input: &str) -> ParseResult<&str, &str> {
-    preceded(tag("//"), cut(is_not("\r\n"))).parse(input)
+    let (rest, (whole, text)) = consumed(preceded(tag("//"), cut(is_not("\r\n")))).parse(input)?;
+    COMMENTS.record(whole, CommentKind::Line);
+    Ok((rest, text))
 }
 
 pub fn marker_directive_comment(input: &str) -> ParseResult<&str, LineMarker> {
     let (input, _) = tag("//#marker id=").parse(input)?;
     let (input, id) = cut(take_while1(|ch: char| ch.is_ascii_alphanumeric() || ch == '_')).parse(input)?;
-    let (input, _) = opt(line_ending).parse(input)?;
+    let (input, _) = opt(line_break).parse(input)?;
 
     let marker = LineMarker::parse_id(id).ok_or_else(|| {
         nom::Err::Error(nom_language::error::VerboseError::from_error_kind(
@@ -43,7 +82,10 @@ pub fn marker_directive_comment(input: &str) -> ParseResult<&str, LineMarker> {
  * returns purely the comment content, without `/*` and `*/`
 */
 pub fn comment_multi(input: &str) -> ParseResult<&str, &str> {
-    delimited(tag("/*"), take_until("*/"), tag("*/")).parse(input)
+    let (rest, (whole, text)) =
+        consumed(delimited(tag("/*"), take_until("*/"), tag("*/"))).parse(input)?;
+    COMMENTS.record(whole, CommentKind::Block);
+    Ok((rest, text))
 }
 
 /// match contiguous comments or whitespaces, which can be multiple lines
@@ -55,6 +97,11 @@ pub fn span0(input: &str) -> ParseResult<&str, ()> {
     .parse(input)
 }
 
+/// match one line break: `\r\n`, `\n` or a lone `\r` (classic Mac OS)
+pub fn line_break(input: &str) -> ParseResult<&str, &str> {
+    alt((tag("\r\n"), tag("\n"), tag("\r"))).parse(input)
+}
+
 /// match contiguous comments or whitespaces, which is only one line
 pub fn span0_inline(input: &str) -> ParseResult<&str, ()> {
     value((), many0(alt((map(comment, |_| ()), value((), space1))))).parse(input)
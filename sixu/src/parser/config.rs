@@ -0,0 +1,84 @@
+//! Options shared by the AST and CST parsers.
+//!
+//! The parsers are plain functions, so the options of a parse are installed for the
+//! current thread while it runs, see [`with_config`].
+
+use std::cell::RefCell;
+
+/// How escapes like `\q` that have no meaning are handled in quoted strings and
+/// template literals
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownEscape {
+    /// Keep the backslash, `"a\qb"` is `a\qb`
+    Keep,
+    /// Drop the backslash, `"a\qb"` is `aqb`
+    Strip,
+    /// Reject the string
+    #[default]
+    Error,
+}
+
+/// Options of a parse, see `parse_story_with_config`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParserConfig {
+    pub unknown_escape: UnknownEscape,
+    /// Parse `[A] text1 [B] text2` as several speaker segments on one line, see
+    /// `ChildContent::MultiTextLine`. Off by default, brackets are plain text then.
+    pub multi_speaker: bool,
+    /// Accept a unit suffix on numbers like `600ms`, `1.5s`, `20px` or `50%`, normalized
+    /// to milliseconds, pixels and fractions: `1.5s` is `1500.0` and `50%` is `0.5`.
+    /// Off by default, a suffix is a syntax error then.
+    pub number_units: bool,
+}
+
+thread_local! {
+    static ACTIVE: RefCell<ParserConfig> = RefCell::new(ParserConfig::default());
+}
+
+/// Run `parse` with `config` as the options of the parsers on this thread
+pub(crate) fn with_config<T>(config: &ParserConfig, parse: impl FnOnce() -> T) -> T {
+    let _restore = Restore(Some(ACTIVE.replace(config.clone())));
+    parse()
+}
+
+/// Puts the previous options back when dropped, also when the parse panics
+struct Restore(Option<ParserConfig>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            ACTIVE.set(previous);
+        }
+    }
+}
+
+/// Policy for unknown escapes of the running parse
+pub(crate) fn unknown_escape() -> UnknownEscape {
+    ACTIVE.with_borrow(|config| config.unknown_escape)
+}
+
+/// Whether the running parse splits lines into several speaker segments
+pub(crate) fn multi_speaker() -> bool {
+    ACTIVE.with_borrow(|config| config.multi_speaker)
+}
+
+/// Whether the running parse accepts unit suffixes on numbers
+pub(crate) fn number_units() -> bool {
+    ACTIVE.with_borrow(|config| config.number_units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_is_restored_after_panic() {
+        let config = ParserConfig {
+            unknown_escape: UnknownEscape::Keep,
+            ..Default::default()
+        };
+        let result = std::panic::catch_unwind(|| with_config(&config, || panic!("parse failed")));
+        assert!(result.is_err());
+        assert_eq!(unknown_escape(), UnknownEscape::Error);
+    }
+}
@@ -0,0 +1,104 @@
+//! Escape sequences of quoted strings and template literals.
+
+use nom::branch::alt;
+use nom::character::complete::{anychar, none_of};
+use nom::combinator::{map, map_opt};
+use nom::{ExtendInto, Parser};
+use nom_language::error::{VerboseError, VerboseErrorKind};
+
+use crate::result::ParseResult;
+
+use super::config::{unknown_escape, UnknownEscape};
+use super::text::parse_unicode;
+
+/// Character of a single character escape `\c` in a quoted string, e.g. `n` for a line feed
+pub(crate) fn simple_escape(c: char) -> Option<char> {
+    match c {
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        '\\' | '/' | '"' | '\'' | '`' => Some(c),
+        _ => None,
+    }
+}
+
+/// Like [`simple_escape`], a template literal also escapes the `$` of `${`
+pub(crate) fn template_escape(c: char) -> Option<char> {
+    match c {
+        '$' => Some(c),
+        _ => simple_escape(c),
+    }
+}
+
+/// Text of an unknown escape `\c` under `policy`, None if it is rejected
+pub(crate) fn unknown_escape_text(c: char, policy: UnknownEscape) -> Option<Escaped> {
+    match policy {
+        UnknownEscape::Keep => Some(Escaped::Kept(c)),
+        UnknownEscape::Strip => Some(Escaped::Char(c)),
+        UnknownEscape::Error => None,
+    }
+}
+
+/// Result of an escape sequence, which may keep its backslash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Escaped {
+    Char(char),
+    /// An unknown escape kept as written, i.e. `\` followed by the character
+    Kept(char),
+}
+
+impl Escaped {
+    pub(crate) fn push_to(self, text: &mut String) {
+        match self {
+            Escaped::Char(c) => text.push(c),
+            Escaped::Kept(c) => {
+                text.push('\\');
+                text.push(c);
+            }
+        }
+    }
+}
+
+impl ExtendInto for Escaped {
+    type Item = char;
+    type Extender = String;
+
+    fn new_builder(&self) -> String {
+        String::new()
+    }
+
+    fn extend_into(&self, acc: &mut String) {
+        self.push_to(acc);
+    }
+}
+
+/// parse the escape sequence after a `\`, an unknown one is handled as configured by
+/// `ParserConfig::unknown_escape`. Line breaks and malformed `\u` escapes are always
+/// rejected.
+pub(crate) fn escape_sequence(input: &str) -> ParseResult<&str, Escaped> {
+    escape_with(simple_escape, input)
+}
+
+/// parse the escape sequence after a `\` in a template literal, see [`escape_sequence`]
+pub(crate) fn template_escape_sequence(input: &str) -> ParseResult<&str, Escaped> {
+    escape_with(template_escape, input)
+}
+
+fn escape_with(known: fn(char) -> Option<char>, input: &str) -> ParseResult<&str, Escaped> {
+    alt((
+        map(parse_unicode, Escaped::Char),
+        map_opt(anychar, |c| known(c).map(Escaped::Char)),
+        unknown,
+    ))
+    .parse(input)
+}
+
+fn unknown(input: &str) -> ParseResult<&str, Escaped> {
+    let (rest, c) = none_of("u\r\n").parse(input)?;
+    match unknown_escape_text(c, unknown_escape()) {
+        Some(escaped) => Ok((rest, escaped)),
+        None => Err(nom::Err::Failure(VerboseError {
+            errors: vec![(input, VerboseErrorKind::Context("unknown escape sequence"))],
+        })),
+    }
+}
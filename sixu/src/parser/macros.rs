@@ -0,0 +1,383 @@
+//! Macros are reusable sequences of children, defined at the top level of a story
+//! with `::macro name { ... }` and invoked inside any block with `@@name`.
+//!
+//! An invocation is parsed as a `ChildContent::MacroInvocation`, and the expansion pass
+//! then replaces it with the children of the macro. An invocation carrying attributes
+//! or a marker is replaced by a block holding the children, so that the attributes
+//! apply to the whole sequence. The runtime only ever sees expanded stories.
+
+use nom::bytes::complete::tag;
+use nom::character::complete::space1;
+use nom::combinator::cut;
+use nom::error::context;
+use nom::sequence::preceded;
+use nom::Parser;
+use thiserror::Error;
+
+use crate::format::{Block, Child, ChildContent, Paragraph};
+use crate::result::ParseResult;
+
+use super::block::block;
+use super::comment::span0;
+use super::identifier::identifier;
+
+/// A macro defined by `::macro name { ... }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macro {
+    pub name: String,
+    pub block: Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum MacroError {
+    #[error("unknown macro `{0}`")]
+    Unknown(String),
+    /// The chain of invocations leading back to the first macro
+    #[error("recursive macro `{}`", .0.join("` -> `"))]
+    Recursive(Vec<String>),
+    #[error("macro `{0}` is defined more than once")]
+    Duplicate(String),
+}
+
+/// Parser contexts of a failed expansion in [`parse`](crate::parser::parse), which can
+/// not carry the error itself: the failure points at the name of the macro instead.
+pub(crate) const ERROR_CONTEXTS: &[&str] = &["unknown macro", "recursive macro", "duplicate macro"];
+
+impl MacroError {
+    /// Parser context of this error, one of [`ERROR_CONTEXTS`]
+    pub(crate) fn context(&self) -> &'static str {
+        match self {
+            Self::Unknown(_) => ERROR_CONTEXTS[0],
+            Self::Recursive(_) => ERROR_CONTEXTS[1],
+            Self::Duplicate(_) => ERROR_CONTEXTS[2],
+        }
+    }
+
+    /// Name of the macro at the invocation or definition that caused the error
+    pub fn macro_name(&self) -> &str {
+        match self {
+            Self::Unknown(name) | Self::Duplicate(name) => name,
+            Self::Recursive(chain) => chain.last().map(String::as_str).unwrap_or_default(),
+        }
+    }
+}
+
+/// parse a macro definition like `::macro fadeOut { ... }`
+pub fn macro_definition(input: &str) -> ParseResult<&str, Macro> {
+    let (input, _) = tag("::macro").parse(input)?;
+    let (input, _) = space1.parse(input)?;
+    let (input, name) = identifier.parse(input)?;
+    let (input, block) = preceded(span0, cut(block)).parse(input)?;
+    Ok((
+        input,
+        Macro {
+            name: name.to_string(),
+            block,
+        },
+    ))
+}
+
+/// parse a macro invocation like `@@fadeOut`
+pub fn macro_invocation(input: &str) -> ParseResult<&str, ChildContent> {
+    let (input, name) = context(
+        "macro invocation",
+        preceded(span0, preceded(tag("@@"), cut(identifier))),
+    )
+    .parse(input)?;
+    Ok((input, ChildContent::MacroInvocation(name.to_string())))
+}
+
+/// The name of the macro invoked by `content`, if it is a macro invocation
+fn macro_invocation_name(content: &ChildContent) -> Option<&str> {
+    match content {
+        ChildContent::MacroInvocation(name) => Some(name),
+        _ => None,
+    }
+}
+
+/// A top-level item of a story. Macros keep the source they were parsed from, to
+/// locate expansion failures.
+pub enum Item<'a> {
+    Paragraph(Paragraph),
+    Macro(Macro, &'a str),
+}
+
+/// Expand every macro invocation in `paragraphs`, after checking the macro
+/// definitions themselves for duplicates, unknown invocations and recursion.
+pub fn expand_story(
+    paragraphs: Vec<Paragraph>,
+    macros: &[Macro],
+) -> Result<Vec<Paragraph>, MacroError> {
+    for (index, m) in macros.iter().enumerate() {
+        if macros[..index].iter().any(|other| other.name == m.name) {
+            return Err(MacroError::Duplicate(m.name.clone()));
+        }
+    }
+    for m in macros {
+        expand_block(m.block.clone(), macros, &mut vec![m.name.clone()])?;
+    }
+
+    paragraphs
+        .into_iter()
+        .map(|mut paragraph| {
+            let block = std::mem::take(&mut paragraph.block);
+            paragraph.block = expand_block(block, macros, &mut Vec::new())?;
+            Ok(paragraph)
+        })
+        .collect()
+}
+
+/// Expand the macros of parsed `items`, locating a failure as a byte offset into `input`
+pub fn expand_items(input: &str, items: Vec<Item>) -> Result<Vec<Paragraph>, (usize, MacroError)> {
+    let mut paragraphs = Vec::new();
+    let mut macros = Vec::new();
+    let mut macro_sources = Vec::new();
+    for item in items {
+        match item {
+            Item::Paragraph(paragraph) => paragraphs.push(paragraph),
+            Item::Macro(m, source) => {
+                macros.push(m);
+                macro_sources.push(source);
+            }
+        }
+    }
+
+    expand_story(paragraphs, &macros).map_err(|e| {
+        let offset_of = |source: &str| source.as_ptr() as usize - input.as_ptr() as usize;
+        let offset = match &e {
+            MacroError::Duplicate(name) => macros
+                .iter()
+                .zip(&macro_sources)
+                .filter(|(m, _)| &m.name == name)
+                .nth(1)
+                .map(|(_, source)| offset_of(source)),
+            MacroError::Unknown(name) => find_invocation(input, name),
+            MacroError::Recursive(chain) => {
+                let caller = &chain[chain.len() - 2];
+                macros
+                    .iter()
+                    .zip(&macro_sources)
+                    .find(|(m, _)| &m.name == caller)
+                    .and_then(|(_, source)| {
+                        find_invocation(source, e.macro_name()).map(|i| offset_of(source) + i)
+                    })
+            }
+        };
+        (offset.unwrap_or(0), e)
+    })
+}
+
+fn expand_block(
+    block: Block,
+    macros: &[Macro],
+    stack: &mut Vec<String>,
+) -> Result<Block, MacroError> {
+    let mut children = Vec::with_capacity(block.children.len());
+
+    for child in block.children {
+        let Some(name) = macro_invocation_name(&child.content) else {
+            let content = match child.content {
+                ChildContent::Block(inner) => {
+                    ChildContent::Block(expand_block(inner, macros, stack)?)
+                }
+                content => content,
+            };
+            children.push(Child { content, ..child });
+            continue;
+        };
+
+        if stack.iter().any(|invoked| invoked == name) {
+            let mut chain = stack.clone();
+            chain.push(name.to_string());
+            return Err(MacroError::Recursive(chain));
+        }
+        let m = macros
+            .iter()
+            .find(|m| m.name == name)
+            .ok_or_else(|| MacroError::Unknown(name.to_string()))?;
+
+        stack.push(m.name.clone());
+        let expanded = expand_block(m.block.clone(), macros, stack)?;
+        stack.pop();
+
+        if child.attributes.is_empty() && child.marker.is_none() {
+            children.extend(expanded.children);
+        } else {
+            children.push(Child {
+                marker: child.marker,
+                attributes: child.attributes,
+                content: ChildContent::Block(expanded),
+            });
+        }
+    }
+
+    Ok(Block {
+        label: block.label,
+        children,
+    })
+}
+
+/// Byte offset of the name in the invocation or definition of a macro at the start
+/// of `source`, i.e. after `@@` or `::macro`
+pub(crate) fn name_offset(source: &str) -> usize {
+    let rest = source
+        .strip_prefix("@@")
+        .or_else(|| source.strip_prefix("::macro").map(str::trim_start))
+        .unwrap_or(source);
+    source.len() - rest.len()
+}
+
+/// Byte offset of the first invocation of macro `name` in `source`
+fn find_invocation(source: &str, name: &str) -> Option<usize> {
+    let pattern = format!("@@{}", name);
+    source.match_indices(&pattern).map(|(i, _)| i).find(|&i| {
+        source[i + pattern.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| !(c.is_alphanumeric() || c == '_'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::CommandLine;
+    use crate::parser::parse_story;
+
+    use super::*;
+
+    fn command(name: &str) -> ChildContent {
+        ChildContent::CommandLine(CommandLine {
+            command: name.to_string(),
+            arguments: vec![],
+        })
+    }
+
+    #[test]
+    fn test_macro_definition() {
+        let (rest, m) = macro_definition("::macro fadeOut {\n@fade\n}").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(m.name, "fadeOut");
+        assert_eq!(m.block.children.len(), 1);
+
+        // a paragraph named `macro` is not a macro definition
+        assert!(macro_definition("::macro {\n}").is_err());
+    }
+
+    #[test]
+    fn test_expand_invocations() {
+        let story = parse_story(
+            "test",
+            r#"
+::macro fadeOut {
+    @fade
+    @@wait
+}
+
+::macro wait {
+    @sleep
+}
+
+::entry {
+    @before
+    @@fadeOut
+    {
+        @@wait
+    }
+    @after
+}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(story.paragraphs.len(), 1);
+        let children = &story.paragraphs[0].block.children;
+        let contents = children.iter().map(|c| &c.content).collect::<Vec<_>>();
+        assert_eq!(contents[0], &command("before"));
+        assert_eq!(contents[1], &command("fade"));
+        assert_eq!(contents[2], &command("sleep"));
+        match contents[3] {
+            ChildContent::Block(block) => assert_eq!(block.children[0].content, command("sleep")),
+            other => panic!("expected a block, got {:?}", other),
+        }
+        assert_eq!(contents[4], &command("after"));
+    }
+
+    #[test]
+    fn test_invocation_with_attributes_is_wrapped_in_block() {
+        let story = parse_story(
+            "test",
+            "::macro twice {\n@a\n@b\n}\n\n::entry {\n#[cond(\"x\")]\n@@twice\n}\n",
+        )
+        .unwrap();
+
+        let children = &story.paragraphs[0].block.children;
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].attributes.len(), 1);
+        match &children[0].content {
+            ChildContent::Block(block) => assert_eq!(block.children.len(), 2),
+            other => panic!("expected a block, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_recursive_macro_is_rejected() {
+        let input = "::macro loop {\n@a\n@@loop\n}\n\n::entry {\n}\n";
+        let err = parse_story("test", input).unwrap_err();
+        assert_eq!((err.line, err.column), (3, 1));
+        assert_eq!(err.contexts, vec!["macro expansion"]);
+        assert_eq!(
+            err.to_string(),
+            "line 3, column 1: in macro expansion, recursive macro `loop` -> `loop`"
+        );
+
+        let input = "::macro a {\n@@b\n}\n\n::macro b {\n    @@a\n}\n\n::entry {\n@@a\n}\n";
+        let err = parse_story("test", input).unwrap_err();
+        assert_eq!(
+            err.reason.as_deref(),
+            Some("recursive macro `a` -> `b` -> `a`")
+        );
+        assert_eq!((err.line, err.column), (6, 5));
+    }
+
+    #[test]
+    fn test_unknown_and_duplicate_macros() {
+        let err = parse_story("test", "::entry {\n    @@missing\n}\n").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 5));
+        assert_eq!(err.reason.as_deref(), Some("unknown macro `missing`"));
+
+        let input = "::macro a {\n}\n::macro a {\n}\n";
+        let err = parse_story("test", input).unwrap_err();
+        assert_eq!(err.line, 3);
+        assert_eq!(
+            err.reason.as_deref(),
+            Some("macro `a` is defined more than once")
+        );
+
+        // the nom error of `parse` still leads to the name of the macro
+        let input = "::entry {\n    @@missing\n}\n";
+        let Err(nom::Err::Failure(err)) = crate::parser::parse("test", input) else {
+            panic!("expected a failure");
+        };
+        let err = crate::error::ParseError::from_verbose(input, err);
+        assert_eq!((err.line, err.column), (2, 7));
+        assert_eq!(err.reason.as_deref(), Some("unknown macro `missing`"));
+
+        let input = "::macro ac {\n}\n::macro  ac {\n}\n";
+        let Err(nom::Err::Failure(err)) = crate::parser::parse("test", input) else {
+            panic!("expected a failure");
+        };
+        let err = crate::error::ParseError::from_verbose(input, err);
+        assert_eq!((err.line, err.column), (3, 10));
+        assert_eq!(err.reason.as_deref(), Some("duplicate macro `ac`"));
+    }
+
+    #[test]
+    fn test_invocation_is_not_a_command() {
+        let child = crate::parser::parse_statement("@@fadeOut").unwrap();
+        assert_eq!(
+            child.content,
+            ChildContent::MacroInvocation("fadeOut".to_string())
+        );
+        assert!(crate::parser::parse_statement("@@").is_err());
+    }
+}
@@ -1,10 +1,12 @@
 use nom::bytes::complete::*;
 use nom::combinator::*;
+use nom::multi::many0;
 use nom::sequence::*;
 use nom::Parser;
 
 use crate::result::ParseResult;
 
+use super::attribute::attribute;
 use super::block::block;
 use super::comment::span0;
 use super::identifier::identifier;
@@ -12,6 +14,7 @@ use super::parameter::parameters;
 use super::Paragraph;
 
 pub fn paragraph(input: &str) -> ParseResult<&str, Paragraph> {
+    let (input, attributes) = many0(terminated(attribute, span0)).parse(input)?;
     let (input, _) = tag("::").parse(input)?;
     let (input, name) = cut(identifier).parse(input)?;
     let (input, parameters) = delimited(span0, opt(parameters), span0).parse(input)?;
@@ -19,6 +22,7 @@ pub fn paragraph(input: &str) -> ParseResult<&str, Paragraph> {
     Ok((
         input,
         Paragraph {
+            attributes,
             name: name.to_string(),
             parameters: parameters.unwrap_or_default(),
             block,
@@ -28,7 +32,7 @@ pub fn paragraph(input: &str) -> ParseResult<&str, Paragraph> {
 
 #[cfg(test)]
 mod tests {
-    use crate::format::{Block, Child, ChildContent, CommandLine};
+    use crate::format::{Attribute, Block, Child, ChildContent, CommandLine};
 
     use super::*;
 
@@ -39,6 +43,7 @@ mod tests {
             Ok((
                 "",
                 Paragraph {
+                    attributes: vec![],
                     name: "a".to_string(),
                     parameters: vec![],
                     block: Default::default(),
@@ -50,6 +55,7 @@ mod tests {
             Ok((
                 "",
                 Paragraph {
+                    attributes: vec![],
                     name: "a".to_string(),
                     parameters: vec![],
                     block: Default::default(),
@@ -61,6 +67,7 @@ mod tests {
             Ok((
                 " ",
                 Paragraph {
+                    attributes: vec![],
                     name: "a".to_string(),
                     parameters: vec![],
                     block: Default::default(),
@@ -72,6 +79,7 @@ mod tests {
             Ok((
                 " // comment",
                 Paragraph {
+                    attributes: vec![],
                     name: "a".to_string(),
                     parameters: vec![],
                     block: Default::default(),
@@ -83,6 +91,7 @@ mod tests {
             Ok((
                 " // comment",
                 Paragraph {
+                    attributes: vec![],
                     name: "a".to_string(),
                     parameters: vec![],
                     block: Default::default(),
@@ -94,9 +103,11 @@ mod tests {
             Ok((
                 "",
                 Paragraph {
+                    attributes: vec![],
                     name: "a".to_string(),
                     parameters: vec![],
                     block: Block {
+                        label: None,
                         children: vec![Child {
                             marker: None,
                             attributes: vec![],
@@ -110,4 +121,19 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn test_paragraph_attributes() {
+        let (rest, parsed) =
+            paragraph("#[platform(\"mobile\")]\n// only on phones\n::a {}").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(parsed.name, "a");
+        assert_eq!(
+            parsed.attributes,
+            vec![Attribute {
+                keyword: "platform".to_string(),
+                condition: Some("mobile".to_string()),
+            }]
+        );
+    }
 }
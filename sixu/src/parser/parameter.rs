@@ -1,38 +1,53 @@
 use nom::bytes::complete::*;
 use nom::combinator::*;
+use nom::error::context;
 use nom::multi::*;
 use nom::sequence::*;
 use nom::Parser;
 
+use crate::format::PARAMETER_TYPES;
 use crate::result::ParseResult;
 
 use super::comment::span0;
 use super::identifier::identifier;
-use super::primitive::primitive;
-use super::Parameter;
+use super::rvalue::rvalue;
+use super::{closing_char, Parameter};
 
 pub fn parameters(input: &str) -> ParseResult<&str, Vec<Parameter>> {
-    let (input, _) = tag("(").parse(input)?;
-    let (input, _) = span0.parse(input)?;
-    let (input, parameters) = cut(separated_list0(
-        delimited(span0, tag(","), span0),
-        cut(parameter),
-    ))
-    .parse(input)?;
-    let (input, _) = span0.parse(input)?;
-    let (input, _) = tag(")").parse(input)?;
-    Ok((input, parameters))
+    context("paragraph parameter list", |input| {
+        let (input, _) = tag("(").parse(input)?;
+        let (input, _) = span0.parse(input)?;
+        let (input, parameters) = cut(separated_list0(
+            delimited(span0, tag(","), span0),
+            cut(parameter),
+        ))
+        .parse(input)?;
+        let (input, _) = span0.parse(input)?;
+        let (input, _) = cut(closing_char(')', &[',', ')'])).parse(input)?;
+        Ok((input, parameters))
+    })
+    .parse(input)
 }
 
 pub fn parameter(input: &str) -> ParseResult<&str, Parameter> {
     let (input, name) = identifier.parse(input)?;
     let (input, _) = span0.parse(input)?;
+    let (input, type_) = opt(preceded(
+        terminated(tag(":"), span0),
+        context(
+            "parameter type",
+            cut(verify(identifier, |t: &str| PARAMETER_TYPES.contains(&t))),
+        ),
+    ))
+    .parse(input)?;
+    let (input, _) = span0.parse(input)?;
     let (input, default_value) =
-        cut(opt(preceded(tag("="), preceded(span0, cut(primitive))))).parse(input)?;
+        cut(opt(preceded(tag("="), preceded(span0, cut(rvalue))))).parse(input)?;
     Ok((
         input,
         Parameter {
             name: name.to_string(),
+            type_: type_.map(str::to_string),
             default_value,
         },
     ))
@@ -40,7 +55,7 @@ pub fn parameter(input: &str) -> ParseResult<&str, Parameter> {
 
 #[cfg(test)]
 mod tests {
-    use crate::format::Literal;
+    use crate::format::{Literal, RValue, Variable};
 
     use super::*;
 
@@ -52,6 +67,7 @@ mod tests {
                 "",
                 vec![Parameter {
                     name: "a".to_string(),
+                    type_: None,
                     default_value: None,
                 }]
             ))
@@ -63,10 +79,12 @@ mod tests {
                 vec![
                     Parameter {
                         name: "a".to_string(),
+                        type_: None,
                         default_value: None,
                     },
                     Parameter {
                         name: "b".to_string(),
+                        type_: None,
                         default_value: None,
                     },
                 ]
@@ -79,14 +97,17 @@ mod tests {
                 vec![
                     Parameter {
                         name: "a".to_string(),
+                        type_: None,
                         default_value: None,
                     },
                     Parameter {
                         name: "b".to_string(),
+                        type_: None,
                         default_value: None,
                     },
                     Parameter {
                         name: "c".to_string(),
+                        type_: None,
                         default_value: None,
                     },
                 ]
@@ -99,7 +120,8 @@ mod tests {
                 "",
                 vec![Parameter {
                     name: "a".to_string(),
-                    default_value: Some(Literal::Integer(1)),
+                    type_: None,
+                    default_value: Some(RValue::Literal(Literal::Integer(1))),
                 }]
             ))
         );
@@ -110,11 +132,13 @@ mod tests {
                 vec![
                     Parameter {
                         name: "a".to_string(),
-                        default_value: Some(Literal::Integer(1)),
+                        type_: None,
+                        default_value: Some(RValue::Literal(Literal::Integer(1))),
                     },
                     Parameter {
                         name: "b".to_string(),
-                        default_value: Some(Literal::String("2".to_string())),
+                        type_: None,
+                        default_value: Some(RValue::Literal(Literal::String("2".to_string()))),
                     },
                 ]
             ))
@@ -126,11 +150,13 @@ mod tests {
                 vec![
                     Parameter {
                         name: "a".to_string(),
-                        default_value: Some(Literal::Integer(1)),
+                        type_: None,
+                        default_value: Some(RValue::Literal(Literal::Integer(1))),
                     },
                     Parameter {
                         name: "b".to_string(),
-                        default_value: Some(Literal::String("2".to_string())),
+                        type_: None,
+                        default_value: Some(RValue::Literal(Literal::String("2".to_string()))),
                     },
                 ]
             ))
@@ -142,15 +168,18 @@ mod tests {
                 vec![
                     Parameter {
                         name: "a".to_string(),
-                        default_value: Some(Literal::Integer(1)),
+                        type_: None,
+                        default_value: Some(RValue::Literal(Literal::Integer(1))),
                     },
                     Parameter {
                         name: "_c".to_string(),
+                        type_: None,
                         default_value: None,
                     },
                     Parameter {
                         name: "b".to_string(),
-                        default_value: Some(Literal::String("2".to_string())),
+                        type_: None,
+                        default_value: Some(RValue::Literal(Literal::String("2".to_string()))),
                     },
                 ]
             ))
@@ -165,18 +194,79 @@ mod tests {
                 vec![
                     Parameter {
                         name: "a".to_string(),
-                        default_value: Some(Literal::Integer(1)),
+                        type_: None,
+                        default_value: Some(RValue::Literal(Literal::Integer(1))),
                     },
                     Parameter {
                         name: "_c".to_string(),
+                        type_: None,
+                        default_value: None,
+                    },
+                    Parameter {
+                        name: "b".to_string(),
+                        type_: None,
+                        default_value: Some(RValue::Literal(Literal::String("2".to_string()))),
+                    },
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parameter_default_referencing_variable() {
+        assert_eq!(
+            parameters("(a, b=a)"),
+            Ok((
+                "",
+                vec![
+                    Parameter {
+                        name: "a".to_string(),
+                        type_: None,
                         default_value: None,
                     },
                     Parameter {
                         name: "b".to_string(),
-                        default_value: Some(Literal::String("2".to_string())),
+                        type_: None,
+                        default_value: Some(RValue::Variable(Variable {
+                            chain: vec!["a".to_string()],
+                        })),
                     },
                 ]
             ))
         );
     }
+
+    #[test]
+    fn test_parameter_types() {
+        assert_eq!(
+            parameters("(location: string, count : number = 0)"),
+            Ok((
+                "",
+                vec![
+                    Parameter {
+                        name: "location".to_string(),
+                        type_: Some("string".to_string()),
+                        default_value: None,
+                    },
+                    Parameter {
+                        name: "count".to_string(),
+                        type_: Some("number".to_string()),
+                        default_value: Some(RValue::Literal(Literal::Integer(0))),
+                    },
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parameter_unknown_type() {
+        assert!(matches!(
+            parameters("(location: place)"),
+            Err(nom::Err::Failure(_))
+        ));
+        assert!(matches!(
+            parameters("(location:)"),
+            Err(nom::Err::Failure(_))
+        ));
+    }
 }
@@ -7,64 +7,124 @@ use nom::multi::*;
 use nom::sequence::*;
 use nom::Parser;
 
-use crate::parser::comment::span0_inline;
+use nom_language::error::{VerboseError, VerboseErrorKind};
+
+use crate::parser::comment::{span0, span0_inline};
+use crate::parser::config::number_units;
+use crate::parser::escape::escape_sequence;
 use crate::result::ParseResult;
 
+use super::identifier::identifier;
 use super::Literal;
 
 pub fn primitive(input: &str) -> ParseResult<&str, Literal> {
-    context("primitive", alt((string, float, integer, boolean, array))).parse(input)
+    context("primitive", alt((string, number, boolean, object, array))).parse(input)
+}
+
+// a float or an integer, followed by a unit suffix if `ParserConfig::number_units` is on
+fn number(input: &str) -> ParseResult<&str, Literal> {
+    let start = input;
+    let (input, literal) = alt((float, integer)).parse(input)?;
+    if !number_units() || start[..start.len() - input.len()].contains(['x', 'X']) {
+        return Ok((input, literal));
+    }
+    let (input, unit) = opt(terminated(
+        alt((tag("ms"), tag("s"), tag("px"), tag("%"))),
+        not(satisfy(|c| c.is_alphanumeric() || c == '_')),
+    ))
+    .parse(input)?;
+    match unit {
+        Some(unit) => {
+            let literal = with_unit(literal, unit)
+                .ok_or_else(|| out_of_range(start, "number out of range"))?;
+            Ok((input, literal))
+        }
+        None => Ok((input, literal)),
+    }
 }
 
+/// Normalize a number written with a `ms`, `s`, `px` or `%` suffix to its base unit:
+/// durations to milliseconds (`1.5s` is `1500.0`), pixels as is, and percentages to a
+/// fraction (`50%` is `0.5`). Integers stay integers except for percentages; `None` when
+/// the result is out of range.
+pub(crate) fn with_unit(literal: Literal, unit: &str) -> Option<Literal> {
+    match (unit, literal) {
+        ("ms" | "px", literal) => Some(literal),
+        ("s", Literal::Integer(n)) => n.checked_mul(1000).map(Literal::Integer),
+        ("s", Literal::Float(n)) => Some(n * 1000.0)
+            .filter(|n| n.is_finite())
+            .map(Literal::Float),
+        ("%", Literal::Integer(n)) => Some(Literal::Float(n as f64 / 100.0)),
+        ("%", Literal::Float(n)) => Some(Literal::Float(n / 100.0)),
+        _ => None,
+    }
+}
+
+// quoted strings support the same escapes as quoted text, and may span lines
 pub fn string(input: &str) -> ParseResult<&str, Literal> {
     let (input, s) = context(
         "string",
         alt((
-            delimited(tag("\""), take_until("\""), tag("\"")),
-            delimited(tag("'"), take_until("'"), tag("'")),
+            delimited(
+                tag("\""),
+                alt((
+                    value(String::new(), peek(tag("\""))),
+                    escaped_transform(none_of("\"\\"), '\\', escape_sequence),
+                )),
+                tag("\""),
+            ),
+            delimited(
+                tag("'"),
+                alt((
+                    value(String::new(), peek(tag("'"))),
+                    escaped_transform(none_of("'\\"), '\\', escape_sequence),
+                )),
+                tag("'"),
+            ),
         )),
     )
     .parse(input)?;
-    Ok((input, Literal::String(s.to_string())))
+    Ok((input, Literal::String(s)))
 }
 
 // all integer, supports decimal and hexadecimal (0x/0X prefix)
 pub fn integer(input: &str) -> ParseResult<&str, Literal> {
-    let (input, n) = context(
+    let start = input;
+    let (input, (sign, _, value)) = context(
         "integer",
-        map_res(
-            (
-                opt(alt((tag("-"), tag("+")))),
-                span0_inline,
-                alt((
-                    // Hexadecimal: 0x123 or 0X123
-                    recognize((
-                        alt((tag("0x"), tag("0X"))),
-                        many1(terminated(hex_digit1, many0(char('_')))),
-                    )),
-                    // Decimal: 123
-                    recognize(many1(terminated(digit1, many0(char('_'))))),
+        (
+            opt(alt((tag("-"), tag("+")))),
+            span0_inline,
+            alt((
+                // Hexadecimal: 0x123 or 0X123, the prefix must be followed by digits
+                recognize((
+                    alt((tag("0x"), tag("0X"))),
+                    cut(many1(terminated(hex_digit1, many0(char('_'))))),
                 )),
-            ),
-            |(sign, _, value)| {
-                let value = &str::replace(value, "_", "");
-                let parsed_value = if value.starts_with("0x") || value.starts_with("0X") {
-                    // Parse hexadecimal
-                    i64::from_str_radix(&value[2..], 16)
-                } else {
-                    // Parse decimal
-                    value.parse::<i64>()
-                };
-                parsed_value.map(|n| if sign == Some("-") { -n } else { n })
-            },
+                // Decimal: 123
+                recognize(many1(terminated(digit1, many0(char('_'))))),
+            )),
         ),
     )
     .parse(input)?;
+
+    // Keep the sign on the digits so that i64::MIN is accepted
+    let sign = if sign == Some("-") { "-" } else { "" };
+    let value = &str::replace(value, "_", "");
+    let parsed_value = if let Some(hex) = value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+        // Parse hexadecimal
+        i64::from_str_radix(&format!("{}{}", sign, hex), 16)
+    } else {
+        // Parse decimal
+        format!("{}{}", sign, value).parse::<i64>()
+    };
+    let n = parsed_value.map_err(|_| out_of_range(start, "integer out of range"))?;
     Ok((input, Literal::Integer(n)))
 }
 
-// float numbers, supports various formats like 123., 123.0, -123.123, 0.111
+// float numbers, supports various formats like 123., 123.0, -123.123, 0.111, 1.5e-3, 2E6
 pub fn float(input: &str) -> ParseResult<&str, Literal> {
+    let start = input;
     let (input, f) = context(
         "float",
         map_res(
@@ -72,16 +132,23 @@ pub fn float(input: &str) -> ParseResult<&str, Literal> {
                 opt(alt((tag("-"), tag("+")))),
                 span0_inline,
                 alt((
-                    // Format: 123.456 or 123.
+                    // Format: 123.456 or 123., with an optional exponent
                     recognize((
                         recognize(many1(terminated(digit1, many0(char('_'))))),
                         tag("."),
                         opt(recognize(many1(terminated(digit1, many0(char('_')))))),
+                        opt(exponent),
                     )),
-                    // Format: .123
+                    // Format: .123, with an optional exponent
                     recognize((
                         tag("."),
                         recognize(many1(terminated(digit1, many0(char('_'))))),
+                        opt(exponent),
+                    )),
+                    // Format: 2e6
+                    recognize((
+                        recognize(many1(terminated(digit1, many0(char('_'))))),
+                        exponent,
                     )),
                 )),
             ),
@@ -94,9 +161,24 @@ pub fn float(input: &str) -> ParseResult<&str, Literal> {
         ),
     )
     .parse(input)?;
+    if !f.is_finite() {
+        return Err(out_of_range(start, "float out of range"));
+    }
     Ok((input, Literal::Float(f)))
 }
 
+// a well-formed number that does not fit its type is a hard error rather than a fallback
+fn out_of_range<'a>(input: &'a str, message: &'static str) -> nom::Err<VerboseError<&'a str>> {
+    nom::Err::Failure(VerboseError {
+        errors: vec![(input, VerboseErrorKind::Context(message))],
+    })
+}
+
+// exponent part of a float: e6, E+6, e-3; the marker must be followed by digits
+fn exponent(input: &str) -> ParseResult<&str, &str> {
+    recognize((one_of("eE"), cut((opt(one_of("+-")), digit1)))).parse(input)
+}
+
 pub fn boolean(input: &str) -> ParseResult<&str, Literal> {
     let (input, b) = context(
         "boolean",
@@ -106,30 +188,74 @@ pub fn boolean(input: &str) -> ParseResult<&str, Literal> {
     Ok((input, Literal::Boolean(b)))
 }
 
-// array of primitives, supports nesting
+// array of primitives, supports nesting, a trailing comma, and line breaks or comments
+// between the brackets
 pub fn array(input: &str) -> ParseResult<&str, Literal> {
     let (input, elements) = context(
         "array",
         delimited(
-            preceded(tag("["), span0_inline),
+            preceded(tag("["), span0),
             terminated(
                 separated_list0(
-                    delimited(span0_inline, tag(","), span0_inline),
-                    preceded(span0_inline, primitive),
+                    delimited(span0, tag(","), span0),
+                    preceded(span0, primitive),
                 ),
-                opt(preceded(span0_inline, tag(","))),
+                opt(preceded(span0, tag(","))),
             ),
-            preceded(span0_inline, tag("]")),
+            preceded(span0, tag("]")),
         ),
     )
     .parse(input)?;
     Ok((input, Literal::Array(elements)))
 }
 
+// object of primitives keyed by identifiers or quoted strings, supports nesting, a trailing
+// comma, and line breaks or comments between the braces
+pub fn object(input: &str) -> ParseResult<&str, Literal> {
+    let (input, entries) = context(
+        "object",
+        delimited(
+            preceded(tag("{"), span0),
+            terminated(
+                separated_list0(
+                    delimited(span0, tag(","), span0),
+                    preceded(span0, object_entry),
+                ),
+                opt(preceded(span0, tag(","))),
+            ),
+            preceded(span0, tag("}")),
+        ),
+    )
+    .parse(input)?;
+    Ok((input, Literal::Object(entries.into_iter().collect())))
+}
+
+fn object_entry(input: &str) -> ParseResult<&str, (String, Literal)> {
+    let (input, key) = alt((
+        map(identifier, str::to_string),
+        map(string, |key| match key {
+            Literal::String(key) => key,
+            _ => unreachable!(),
+        }),
+    ))
+    .parse(input)?;
+    let (input, _) = (span0, tag(":"), span0).parse(input)?;
+    // values are stored in a `Literal::Object`, so variables are not allowed
+    let (input, value) = primitive.parse(input).map_err(|e| match e {
+        nom::Err::Failure(_) => e,
+        _ => nom::Err::Failure(VerboseError {
+            errors: vec![(
+                input,
+                VerboseErrorKind::Context("object value (literals only)"),
+            )],
+        }),
+    })?;
+    Ok((input, (key, value)))
+}
+
 #[cfg(test)]
 mod tests {
     use nom::Err;
-    use nom_language::error::{VerboseError, VerboseErrorKind};
 
     use super::*;
 
@@ -154,6 +280,8 @@ mod tests {
         assert_eq!(primitive("0xFF"), Ok(("", Literal::Integer(0xFF))));
         assert_eq!(primitive("0xAB_CD"), Ok(("", Literal::Integer(0xABCD))));
         assert_eq!(primitive("0x0"), Ok(("", Literal::Integer(0))));
+        assert!(matches!(primitive("0x"), Err(Err::Failure(_))));
+        assert!(matches!(primitive("0xg1"), Err(Err::Failure(_))));
         assert_eq!(primitive("123."), Ok(("", Literal::Float(123.))));
         assert_eq!(primitive("123.0"), Ok(("", Literal::Float(123.0))));
         assert_eq!(primitive("123.456"), Ok(("", Literal::Float(123.456))));
@@ -165,6 +293,39 @@ mod tests {
         assert_eq!(primitive("- .456"), Ok(("", Literal::Float(-0.456))));
         assert_eq!(primitive("12_3.45_6"), Ok(("", Literal::Float(123.456))));
         assert_eq!(primitive("0."), Ok(("", Literal::Float(0.))));
+        assert_eq!(
+            primitive("-9223372036854775808"),
+            Ok(("", Literal::Integer(i64::MIN)))
+        );
+        // Out of range numbers are errors instead of silently becoming 0
+        assert_eq!(
+            primitive("99999999999999999999"),
+            Err(Err::Failure(VerboseError {
+                errors: vec![
+                    (
+                        "99999999999999999999",
+                        VerboseErrorKind::Context("integer out of range")
+                    ),
+                    (
+                        "99999999999999999999",
+                        VerboseErrorKind::Context("primitive")
+                    )
+                ]
+            }))
+        );
+        assert!(matches!(
+            primitive("0x1_0000_0000_0000_0000"),
+            Err(Err::Failure(_))
+        ));
+        assert!(matches!(primitive("1e999"), Err(Err::Failure(_))));
+        // Scientific notation tests
+        assert_eq!(primitive("1.5e-3"), Ok(("", Literal::Float(1.5e-3))));
+        assert_eq!(primitive("2E6"), Ok(("", Literal::Float(2e6))));
+        assert_eq!(primitive("-2e+2"), Ok(("", Literal::Float(-200.))));
+        assert_eq!(primitive(".5e1"), Ok(("", Literal::Float(5.))));
+        assert_eq!(primitive("1_0e10"), Ok(("", Literal::Float(1e11))));
+        assert!(matches!(primitive("1e"), Err(Err::Failure(_))));
+        assert!(matches!(primitive("1.5e-"), Err(Err::Failure(_))));
         // Array tests
         assert_eq!(primitive("[]"), Ok(("", Literal::Array(vec![]))));
         assert_eq!(
@@ -226,6 +387,61 @@ mod tests {
                 ])
             ))
         );
+        assert_eq!(
+            primitive("[\n  \"a\",\n  [\"b]\", 2], // c\n]"),
+            Ok((
+                "",
+                Literal::Array(vec![
+                    Literal::String("a".to_string()),
+                    Literal::Array(vec![Literal::String("b]".to_string()), Literal::Integer(2)]),
+                ])
+            ))
+        );
+        assert_eq!(
+            primitive("{}"),
+            Ok(("", Literal::Object(Default::default())))
+        );
+        assert_eq!(
+            primitive("{retries: 3, \"label\": 'ok', nested: {list: [1, {}]},\n}"),
+            Ok((
+                "",
+                Literal::Object(
+                    [
+                        ("retries".to_string(), Literal::Integer(3)),
+                        ("label".to_string(), Literal::String("ok".to_string())),
+                        (
+                            "nested".to_string(),
+                            Literal::Object(
+                                [(
+                                    "list".to_string(),
+                                    Literal::Array(vec![
+                                        Literal::Integer(1),
+                                        Literal::Object(Default::default())
+                                    ])
+                                )]
+                                .into_iter()
+                                .collect()
+                            )
+                        ),
+                    ]
+                    .into_iter()
+                    .collect()
+                )
+            ))
+        );
+        assert_eq!(
+            primitive("{a: player.name}"),
+            Err(Err::Failure(VerboseError {
+                errors: vec![
+                    (
+                        "player.name}",
+                        VerboseErrorKind::Context("object value (literals only)")
+                    ),
+                    ("{a: player.name}", VerboseErrorKind::Context("object")),
+                    ("{a: player.name}", VerboseErrorKind::Context("primitive"))
+                ]
+            }))
+        );
         assert_eq!(
             primitive("_123"),
             Err(Err::Error(VerboseError {
@@ -241,9 +457,54 @@ mod tests {
             primitive("\"hello\""),
             Ok(("", Literal::String("hello".to_string())))
         );
+        assert_eq!(primitive("\"\""), Ok(("", Literal::String(String::new()))));
+        assert_eq!(
+            primitive(r#""she said \"hi\"""#),
+            Ok(("", Literal::String("she said \"hi\"".to_string())))
+        );
+        assert_eq!(
+            primitive(r"'it\'s\n\t\\\u0041\u{1F600}'"),
+            Ok(("", Literal::String("it's\n\t\\A\u{1F600}".to_string())))
+        );
         assert_eq!(
             primitive("'hello'"),
             Ok(("", Literal::String("hello".to_string())))
         );
     }
+
+    #[test]
+    fn test_number_units() {
+        let config = crate::parser::ParserConfig {
+            number_units: true,
+            ..Default::default()
+        };
+        let parse = |input| crate::parser::config::with_config(&config, || primitive(input));
+
+        assert_eq!(parse("600ms"), Ok(("", Literal::Integer(600))));
+        assert_eq!(parse("2s"), Ok(("", Literal::Integer(2000))));
+        assert_eq!(parse("1.5s"), Ok(("", Literal::Float(1500.0))));
+        assert_eq!(parse("-20px"), Ok(("", Literal::Integer(-20))));
+        assert_eq!(parse("50%"), Ok(("", Literal::Float(0.5))));
+        assert_eq!(parse("12.5%"), Ok(("", Literal::Float(0.125))));
+        assert_eq!(
+            parse("[1s, 250ms]"),
+            Ok((
+                "",
+                Literal::Array(vec![Literal::Integer(1000), Literal::Integer(250)])
+            ))
+        );
+        // plain numbers parse as before
+        assert_eq!(parse("600"), Ok(("", Literal::Integer(600))));
+        assert_eq!(parse("1.5"), Ok(("", Literal::Float(1.5))));
+        assert_eq!(parse("0xFF"), Ok(("", Literal::Integer(0xFF))));
+        // an unknown suffix is not a unit
+        assert_eq!(parse("3sec"), Ok(("sec", Literal::Integer(3))));
+        assert!(matches!(
+            parse("9223372036854775807s"),
+            Err(Err::Failure(_))
+        ));
+
+        // without the option the suffix is left unparsed
+        assert_eq!(primitive("600ms"), Ok(("ms", Literal::Integer(600))));
+    }
 }
@@ -0,0 +1,103 @@
+//! Reading story sources from byte streams, validating UTF-8 chunk by chunk.
+
+use std::io::{ErrorKind, Read};
+
+use crate::error::{line_column, ReadError};
+
+/// Size of the chunks read from the stream
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Read `reader` to its end into a string.
+///
+/// Short reads are not errors: the stream is read until it reports end of file, and
+/// interrupted reads are retried. Any other I/O error aborts the read. Invalid UTF-8,
+/// including a sequence cut off by the end of the stream, is reported at the byte
+/// offset where it starts.
+pub(crate) fn read_to_string(mut reader: impl Read) -> Result<String, ReadError> {
+    let mut text = String::new();
+    let mut chunk = vec![0; CHUNK_SIZE];
+    // bytes of a multi-byte character split across two chunks
+    let mut pending: Vec<u8> = Vec::new();
+
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(ReadError::Io(e)),
+        };
+        pending.extend_from_slice(&chunk[..read]);
+
+        match std::str::from_utf8(&pending) {
+            Ok(valid) => {
+                text.push_str(valid);
+                pending.clear();
+            }
+            Err(e) => {
+                let valid_up_to = e.valid_up_to();
+                text.push_str(std::str::from_utf8(&pending[..valid_up_to]).unwrap());
+                if e.error_len().is_some() {
+                    return Err(invalid_utf8(&text));
+                }
+                // an incomplete character at the end of the chunk, wait for the rest
+                pending.drain(..valid_up_to);
+            }
+        }
+    }
+
+    if pending.is_empty() {
+        Ok(text)
+    } else {
+        Err(invalid_utf8(&text))
+    }
+}
+
+/// Error for invalid bytes found right after the decoded `text`
+fn invalid_utf8(text: &str) -> ReadError {
+    ReadError::InvalidUtf8 {
+        offset: text.len(),
+        line: line_column(text, text.len()).0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    /// Reader handing out one byte per call
+    struct ByteByByte<'a>(&'a [u8]);
+
+    impl Read for ByteByByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.split_first() {
+                Some((first, rest)) if !buf.is_empty() => {
+                    buf[0] = *first;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_characters_split_across_reads() {
+        let text = "::entry {\n    \"你好，世界\"\n}\n";
+        assert_eq!(read_to_string(ByteByByte(text.as_bytes())).unwrap(), text);
+    }
+
+    #[test]
+    fn test_truncated_character_at_end() {
+        let bytes = "ab\n你".as_bytes();
+        let err = read_to_string(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(matches!(err, ReadError::InvalidUtf8 { offset: 3, line: 2 }));
+    }
+
+    #[test]
+    fn test_line_after_lone_cr() {
+        let err = read_to_string(&b"a\r\r\nb\r\xff"[..]).unwrap_err();
+        assert!(matches!(err, ReadError::InvalidUtf8 { offset: 6, line: 4 }));
+    }
+}
@@ -0,0 +1,118 @@
+//! Side channel for what the parsers meet but do not return, like the comments of
+//! `parse_story_with_comments` or the violations of `parse_strict`.
+//!
+//! The parsers only see `&str` slices of the input, so a record is keyed by the
+//! address range of the slice it is about. [`Recorder::collect`] turns those into byte
+//! ranges of the input and drops records about other strings, e.g. expanded macro
+//! bodies.
+
+use std::cell::RefCell;
+use std::ops::Range;
+use std::thread::LocalKey;
+
+/// Address or byte range of the source a record is about, and the record
+type Records<T> = Vec<(Range<usize>, T)>;
+
+/// Records of the running collection, None while nothing collects
+pub(crate) type Slot<T> = RefCell<Option<Records<T>>>;
+
+/// Handle to the thread-local [`Slot`] of one kind of record
+pub(crate) struct Recorder<T: 'static>(pub(crate) &'static LocalKey<Slot<T>>);
+
+impl<T> Recorder<T> {
+    /// Record `item` about `source` if a collection is running
+    pub(crate) fn record(&self, source: &str, item: T) {
+        self.0.with_borrow_mut(|slot| {
+            if let Some(records) = slot {
+                let start = source.as_ptr() as usize;
+                records.push((start..start + source.len(), item));
+            }
+        });
+    }
+
+    pub(crate) fn is_recording(&self) -> bool {
+        self.0.with_borrow(Option::is_some)
+    }
+
+    /// Run `parse` over `input` and collect what it records about `input`, as byte
+    /// ranges of `input` in source order. Records found more than once due to
+    /// backtracking are all kept, callers dedup them as they see fit. The outer
+    /// collection, if any, is restored even when `parse` panics.
+    pub(crate) fn collect<R>(&self, input: &str, parse: impl FnOnce() -> R) -> (R, Records<T>) {
+        let restore = Restore {
+            slot: self.0,
+            previous: Some(self.0.replace(Some(Vec::new()))),
+        };
+        let result = parse();
+        let records = restore.finish();
+
+        let base = input.as_ptr() as usize;
+        let mut records: Vec<_> = records
+            .into_iter()
+            .filter(|(range, _)| range.start >= base && range.end <= base + input.len())
+            .map(|(range, item)| (range.start - base..range.end - base, item))
+            .collect();
+        records.sort_by_key(|(range, _)| range.start);
+
+        (result, records)
+    }
+}
+
+/// Puts the outer collection back into the slot when dropped
+struct Restore<T: 'static> {
+    slot: &'static LocalKey<Slot<T>>,
+    previous: Option<Option<Records<T>>>,
+}
+
+impl<T> Restore<T> {
+    /// Restore the outer collection and take the records of this one
+    fn finish(mut self) -> Records<T> {
+        let previous = self.previous.take().flatten();
+        self.slot.replace(previous).unwrap_or_default()
+    }
+}
+
+impl<T> Drop for Restore<T> {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            self.slot.set(previous);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    thread_local! {
+        static SLOT: Slot<char> = const { RefCell::new(None) };
+    }
+
+    static RECORDER: Recorder<char> = Recorder(&SLOT);
+
+    #[test]
+    fn test_collect_keeps_records_of_input() {
+        let input = "abc";
+        let other = String::from("x");
+        let ((), records) = RECORDER.collect(input, || {
+            RECORDER.record(&input[2..], 'c');
+            RECORDER.record(&other, 'x');
+            RECORDER.record(&input[..1], 'a');
+        });
+        assert_eq!(records, vec![(0..1, 'a'), (2..3, 'c')]);
+        assert!(!RECORDER.is_recording());
+    }
+
+    #[test]
+    fn test_collection_is_restored_after_panic() {
+        let input = "abc";
+        let ((), records) = RECORDER.collect(input, || {
+            let result = std::panic::catch_unwind(|| {
+                RECORDER.collect(input, || -> () { panic!("parse failed") })
+            });
+            assert!(result.is_err());
+            RECORDER.record(&input[1..2], 'b');
+        });
+        assert_eq!(records, vec![(1..2, 'b')]);
+    }
+}
@@ -0,0 +1,181 @@
+//! Stricter rules of `parse_strict`.
+//!
+//! The checks run inside the regular parsers while `record_violations` is active and
+//! only record what they find, so the parse itself, and therefore the resulting story,
+//! is the same as that of `parse_story`.
+
+use std::cell::RefCell;
+
+use crate::error::{SyntaxError, SyntaxErrorKind};
+use crate::format::RValue;
+
+use super::recorder::{Recorder, Slot};
+
+thread_local! {
+    /// Kind and message of the violations found while `record_violations` runs
+    static FOUND: Slot<(SyntaxErrorKind, String)> = const { RefCell::new(None) };
+}
+
+static VIOLATIONS: Recorder<(SyntaxErrorKind, String)> = Recorder(&FOUND);
+
+/// Extensions of asset files, which are usually meant as strings when written unquoted
+const FILE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "webp", "gif", "bmp", "svg", "ogg", "mp3", "wav", "flac", "m4a", "mp4",
+    "webm", "json", "txt",
+];
+
+fn record(at: &str, kind: SyntaxErrorKind, message: String) {
+    VIOLATIONS.record(at, (kind, message));
+}
+
+fn is_recording() -> bool {
+    VIOLATIONS.is_recording()
+}
+
+/// Run `parse` over `input` and collect every strict rule violation it meets, in
+/// source order. Violations found more than once due to backtracking are reported
+/// once, and those found in other strings are dropped.
+pub(crate) fn record_violations<T>(
+    input: &str,
+    parse: impl FnOnce() -> T,
+) -> (T, Vec<SyntaxError>) {
+    let (result, found) = VIOLATIONS.collect(input, parse);
+    let mut errors: Vec<SyntaxError> = found
+        .into_iter()
+        .map(|(range, (kind, message))| SyntaxError::new(input, range.start, kind, message))
+        .collect();
+    errors.dedup_by(|a, b| a.offset == b.offset && a.kind == b.kind);
+
+    (result, errors)
+}
+
+/// Check an argument value parsed from `source`, where `rest` is the input after it
+pub(crate) fn check_value(source: &str, value: &RValue, rest: &str) {
+    if !is_recording() {
+        return;
+    }
+
+    // `src=bg-01.png` stops at `-`, leaving the remainder to be parsed as something else
+    if !ends_value(rest) {
+        let token = &rest[..token_len(rest)];
+        record(
+            source,
+            SyntaxErrorKind::UnquotedValue,
+            format!("unquoted value `{}{}`, quote it as a string", source, token),
+        );
+        return;
+    }
+
+    let RValue::Variable(variable) = value else {
+        return;
+    };
+    let last = variable
+        .chain
+        .last()
+        .map(String::as_str)
+        .unwrap_or_default();
+    if variable.chain.len() > 1 && FILE_EXTENSIONS.contains(&last.to_ascii_lowercase().as_str()) {
+        record(
+            source,
+            SyntaxErrorKind::UnquotedValue,
+            format!("unquoted value `{}`, quote it as a string", source),
+        );
+    } else if variable.chain.len() == 1 {
+        if let Some(keyword) = ["true", "false"]
+            .into_iter()
+            .find(|keyword| looks_like(last, keyword))
+        {
+            record(
+                source,
+                SyntaxErrorKind::SuspiciousIdentifier,
+                format!("`{}` looks like a misspelled `{}`", last, keyword),
+            );
+        }
+    }
+}
+
+/// Check what follows a parenthesized argument list on its line
+pub(crate) fn check_after_arguments(rest: &str) {
+    if !is_recording() {
+        return;
+    }
+
+    let rest = rest.trim_start_matches([' ', '\t']);
+    if !ends_value(rest) {
+        let line = rest
+            .split(['\n', '\r'])
+            .next()
+            .unwrap_or_default()
+            .trim_end();
+        record(
+            rest,
+            SyntaxErrorKind::TrailingContent,
+            format!("unexpected `{}` after the argument list", line),
+        );
+    }
+}
+
+/// Whether a value may end before `rest`
+fn ends_value(rest: &str) -> bool {
+    match rest.chars().next() {
+        None => true,
+        Some(c) if c.is_whitespace() || matches!(c, ',' | ')' | '}') => true,
+        Some('/') => rest.starts_with("//") || rest.starts_with("/*"),
+        Some(_) => false,
+    }
+}
+
+/// Length of the token at the start of `rest`, up to a separator
+fn token_len(rest: &str) -> usize {
+    rest.find(|c: char| c.is_whitespace() || matches!(c, ',' | ')' | '}'))
+        .unwrap_or(rest.len())
+}
+
+/// Whether `word` differs from `keyword` only in case or by a single edit, where
+/// swapping two neighbouring characters counts as one edit
+fn looks_like(word: &str, keyword: &str) -> bool {
+    word != keyword
+        && (word.eq_ignore_ascii_case(keyword)
+            || edit_distance(&word.to_ascii_lowercase(), keyword) <= 1)
+}
+
+/// Optimal string alignment distance between `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_looks_like() {
+        assert!(looks_like("ture", "true"));
+        assert!(looks_like("True", "true"));
+        assert!(looks_like("flase", "false"));
+        assert!(looks_like("fals", "false"));
+        assert!(!looks_like("true", "true"));
+        assert!(!looks_like("time", "true"));
+        assert!(!looks_like("value", "false"));
+    }
+}
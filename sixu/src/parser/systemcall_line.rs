@@ -1,5 +1,6 @@
 use nom::character::complete::char;
 use nom::combinator::cut;
+use nom::error::context;
 use nom::sequence::*;
 use nom::Parser;
 
@@ -13,11 +14,14 @@ use super::ChildContent;
 use super::SystemCallLine;
 
 pub fn systemcall_line(input: &str) -> ParseResult<&str, ChildContent> {
-    let (input, (command, arguments)) = preceded(
-        span0,
-        (
-            preceded(char('#'), cut(identifier)),
-            delimited(span0_inline, cut(arguments), span0_inline),
+    let (input, (command, arguments)) = context(
+        "system call",
+        preceded(
+            span0,
+            (
+                preceded(char('#'), cut(identifier)),
+                delimited(span0_inline, cut(arguments), span0_inline),
+            ),
         ),
     )
     .parse(input)?;
@@ -58,6 +62,7 @@ mod tests {
                     arguments: vec![Argument {
                         name: "a".to_string(),
                         value: RValue::Literal(Literal::Integer(1)),
+                        span: None,
                     }],
                 })
             ))
@@ -72,10 +77,12 @@ mod tests {
                         Argument {
                             name: "a".to_string(),
                             value: RValue::Literal(Literal::Integer(1)),
+                            span: None,
                         },
                         Argument {
                             name: "b".to_string(),
                             value: RValue::Literal(Literal::String("aa".to_string())),
+                            span: None,
                         }
                     ],
                 })
@@ -91,10 +98,12 @@ mod tests {
                         Argument {
                             name: "a".to_string(),
                             value: RValue::Literal(Literal::Integer(1)),
+                            span: None,
                         },
                         Argument {
                             name: "b".to_string(),
                             value: RValue::Literal(Literal::String("aa".to_string())),
+                            span: None,
                         }
                     ],
                 })
@@ -1,39 +1,39 @@
 use nom::branch::alt;
 use nom::bytes::complete::{escaped_transform, tag};
-use nom::character::complete::{char, none_of};
-use nom::combinator::{cut, map_res, value};
+use nom::character::complete::{char, none_of, one_of};
+use nom::combinator::{cut, map, map_res, not, opt};
 use nom::error::context;
 use nom::multi::many0;
-use nom::sequence::delimited;
+use nom::sequence::{delimited, preceded};
 use nom::Parser;
 
 use crate::format::{TemplateLiteral, TemplateLiteralPart};
 use crate::result::ParseResult;
 
+use super::escape::template_escape_sequence;
 use super::rvalue::rvalue;
-use super::text::parse_unicode;
 
 /// parse template literals like the same as JS, but only support primitive types or variable reference,
 /// expression is not supported yet.
+///
+/// `${?condition}shown${:}otherwise${/}` is a conditional span, the `${:}` branch is optional.
 pub fn template_literal(input: &str) -> ParseResult<&str, TemplateLiteral> {
+    let (input, parts) = context(
+        "template_literal",
+        delimited(char('`'), cut(template_parts), char('`')),
+    )
+    .parse(input)?;
+
+    Ok((input, TemplateLiteral { parts }))
+}
+
+/// Parts of a template up to its closing backtick, or up to the `${:}` or `${/}` of the
+/// conditional span they are in
+fn template_parts(input: &str) -> ParseResult<&str, Vec<TemplateLiteralPart>> {
     let escaped_text = context(
         "escaped_text",
         map_res(
-            escaped_transform(
-                none_of("`$\\"),
-                '\\',
-                alt((
-                    parse_unicode,
-                    value('\n', char('n')),
-                    value('\r', char('r')),
-                    value('\t', char('t')),
-                    value('\\', char('\\')),
-                    value('/', char('/')),
-                    value('"', char('"')),
-                    value('\'', char('\'')),
-                    value('`', char('`')),
-                )),
-            ),
+            escaped_transform(none_of("`$\\"), '\\', template_escape_sequence),
             |s: String| {
                 Ok::<TemplateLiteralPart, nom::error::Error<&str>>(TemplateLiteralPart::Text(s))
             },
@@ -42,18 +42,34 @@ pub fn template_literal(input: &str) -> ParseResult<&str, TemplateLiteral> {
 
     let value = context(
         "expression",
-        map_res(delimited(tag("${"), cut(rvalue), char('}')), |v| {
-            Ok::<TemplateLiteralPart, nom::error::Error<&str>>(TemplateLiteralPart::Value(v))
-        }),
+        map_res(
+            delimited((tag("${"), not(one_of("?:/"))), cut(rvalue), char('}')),
+            |v| Ok::<TemplateLiteralPart, nom::error::Error<&str>>(TemplateLiteralPart::Value(v)),
+        ),
     );
 
-    let (input, parts) = context(
-        "template_literal",
-        delimited(char('`'), cut(many0(alt((escaped_text, value)))), char('`')),
-    )
-    .parse(input)?;
+    many0(alt((escaped_text, conditional_span, value))).parse(input)
+}
 
-    Ok((input, TemplateLiteral { parts }))
+/// parse a conditional span `${?condition}...${:}...${/}`
+fn conditional_span(input: &str) -> ParseResult<&str, TemplateLiteralPart> {
+    context(
+        "conditional span",
+        map(
+            (
+                delimited(tag("${?"), cut(rvalue), cut(char('}'))),
+                cut(template_parts),
+                opt(preceded(tag("${:}"), cut(template_parts))),
+                cut(tag("${/}")),
+            ),
+            |(condition, then, otherwise, _)| TemplateLiteralPart::Conditional {
+                condition,
+                then,
+                otherwise: otherwise.unwrap_or_default(),
+            },
+        ),
+    )
+    .parse(input)
 }
 
 #[cfg(test)]
@@ -78,4 +94,35 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_conditional_span() {
+        let input = "`Hi${?met} again${:}, nice to meet you${/}!`";
+        let (remaining, result) = template_literal.parse(input).unwrap();
+        assert_eq!(remaining, "");
+        assert_eq!(
+            result.parts,
+            vec![
+                TemplateLiteralPart::Text("Hi".to_string()),
+                TemplateLiteralPart::Conditional {
+                    condition: RValue::Variable(Variable {
+                        chain: vec!["met".to_string()],
+                    }),
+                    then: vec![TemplateLiteralPart::Text(" again".to_string())],
+                    otherwise: vec![TemplateLiteralPart::Text(", nice to meet you".to_string())],
+                },
+                TemplateLiteralPart::Text("!".to_string()),
+            ]
+        );
+        assert_eq!(
+            result.get_values(),
+            vec![RValue::Variable(Variable {
+                chain: vec!["met".to_string()],
+            })]
+        );
+
+        // unterminated spans and stray separators are errors
+        assert!(template_literal.parse("`${?met}again`").is_err());
+        assert!(template_literal.parse("`again${/}`").is_err());
+    }
 }
@@ -1,8 +1,9 @@
 use nom::branch::alt;
 use nom::bytes::complete::{escaped_transform, take_while, take_while1, take_while_m_n};
 use nom::character::complete::{char, none_of, one_of};
-use nom::combinator::{cut, map_opt, map_res, not, opt, peek, success, value};
+use nom::combinator::{cut, map_opt, map_res, not, opt, peek, success, value, verify};
 use nom::error::{context, FromExternalError, ParseError};
+use nom::multi::many1;
 use nom::sequence::{delimited, preceded};
 use nom::{IResult, Parser};
 
@@ -10,6 +11,8 @@ use crate::format::{ChildContent, LeadingText, TailingText, TemplateLiteral, Tex
 use crate::result::ParseResult;
 
 use super::comment::{span0, span0_inline};
+use super::config::multi_speaker;
+use super::escape::escape_sequence;
 use super::template::template_literal;
 
 /// Parse tailing text in the format #<non-whitespace-chars>
@@ -29,6 +32,12 @@ pub fn tailing_text(input: &str) -> ParseResult<&str, TailingText> {
 }
 
 pub fn text_line(input: &str) -> ParseResult<&str, ChildContent> {
+    if multi_speaker() {
+        if let Ok(result) = multi_text_line(input) {
+            return Ok(result);
+        }
+    }
+
     let (input, (_, _, leading, _, text, _, tailing)) = delimited(
         span0,
         (
@@ -47,6 +56,60 @@ pub fn text_line(input: &str) -> ParseResult<&str, ChildContent> {
     Ok((input, ChildContent::TextLine(leading, text, tailing)))
 }
 
+/// Parse a line of two or more `[speaker] text` segments, see [`speaker_boundary`]
+fn multi_text_line(input: &str) -> ParseResult<&str, ChildContent> {
+    let (input, (_, _, segments, tailing)) = delimited(
+        span0,
+        (
+            not(one_of("}@#")),
+            span0_inline,
+            verify(
+                many1((
+                    leading_text,
+                    delimited(span0_inline, segment_text, span0_inline),
+                )),
+                |segments: &Vec<_>| segments.len() > 1,
+            ),
+            alt((tailing_text, success(TailingText::None))),
+        ),
+        span0_inline,
+    )
+    .parse(input)?;
+
+    Ok((input, ChildContent::MultiTextLine(segments, tailing)))
+}
+
+/// Text of one speaker segment, plain text stops before the next speaker
+fn segment_text(input: &str) -> ParseResult<&str, Text> {
+    let line_end = input.find(['\n', '\r']).unwrap_or(input.len());
+    match speaker_boundary(&input[..line_end]) {
+        Some(end) => {
+            let segment = input[..end].trim_end();
+            if segment.starts_with(['"', '\'', '`']) {
+                // quoted text ends by itself and may contain brackets
+                return text(input);
+            }
+            Ok((&input[segment.len()..], Text::Text(segment.to_string())))
+        }
+        None => text(input),
+    }
+}
+
+/// Byte offset of the `[` starting the next speaker segment of a multi-speaker line.
+///
+/// A segment starts at a `[` preceded by whitespace and closed by a `]` later on the
+/// same line, so `a[1]` and an unclosed `[` stay plain text.
+pub(crate) fn speaker_boundary(line: &str) -> Option<usize> {
+    let mut previous = None;
+    for (index, c) in line.char_indices() {
+        if c == '[' && previous.is_some_and(char::is_whitespace) && line[index..].contains(']') {
+            return Some(index);
+        }
+        previous = Some(c);
+    }
+    None
+}
+
 pub fn leading_text(input: &str) -> ParseResult<&str, LeadingText> {
     context(
         "leading_text",
@@ -115,32 +178,10 @@ pub fn plain_text(input: &str) -> ParseResult<&str, String> {
     // allowed after quoted text ("...", '...', or `...`). When text is plain/bare,
     // any '#' and subsequent characters become part of the text itself.
 
-    let mut end_pos = 0;
-    let chars: Vec<char> = input.chars().collect();
-
-    for i in 0..chars.len() {
-        let ch = chars[i];
-
-        // Stop at newline
-        if ch == '\n' || ch == '\r' {
-            break;
-        }
-
-        end_pos = i + 1;
-    }
-
-    if end_pos == 0 {
-        // Empty text is still valid
-        return Ok((input, String::new()));
-    }
-
-    let (text, remaining) = input.split_at(
-        input
-            .char_indices()
-            .nth(end_pos)
-            .map(|(pos, _)| pos)
-            .unwrap_or(input.len()),
-    );
+    // Scan bytes directly: both stop characters are ASCII, so the offset is
+    // always a char boundary and long lines are handled in linear time.
+    let end_pos = input.find(['\n', '\r']).unwrap_or(input.len());
+    let (text, remaining) = input.split_at(end_pos);
 
     Ok((remaining, text.to_string()))
 }
@@ -153,21 +194,7 @@ pub fn escaped_text(input: &str) -> ParseResult<&str, String> {
                 char('"'),
                 cut(alt((
                     value(String::new(), peek(char('"'))),
-                    escaped_transform(
-                        none_of("\"\\\n\r"),
-                        '\\',
-                        alt((
-                            parse_unicode,
-                            value('\n', char('n')),
-                            value('\r', char('r')),
-                            value('\t', char('t')),
-                            value('\\', char('\\')),
-                            value('/', char('/')),
-                            value('"', char('"')),
-                            value('\'', char('\'')),
-                            value('`', char('`')),
-                        )),
-                    ),
+                    escaped_transform(none_of("\"\\\n\r"), '\\', escape_sequence),
                 ))),
                 char('"'),
             ),
@@ -175,21 +202,7 @@ pub fn escaped_text(input: &str) -> ParseResult<&str, String> {
                 char('\''),
                 cut(alt((
                     value(String::new(), peek(char('\''))),
-                    escaped_transform(
-                        none_of("\'\\\n\r"),
-                        '\\',
-                        alt((
-                            parse_unicode,
-                            value('\n', char('n')),
-                            value('\r', char('r')),
-                            value('\t', char('t')),
-                            value('\\', char('\\')),
-                            value('/', char('/')),
-                            value('"', char('"')),
-                            value('\'', char('\'')),
-                            value('`', char('`')),
-                        )),
-                    ),
+                    escaped_transform(none_of("\'\\\n\r"), '\\', escape_sequence),
                 ))),
                 char('\''),
             ),
@@ -333,7 +346,7 @@ mod tests {
         assert_eq!(
             text_line("foo\n  \r"),
             Ok((
-                "",
+                "\n  \r",
                 ChildContent::TextLine(
                     LeadingText::None,
                     Text::Text("foo".to_string()),
@@ -420,7 +433,7 @@ mod tests {
         assert_eq!(
             text_line("[ foo bar ] aaaaaa\n"),
             Ok((
-                "",
+                "\n",
                 ChildContent::TextLine(
                     LeadingText::Text(" foo bar ".to_string()),
                     Text::Text("aaaaaa".to_string()),
@@ -432,7 +445,7 @@ mod tests {
         assert_eq!(
             text_line("[ 'foo bar' ] \naaaaaa\r\n"),
             Ok((
-                "aaaaaa\r\n",
+                "\naaaaaa\r\n",
                 ChildContent::TextLine(
                     LeadingText::Text("foo bar".to_string()),
                     Text::Text("".to_string()),
@@ -444,7 +457,7 @@ mod tests {
         assert_eq!(
             text_line("[ 'foo bar' ''] \naaaaaa\r\n"),
             Ok((
-                "aaaaaa\r\n",
+                "\naaaaaa\r\n",
                 ChildContent::TextLine(
                     LeadingText::Text(" 'foo bar' ''".to_string()),
                     Text::Text("".to_string()),
@@ -456,7 +469,7 @@ mod tests {
         assert_eq!(
             text_line("[ `foo ${bar}` ] \naaaaaa\r\n"),
             Ok((
-                "aaaaaa\r\n",
+                "\naaaaaa\r\n",
                 ChildContent::TextLine(
                     LeadingText::TemplateLiteral(TemplateLiteral {
                         parts: vec![
@@ -477,7 +490,7 @@ mod tests {
     fn test_template_line() {
         let input = "  \n `hello \n${world} ${123} world` \n";
         let (remaining, result) = text_line.parse(input).unwrap();
-        assert_eq!(remaining, "");
+        assert_eq!(remaining, "\n");
         assert_eq!(
             result,
             ChildContent::TextLine(
@@ -676,4 +689,59 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn test_multi_speaker_text_line() {
+        let config = crate::parser::ParserConfig {
+            multi_speaker: true,
+            ..Default::default()
+        };
+        let line = "[A] text1 [B] 'text2 [x]' #tag";
+        assert_eq!(
+            super::super::config::with_config(&config, || text_line(line)),
+            Ok((
+                "",
+                ChildContent::MultiTextLine(
+                    vec![
+                        (
+                            LeadingText::Text("A".to_string()),
+                            Text::Text("text1".to_string())
+                        ),
+                        (
+                            LeadingText::Text("B".to_string()),
+                            Text::Text("text2 [x]".to_string())
+                        ),
+                    ],
+                    TailingText::Text("tag".to_string())
+                )
+            ))
+        );
+
+        // a bracket without whitespace before it, or without a speaker before the
+        // line, is plain text
+        assert_eq!(
+            super::super::config::with_config(&config, || text_line("[A] a[1] b")),
+            Ok((
+                "",
+                ChildContent::TextLine(
+                    LeadingText::Text("A".to_string()),
+                    Text::Text("a[1] b".to_string()),
+                    TailingText::None
+                )
+            ))
+        );
+
+        // off by default
+        assert_eq!(
+            text_line("[A] text1 [B] text2"),
+            Ok((
+                "",
+                ChildContent::TextLine(
+                    LeadingText::Text("A".to_string()),
+                    Text::Text("text1 [B] text2".to_string()),
+                    TailingText::None
+                )
+            ))
+        );
+    }
 }
@@ -0,0 +1,125 @@
+//! Short text previews of paragraphs, e.g. for editor hints next to a `#goto`.
+
+use crate::format::{
+    ChildContent, LeadingText, RValue, Story, TemplateLiteral, TemplateLiteralPart, Text,
+};
+
+impl Story {
+    /// The first `n` text lines of paragraph `paragraph`, in source order.
+    ///
+    /// Lines are rendered without a runtime: a speaker is shown as `[speaker] text`,
+    /// variables in templates as `${name}` placeholders. Commands, system calls and
+    /// embedded code are skipped, and lines under attributes are included as if their
+    /// conditions held. An unknown paragraph has no lines.
+    pub fn preview_lines(&self, paragraph: &str, n: usize) -> Vec<String> {
+        let Some(paragraph) = self.paragraphs.iter().find(|p| p.name == paragraph) else {
+            return Vec::new();
+        };
+        paragraph
+            .block
+            .descendants()
+            .filter_map(|child| match &child.content {
+                ChildContent::TextLine(leading, text, _) => render_line(leading, text),
+                ChildContent::MultiTextLine(segments, _) => Some(
+                    segments
+                        .iter()
+                        .filter_map(|(leading, text)| render_line(leading, text))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+                ChildContent::Block(_)
+                | ChildContent::CommandLine(_)
+                | ChildContent::SystemCallLine(_)
+                | ChildContent::EmbeddedCode(_)
+                | ChildContent::MacroInvocation(_) => None,
+            })
+            .take(n)
+            .collect()
+    }
+}
+
+fn render_line(leading: &LeadingText, text: &Text) -> Option<String> {
+    let leading = match leading {
+        LeadingText::None => None,
+        LeadingText::Text(t) => Some(t.clone()),
+        LeadingText::TemplateLiteral(template) => Some(render_template(template)),
+    };
+    let text = match text {
+        Text::None => None,
+        Text::Text(t) => Some(t.clone()),
+        Text::TemplateLiteral(template) => Some(render_template(template)),
+    };
+    match (leading, text) {
+        (Some(leading), Some(text)) => Some(format!("[{}] {}", leading, text)),
+        (Some(leading), None) => Some(format!("[{}]", leading)),
+        (None, Some(text)) => Some(text),
+        (None, None) => None,
+    }
+}
+
+fn render_template(template: &TemplateLiteral) -> String {
+    render_parts(&template.parts)
+}
+
+fn render_parts(parts: &[TemplateLiteralPart]) -> String {
+    parts
+        .iter()
+        .map(|part| match part {
+            TemplateLiteralPart::Text(text) => text.clone(),
+            TemplateLiteralPart::Value(value) => render_value(value),
+            // both branches are shown, as `{then|otherwise}`
+            TemplateLiteralPart::Conditional {
+                then, otherwise, ..
+            } => {
+                if otherwise.is_empty() {
+                    format!("{{{}}}", render_parts(then))
+                } else {
+                    format!("{{{}|{}}}", render_parts(then), render_parts(otherwise))
+                }
+            }
+        })
+        .collect()
+}
+
+fn render_value(value: &RValue) -> String {
+    match value {
+        RValue::Literal(literal) => literal.to_string(),
+        RValue::Variable(variable) => format!("${{{}}}", variable.chain.join(".")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_story;
+
+    const SCRIPT: &str = r#"
+::entry {
+    @bg src="school.png"
+    "First line"
+    #[cond("met_before")]
+    {
+        @show name="hero"
+        [`${hero.name}`] `Hello, ${player}!`
+    }
+    "Third line"
+}
+"#;
+
+    #[test]
+    fn test_first_two_lines_skip_commands() {
+        let story = parse_story("test", SCRIPT).unwrap();
+        assert_eq!(
+            story.preview_lines("entry", 2),
+            vec!["First line", "[${hero.name}] Hello, ${player}!"]
+        );
+    }
+
+    #[test]
+    fn test_respects_n_and_unknown_paragraph() {
+        let story = parse_story("test", SCRIPT).unwrap();
+        assert_eq!(story.preview_lines("entry", 1), vec!["First line"]);
+        assert_eq!(story.preview_lines("entry", 10).len(), 3);
+        assert!(story.preview_lines("entry", 0).is_empty());
+        assert!(story.preview_lines("missing", 2).is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+//! Cross-story references of a story, so embedders know upfront which other stories a
+//! run may need to load.
+
+use std::collections::BTreeSet;
+
+use crate::format::{ChildContent, Literal, RValue, Story, SystemCallLine};
+
+/// System calls which may load the story named by their `story` argument
+const STORY_SYSTEM_CALLS: &[&str] = &["goto", "call", "replace"];
+
+impl Story {
+    /// Names of the other stories targeted by `#goto`, `#call` and `#replace` with a
+    /// literal `story=` argument, including those in nested blocks and under attributes.
+    ///
+    /// Running the story may load these lazily through `StepResult::NeedsStoryFile`,
+    /// so an embedder can preload them instead. References to the story itself are
+    /// skipped, and targets given by a variable are not known here, see
+    /// [`has_dynamic_targets`](Story::has_dynamic_targets).
+    pub fn cross_story_references(&self) -> BTreeSet<String> {
+        self.story_calls()
+            .filter_map(|call| match call.get_argument("story") {
+                Some(RValue::Literal(Literal::String(story))) if *story != self.name => {
+                    Some(story.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Whether any `#goto`, `#call` or `#replace` takes its `story=` target from a
+    /// variable, so [`cross_story_references`](Story::cross_story_references) cannot
+    /// name every story a run may load.
+    pub fn has_dynamic_targets(&self) -> bool {
+        self.story_calls()
+            .any(|call| matches!(call.get_argument("story"), Some(RValue::Variable(_))))
+    }
+
+    /// Every system call of the story which may load another story
+    fn story_calls(&self) -> impl Iterator<Item = &SystemCallLine> {
+        self.paragraphs
+            .iter()
+            .flat_map(|paragraph| paragraph.block.descendants())
+            .filter_map(|child| match &child.content {
+                ChildContent::SystemCallLine(call)
+                    if STORY_SYSTEM_CALLS.contains(&call.command.as_str()) =>
+                {
+                    Some(call)
+                }
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::parser::parse_story;
+
+    #[test]
+    fn test_static_and_dynamic_targets() {
+        let story = parse_story(
+            "main",
+            r#"
+::entry {
+    #goto paragraph="start" story="chapter2"
+    {
+        #[cond("bonus")]
+        #call(paragraph="extra", story="bonus")
+    }
+    #replace paragraph="loop" story="chapter2"
+    #goto paragraph="local"
+    #goto paragraph="self" story="main"
+    #goto paragraph="start" story=next_chapter
+    @load story="not_a_jump"
+}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            story.cross_story_references(),
+            BTreeSet::from(["bonus".to_string(), "chapter2".to_string()])
+        );
+        assert!(story.has_dynamic_targets());
+    }
+
+    #[test]
+    fn test_story_without_cross_references() {
+        let story = parse_story("main", "::entry {\n    #goto paragraph=\"next\"\n}\n").unwrap();
+        assert!(story.cross_story_references().is_empty());
+        assert!(!story.has_dynamic_targets());
+    }
+}
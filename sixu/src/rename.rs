@@ -0,0 +1,104 @@
+//! Renaming of commands throughout a story, e.g. when migrating to a new command name.
+
+use crate::format::{Block, ChildContent, Story};
+
+impl Story {
+    /// Rename every invocation of command `old` to `new`, including those in nested
+    /// blocks, and return how many were renamed.
+    ///
+    /// Only command lines are touched: system calls, text and embedded code that
+    /// happen to contain the name are left alone.
+    pub fn rename_command(&mut self, old: &str, new: &str) -> usize {
+        self.paragraphs
+            .iter_mut()
+            .map(|paragraph| rename_in_block(&mut paragraph.block, old, new))
+            .sum()
+    }
+}
+
+fn rename_in_block(block: &mut Block, old: &str, new: &str) -> usize {
+    let mut renamed = 0;
+    for child in &mut block.children {
+        match &mut child.content {
+            ChildContent::Block(block) => renamed += rename_in_block(block, old, new),
+            ChildContent::CommandLine(command) if command.command == old => {
+                command.command = new.to_string();
+                renamed += 1;
+            }
+            _ => {}
+        }
+    }
+    renamed
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{Block, ChildContent, LeadingText, Story, TailingText, Text};
+    use crate::parser::parse_story;
+
+    const SCRIPT: &str = r#"
+::entry {
+    @oldCmd a=1
+    "oldCmd"
+    #oldCmd
+    {
+        #[cond("ready")]
+        {
+            @oldCmd b=2
+        }
+        @other
+    }
+}
+
+::second {
+    @oldCmd
+}
+"#;
+
+    fn commands(story: &Story) -> Vec<String> {
+        fn visit(block: &Block, out: &mut Vec<String>) {
+            for child in &block.children {
+                match &child.content {
+                    ChildContent::Block(block) => visit(block, out),
+                    ChildContent::CommandLine(command) => out.push(command.command.clone()),
+                    _ => {}
+                }
+            }
+        }
+        let mut out = Vec::new();
+        for paragraph in &story.paragraphs {
+            visit(&paragraph.block, &mut out);
+        }
+        out
+    }
+
+    #[test]
+    fn test_renames_nested_invocations() {
+        let mut story = parse_story("test", SCRIPT).unwrap();
+        assert_eq!(story.rename_command("oldCmd", "newCmd"), 3);
+        assert_eq!(
+            commands(&story),
+            vec!["newCmd", "newCmd", "other", "newCmd"]
+        );
+    }
+
+    #[test]
+    fn test_leaves_text_and_system_calls_alone() {
+        let mut story = parse_story("test", SCRIPT).unwrap();
+        story.rename_command("oldCmd", "newCmd");
+        let children = &story.paragraphs[0].block.children;
+        assert_eq!(
+            children[1].content,
+            ChildContent::TextLine(
+                LeadingText::None,
+                Text::Text("oldCmd".to_string()),
+                TailingText::None
+            )
+        );
+        let ChildContent::SystemCallLine(call) = &children[2].content else {
+            panic!("expected a system call, got {:?}", children[2].content);
+        };
+        assert_eq!(call.command, "oldCmd");
+        assert_eq!(story.rename_command("missing", "newCmd"), 0);
+    }
+}
@@ -3,14 +3,33 @@ mod datasource;
 mod executor;
 mod state;
 
-pub use self::callback::*;
-pub use self::datasource::{LoopControl, RuntimeContext};
-pub use self::executor::RuntimeExecutor;
-pub use self::state::ExecutionState;
+use std::collections::{HashSet, VecDeque};
 
 use crate::error::{Result, RuntimeError};
 use crate::format::*;
 
+pub use self::callback::*;
+pub use self::datasource::{LoopControl, RuntimeContext};
+pub use self::executor::{RuntimeExecutor, DEFAULT_TEMPLATE_RECURSION_LIMIT};
+pub use self::state::{ExecutionState, SaveState};
+
+/// Attribute keywords consumed by the runtime for control flow. Any other attribute
+/// is left to the executor through [`RuntimeContext::current_attributes`].
+pub const BUILTIN_ATTRIBUTES: &[&str] = &[
+    "cond", "if", "elif", "else", "while", "loop", "switch", "case", "default",
+];
+
+/// Attribute keyword restricting a paragraph or child to a build flag, e.g.
+/// `#[platform("mobile")]`. See [`Runtime::feature_flags`].
+pub const PLATFORM_ATTRIBUTE: &str = "platform";
+
+/// Attribute keyword making a paragraph or child run only the first time it is
+/// reached in a session, see [`RuntimeContext::visited_once`]
+pub const ONCE_ATTRIBUTE: &str = "once";
+
+/// Local variable of the caller which receives the value of `#return value=...`
+pub const RETURN_VALUE_LOCAL: &str = "result";
+
 /// Result of a single step of runtime execution
 #[derive(Debug)]
 pub enum StepResult {
@@ -22,16 +41,22 @@ pub enum StepResult {
     /// The runtime needs a script to be evaluated externally.
     /// Call `resume_script()` with the result, then call `step()` again.
     NeedsScript(String),
+    /// The runtime needs an expression, e.g. of a `switch`, to be evaluated externally.
+    /// Call `resume_expression()` with its value, then call `step()` again.
+    NeedsExpression(String),
     /// The runtime needs a story file to be loaded.
     /// Call `provide_story_data()` with the file contents, then call `step()` again.
     NeedsStoryFile(String),
 }
 
 /// Internal state tracking for step/resume execution
+#[derive(Default)]
 enum StepPhase {
     /// Ready for normal execution
+    #[default]
     Ready,
-    /// Yielded for condition evaluation; child is saved for resumption
+    /// Yielded for the evaluation of a condition or an expression;
+    /// child is saved for resumption
     AwaitingCondition { child: Child },
     /// Yielded for script evaluation
     AwaitingScript,
@@ -39,16 +64,15 @@ enum StepPhase {
     AwaitingStoryFile {
         story_name: String,
         paragraph_name: String,
+        arguments: Vec<ResolvedArgument>,
     },
 }
 
-impl Default for StepPhase {
-    fn default() -> Self {
-        StepPhase::Ready
-    }
-}
-
 /// Runtime manages the execution context and executor together
+///
+/// With the `tracing` feature, the runtime emits events with the target `sixu::runtime`:
+/// `frame_push`, `frame_pop` and `frame_clear` for the execution stack, `line` for each
+/// child executed, `condition` for attribute condition results and `system_call`.
 pub struct Runtime<E: RuntimeExecutor> {
     context: RuntimeContext,
     executor: E,
@@ -58,6 +82,18 @@ pub struct Runtime<E: RuntimeExecutor> {
     condition_result: Option<bool>,
     /// Script result provided by the caller after NeedsScript
     script_result: Option<(Option<RValue>, bool)>,
+    /// Expression value provided by the caller after NeedsExpression
+    expression_result: Option<Option<Literal>>,
+    /// Active build flags, see `feature_flags`
+    feature_flags: HashSet<String>,
+    /// Iteration budget of a single `step()`, see `set_max_steps`
+    max_steps: Option<usize>,
+    /// Snapshots taken before each step, oldest first, see `rewind`
+    history: VecDeque<SaveState>,
+    /// Maximum number of snapshots kept in `history`
+    history_capacity: usize,
+    /// Whether loaded stories are checked for duplicate paragraphs, see `new_strict`
+    strict: bool,
 }
 
 impl<E: RuntimeExecutor> Runtime<E> {
@@ -68,6 +104,12 @@ impl<E: RuntimeExecutor> Runtime<E> {
             phase: StepPhase::default(),
             condition_result: None,
             script_result: None,
+            expression_result: None,
+            feature_flags: HashSet::new(),
+            max_steps: None,
+            history: VecDeque::new(),
+            history_capacity: 0,
+            strict: false,
         }
     }
 
@@ -78,6 +120,25 @@ impl<E: RuntimeExecutor> Runtime<E> {
             phase: StepPhase::default(),
             condition_result: None,
             script_result: None,
+            expression_result: None,
+            feature_flags: HashSet::new(),
+            max_steps: None,
+            history: VecDeque::new(),
+            history_capacity: 0,
+            strict: false,
+        }
+    }
+
+    /// Create a runtime which rejects stories defining a paragraph more than once with
+    /// [`RuntimeError::DuplicateParagraph`] when they are added by `try_add_story`,
+    /// `provide_story_data` or `load_story_from_reader`. `add_story` logs and drops them.
+    ///
+    /// Paragraphs excluded by the feature flags active at that time are not counted, so
+    /// platform variants of a paragraph may share its name.
+    pub fn new_strict(executor: E) -> Self {
+        Self {
+            strict: true,
+            ..Self::new(executor)
         }
     }
 
@@ -97,8 +158,39 @@ impl<E: RuntimeExecutor> Runtime<E> {
         &mut self.executor
     }
 
+    /// Active build flags. Paragraphs and children tagged with `#[platform("flag")]`
+    /// are only executed when one of their flags is active, untagged content always is.
+    /// Excluded paragraphs are also left out of lookups and `list_paragraphs`.
+    pub fn feature_flags(&self) -> &HashSet<String> {
+        &self.feature_flags
+    }
+
+    pub fn feature_flags_mut(&mut self) -> &mut HashSet<String> {
+        &mut self.feature_flags
+    }
+
+    /// Add a story. A strict runtime (see `new_strict`) logs and drops a story with
+    /// duplicate paragraphs, use `try_add_story` to get the error instead.
     pub fn add_story(&mut self, story: Story) {
+        let name = story.name.clone();
+        if let Err(error) = self.try_add_story(story) {
+            log::error!("Story `{}` not added: {}", name, error);
+        }
+    }
+
+    /// Add a story, checking it for duplicate paragraphs first on a strict runtime
+    /// (see `new_strict`), in which case a story failing the check is not added
+    pub fn try_add_story(&mut self, story: Story) -> Result<()> {
+        if self.strict {
+            let mut names = HashSet::new();
+            for paragraph in &story.paragraphs {
+                if self.is_included(&paragraph.attributes) && !names.insert(&paragraph.name) {
+                    return Err(RuntimeError::DuplicateParagraph(paragraph.name.clone()));
+                }
+            }
+        }
         self.context.stories_mut().push(story);
+        Ok(())
     }
 
     pub fn has_story(&self, name: &str) -> bool {
@@ -118,7 +210,7 @@ impl<E: RuntimeExecutor> Runtime<E> {
         story
             .paragraphs
             .iter()
-            .find(|s| s.name == name)
+            .find(|s| s.name == name && self.is_included(&s.attributes))
             .ok_or(RuntimeError::ParagraphNotFound(name.to_string()))
     }
 
@@ -132,7 +224,70 @@ impl<E: RuntimeExecutor> Runtime<E> {
 
     pub fn list_paragraphs(&self, story_name: &str) -> Result<Vec<String>> {
         let story = self.get_story(story_name)?;
-        Ok(story.paragraphs.iter().map(|p| p.name.clone()).collect())
+        Ok(story
+            .paragraphs
+            .iter()
+            .filter(|p| self.is_included(&p.attributes))
+            .map(|p| p.name.clone())
+            .collect())
+    }
+
+    /// Whether content with `attributes` is included under the active feature flags
+    fn is_included(&self, attributes: &[Attribute]) -> bool {
+        is_included(attributes, &self.feature_flags)
+    }
+
+    /// Statically trace the paragraphs visited from `entry_name`, in order, as
+    /// `(story, paragraph)` pairs.
+    ///
+    /// The trace follows falling through to the next paragraph of the story and
+    /// `#goto`/`#replace` with string literal targets. It ends at the first point where
+    /// the path is not deterministic or leaves the paragraph sequence:
+    /// - a child guarded by a control attribute (`cond`, `if`, `while`, `loop`) that
+    ///   contains a system call, i.e. a conditional branch
+    /// - `#call`, `#finish`, `#break`, `#continue` or a jump to a non-literal target
+    /// - the end of the story, a story or paragraph that is not loaded, or a paragraph
+    ///   already visited
+    ///
+    /// Paragraphs and children excluded by the [`feature_flags`](Self::feature_flags)
+    /// are skipped. Nothing is executed, so executor hooks such as `rewrite_system_call`
+    /// are not applied.
+    pub fn execution_order(&self, story_name: &str, entry_name: &str) -> Vec<(String, String)> {
+        let mut order: Vec<(String, String)> = Vec::new();
+        let mut next = Some((story_name.to_string(), entry_name.to_string()));
+
+        while let Some((story_name, paragraph_name)) = next.take() {
+            if order
+                .iter()
+                .any(|(s, p)| s == &story_name && p == &paragraph_name)
+            {
+                break;
+            }
+            let Ok(story) = self.get_story(&story_name) else {
+                break;
+            };
+            let Some(index) = story
+                .paragraphs
+                .iter()
+                .position(|p| p.name == paragraph_name && self.is_included(&p.attributes))
+            else {
+                break;
+            };
+
+            next = match trace_block(&story.paragraphs[index].block, &self.feature_flags) {
+                Flow::FallThrough => story.paragraphs[index + 1..]
+                    .iter()
+                    .find(|p| self.is_included(&p.attributes))
+                    .map(|p| (story_name.clone(), p.name.clone())),
+                Flow::Jump { story, paragraph } => {
+                    Some((story.unwrap_or_else(|| story_name.clone()), paragraph))
+                }
+                Flow::Stop => None,
+            };
+            order.push((story_name, paragraph_name));
+        }
+
+        order
     }
 
     pub fn traverse_lines<F>(
@@ -156,13 +311,19 @@ impl<E: RuntimeExecutor> Runtime<E> {
         Ok(())
     }
 
-    pub fn save(&self) -> Result<Vec<ExecutionState>> {
-        let stack = self.context.stack().clone();
-        Ok(stack)
+    /// Snapshot the execution stack and the `#[once]` content already run
+    pub fn save(&self) -> Result<SaveState> {
+        Ok(SaveState {
+            stack: self.context.stack().clone(),
+            visited_once: self.context.visited_once().clone(),
+        })
     }
 
-    pub fn restore(&mut self, states: Vec<ExecutionState>) -> Result<()> {
-        *self.context.stack_mut() = states;
+    /// Restore a snapshot taken by [`save`](Self::save). A bare stack saved by older
+    /// versions can be restored with `restore(states.into())`.
+    pub fn restore(&mut self, state: SaveState) -> Result<()> {
+        *self.context.stack_mut() = state.stack;
+        *self.context.visited_once_mut() = state.visited_once;
         Ok(())
     }
 
@@ -174,13 +335,7 @@ impl<E: RuntimeExecutor> Runtime<E> {
         let is_empty = self.context.stack().is_empty();
         if is_empty {
             let entry_name = entry_name.unwrap_or("entry");
-            let paragraph = self.get_paragraph(story_name, entry_name)?;
-            let block = paragraph.block.clone();
-            self.context.stack_mut().push(ExecutionState::new(
-                story_name.to_string(),
-                entry_name.to_string(),
-                block,
-            ));
+            self.enter_paragraph(story_name.to_string(), entry_name.to_string(), &[])?;
         } else {
             return Err(RuntimeError::StoryStarted);
         }
@@ -193,7 +348,7 @@ impl<E: RuntimeExecutor> Runtime<E> {
             return Err(RuntimeError::StoryNotStarted);
         }
 
-        self.context.stack_mut().clear();
+        self.clear_frames();
         self.context
             .archive_variables_mut()
             .as_object_mut()?
@@ -218,7 +373,7 @@ impl<E: RuntimeExecutor> Runtime<E> {
     }
 
     pub fn break_current_block(&mut self) -> Result<()> {
-        if let Some(state) = self.context.stack_mut().pop() {
+        if let Some(state) = self.pop_frame() {
             // if the stack is empty, try to load the next paragraph of the current story
             if self.context.stack().is_empty() {
                 if let Some(next_paragraph) = {
@@ -226,13 +381,11 @@ impl<E: RuntimeExecutor> Runtime<E> {
                     let mut paragraph_iter = story.paragraphs.iter();
                     paragraph_iter.position(|s| s.name == state.paragraph);
 
-                    paragraph_iter.next().cloned()
+                    paragraph_iter
+                        .find(|p| self.is_included(&p.attributes))
+                        .map(|p| p.name.clone())
                 } {
-                    self.context.stack_mut().push(ExecutionState::new(
-                        state.story.clone(),
-                        next_paragraph.name,
-                        next_paragraph.block,
-                    ));
+                    self.enter_paragraph(state.story.clone(), next_paragraph, &[])?;
                 } else {
                     self.executor.finished(&mut self.context);
                 }
@@ -246,6 +399,81 @@ impl<E: RuntimeExecutor> Runtime<E> {
         }
     }
 
+    /// Push the entry state of a paragraph and bind its parameters from `arguments`.
+    fn enter_paragraph(
+        &mut self,
+        story_name: String,
+        paragraph_name: String,
+        arguments: &[ResolvedArgument],
+    ) -> Result<()> {
+        let paragraph = self.get_paragraph(&story_name, &paragraph_name)?.clone();
+        self.push_frame(ExecutionState::new(
+            story_name.clone(),
+            paragraph_name.clone(),
+            paragraph.block,
+        ));
+        if let Err(error) = self.bind_parameters(&paragraph.parameters, arguments) {
+            // The paragraph has not been entered, drop its state without the exit hook
+            self.context.stack_mut().pop();
+            return Err(error);
+        }
+        // a `#[once]` paragraph entered again runs as if it were empty
+        if has_once(&paragraph.attributes)
+            && !self
+                .context
+                .visited_once_mut()
+                .insert(format!("{}::{}", story_name, paragraph_name))
+        {
+            self.get_current_state_mut()?.block = Block::default();
+        }
+        self.executor
+            .on_paragraph_enter(&mut self.context, &story_name, &paragraph_name);
+        Ok(())
+    }
+
+    /// Bind parameters into the local scope of the paragraph state on top of the stack.
+    ///
+    /// Parameters are bound left to right. An omitted argument takes the declared default,
+    /// which is resolved in the new scope so it may refer to parameters bound before it.
+    /// Referring to the parameter itself or to a later one is a `ForwardParameterReference`.
+    /// Parameters without argument or default are bound to `null`, and an argument that
+    /// matches no parameter is an `UnknownParagraphArgument`.
+    fn bind_parameters(
+        &mut self,
+        parameters: &[Parameter],
+        arguments: &[ResolvedArgument],
+    ) -> Result<()> {
+        check_arguments(&self.get_current_state()?.paragraph, parameters, arguments)?;
+        for (index, parameter) in parameters.iter().enumerate() {
+            let value = match arguments.iter().find(|arg| arg.name == parameter.name) {
+                Some(arg) => arg.value.clone(),
+                None => match &parameter.default_value {
+                    Some(default_value) => {
+                        if let RValue::Variable(variable) = default_value {
+                            let referenced = &variable.chain[0];
+                            if parameters[index..].iter().any(|p| &p.name == referenced) {
+                                return Err(RuntimeError::ForwardParameterReference {
+                                    parameter: parameter.name.clone(),
+                                    referenced: referenced.clone(),
+                                });
+                            }
+                        }
+                        self.executor
+                            .get_rvalue(&self.context, default_value)?
+                            .clone()
+                    }
+                    None => Literal::Null,
+                },
+            };
+
+            self.get_current_state_mut()?
+                .locals
+                .get_or_insert_with(Default::default)
+                .insert(parameter.name.clone(), value);
+        }
+        Ok(())
+    }
+
     /// Resolve all variables in the argument list to literal values
     pub fn resolve_arguments(&mut self, args: Vec<Argument>) -> Result<Vec<ResolvedArgument>> {
         let mut resolved_args = Vec::new();
@@ -257,19 +485,117 @@ impl<E: RuntimeExecutor> Runtime<E> {
             resolved_args.push(ResolvedArgument {
                 name: arg.name.clone(),
                 value: resolved_value,
+                span: arg.span.clone(),
             });
         }
         Ok(resolved_args)
     }
 
+    /// Resolve the arguments of a `#goto`, `#call` or `#replace`. Its `story` and
+    /// `paragraph` targets go through `RuntimeExecutor::resolve_literal`, so a chain like
+    /// `save.next` is followed and a missing variable is an error instead of null.
+    fn resolve_jump_arguments(&mut self, args: Vec<Argument>) -> Result<Vec<ResolvedArgument>> {
+        let mut resolved_args = Vec::new();
+        for arg in args {
+            let resolved_value = if arg.name == "story" || arg.name == "paragraph" {
+                self.executor.resolve_literal(&self.context, &arg.value)?
+            } else {
+                self.executor
+                    .get_rvalue(&self.context, &arg.value)?
+                    .to_owned()
+            };
+            resolved_args.push(ResolvedArgument {
+                name: arg.name,
+                value: resolved_value,
+                span: arg.span,
+            });
+        }
+        Ok(resolved_args)
+    }
+
+    /// Keep a snapshot before each of the last `capacity` steps so that they can be
+    /// undone with [`rewind`](Self::rewind). History is off (capacity 0) by default.
+    pub fn set_history_capacity(&mut self, capacity: usize) {
+        self.history_capacity = capacity;
+        while self.history.len() > capacity {
+            self.history.pop_front();
+        }
+    }
+
+    /// Number of steps that can currently be undone with [`rewind`](Self::rewind)
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Snapshot the current position into the history, dropping the oldest snapshot
+    /// once the capacity is reached. [`step`](Self::step) calls this before it runs,
+    /// except when resuming after `NeedsCondition`, `NeedsScript`, `NeedsExpression` or
+    /// `NeedsStoryFile`.
+    pub fn history_push(&mut self) -> Result<()> {
+        if self.history_capacity == 0 {
+            return Ok(());
+        }
+        if self.history.len() == self.history_capacity {
+            self.history.pop_front();
+        }
+        let snapshot = self.save()?;
+        self.history.push_back(snapshot);
+        Ok(())
+    }
+
+    /// Go back `steps` steps, so that the next [`step`](Self::step) runs again from
+    /// where the `steps`-th last step started. Like [`restore`](Self::restore) this
+    /// resets the execution stack and `#[once]` content, but not variables. Fails with
+    /// `NotEnoughHistory` when fewer steps were recorded.
+    pub fn rewind(&mut self, steps: usize) -> Result<()> {
+        if steps == 0 {
+            return Ok(());
+        }
+        if steps > self.history.len() {
+            return Err(RuntimeError::NotEnoughHistory(self.history.len()));
+        }
+        self.history.truncate(self.history.len() - steps + 1);
+        let snapshot = self
+            .history
+            .pop_back()
+            .expect("history has enough snapshots");
+        self.phase = StepPhase::Ready;
+        self.condition_result = None;
+        self.script_result = None;
+        self.expression_result = None;
+        self.restore(snapshot)
+    }
+
+    /// Limit how many children and block transitions a single [`step`](Self::step) may
+    /// run before failing with `StepLimitExceeded`, so that a `#[loop]` which never
+    /// pauses or breaks cannot hang the host. Unbounded by default.
+    pub fn set_max_steps(&mut self, max_steps: usize) {
+        self.max_steps = Some(max_steps);
+    }
+
     /// Execute steps synchronously until paused or an external async operation is needed.
     ///
     /// Returns `StepResult::Done` when execution pauses (e.g. awaiting user input).
-    /// Returns `StepResult::NeedsCondition`, `NeedsScript`, or `NeedsStoryFile` when
+    /// Returns `StepResult::NeedsCondition`, `NeedsScript`, `NeedsExpression`, or
+    /// `NeedsStoryFile` when
     /// an external async operation is required. The caller should perform the operation,
     /// call the corresponding resume method, then call `step()` again.
     pub fn step(&mut self) -> Result<StepResult> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!(target: "sixu::runtime", "step").entered();
+
+        if matches!(self.phase, StepPhase::Ready) && !self.context.stack().is_empty() {
+            self.history_push()?;
+        }
+
+        let mut steps = 0;
         loop {
+            if let Some(max_steps) = self.max_steps {
+                if steps >= max_steps {
+                    return Err(RuntimeError::StepLimitExceeded(max_steps));
+                }
+            }
+            steps += 1;
             if let Some(result) = self.step_one()? {
                 return Ok(result);
             }
@@ -301,18 +627,19 @@ impl<E: RuntimeExecutor> Runtime<E> {
             StepPhase::AwaitingStoryFile {
                 story_name,
                 paragraph_name,
+                arguments,
             } => {
                 // Story should now be loaded, look up the paragraph and push state
-                let paragraph = self.get_paragraph(&story_name, &paragraph_name)?.clone();
-                self.context.stack_mut().push(ExecutionState::new(
-                    story_name,
-                    paragraph_name,
-                    paragraph.block,
-                ));
+                self.enter_paragraph(story_name, paragraph_name, &arguments)?;
                 return Ok(None); // continue execution
             }
         }
 
+        // Finish the multi-speaker text line the executor paused in
+        if self.get_current_state()?.segment > 0 {
+            return self.resume_segments();
+        }
+
         // Check loop control signal from #break / #continue
         if let Some(control) = self.context.take_loop_control() {
             // Pop states until we find the loop body state
@@ -338,6 +665,15 @@ impl<E: RuntimeExecutor> Runtime<E> {
 
         let current_state = self.get_current_state_mut()?;
         if let Some(child) = current_state.next_line() {
+            #[cfg(feature = "tracing")]
+            tracing::event!(
+                name: "line",
+                target: "sixu::runtime",
+                tracing::Level::TRACE,
+                story = %current_state.story,
+                paragraph = %current_state.paragraph,
+                line = current_state.index - 1,
+            );
             self.process_child(child)
         } else {
             self.break_current_block()?;
@@ -347,41 +683,93 @@ impl<E: RuntimeExecutor> Runtime<E> {
 
     /// Process a single child (attributes + content).
     /// Called both for fresh children and when resuming after condition evaluation.
-    fn process_child(&mut self, child: Child) -> Result<Option<StepResult>> {
+    fn process_child(&mut self, mut child: Child) -> Result<Option<StepResult>> {
         let mut is_loop = false;
         let marker = child.marker.clone();
 
-        // Extract attribute info before potentially moving child
-        let (keyword, condition) = if !child.attributes.is_empty() {
-            if child.attributes.len() > 1 {
-                log::warn!("Multiple attributes on same child, only last one is used");
+        if !self.is_included(&child.attributes) {
+            if let Some(marker) = marker.as_ref() {
+                self.executor.handle_marker(&mut self.context, marker)?;
             }
-            let attr = child.attributes.last().unwrap();
-            (attr.keyword.clone(), attr.condition.clone())
+            return Ok(None); // tagged for an inactive flag, skip this child
+        }
+
+        // Taken before a loop attribute rewinds the state to run the child again
+        let once_identity = if has_once(&child.attributes) {
+            Some(self.current_child_identity()?)
         } else {
-            (String::new(), None)
+            None
+        };
+
+        // Extract attribute info before potentially moving child. Built-in control
+        // flow attributes are consumed here, the others are left to the executor.
+        let (control, custom): (Vec<_>, Vec<_>) = child
+            .attributes
+            .iter()
+            .filter(|attr| attr.keyword != PLATFORM_ATTRIBUTE && attr.keyword != ONCE_ATTRIBUTE)
+            .cloned()
+            .partition(|attr| BUILTIN_ATTRIBUTES.contains(&attr.keyword.as_str()));
+        let (keyword, condition) = match control.last() {
+            Some(attr) => {
+                if control.len() > 1 {
+                    log::warn!("Multiple control attributes on same child, only last one is used");
+                }
+                (attr.keyword.clone(), attr.condition.clone())
+            }
+            None => (String::new(), None),
         };
 
+        // `elif` and `else` continue the chain opened by the preceding `if`/`elif` of the
+        // same block, any other child closes it
+        let chain = self.get_current_state()?.branch_matched;
+        if matches!(keyword.as_str(), "elif" | "else") && chain.is_none() {
+            log::warn!("`{}` attribute without a preceding `if` or `elif`", keyword);
+        }
+        if !matches!(keyword.as_str(), "cond" | "if" | "elif") {
+            self.get_current_state_mut()?.branch_matched = None;
+        }
+        if matches!(keyword.as_str(), "elif" | "else") && chain == Some(true) {
+            if keyword == "elif" {
+                self.get_current_state_mut()?.branch_matched = chain;
+            }
+            if let Some(marker) = marker.as_ref() {
+                self.executor.handle_marker(&mut self.context, marker)?;
+            }
+            return Ok(None); // an earlier branch of the chain has run, skip this child
+        }
+
         // Process attributes
         if !keyword.is_empty() {
             match keyword.as_str() {
-                "cond" | "if" => {
+                "else" => {}
+                "cond" | "if" | "elif" => {
                     if let Some(ref cond_str) = condition {
                         let result = match self.condition_result.take() {
                             Some(r) => r,
                             None => {
                                 let cond_str = cond_str.clone();
-                                self.phase = StepPhase::AwaitingCondition { child };
+                                self.yield_for_condition(child)?;
                                 return Ok(Some(StepResult::NeedsCondition(cond_str)));
                             }
                         };
+                        #[cfg(feature = "tracing")]
+                        tracing::event!(
+                            name: "condition",
+                            target: "sixu::runtime",
+                            tracing::Level::DEBUG,
+                            keyword = %keyword,
+                            condition = %cond_str,
+                            result,
+                        );
                         if !result {
+                            self.get_current_state_mut()?.branch_matched = Some(false);
                             if let Some(marker) = marker.as_ref() {
                                 self.executor.handle_marker(&mut self.context, marker)?;
                             }
                             return Ok(None); // condition not met, skip this child
                         }
                     }
+                    self.get_current_state_mut()?.branch_matched = Some(true);
                 }
                 "while" => {
                     if let Some(ref cond_str) = condition {
@@ -389,10 +777,19 @@ impl<E: RuntimeExecutor> Runtime<E> {
                             Some(r) => r,
                             None => {
                                 let cond_str = cond_str.clone();
-                                self.phase = StepPhase::AwaitingCondition { child };
+                                self.yield_for_condition(child)?;
                                 return Ok(Some(StepResult::NeedsCondition(cond_str)));
                             }
                         };
+                        #[cfg(feature = "tracing")]
+                        tracing::event!(
+                            name: "condition",
+                            target: "sixu::runtime",
+                            tracing::Level::DEBUG,
+                            keyword = %keyword,
+                            condition = %cond_str,
+                            result,
+                        );
                         if !result {
                             if let Some(marker) = marker.as_ref() {
                                 self.executor.handle_marker(&mut self.context, marker)?;
@@ -407,24 +804,68 @@ impl<E: RuntimeExecutor> Runtime<E> {
                     self.get_current_state_mut()?.index -= 1;
                     is_loop = true;
                 }
-                _ => {
-                    log::warn!("Unknown attribute keyword: {}", keyword);
+                "switch" => {
+                    let (Some(expression), ChildContent::Block(block)) =
+                        (&condition, &child.content)
+                    else {
+                        log::warn!("`switch` attribute needs an expression and a block, skipped");
+                        if let Some(marker) = marker.as_ref() {
+                            self.executor.handle_marker(&mut self.context, marker)?;
+                        }
+                        return Ok(None);
+                    };
+                    // The expression is evaluated once by the engine, its value picks the
+                    // case to run
+                    let value = match self.expression_result.take() {
+                        Some(value) => value,
+                        None => {
+                            let expression = expression.clone();
+                            self.yield_for_condition(child)?;
+                            return Ok(Some(StepResult::NeedsExpression(expression)));
+                        }
+                    };
+                    child.content = ChildContent::Block(select_case(block, value.as_ref()));
+                }
+                "case" | "default" => {
+                    log::warn!(
+                        "`{}` attribute outside of a `switch` block, skipped",
+                        keyword
+                    );
+                    if let Some(marker) = marker.as_ref() {
+                        self.executor.handle_marker(&mut self.context, marker)?;
+                    }
+                    return Ok(None);
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        // A `#[once]` child is only run the first time, its guards have been met here
+        if let Some(identity) = once_identity {
+            if !self.context.visited_once_mut().insert(identity) {
+                if is_loop {
+                    self.get_current_state_mut()?.index += 1;
+                }
+                if let Some(marker) = marker.as_ref() {
+                    self.executor.handle_marker(&mut self.context, marker)?;
                 }
+                return Ok(None); // already run, skip this child
             }
         }
 
         // Process content
+        self.context.set_current_attributes(custom);
         let is_continue = match child.content {
             ChildContent::Block(block) => {
                 let current_state = self.get_current_state()?.clone();
                 if is_loop {
-                    self.context.stack_mut().push(ExecutionState::new_loop_body(
+                    self.push_frame(ExecutionState::new_loop_body(
                         current_state.story,
                         current_state.paragraph,
                         block.clone(),
                     ));
                 } else {
-                    self.context.stack_mut().push(ExecutionState::new(
+                    self.push_frame(ExecutionState::new_block(
                         current_state.story,
                         current_state.paragraph,
                         block.clone(),
@@ -433,36 +874,24 @@ impl<E: RuntimeExecutor> Runtime<E> {
                 true
             }
             ChildContent::TextLine(leading, text, tailing) => {
-                let leading = match leading {
-                    LeadingText::None => None,
-                    LeadingText::Text(t) => Some(t),
-                    LeadingText::TemplateLiteral(template_literal) => {
-                        let text = self
-                            .executor
-                            .calculate_template_literal(&self.context, &template_literal)?;
-                        Some(text)
-                    }
-                };
-                let text = match text {
-                    Text::None => None,
-                    Text::Text(t) => Some(t),
-                    Text::TemplateLiteral(template_literal) => {
-                        let text = self
-                            .executor
-                            .calculate_template_literal(&self.context, &template_literal)?;
-                        Some(text)
-                    }
-                };
                 let tailing = match tailing {
                     TailingText::None => None,
                     TailingText::Text(t) => Some(t),
                 };
-                self.executor.handle_text(
-                    &mut self.context,
-                    leading.as_deref(),
-                    text.as_deref(),
-                    tailing.as_deref(),
-                )?
+                self.emit_text(leading, text, tailing.as_deref())?
+            }
+            ChildContent::MultiTextLine(segments, tailing) => {
+                match self.emit_segments(segments, tailing, 0)? {
+                    Some(is_continue) => is_continue,
+                    None => {
+                        // Paused before the last segment, keep the line as the current child
+                        // so the next step resumes it
+                        if !is_loop {
+                            self.get_current_state_mut()?.index -= 1;
+                        }
+                        return Ok(Some(StepResult::Done));
+                    }
+                }
             }
             ChildContent::CommandLine(command) => {
                 let command = ResolvedCommandLine {
@@ -472,11 +901,7 @@ impl<E: RuntimeExecutor> Runtime<E> {
                 self.executor.handle_command(&mut self.context, &command)?
             }
             ChildContent::SystemCallLine(systemcall) => {
-                let systemcall = ResolvedSystemCallLine {
-                    command: systemcall.command,
-                    arguments: self.resolve_arguments(systemcall.arguments)?,
-                };
-                match self.handle_system_call(&systemcall)? {
+                match self.dispatch_system_call(systemcall)? {
                     Some(v) => v,
                     None => {
                         // Phase was set to AwaitingStoryFile by handle_system_call
@@ -496,6 +921,11 @@ impl<E: RuntimeExecutor> Runtime<E> {
                     return Ok(Some(StepResult::NeedsScript(script)));
                 }
             }
+            ChildContent::MacroInvocation(name) => {
+                // parsed stories are expanded, only a story built by hand can hold one
+                log::warn!("Macro invocation `@@{}` was not expanded, skipped", name);
+                true
+            }
         };
 
         if let Some(marker) = marker.as_ref() {
@@ -509,6 +939,120 @@ impl<E: RuntimeExecutor> Runtime<E> {
         })
     }
 
+    /// Emit the segments of a multi-speaker text line from `start` in order, the
+    /// tailing text goes with the last one. When the executor pauses before the last
+    /// segment, the next one is recorded in the current state and `None` is returned.
+    fn emit_segments(
+        &mut self,
+        segments: Vec<(LeadingText, Text)>,
+        tailing: TailingText,
+        start: usize,
+    ) -> Result<Option<bool>> {
+        let tailing = match tailing {
+            TailingText::None => None,
+            TailingText::Text(t) => Some(t),
+        };
+        let last = segments.len().saturating_sub(1);
+        for (index, (leading, text)) in segments.into_iter().enumerate().skip(start) {
+            let is_last = index == last;
+            let is_continue = self.emit_text(
+                leading,
+                text,
+                if is_last { tailing.as_deref() } else { None },
+            )?;
+            if is_last {
+                self.get_current_state_mut()?.segment = 0;
+                return Ok(Some(is_continue));
+            }
+            if !is_continue {
+                self.get_current_state_mut()?.segment = index + 1;
+                return Ok(None);
+            }
+        }
+        Ok(Some(true))
+    }
+
+    /// Resume the multi-speaker text line paused at `ExecutionState::segment`
+    fn resume_segments(&mut self) -> Result<Option<StepResult>> {
+        let state = self.get_current_state()?;
+        let (start, child) = (
+            state.segment,
+            state.block.children.get(state.index).cloned(),
+        );
+        let Some(Child {
+            attributes,
+            content: ChildContent::MultiTextLine(segments, tailing),
+            marker,
+        }) = child
+        else {
+            log::warn!("Paused text line not found, skipped");
+            self.get_current_state_mut()?.segment = 0;
+            return Ok(None);
+        };
+
+        let Some(is_continue) = self.emit_segments(segments, tailing, start)? else {
+            return Ok(Some(StepResult::Done));
+        };
+        // A looping line stays the current child to be evaluated again
+        if !is_looping(&attributes) {
+            self.get_current_state_mut()?.index += 1;
+        }
+        if let Some(marker) = marker.as_ref() {
+            self.executor.handle_marker(&mut self.context, marker)?;
+        }
+        Ok(if is_continue {
+            None
+        } else {
+            Some(StepResult::Done)
+        })
+    }
+
+    /// Resolve the templates of a text line and hand it to `handle_text`
+    fn emit_text(
+        &mut self,
+        leading: LeadingText,
+        text: Text,
+        tailing: Option<&str>,
+    ) -> Result<bool> {
+        let leading = match leading {
+            LeadingText::None => None,
+            LeadingText::Text(t) => Some(t),
+            LeadingText::TemplateLiteral(template_literal) => {
+                let text = self
+                    .executor
+                    .calculate_template_literal(&self.context, &template_literal)?;
+                Some(text)
+            }
+        };
+        let text = match text {
+            Text::None => None,
+            Text::Text(t) => Some(t),
+            Text::TemplateLiteral(template_literal) => {
+                let text = self
+                    .executor
+                    .calculate_template_literal(&self.context, &template_literal)?;
+                Some(text)
+            }
+        };
+        self.executor.handle_text(
+            &mut self.context,
+            leading.as_deref(),
+            text.as_deref(),
+            tailing,
+        )
+    }
+
+    /// Save the child for resumption after the condition has been evaluated.
+    /// The marker is emitted before yielding and stripped from the saved child,
+    /// so it is not emitted again on resume.
+    fn yield_for_condition(&mut self, mut child: Child) -> Result<()> {
+        if let Some(marker) = child.marker.take() {
+            self.executor.handle_marker(&mut self.context, &marker)?;
+        }
+        self.phase = StepPhase::AwaitingCondition { child };
+        Ok(())
+    }
+
     /// Provide the result of a condition evaluation after `step()` returned `NeedsCondition`.
     /// Call `step()` again after this to continue execution.
     pub fn resume_condition(&mut self, result: bool) {
@@ -523,29 +1067,149 @@ impl<E: RuntimeExecutor> Runtime<E> {
         self.script_result = Some((result, is_continue));
     }
 
+    /// Provide the value of an expression after `step()` returned `NeedsExpression`,
+    /// `None` when it has no value. Call `step()` again after this to continue execution.
+    pub fn resume_expression(&mut self, value: Option<Literal>) {
+        self.expression_result = Some(value);
+    }
+
     /// Provide story file data after `step()` returned `NeedsStoryFile`.
     /// The data will be parsed and added to the story list.
     /// Call `step()` again after this to continue execution.
     pub fn provide_story_data(&mut self, story_name: &str, data: Vec<u8>) -> Result<()> {
-        let text = String::from_utf8(data)
-            .map_err(|e| anyhow::anyhow!("Failed to parse story file: {}", e))?;
+        self.load_story_from_reader(story_name, data.as_slice())
+    }
 
-        let (_, story) = crate::parser::parse(story_name, &text).map_err(|e| {
-            anyhow::anyhow!(
-                "Failed to parse story file '{}': {}",
-                story_name,
-                e.to_string()
-            )
-        })?;
+    /// Parse story `story_name` from a byte stream with [`RuntimeExecutor::parse_story`]
+    /// and add it to the story list.
+    ///
+    /// With the default parser, fails with [`RuntimeError::ReadError`] on I/O errors,
+    /// invalid UTF-8 or a parse failure, in which case no story is added. A strict
+    /// runtime also rejects duplicate paragraphs, see `try_add_story`.
+    pub fn load_story_from_reader(
+        &mut self,
+        story_name: &str,
+        mut reader: impl std::io::Read,
+    ) -> Result<()> {
+        let mut data = Vec::new();
+        reader
+            .read_to_end(&mut data)
+            .map_err(crate::error::ReadError::from)?;
+        let story = self.executor.parse_story(story_name, &data)?;
+        self.try_add_story(story)
+    }
 
-        self.context.stories_mut().push(story);
-        Ok(())
+    /// Identity of the child being processed for `#[once]`: its story and paragraph
+    /// followed by its index in every nested block of the paragraph
+    fn current_child_identity(&self) -> Result<String> {
+        let stack = self.context.stack();
+        let top = stack.last().ok_or(RuntimeError::StoryNotStarted)?;
+        let entry = stack
+            .iter()
+            .rposition(|state| state.locals.is_some())
+            .unwrap_or(0);
+
+        let mut identity = format!("{}::{}", top.story, top.paragraph);
+        for (offset, state) in stack[entry..].iter().enumerate() {
+            // a state points past its current child, unless it is re-running a loop
+            let index = match stack.get(entry + offset + 1) {
+                Some(body) if body.is_loop_body => state.index,
+                _ => state.index.saturating_sub(1),
+            };
+            identity.push_str(&format!("/{}", index));
+        }
+        Ok(identity)
+    }
+
+    /// Push a state on the execution stack
+    fn push_frame(&mut self, state: ExecutionState) {
+        #[cfg(feature = "tracing")]
+        tracing::event!(
+            name: "frame_push",
+            target: "sixu::runtime",
+            tracing::Level::DEBUG,
+            story = %state.story,
+            paragraph = %state.paragraph,
+            loop_body = state.is_loop_body,
+            depth = self.context.stack().len() + 1,
+        );
+        self.context.stack_mut().push(state);
+    }
+
+    /// Pop the top state of the execution stack, notifying the executor when it is the
+    /// entry state of a paragraph
+    fn pop_frame(&mut self) -> Option<ExecutionState> {
+        let state = self.context.stack_mut().pop();
+        if let Some(state) = &state {
+            #[cfg(feature = "tracing")]
+            tracing::event!(
+                name: "frame_pop",
+                target: "sixu::runtime",
+                tracing::Level::DEBUG,
+                story = %state.story,
+                paragraph = %state.paragraph,
+                loop_body = state.is_loop_body,
+                depth = self.context.stack().len(),
+            );
+            if state.locals.is_some() {
+                self.executor
+                    .on_paragraph_exit(&mut self.context, &state.story, &state.paragraph);
+            }
+        }
+        state
+    }
+
+    /// Drop every state of the execution stack, leaving the paragraphs innermost first
+    fn clear_frames(&mut self) {
+        #[cfg(feature = "tracing")]
+        tracing::event!(
+            name: "frame_clear",
+            target: "sixu::runtime",
+            tracing::Level::DEBUG,
+            depth = self.context.stack().len(),
+        );
+        for state in std::mem::take(self.context.stack_mut()).into_iter().rev() {
+            if state.locals.is_some() {
+                self.executor
+                    .on_paragraph_exit(&mut self.context, &state.story, &state.paragraph);
+            }
+        }
+    }
+
+    /// Pop every state of the current paragraph and hand `value` (or `null`) to the
+    /// caller as the local [`RETURN_VALUE_LOCAL`]. Returns false without touching the
+    /// stack when the paragraph was not entered by `#call`, i.e. there is no caller below it.
+    fn return_from_paragraph(&mut self, value: Option<&Literal>) -> bool {
+        let stack = self.context.stack();
+        let entry = stack
+            .iter()
+            .rposition(|state| state.locals.is_some())
+            .unwrap_or(0);
+        if entry == 0 {
+            return false;
+        }
+
+        let value = value.cloned().unwrap_or(Literal::Null);
+        while self.context.stack().len() > entry {
+            self.pop_frame();
+        }
+
+        let stack = self.context.stack_mut();
+        let caller = stack
+            .iter()
+            .rposition(|state| state.locals.is_some())
+            .unwrap_or(0);
+        stack[caller]
+            .locals
+            .get_or_insert_with(Default::default)
+            .insert(RETURN_VALUE_LOCAL.to_string(), value);
+        true
     }
 
     /// Pop states from the stack until a loop body state is found and popped.
     /// Returns true if a loop body was found, false otherwise.
     fn pop_to_loop_body(&mut self) -> bool {
-        while let Some(state) = self.context.stack_mut().pop() {
+        while let Some(state) = self.pop_frame() {
             if state.is_loop_body {
                 return true;
             }
@@ -553,6 +1217,82 @@ impl<E: RuntimeExecutor> Runtime<E> {
         false
     }
 
+    /// Target story and paragraph of a `#goto`, `#call` or `#replace`. The story defaults
+    /// to the one currently running.
+    fn jump_target(&self, systemcall_line: &ResolvedSystemCallLine) -> Result<(String, String)> {
+        let string_argument = |name| match systemcall_line.get_argument(name) {
+            Some(Literal::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(RuntimeError::WrongArgumentSystemCallLine(
+                "Expected a string argument".to_string(),
+            )),
+            None => Ok(None),
+        };
+        let story_name = match string_argument("story")? {
+            Some(story_name) => story_name,
+            None => self.get_current_state()?.story.clone(),
+        };
+        let paragraph_name = string_argument("paragraph")?.ok_or_else(|| {
+            RuntimeError::WrongArgumentSystemCallLine("Paragraph name not provided".to_string())
+        })?;
+        Ok((story_name, paragraph_name))
+    }
+
+    /// Check the target of a `#goto` or `#replace` before it leaves the current paragraph,
+    /// so a missing paragraph or an unknown argument keeps the stack as it is. A target
+    /// in a story not loaded yet is only checked when it is entered.
+    fn check_jump(
+        &self,
+        story_name: &str,
+        paragraph_name: &str,
+        arguments: &[ResolvedArgument],
+    ) -> Result<()> {
+        if !self.has_story(story_name) {
+            return Ok(());
+        }
+        let paragraph = self.get_paragraph(story_name, paragraph_name)?;
+        check_arguments(paragraph_name, &paragraph.parameters, arguments)
+    }
+
+    /// Rewrite a system call line with the executor, resolve its arguments and handle it,
+    /// see `handle_system_call`
+    fn dispatch_system_call(&mut self, systemcall_line: SystemCallLine) -> Result<Option<bool>> {
+        let rewritten = self.executor.rewrite_system_call(&systemcall_line);
+        #[cfg(feature = "tracing")]
+        let is_rewritten = rewritten.is_some();
+        let systemcall_line = rewritten.unwrap_or(systemcall_line);
+
+        let arguments = match systemcall_line.command.as_str() {
+            "goto" | "call" | "replace" => {
+                self.resolve_jump_arguments(systemcall_line.arguments)?
+            }
+            _ => self.resolve_arguments(systemcall_line.arguments)?,
+        };
+        let systemcall_line = ResolvedSystemCallLine {
+            command: systemcall_line.command,
+            arguments,
+        };
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            target: "sixu::runtime",
+            "system_call",
+            command = %systemcall_line.command,
+        )
+        .entered();
+        #[cfg(feature = "tracing")]
+        tracing::event!(
+            name: "system_call",
+            target: "sixu::runtime",
+            tracing::Level::DEBUG,
+            command = %systemcall_line.command,
+            rewritten = is_rewritten,
+        );
+
+        self.executor
+            .on_system_call(&mut self.context, &systemcall_line);
+        self.handle_system_call(&systemcall_line)
+    }
+
     /// Handle system call line synchronously.
     /// Returns `Ok(Some(is_continue))` for normal completion, or `Ok(None)` when
     /// a story file needs to be loaded (phase set to `AwaitingStoryFile`).
@@ -562,161 +1302,77 @@ impl<E: RuntimeExecutor> Runtime<E> {
     ) -> Result<Option<bool>> {
         match systemcall_line.command.as_str() {
             "goto" => {
-                let story_name = match systemcall_line.get_argument("story") {
-                    Some(v) => {
-                        if v.is_string() {
-                            v.to_string()
-                        } else {
-                            return Err(RuntimeError::WrongArgumentSystemCallLine(
-                                "Expected a string argument".to_string(),
-                            ));
-                        }
-                    }
-                    None => self.get_current_state().unwrap().story.clone(),
-                };
+                let (story_name, paragraph_name) = self.jump_target(systemcall_line)?;
+                let arguments = paragraph_arguments(systemcall_line);
+                self.check_jump(&story_name, &paragraph_name, &arguments)?;
 
-                if let Some(paragraph_name) = systemcall_line.get_argument("paragraph") {
-                    let paragraph_name = if paragraph_name.is_string() {
-                        paragraph_name.to_string()
-                    } else {
-                        return Err(RuntimeError::WrongArgumentSystemCallLine(
-                            "Expected a string argument".to_string(),
-                        ));
-                    };
+                self.clear_frames();
 
-                    self.context.stack_mut().clear();
-
-                    if self.has_story(&story_name) {
-                        let paragraph = self.get_paragraph(&story_name, &paragraph_name)?.clone();
-                        self.context.stack_mut().push(ExecutionState::new(
-                            story_name,
-                            paragraph_name,
-                            paragraph.block,
-                        ));
-                    } else {
-                        self.phase = StepPhase::AwaitingStoryFile {
-                            story_name,
-                            paragraph_name,
-                        };
-                        return Ok(None);
-                    }
+                if self.has_story(&story_name) {
+                    self.enter_paragraph(story_name, paragraph_name, &arguments)?;
                 } else {
-                    return Err(RuntimeError::WrongArgumentSystemCallLine(
-                        "Paragraph name not provided".to_string(),
-                    ));
+                    self.phase = StepPhase::AwaitingStoryFile {
+                        story_name,
+                        paragraph_name,
+                        arguments,
+                    };
+                    return Ok(None);
                 }
 
                 Ok(Some(true))
             }
             "replace" => {
-                let story_name = match systemcall_line.get_argument("story") {
-                    Some(v) => {
-                        if v.is_string() {
-                            v.to_string()
-                        } else {
-                            return Err(RuntimeError::WrongArgumentSystemCallLine(
-                                "Expected a string argument".to_string(),
-                            ));
-                        }
-                    }
-                    None => self.get_current_state().unwrap().story.clone(),
-                };
-
-                if let Some(paragraph_name) = systemcall_line.get_argument("paragraph") {
-                    let paragraph_name = if paragraph_name.is_string() {
-                        paragraph_name.to_string()
-                    } else {
-                        return Err(RuntimeError::WrongArgumentSystemCallLine(
-                            "Expected a string argument".to_string(),
-                        ));
-                    };
+                let (story_name, paragraph_name) = self.jump_target(systemcall_line)?;
+                let arguments = paragraph_arguments(systemcall_line);
+                self.check_jump(&story_name, &paragraph_name, &arguments)?;
 
-                    let current_paragraph = self
-                        .context
-                        .stack_mut()
-                        .pop()
-                        .expect("No paragraph in stack to replace, this should not happen.");
+                let current_paragraph = self
+                    .pop_frame()
+                    .expect("No paragraph in stack to replace, this should not happen.");
 
-                    loop {
-                        if self.context.stack().is_empty() {
-                            break;
-                        }
-
-                        // pop the stack until the last state is not the same on story and paragraph
-                        // to remove all sub-blocks on the same paragraph
-                        let last_state = self.context.stack().last().unwrap();
-                        if last_state.story == current_paragraph.story
-                            && last_state.paragraph == current_paragraph.paragraph
-                        {
-                            self.context.stack_mut().pop();
-                        } else {
-                            break;
-                        }
+                loop {
+                    if self.context.stack().is_empty() {
+                        break;
                     }
 
-                    if self.has_story(&story_name) {
-                        let paragraph = self.get_paragraph(&story_name, &paragraph_name)?.clone();
-                        self.context.stack_mut().push(ExecutionState::new(
-                            story_name,
-                            paragraph_name,
-                            paragraph.block,
-                        ));
+                    // pop the stack until the last state is not the same on story and paragraph
+                    // to remove all sub-blocks on the same paragraph
+                    let last_state = self.context.stack().last().unwrap();
+                    if last_state.story == current_paragraph.story
+                        && last_state.paragraph == current_paragraph.paragraph
+                    {
+                        self.pop_frame();
                     } else {
-                        self.phase = StepPhase::AwaitingStoryFile {
-                            story_name,
-                            paragraph_name,
-                        };
-                        return Ok(None);
+                        break;
                     }
+                }
+
+                if self.has_story(&story_name) {
+                    self.enter_paragraph(story_name, paragraph_name, &arguments)?;
                 } else {
-                    return Err(RuntimeError::WrongArgumentSystemCallLine(
-                        "Paragraph name not provided".to_string(),
-                    ));
+                    self.phase = StepPhase::AwaitingStoryFile {
+                        story_name,
+                        paragraph_name,
+                        arguments,
+                    };
+                    return Ok(None);
                 }
 
                 Ok(Some(true))
             }
             "call" => {
-                let story_name = match systemcall_line.get_argument("story") {
-                    Some(v) => {
-                        if v.is_string() {
-                            v.to_string()
-                        } else {
-                            return Err(RuntimeError::WrongArgumentSystemCallLine(
-                                "Expected a string argument".to_string(),
-                            ));
-                        }
-                    }
-                    None => self.get_current_state().unwrap().story.clone(),
-                };
+                let (story_name, paragraph_name) = self.jump_target(systemcall_line)?;
 
-                if let Some(paragraph_name) = systemcall_line.get_argument("paragraph") {
-                    let paragraph_name = if paragraph_name.is_string() {
-                        paragraph_name.to_string()
-                    } else {
-                        return Err(RuntimeError::WrongArgumentSystemCallLine(
-                            "Expected a string argument".to_string(),
-                        ));
-                    };
-
-                    if self.has_story(&story_name) {
-                        let paragraph = self.get_paragraph(&story_name, &paragraph_name)?.clone();
-                        self.context.stack_mut().push(ExecutionState::new(
-                            story_name,
-                            paragraph_name,
-                            paragraph.block,
-                        ));
-                    } else {
-                        self.phase = StepPhase::AwaitingStoryFile {
-                            story_name,
-                            paragraph_name,
-                        };
-                        return Ok(None);
-                    }
+                let arguments = paragraph_arguments(systemcall_line);
+                if self.has_story(&story_name) {
+                    self.enter_paragraph(story_name, paragraph_name, &arguments)?;
                 } else {
-                    return Err(RuntimeError::WrongArgumentSystemCallLine(
-                        "Paragraph name not provided".to_string(),
-                    ));
+                    self.phase = StepPhase::AwaitingStoryFile {
+                        story_name,
+                        paragraph_name,
+                        arguments,
+                    };
+                    return Ok(None);
                 }
 
                 Ok(Some(true))
@@ -725,6 +1381,15 @@ impl<E: RuntimeExecutor> Runtime<E> {
                 self.break_current_block()?;
                 Ok(Some(true))
             }
+            "return" => {
+                if self.return_from_paragraph(systemcall_line.get_argument("value")) {
+                    return Ok(Some(true));
+                }
+                // Nothing to return to: a top-level paragraph ends the story like `#finish`
+                self.clear_frames();
+                self.executor.finished(&mut self.context);
+                Ok(Some(false))
+            }
             "break" => {
                 self.context.set_loop_control(LoopControl::Break);
                 Ok(Some(true))
@@ -734,7 +1399,7 @@ impl<E: RuntimeExecutor> Runtime<E> {
                 Ok(Some(true))
             }
             "finish" => {
-                self.context.stack_mut().clear();
+                self.clear_frames();
                 self.executor.finished(&mut self.context);
                 Ok(Some(false))
             }
@@ -745,3 +1410,201 @@ impl<E: RuntimeExecutor> Runtime<E> {
         }
     }
 }
+
+/// Fail with `UnknownParagraphArgument` when an argument matches no parameter of `paragraph`
+fn check_arguments(
+    paragraph: &str,
+    parameters: &[Parameter],
+    arguments: &[ResolvedArgument],
+) -> Result<()> {
+    match arguments
+        .iter()
+        .find(|arg| !parameters.iter().any(|p| p.name == arg.name))
+    {
+        Some(unknown) => Err(RuntimeError::UnknownParagraphArgument {
+            paragraph: paragraph.to_string(),
+            argument: unknown.name.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Arguments of a `#goto`/`#replace`/`#call` that are passed on to the target paragraph,
+/// i.e. everything but the `story` and `paragraph` targets.
+fn paragraph_arguments(systemcall_line: &ResolvedSystemCallLine) -> Vec<ResolvedArgument> {
+    systemcall_line
+        .arguments
+        .iter()
+        .filter(|arg| arg.name != "story" && arg.name != "paragraph")
+        .cloned()
+        .collect()
+}
+
+/// How control leaves a block when traced statically by [`Runtime::execution_order`]
+enum Flow {
+    /// The block runs to its end
+    FallThrough,
+    /// An unconditional jump to a literal target, `story` is None for the current story
+    Jump {
+        story: Option<String>,
+        paragraph: String,
+    },
+    /// The path is not deterministic from here
+    Stop,
+}
+
+fn trace_block(block: &Block, feature_flags: &HashSet<String>) -> Flow {
+    for child in &block.children {
+        if !is_included(&child.attributes, feature_flags) {
+            continue;
+        }
+        let guarded = child
+            .attributes
+            .iter()
+            .any(|attr| BUILTIN_ATTRIBUTES.contains(&attr.keyword.as_str()));
+        if guarded {
+            // a `switch` runs at most one of its cases, each is conditional like a branch
+            // of an `if` chain, and the other children of its block never run
+            let is_switch = child.attributes.iter().any(|attr| attr.keyword == "switch");
+            let may_jump = match &child.content {
+                ChildContent::Block(block) if is_switch => block
+                    .children
+                    .iter()
+                    .filter(|case| {
+                        case.attributes
+                            .iter()
+                            .any(|attr| attr.keyword == "case" || attr.keyword == "default")
+                    })
+                    .any(|case| contains_system_call(&case.content)),
+                content => contains_system_call(content),
+            };
+            if may_jump {
+                return Flow::Stop;
+            }
+            continue;
+        }
+
+        match &child.content {
+            ChildContent::Block(block) => match trace_block(block, feature_flags) {
+                Flow::FallThrough => {}
+                flow => return flow,
+            },
+            ChildContent::SystemCallLine(call) => match call.command.as_str() {
+                "goto" | "replace" => {
+                    let literal = |name| match call.get_argument(name) {
+                        Some(RValue::Literal(Literal::String(value))) => Some(Some(value.clone())),
+                        Some(_) => None,
+                        None => Some(None),
+                    };
+                    return match (literal("story"), literal("paragraph")) {
+                        (Some(story), Some(Some(paragraph))) => Flow::Jump { story, paragraph },
+                        _ => Flow::Stop,
+                    };
+                }
+                // leaving a block resumes its parent, leaving the paragraph falls through
+                "leave" => return Flow::FallThrough,
+                "call" | "return" | "finish" | "break" | "continue" => return Flow::Stop,
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    Flow::FallThrough
+}
+
+/// The block run for a `switch` whose expression evaluated to `value`: the first
+/// `case` child whose value equals `value`, else the first `default` child, else nothing.
+/// Other children of the switch block are ignored.
+fn select_case(block: &Block, value: Option<&Literal>) -> Block {
+    let is_case = |child: &&Child| {
+        child.attributes.iter().any(|attr| {
+            attr.keyword == "case"
+                && value.is_some()
+                && attr.condition.as_deref().map(case_value).as_ref() == value
+        })
+    };
+    let is_default = |child: &&Child| {
+        child
+            .attributes
+            .iter()
+            .any(|attr| attr.keyword == "default")
+    };
+    let Some(selected) = block
+        .children
+        .iter()
+        .find(is_case)
+        .or_else(|| block.children.iter().find(is_default))
+    else {
+        return Block {
+            label: None,
+            children: Vec::new(),
+        };
+    };
+
+    let mut selected = selected.clone();
+    selected
+        .attributes
+        .retain(|attr| attr.keyword != "case" && attr.keyword != "default");
+    match selected.content {
+        ChildContent::Block(block)
+            if selected.attributes.is_empty() && selected.marker.is_none() =>
+        {
+            block
+        }
+        _ => Block {
+            label: None,
+            children: vec![selected],
+        },
+    }
+}
+
+fn contains_system_call(content: &ChildContent) -> bool {
+    match content {
+        ChildContent::SystemCallLine(_) => true,
+        ChildContent::Block(block) => block
+            .descendants()
+            .any(|child| matches!(child.content, ChildContent::SystemCallLine(_))),
+        _ => false,
+    }
+}
+
+/// The value of a `case` attribute: a literal as written in arguments, so `2` is an
+/// integer and `"2"` a string, or a string of the text itself when it is none, e.g. `calm`
+fn case_value(text: &str) -> Literal {
+    match crate::parser::primitive::primitive(text.trim()) {
+        Ok(("", literal)) => literal,
+        _ => Literal::String(text.to_string()),
+    }
+}
+
+/// Whether a child with `attributes` that has run is a loop, i.e. its control attribute
+/// is `loop` or a `while` with a condition
+fn is_looping(attributes: &[Attribute]) -> bool {
+    attributes
+        .iter()
+        .rfind(|attr| BUILTIN_ATTRIBUTES.contains(&attr.keyword.as_str()))
+        .is_some_and(|attr| {
+            attr.keyword == "loop" || (attr.keyword == "while" && attr.condition.is_some())
+        })
+}
+
+/// Whether `attributes` contain [`ONCE_ATTRIBUTE`]
+fn has_once(attributes: &[Attribute]) -> bool {
+    attributes.iter().any(|attr| attr.keyword == ONCE_ATTRIBUTE)
+}
+
+/// Whether content with `attributes` runs under `feature_flags`: untagged content
+/// always does, tagged content when any of its `platform` flags is active.
+fn is_included(attributes: &[Attribute], feature_flags: &HashSet<String>) -> bool {
+    let mut flags = attributes
+        .iter()
+        .filter(|attr| attr.keyword == PLATFORM_ATTRIBUTE)
+        .peekable();
+    flags.peek().is_none()
+        || flags.any(|attr| {
+            attr.condition
+                .as_ref()
+                .is_some_and(|flag| feature_flags.contains(flag))
+        })
+}
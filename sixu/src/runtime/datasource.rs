@@ -1,4 +1,9 @@
-use crate::format::{Literal, Story};
+use std::any::Any;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::error::{Result, RuntimeError};
+use crate::format::{Attribute, Literal, Story, Variable};
 
 use super::ExecutionState;
 
@@ -24,6 +29,12 @@ pub struct RuntimeContext {
     global_variables: Literal,
     /// Pending loop control signal
     loop_control: Option<LoopControl>,
+    /// Custom attributes of the child being processed
+    current_attributes: Vec<Attribute>,
+    /// Embedder state, see `set_user_data`
+    user_data: Option<Arc<dyn Any + Send + Sync>>,
+    /// Identities of the `#[once]` paragraphs and children already run
+    visited_once: BTreeSet<String>,
 }
 
 impl Default for RuntimeContext {
@@ -34,6 +45,9 @@ impl Default for RuntimeContext {
             archive_variables: Literal::Object(Default::default()),
             global_variables: Literal::Object(Default::default()),
             loop_control: None,
+            current_attributes: Vec::new(),
+            user_data: None,
+            visited_once: BTreeSet::new(),
         }
     }
 }
@@ -75,6 +89,69 @@ impl RuntimeContext {
         &mut self.global_variables
     }
 
+    /// Look up a local variable in the scope of the current paragraph
+    pub fn get_local(&self, name: &str) -> Option<&Literal> {
+        self.stack
+            .iter()
+            .rev()
+            .find_map(|state| state.locals.as_ref())
+            .and_then(|locals| locals.get(name))
+    }
+
+    /// Look up a variable by name among the locals of the current paragraph, then the
+    /// archive and the global variables. Fails with `NotAObject` when the archive
+    /// variables are not an object, global variables which are not read as null.
+    pub(crate) fn lookup_variable(&self, name: &str) -> Result<Option<&Literal>> {
+        if let Some(value) = self.get_local(name) {
+            return Ok(Some(value));
+        }
+        if let Some(value) = self.archive_variables.as_object()?.get(name) {
+            return Ok(Some(value));
+        }
+        Ok(match self.global_variables.as_object() {
+            Ok(variables) => variables.get(name),
+            Err(_) => Some(&Literal::Null),
+        })
+    }
+
+    /// Look up a variable chain like `a.b.c`: the first name like
+    /// `RuntimeExecutor::get_variable` does, and each following name as a key of the
+    /// object reached so far.
+    ///
+    /// Fails with `VariableNotFound` (carrying the chain up to the missing name) when a
+    /// name is missing, and with `NotAObject` when the archive variables or a value on the
+    /// way is not an object.
+    pub fn resolve_variable(&self, variable: &Variable) -> Result<&Literal> {
+        let not_found =
+            |depth: usize| RuntimeError::VariableNotFound(variable.chain[..depth].join("."));
+        let (name, keys) = variable.chain.split_first().ok_or_else(|| not_found(0))?;
+        let mut value = self.lookup_variable(name)?.ok_or_else(|| not_found(1))?;
+        for (depth, key) in keys.iter().enumerate() {
+            value = value
+                .as_object()?
+                .get(key)
+                .ok_or_else(|| not_found(depth + 2))?;
+        }
+        Ok(value)
+    }
+
+    /// Set a local variable in the scope of the current paragraph, which is discarded
+    /// when the paragraph returns. Returns false when no paragraph is running.
+    pub fn set_local(&mut self, name: &str, value: Literal) -> bool {
+        match self
+            .stack
+            .iter_mut()
+            .rev()
+            .find_map(|state| state.locals.as_mut())
+        {
+            Some(locals) => {
+                locals.insert(name.to_string(), value);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Set a loop control signal
     pub fn set_loop_control(&mut self, control: LoopControl) {
         self.loop_control = Some(control);
@@ -84,4 +161,55 @@ impl RuntimeContext {
     pub fn take_loop_control(&mut self) -> Option<LoopControl> {
         self.loop_control.take()
     }
+
+    /// Attributes attached to the child being processed which are not consumed by
+    /// the runtime, i.e. everything except the
+    /// [`BUILTIN_ATTRIBUTES`](super::BUILTIN_ATTRIBUTES),
+    /// [`PLATFORM_ATTRIBUTE`](super::PLATFORM_ATTRIBUTE) and
+    /// [`ONCE_ATTRIBUTE`](super::ONCE_ATTRIBUTE)
+    pub fn current_attributes(&self) -> &[Attribute] {
+        &self.current_attributes
+    }
+
+    /// Get a custom attribute of the child being processed by keyword
+    pub fn current_attribute(&self, keyword: &str) -> Option<&Attribute> {
+        self.current_attributes
+            .iter()
+            .find(|attr| attr.keyword == keyword)
+    }
+
+    /// Attach shared state of the embedder, e.g. asset handles or an audio mixer, so
+    /// executors can reach it from any handler. Replaces the previous user data.
+    ///
+    /// User data is ephemeral: it is never part of save data, and clones of the context
+    /// share the same value, so state that changes needs interior mutability.
+    pub fn set_user_data<T: Any + Send + Sync>(&mut self, data: T) {
+        self.user_data = Some(Arc::new(data));
+    }
+
+    /// The user data, if it was set with type `T`
+    pub fn user_data<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.user_data.as_deref()?.downcast_ref()
+    }
+
+    /// Detach the user data
+    pub fn clear_user_data(&mut self) {
+        self.user_data = None;
+    }
+
+    /// Identities of the `#[once]` paragraphs and children already run in this session.
+    ///
+    /// A paragraph is identified as `story::paragraph`, a child as the path of its
+    /// indices in the nested blocks of its paragraph, e.g. `story::paragraph/2/0`.
+    pub fn visited_once(&self) -> &BTreeSet<String> {
+        &self.visited_once
+    }
+
+    pub fn visited_once_mut(&mut self) -> &mut BTreeSet<String> {
+        &mut self.visited_once
+    }
+
+    pub(crate) fn set_current_attributes(&mut self, attributes: Vec<Attribute>) {
+        self.current_attributes = attributes;
+    }
 }
@@ -1,8 +1,11 @@
-use crate::error::Result;
+use crate::error::{Result, RuntimeError};
 use crate::format::*;
 
 use super::RuntimeContext;
 
+/// Default value of `RuntimeExecutor::template_recursion_limit`
+pub const DEFAULT_TEMPLATE_RECURSION_LIMIT: usize = 32;
+
 /// Trait defining the executor behavior for runtime execution
 pub trait RuntimeExecutor: Send + Sync {
     /// Handle a marker event after a marked child has finished processing.
@@ -20,6 +23,24 @@ pub trait RuntimeExecutor: Send + Sync {
         ctx: &mut RuntimeContext,
         command_line: &ResolvedCommandLine,
     ) -> Result<bool>;
+    /// Rewrite a system call before it is dispatched, e.g. to alias engine specific sugar
+    /// like `#jumpScene` into a built-in `#goto`. Returning `None` handles the call as is.
+    ///
+    /// Calls are rewritten before their arguments are resolved, so a call rewritten into
+    /// `#goto`, `#call` or `#replace` resolves its target like the built-in written out.
+    fn rewrite_system_call(&self, _systemcall_line: &SystemCallLine) -> Option<SystemCallLine> {
+        None
+    }
+
+    /// Called for every system call, built-in ones included, once it is rewritten and its
+    /// arguments are resolved, right before it is dispatched
+    fn on_system_call(
+        &mut self,
+        _ctx: &mut RuntimeContext,
+        _systemcall_line: &ResolvedSystemCallLine,
+    ) {
+    }
+
     /// Handle an extra system call line input, returns true if next line should be executed immediately
     fn handle_extra_system_call(
         &mut self,
@@ -37,6 +58,22 @@ pub trait RuntimeExecutor: Send + Sync {
     /// Called when the scenario execution is finished
     fn finished(&mut self, ctx: &mut RuntimeContext);
 
+    /// Called after a paragraph is entered by `start`, `#goto`, `#call`, `#replace` or by
+    /// running on into the next paragraph, once its parameters are bound
+    fn on_paragraph_enter(&mut self, _ctx: &mut RuntimeContext, _story: &str, _paragraph: &str) {}
+
+    /// Called after a paragraph is left, either by running to its end, `#return`, or by
+    /// `#goto`, `#replace`, `#finish` and `terminate` dropping it from the stack
+    fn on_paragraph_exit(&mut self, _ctx: &mut RuntimeContext, _story: &str, _paragraph: &str) {}
+
+    /// Turn the data of story file `name` into a story, see `Runtime::provide_story_data`.
+    ///
+    /// The default parses the data as UTF-8 source text. Override it to preprocess the
+    /// data, e.g. to decrypt it or to load a precompiled story.
+    fn parse_story(&self, name: &str, data: &[u8]) -> Result<Story> {
+        Ok(crate::parser::parse_story_from_reader(name, data)?)
+    }
+
     /// Helper method to get variable value from context
     ///
     /// NOTE: This is a default implementation and should not be overridden in most cases
@@ -46,19 +83,9 @@ pub trait RuntimeExecutor: Send + Sync {
         value: &'a Variable,
     ) -> Result<&'a Literal> {
         if value.chain.len() == 1 {
-            let name = &value.chain[0];
-            let v = ctx
-                .archive_variables()
-                .as_object()?
-                .get(name)
-                .or_else(|| {
-                    ctx.global_variables()
-                        .as_object()
-                        .map(|o| o.get(name))
-                        .unwrap_or_else(|_| Some(&Literal::Null))
-                })
-                .unwrap_or(&Literal::Null);
-            Ok(v)
+            Ok(ctx
+                .lookup_variable(&value.chain[0])?
+                .unwrap_or(&Literal::Null))
         } else {
             log::warn!(
                 "Variable chain with more than one element is not supported: {:?}",
@@ -68,6 +95,11 @@ pub trait RuntimeExecutor: Send + Sync {
         }
     }
 
+    /// Maximum nesting depth of template interpolation, see `interpolate_value`
+    fn template_recursion_limit(&self) -> usize {
+        DEFAULT_TEMPLATE_RECURSION_LIMIT
+    }
+
     /// Helper method to calculate template literal from context
     ///
     /// NOTE: This is a default implementation and should not be overridden in most cases
@@ -76,14 +108,47 @@ pub trait RuntimeExecutor: Send + Sync {
         ctx: &'a RuntimeContext,
         template: &'a crate::format::TemplateLiteral,
     ) -> Result<String> {
-        let text = template
-            .parts
-            .iter()
-            .map(|part| match part {
-                crate::format::TemplateLiteralPart::Text(text) => text.to_owned(),
+        self.calculate_template_literal_at_depth(ctx, template, 0)
+    }
+
+    /// Helper method to calculate a template literal nested `depth` levels deep in
+    /// interpolation, failing with `TemplateRecursionLimit` once the limit is exceeded.
+    ///
+    /// NOTE: This is a default implementation and should not be overridden in most cases
+    fn calculate_template_literal_at_depth(
+        &self,
+        ctx: &RuntimeContext,
+        template: &crate::format::TemplateLiteral,
+        depth: usize,
+    ) -> Result<String> {
+        let limit = self.template_recursion_limit();
+        if depth > limit {
+            return Err(RuntimeError::TemplateRecursionLimit(limit));
+        }
+
+        let mut text = String::new();
+        self.render_template_parts(ctx, &template.parts, depth, &mut text)?;
+        Ok(text)
+    }
+
+    /// Helper method to render template parts into `text`, choosing the branch of
+    /// each conditional span by the truthiness of its condition.
+    ///
+    /// NOTE: This is a default implementation and should not be overridden in most cases
+    fn render_template_parts(
+        &self,
+        ctx: &RuntimeContext,
+        parts: &[crate::format::TemplateLiteralPart],
+        depth: usize,
+        text: &mut String,
+    ) -> Result<()> {
+        for part in parts {
+            match part {
+                crate::format::TemplateLiteralPart::Text(t) => text.push_str(t),
                 crate::format::TemplateLiteralPart::Value(value) => {
-                    match self.get_rvalue(ctx, value) {
-                        Ok(v) => v.to_string(),
+                    match self.interpolate_value(ctx, value, depth) {
+                        Ok(v) => text.push_str(&v),
+                        Err(err @ RuntimeError::TemplateRecursionLimit(_)) => return Err(err),
                         Err(err) => {
                             log::error!(
                                 "Failed to get rvalue from template literal: {:?}.\
@@ -91,13 +156,47 @@ pub trait RuntimeExecutor: Send + Sync {
                                 value,
                                 err
                             );
-                            "[Error]".to_string()
+                            text.push_str("[Error]");
                         }
                     }
                 }
-            })
-            .collect::<String>();
-        Ok(text)
+                crate::format::TemplateLiteralPart::Conditional {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    let truthy = match self.get_rvalue(ctx, condition) {
+                        Ok(value) => value.is_truthy(),
+                        Err(err) => {
+                            log::error!(
+                                "Failed to evaluate template condition: {:?}. Error: {:?}",
+                                condition,
+                                err
+                            );
+                            false
+                        }
+                    };
+                    let branch = if truthy { then } else { otherwise };
+                    self.render_template_parts(ctx, branch, depth, text)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert an interpolated value of a template literal at `depth` into text.
+    ///
+    /// Executors whose values may themselves be templates can override this and expand
+    /// them with `calculate_template_literal_at_depth(ctx, nested, depth + 1)`, so that
+    /// self-referential templates stop at `template_recursion_limit` instead of
+    /// overflowing the stack.
+    fn interpolate_value(
+        &self,
+        ctx: &RuntimeContext,
+        value: &RValue,
+        _depth: usize,
+    ) -> Result<String> {
+        Ok(self.get_rvalue(ctx, value)?.to_string())
     }
 
     /// Helper method to get RValue from context
@@ -109,4 +208,17 @@ pub trait RuntimeExecutor: Send + Sync {
             RValue::Variable(v) => self.get_variable(ctx, v),
         }
     }
+
+    /// Helper method to resolve an RValue into a concrete literal, following variable
+    /// chains like `a.b.c` through nested objects with `RuntimeContext::resolve_variable`.
+    /// Unlike `get_rvalue`, a missing variable is an error instead of null, so the result
+    /// can be read as a typed value with `as_integer`, `as_boolean` and so on.
+    ///
+    /// NOTE: This is a default implementation and should not be overridden in most cases
+    fn resolve_literal(&self, ctx: &RuntimeContext, value: &RValue) -> Result<Literal> {
+        match value {
+            RValue::Literal(literal) => Ok(literal.clone()),
+            RValue::Variable(variable) => ctx.resolve_variable(variable).cloned(),
+        }
+    }
 }
@@ -1,7 +1,9 @@
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::format::{Block, Child};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::format::{Block, Child, Literal};
 
 /// Represents a state in the stack of the runtime.
 #[derive(Debug, Default, Clone)]
@@ -21,9 +23,21 @@ pub struct ExecutionState {
     /// Whether this state is the body of a loop (while/loop attribute).
     /// Used by `#break` and `#continue` to find the loop boundary.
     pub is_loop_body: bool,
+    /// Local variables of the paragraph entered by this state, e.g. bound parameters.
+    /// Only paragraph entry states own a scope, sub-block states keep `None` and
+    /// share the scope of the closest paragraph entry below them in the stack.
+    pub locals: Option<HashMap<String, Literal>>,
+    /// Outcome of the `if`/`elif` chain opened by the last child of this block:
+    /// `Some(true)` once one of its branches has run, `Some(false)` while none has,
+    /// and `None` when the last child did not open or continue a chain.
+    pub branch_matched: Option<bool>,
+    /// Next segment to emit of the multi-speaker text line the executor paused in.
+    /// `0` when no line is paused, otherwise the paused line is the child at `index`.
+    pub segment: usize,
 }
 
 impl ExecutionState {
+    /// Create the entry state of a paragraph, which owns a fresh local scope
     pub fn new(story: String, paragraph: String, block: Block) -> Self {
         Self {
             story,
@@ -31,6 +45,23 @@ impl ExecutionState {
             block,
             index: 0,
             is_loop_body: false,
+            locals: Some(HashMap::new()),
+            branch_matched: None,
+            segment: 0,
+        }
+    }
+
+    /// Create the state of a sub-block inside the current paragraph
+    pub fn new_block(story: String, paragraph: String, block: Block) -> Self {
+        Self {
+            story,
+            paragraph,
+            block,
+            index: 0,
+            is_loop_body: false,
+            locals: None,
+            branch_matched: None,
+            segment: 0,
         }
     }
 
@@ -41,11 +72,70 @@ impl ExecutionState {
             block,
             index: 0,
             is_loop_body: true,
+            locals: None,
+            branch_matched: None,
+            segment: 0,
         }
     }
+
     pub fn next_line(&mut self) -> Option<Child> {
         let line = self.block.children.get(self.index).cloned();
         self.index += 1;
         line
     }
 }
+
+/// Snapshot of a runtime for save archives, see `Runtime::save`.
+///
+/// Archives written before `SaveState` existed hold the bare execution stack, they
+/// still deserialize, with nothing visited.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SaveState {
+    /// Execution stack
+    pub stack: Vec<ExecutionState>,
+    /// Identities of the `#[once]` paragraphs and children already run,
+    /// see `RuntimeContext::visited_once`
+    pub visited_once: BTreeSet<String>,
+}
+
+/// Restore a stack saved before `SaveState` existed, with nothing visited
+impl From<Vec<ExecutionState>> for SaveState {
+    fn from(stack: Vec<ExecutionState>) -> Self {
+        Self {
+            stack,
+            visited_once: BTreeSet::new(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for SaveState {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// The fields of `SaveState`, missing ones take their defaults
+        #[derive(Default, Deserialize)]
+        #[serde(default)]
+        struct Fields {
+            stack: Vec<ExecutionState>,
+            visited_once: BTreeSet<String>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Archive {
+            Stack(Vec<ExecutionState>),
+            State(Fields),
+        }
+
+        Ok(match Archive::deserialize(deserializer)? {
+            Archive::Stack(stack) => stack.into(),
+            Archive::State(Fields {
+                stack,
+                visited_once,
+            }) => Self {
+                stack,
+                visited_once,
+            },
+        })
+    }
+}
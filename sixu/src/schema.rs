@@ -0,0 +1,379 @@
+//! Command schema of a project, the `commands.schema.json` the language server checks
+//! command calls against, and [`SchemaBuilder`] to define it in code.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::error::SchemaError;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct CommandSchema {
+    #[serde(rename = "oneOf")]
+    pub commands: Vec<CommandDefinition>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct CommandDefinition {
+    pub description: Option<String>,
+    pub properties: HashMap<String, Property>,
+    pub required: Option<Vec<String>>,
+    /// File implementing the command, e.g. its handler in the engine. A relative path is
+    /// relative to the directory of the schema file
+    #[serde(rename = "definitionPath")]
+    pub definition_path: Option<String>,
+    /// Line of the implementation in `definition_path` (1-based)
+    #[serde(rename = "definitionLine")]
+    pub definition_line: Option<u32>,
+}
+
+impl CommandDefinition {
+    pub fn get_command_name(&self) -> Option<String> {
+        self.properties
+            .get("command")
+            .and_then(|p| p.const_value.clone())
+    }
+
+    /// Parameters of the command, without `command`. Required ones come first in the
+    /// order of `required`, the others follow sorted by name
+    pub fn parameters(&self) -> Vec<(&String, &Property)> {
+        let required = self.required.as_deref().unwrap_or_default();
+        let mut parameters: Vec<_> = self
+            .properties
+            .iter()
+            .filter(|(name, _)| *name != "command")
+            .collect();
+        parameters.sort_by_key(|(name, _)| {
+            let position = required.iter().position(|r| r == *name);
+            (position.unwrap_or(usize::MAX), name.as_str())
+        });
+        parameters
+    }
+}
+
+impl Property {
+    /// Type name for display, union types joined by `|` and `any` when no type is declared
+    pub fn type_label(&self) -> String {
+        match &self.type_ {
+            Some(StringOrArray::String(type_)) => type_.clone(),
+            Some(StringOrArray::Array(types)) => types.join(" | "),
+            None => "any".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct Property {
+    #[serde(rename = "type")]
+    pub type_: Option<StringOrArray>,
+    pub description: Option<String>,
+    #[serde(rename = "const")]
+    pub const_value: Option<String>,
+    #[serde(rename = "enum")]
+    #[allow(dead_code)]
+    pub enum_values: Option<Vec<String>>,
+    pub default: Option<serde_json::Value>,
+    /// Format of the value, e.g. `duration`, checked by the value parser registered for it,
+    /// see [`ValueParsers`](crate::ValueParsers)
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum StringOrArray {
+    String(String),
+    Array(Vec<String>),
+}
+
+/// Builds a [`CommandSchema`] in code, for engines generating it from their command
+/// registry. The result equals the schema loaded from a `commands.schema.json` with the
+/// same content.
+///
+/// ```
+/// use sixu::schema::SchemaBuilder;
+///
+/// let schema = SchemaBuilder::new()
+///     .command("changebg", |c| {
+///         c.required("src")
+///             .string("src")
+///             .number("fadeTime")
+///             .default(600)
+///     })
+///     .build()
+///     .unwrap();
+/// assert_eq!(schema.commands[0].get_command_name().as_deref(), Some("changebg"));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct SchemaBuilder {
+    commands: Vec<CommandDefinition>,
+    /// First misuse of a [`CommandBuilder`], reported by `build`
+    error: Option<SchemaError>,
+}
+
+impl SchemaBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the command `name`, declaring its parameters in `define`
+    pub fn command(
+        mut self,
+        name: impl Into<String>,
+        define: impl FnOnce(CommandBuilder) -> CommandBuilder,
+    ) -> Self {
+        let command = define(CommandBuilder::new(name.into()));
+        if self.error.is_none() {
+            self.error = command.error;
+        }
+        self.commands.push(command.definition);
+        self
+    }
+
+    /// Fails with the first misuse of a [`CommandBuilder`], e.g. a `default` before any
+    /// parameter was declared
+    pub fn build(self) -> Result<CommandSchema, SchemaError> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(CommandSchema {
+                commands: self.commands,
+            }),
+        }
+    }
+}
+
+/// Definition of one command, see [`SchemaBuilder::command`].
+///
+/// `description`, `default`, `format` and `enum_values` apply to the last declared
+/// parameter.
+#[derive(Debug, Clone)]
+pub struct CommandBuilder {
+    definition: CommandDefinition,
+    /// Last declared parameter
+    last: Option<String>,
+    /// First method called before any parameter that needs one
+    error: Option<SchemaError>,
+}
+
+impl CommandBuilder {
+    fn new(name: String) -> Self {
+        let command = Property {
+            type_: Some(StringOrArray::String("string".to_string())),
+            const_value: Some(name),
+            ..Default::default()
+        };
+        Self {
+            definition: CommandDefinition {
+                description: None,
+                properties: HashMap::from([("command".to_string(), command)]),
+                required: Some(vec!["command".to_string()]),
+                definition_path: None,
+                definition_line: None,
+            },
+            last: None,
+            error: None,
+        }
+    }
+
+    /// Mark the parameter `name` as required
+    pub fn required(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        let required = self.definition.required.get_or_insert_with(Vec::new);
+        if !required.contains(&name) {
+            required.push(name);
+        }
+        self
+    }
+
+    /// Declare the parameter `name`, replacing a previous one of the same name
+    pub fn property(mut self, name: impl Into<String>, property: Property) -> Self {
+        let name = name.into();
+        self.definition.properties.insert(name.clone(), property);
+        self.last = Some(name);
+        self
+    }
+
+    /// Declare a parameter of type `type_`, e.g. `"string"` or `"number"`
+    pub fn typed(self, name: impl Into<String>, type_: &str) -> Self {
+        self.property(
+            name,
+            Property {
+                type_: Some(StringOrArray::String(type_.to_string())),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Declare a parameter of any of `types`
+    pub fn union(self, name: impl Into<String>, types: &[&str]) -> Self {
+        self.property(
+            name,
+            Property {
+                type_: Some(StringOrArray::Array(
+                    types.iter().map(|t| t.to_string()).collect(),
+                )),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Declare a parameter of any type
+    pub fn any(self, name: impl Into<String>) -> Self {
+        self.property(name, Property::default())
+    }
+
+    pub fn string(self, name: impl Into<String>) -> Self {
+        self.typed(name, "string")
+    }
+
+    pub fn number(self, name: impl Into<String>) -> Self {
+        self.typed(name, "number")
+    }
+
+    pub fn integer(self, name: impl Into<String>) -> Self {
+        self.typed(name, "integer")
+    }
+
+    pub fn boolean(self, name: impl Into<String>) -> Self {
+        self.typed(name, "boolean")
+    }
+
+    pub fn array(self, name: impl Into<String>) -> Self {
+        self.typed(name, "array")
+    }
+
+    pub fn object(self, name: impl Into<String>) -> Self {
+        self.typed(name, "object")
+    }
+
+    /// Description of the last declared parameter, or of the command before any
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        let description = Some(description.into());
+        match self.last_property() {
+            Some(property) => property.description = description,
+            None => self.definition.description = description,
+        }
+        self
+    }
+
+    /// File and line (1-based) implementing the command, to go from a call to it
+    pub fn definition(mut self, path: impl Into<String>, line: u32) -> Self {
+        self.definition.definition_path = Some(path.into());
+        self.definition.definition_line = Some(line);
+        self
+    }
+
+    /// Default value of the last declared parameter
+    pub fn default(mut self, value: impl Into<serde_json::Value>) -> Self {
+        if let Some(property) = self.expect_last_property("default") {
+            property.default = Some(value.into());
+        }
+        self
+    }
+
+    /// Format of the value of the last declared parameter, checked by the value parser
+    /// registered for it
+    pub fn format(mut self, format: impl Into<String>) -> Self {
+        if let Some(property) = self.expect_last_property("format") {
+            property.format = Some(format.into());
+        }
+        self
+    }
+
+    /// Allowed values of the last declared parameter
+    pub fn enum_values(mut self, values: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        if let Some(property) = self.expect_last_property("enum_values") {
+            property.enum_values = Some(values.into_iter().map(Into::into).collect());
+        }
+        self
+    }
+
+    fn last_property(&mut self) -> Option<&mut Property> {
+        let last = self.last.as_ref()?;
+        self.definition.properties.get_mut(last)
+    }
+
+    /// The last declared parameter, recording a [`SchemaError::MissingParameter`] for
+    /// `method` when there is none
+    fn expect_last_property(&mut self, method: &'static str) -> Option<&mut Property> {
+        if self.last.is_none() && self.error.is_none() {
+            self.error = Some(SchemaError::MissingParameter {
+                command: self.definition.get_command_name().unwrap_or_default(),
+                method,
+            });
+        }
+        self.last_property()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_matches_json() {
+        let built = SchemaBuilder::new()
+            .command("changebg", |c| {
+                c.description("Change background")
+                    .required("src")
+                    .string("src")
+                    .description("image source")
+                    .number("fadeTime")
+                    .default(600)
+                    .boolean("skippable")
+            })
+            .command("fade", |c| {
+                c.definition("src/commands/fade.ts", 12)
+                    .string("time")
+                    .format("duration")
+                    .string("mode")
+                    .enum_values(["in", "out"])
+                    .union("target", &["string", "array"])
+                    .any("extra")
+            })
+            .build()
+            .unwrap();
+
+        let json: CommandSchema = serde_json::from_str(
+            r#"{
+              "oneOf": [
+                {
+                  "description": "Change background",
+                  "properties": {
+                    "command": { "type": "string", "const": "changebg" },
+                    "src": { "type": "string", "description": "image source" },
+                    "fadeTime": { "type": "number", "default": 600 },
+                    "skippable": { "type": "boolean" }
+                  },
+                  "required": ["command", "src"]
+                },
+                {
+                  "properties": {
+                    "command": { "type": "string", "const": "fade" },
+                    "time": { "type": "string", "format": "duration" },
+                    "mode": { "type": "string", "enum": ["in", "out"] },
+                    "target": { "type": ["string", "array"] },
+                    "extra": {}
+                  },
+                  "required": ["command"],
+                  "definitionPath": "src/commands/fade.ts",
+                  "definitionLine": 12
+                }
+              ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(built, json);
+    }
+
+    #[test]
+    fn test_builder_default_without_parameter() {
+        let error = SchemaBuilder::new()
+            .command("wait", |c| c.default(1).number("time"))
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`default` of command wait must follow a parameter declaration"
+        );
+    }
+}
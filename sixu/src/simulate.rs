@@ -0,0 +1,224 @@
+//! Dry runs of a story, listing what a runtime would emit without an engine.
+//!
+//! [`Story::simulate`] drives a real [`Runtime`] with a recording executor, so the
+//! control flow is exactly the one of the interpreter: attribute conditions are
+//! answered by an oracle, embedded code is skipped, and every text line, command and
+//! system call is recorded in order. Variables are not tracked, so unknown variables
+//! resolve to `null` like they do in a fresh runtime.
+//!
+//! [`Runtime::record_events`] runs the same simulation over the stories of a runtime
+//! and also records paragraph boundaries, giving a stable, diffable event list for
+//! golden tests of engines.
+
+use std::sync::Mutex;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::format::{ResolvedCommandLine, ResolvedSystemCallLine, Story, SystemCallLine};
+use crate::runtime::{Runtime, RuntimeContext, RuntimeExecutor, StepResult};
+
+/// Maximum number of events and condition evaluations of a simulation, which caps
+/// loops whose conditions the oracle never turns false
+pub const SIMULATION_STEP_LIMIT: usize = 10_000;
+
+/// An observable event of a simulated run
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SimEvent {
+    Text {
+        leading: Option<String>,
+        text: Option<String>,
+        tailing: Option<String>,
+    },
+    Command(ResolvedCommandLine),
+    /// Any system call, including built-in ones such as `#goto`
+    SystemCall(ResolvedSystemCallLine),
+    /// A paragraph was entered, only recorded by [`Runtime::record_events`]
+    EnterParagraph {
+        story: String,
+        paragraph: String,
+    },
+    /// A paragraph was left, only recorded by [`Runtime::record_events`]
+    LeaveParagraph {
+        story: String,
+        paragraph: String,
+    },
+}
+
+impl Story {
+    /// Run this story from paragraph `entry` without an engine, answering every
+    /// attribute condition with `condition_oracle`, and return the emitted events.
+    /// Scripts and expressions are not evaluated, so every `case` of a `switch` is taken
+    /// as not matching and only its `default` runs.
+    ///
+    /// The simulation ends when the story finishes, when a jump needs a story that is
+    /// not this one, on a runtime error, or after [`SIMULATION_STEP_LIMIT`] steps.
+    pub fn simulate(&self, entry: &str, condition_oracle: impl Fn(&str) -> bool) -> Vec<SimEvent> {
+        let mut runtime = Runtime::new(SimExecutor::default());
+        runtime.add_story(self.clone());
+        run_simulation(runtime, &self.name, entry, condition_oracle)
+    }
+}
+
+impl<E: RuntimeExecutor> Runtime<E> {
+    /// Run paragraph `paragraph` of story `story` on a copy of the loaded stories
+    /// without an engine, like [`Story::simulate`], and return the emitted events
+    /// including [`SimEvent::EnterParagraph`] and [`SimEvent::LeaveParagraph`].
+    ///
+    /// Argument spans are dropped so that the events only change when the behavior
+    /// of the story does. The state of this runtime is left untouched.
+    pub fn record_events(
+        &self,
+        story: &str,
+        paragraph: &str,
+        condition_oracle: impl Fn(&str) -> bool,
+    ) -> Vec<SimEvent> {
+        let executor = SimExecutor {
+            recording: true,
+            ..Default::default()
+        };
+        let mut runtime = Runtime::new(executor);
+        for loaded in self.context().stories() {
+            runtime.add_story(loaded.clone());
+        }
+        run_simulation(runtime, story, paragraph, condition_oracle)
+    }
+}
+
+/// Drive `runtime` from `entry` of `story` until it finishes, see [`Story::simulate`]
+fn run_simulation(
+    mut runtime: Runtime<SimExecutor>,
+    story: &str,
+    entry: &str,
+    condition_oracle: impl Fn(&str) -> bool,
+) -> Vec<SimEvent> {
+    if runtime.start(story, Some(entry)).is_err() {
+        return Vec::new();
+    }
+
+    let mut steps = 0;
+    while steps < SIMULATION_STEP_LIMIT {
+        steps += 1;
+        match runtime.step() {
+            Ok(StepResult::Done) => {}
+            Ok(StepResult::NeedsCondition(condition)) => {
+                runtime.resume_condition(condition_oracle(&condition));
+            }
+            Ok(StepResult::NeedsScript(_)) => runtime.resume_script(None, true),
+            // expressions have no value without an engine, so a `switch` runs its `default`
+            Ok(StepResult::NeedsExpression(_)) => runtime.resume_expression(None),
+            Ok(StepResult::NeedsStoryFile(_)) | Err(_) => break,
+        }
+    }
+
+    let executor = runtime.executor_mut();
+    std::mem::take(executor.events.get_mut().unwrap())
+}
+
+/// Executor recording every event and pausing after each one, so that the driver
+/// loop of [`Story::simulate`] can count them
+#[derive(Default)]
+struct SimExecutor {
+    // `rewrite_system_call` only borrows the executor immutably
+    events: Mutex<Vec<SimEvent>>,
+    /// Whether this executor runs for [`Runtime::record_events`]
+    recording: bool,
+}
+
+impl SimExecutor {
+    fn record(&self, mut event: SimEvent) {
+        if self.recording {
+            match &mut event {
+                SimEvent::Command(ResolvedCommandLine { arguments, .. })
+                | SimEvent::SystemCall(ResolvedSystemCallLine { arguments, .. }) => {
+                    for arg in arguments {
+                        arg.span = None;
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.events.lock().unwrap().push(event);
+    }
+
+    /// Whether [`SIMULATION_STEP_LIMIT`] events have been recorded
+    fn is_capped(&self) -> bool {
+        self.events.lock().unwrap().len() >= SIMULATION_STEP_LIMIT
+    }
+}
+
+impl RuntimeExecutor for SimExecutor {
+    fn handle_command(
+        &mut self,
+        _ctx: &mut RuntimeContext,
+        command_line: &ResolvedCommandLine,
+    ) -> Result<bool> {
+        self.record(SimEvent::Command(command_line.clone()));
+        Ok(false)
+    }
+
+    fn rewrite_system_call(&self, _systemcall_line: &SystemCallLine) -> Option<SystemCallLine> {
+        // Built-in calls do not pause, so a loop made only of jumps would never yield
+        // back to the driver: end the story once the limit is reached.
+        self.is_capped().then(|| SystemCallLine {
+            command: "finish".to_string(),
+            arguments: Vec::new(),
+        })
+    }
+
+    fn on_system_call(
+        &mut self,
+        _ctx: &mut RuntimeContext,
+        systemcall_line: &ResolvedSystemCallLine,
+    ) {
+        // the `#finish` ending a capped simulation is not part of the story
+        if !self.is_capped() {
+            self.record(SimEvent::SystemCall(systemcall_line.clone()));
+        }
+    }
+
+    fn handle_extra_system_call(
+        &mut self,
+        _ctx: &mut RuntimeContext,
+        _systemcall_line: &ResolvedSystemCallLine,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn handle_text(
+        &mut self,
+        _ctx: &mut RuntimeContext,
+        leading: Option<&str>,
+        text: Option<&str>,
+        tailing: Option<&str>,
+    ) -> Result<bool> {
+        self.record(SimEvent::Text {
+            leading: leading.map(str::to_string),
+            text: text.map(str::to_string),
+            tailing: tailing.map(str::to_string),
+        });
+        Ok(false)
+    }
+
+    fn finished(&mut self, _ctx: &mut RuntimeContext) {}
+
+    fn on_paragraph_enter(&mut self, _ctx: &mut RuntimeContext, story: &str, paragraph: &str) {
+        if self.recording {
+            self.record(SimEvent::EnterParagraph {
+                story: story.to_string(),
+                paragraph: paragraph.to_string(),
+            });
+        }
+    }
+
+    fn on_paragraph_exit(&mut self, _ctx: &mut RuntimeContext, story: &str, paragraph: &str) {
+        if self.recording {
+            self.record(SimEvent::LeaveParagraph {
+                story: story.to_string(),
+                paragraph: paragraph.to_string(),
+            });
+        }
+    }
+}
@@ -0,0 +1,409 @@
+//! Printing a parsed story back to `.sixu` source, for cases where only the AST is
+//! at hand, e.g. a story deserialized from JSON. The CST formatter keeps comments
+//! and layout, this printer emits one canonical layout instead.
+
+use crate::format::{
+    Argument, Attribute, Block, Child, ChildContent, LeadingText, Literal, Paragraph, RValue,
+    Story, TailingText, TemplateLiteral, TemplateLiteralPart, Text,
+};
+
+const INDENT: &str = "    ";
+
+impl Story {
+    /// Print this story as `.sixu` source which parses back to an equal story.
+    ///
+    /// The output is canonical: children are indented by four spaces, attributes and
+    /// markers get their own lines, arguments are parenthesized, strings use double
+    /// quotes and object keys are sorted. Comments are not part of the AST and are
+    /// lost, and paragraphs expanded from macros are printed as plain paragraphs.
+    ///
+    /// Two values have no source syntax and do not round-trip: a [`Literal::Null`],
+    /// printed as `null` which parses as a variable, and a text line with
+    /// [`Text::None`], printed as `""`. Lines with several speakers are only parsed
+    /// back with `ParserConfig::multi_speaker`.
+    pub fn to_source(&self) -> String {
+        let paragraphs: Vec<String> = self.paragraphs.iter().map(paragraph_source).collect();
+        paragraphs.join("\n")
+    }
+}
+
+fn paragraph_source(paragraph: &Paragraph) -> String {
+    let mut out = String::new();
+    write_attributes(&paragraph.attributes, "", &mut out);
+    out.push_str("::");
+    out.push_str(&paragraph.name);
+    if !paragraph.parameters.is_empty() {
+        let parameters: Vec<String> = paragraph
+            .parameters
+            .iter()
+            .map(|parameter| {
+                let mut source = parameter.name.clone();
+                if let Some(type_) = &parameter.type_ {
+                    source.push_str(": ");
+                    source.push_str(type_);
+                }
+                if let Some(default_value) = &parameter.default_value {
+                    source.push_str(" = ");
+                    source.push_str(&rvalue_source(default_value));
+                }
+                source
+            })
+            .collect();
+        out.push_str(&format!("({})", parameters.join(", ")));
+    }
+    out.push(' ');
+    write_block(&paragraph.block, "", &mut out);
+    out.push('\n');
+    out
+}
+
+fn write_attributes(attributes: &[Attribute], indent: &str, out: &mut String) {
+    for attribute in attributes {
+        out.push_str(indent);
+        match &attribute.condition {
+            // conditions are taken verbatim up to the closing quote
+            Some(condition) => {
+                let quote = if condition.contains('"') { '\'' } else { '"' };
+                out.push_str(&format!(
+                    "#[{}({}{}{})]\n",
+                    attribute.keyword, quote, condition, quote
+                ));
+            }
+            None => out.push_str(&format!("#[{}]\n", attribute.keyword)),
+        }
+    }
+}
+
+/// Write `block` from its opening brace to its closing brace, whose line is
+/// indented by `indent`
+fn write_block(block: &Block, indent: &str, out: &mut String) {
+    if let Some(label) = &block.label {
+        out.push_str(&format!("group {} ", string_source(label)));
+    }
+    if block.children.is_empty() {
+        out.push_str("{}");
+        return;
+    }
+    out.push_str("{\n");
+    let child_indent = format!("{}{}", indent, INDENT);
+    for child in &block.children {
+        write_child(child, &child_indent, out);
+    }
+    out.push_str(indent);
+    out.push('}');
+}
+
+fn write_child(child: &Child, indent: &str, out: &mut String) {
+    if let Some(marker) = &child.marker {
+        out.push_str(&format!("{}//#marker id={}\n", indent, marker.id));
+    }
+    write_attributes(&child.attributes, indent, out);
+    match &child.content {
+        // embedded code is kept verbatim, without indentation
+        ChildContent::EmbeddedCode(code) => {
+            out.push_str(indent);
+            out.push_str("##\n");
+            out.push_str(code);
+            out.push_str("##\n");
+            return;
+        }
+        ChildContent::Block(block) => {
+            out.push_str(indent);
+            write_block(block, indent, out);
+        }
+        ChildContent::TextLine(leading, text, tailing) => {
+            out.push_str(indent);
+            out.push_str(&leading_source(leading));
+            out.push_str(&text_source(text));
+            out.push_str(&tailing_source(tailing));
+        }
+        ChildContent::MultiTextLine(segments, tailing) => {
+            let segments: Vec<String> = segments
+                .iter()
+                .map(|(leading, text)| format!("{}{}", leading_source(leading), text_source(text)))
+                .collect();
+            out.push_str(indent);
+            out.push_str(&segments.join(" "));
+            out.push_str(&tailing_source(tailing));
+        }
+        ChildContent::CommandLine(command) => {
+            out.push_str(&format!(
+                "{}@{}{}",
+                indent,
+                command.command,
+                arguments_source(&command.arguments)
+            ));
+        }
+        ChildContent::SystemCallLine(call) => {
+            out.push_str(&format!(
+                "{}#{}{}",
+                indent,
+                call.command,
+                arguments_source(&call.arguments)
+            ));
+        }
+        ChildContent::MacroInvocation(name) => {
+            out.push_str(&format!("{}@@{}", indent, name));
+        }
+    }
+    out.push('\n');
+}
+
+fn arguments_source(arguments: &[Argument]) -> String {
+    if arguments.is_empty() {
+        return String::new();
+    }
+    let arguments: Vec<String> = arguments
+        .iter()
+        .map(|arg| format!("{}={}", arg.name, rvalue_source(&arg.value)))
+        .collect();
+    format!("({})", arguments.join(", "))
+}
+
+/// `[speaker] `, written bare unless the name needs quoting
+fn leading_source(leading: &LeadingText) -> String {
+    match leading {
+        LeadingText::None => String::new(),
+        LeadingText::Text(text) => {
+            let bare = !text.is_empty()
+                && !text.starts_with(['"', '\'', '`'])
+                && !text.contains([']', '\n', '\r']);
+            if bare {
+                format!("[{}] ", text)
+            } else {
+                format!("[{}] ", string_source(text))
+            }
+        }
+        LeadingText::TemplateLiteral(template) => format!("[{}] ", template_source(template)),
+    }
+}
+
+/// Text is always quoted, so that it cannot be read as a command or a comment
+fn text_source(text: &Text) -> String {
+    match text {
+        Text::None => "\"\"".to_string(),
+        Text::Text(text) => string_source(text),
+        Text::TemplateLiteral(template) => template_source(template),
+    }
+}
+
+fn tailing_source(tailing: &TailingText) -> String {
+    match tailing {
+        TailingText::None => String::new(),
+        TailingText::Text(tag) => format!(" #{}", tag),
+    }
+}
+
+fn rvalue_source(value: &RValue) -> String {
+    match value {
+        RValue::Literal(literal) => literal_source(literal),
+        RValue::Variable(variable) => variable.chain.join("."),
+    }
+}
+
+pub(crate) fn literal_source(literal: &Literal) -> String {
+    match literal {
+        Literal::Null => "null".to_string(),
+        Literal::String(s) => string_source(s),
+        Literal::Integer(i) => i.to_string(),
+        // `Debug` keeps the fraction of whole numbers, e.g. `1.0`, so they stay floats
+        Literal::Float(f) => format!("{:?}", f),
+        Literal::Boolean(b) => b.to_string(),
+        Literal::Array(elements) => {
+            let elements: Vec<String> = elements.iter().map(literal_source).collect();
+            format!("[{}]", elements.join(", "))
+        }
+        Literal::Object(entries) => {
+            let mut entries: Vec<_> = entries.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            let entries: Vec<String> = entries
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = if is_identifier(key) {
+                        key.clone()
+                    } else {
+                        string_source(key)
+                    };
+                    format!("{}: {}", key, literal_source(value))
+                })
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+    }
+}
+
+/// Whether `s` can be written as a bare object key, the parser only accepts ASCII
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A double quoted string with escapes
+fn string_source(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            _ => push_escaped(c, &mut out),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn template_source(template: &TemplateLiteral) -> String {
+    let mut out = String::from("`");
+    write_template_parts(&template.parts, &mut out);
+    out.push('`');
+    out
+}
+
+fn write_template_parts(parts: &[TemplateLiteralPart], out: &mut String) {
+    for part in parts {
+        match part {
+            TemplateLiteralPart::Text(text) => {
+                for c in text.chars() {
+                    match c {
+                        '`' => out.push_str("\\`"),
+                        '$' => out.push_str("\\$"),
+                        _ => push_escaped(c, out),
+                    }
+                }
+            }
+            TemplateLiteralPart::Value(value) => {
+                out.push_str(&format!("${{{}}}", rvalue_source(value)));
+            }
+            TemplateLiteralPart::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => {
+                out.push_str(&format!("${{?{}}}", rvalue_source(condition)));
+                write_template_parts(then, out);
+                if !otherwise.is_empty() {
+                    out.push_str("${:}");
+                    write_template_parts(otherwise, out);
+                }
+                out.push_str("${/}");
+            }
+        }
+    }
+}
+
+/// Escapes shared by strings and template literals
+fn push_escaped(c: char, out: &mut String) {
+    match c {
+        '\\' => out.push_str("\\\\"),
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        _ => out.push(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse_story, parse_story_with_config, ParserConfig};
+
+    const SOURCE: &str = r###"
+#[platform("mobile")]
+::entry(name: string, count = 3, mood = other.mood) {
+    @bg(src="bg 01.png", fade=0.5, pos=[1, -2, 3.0], data={b: true, "a key": {}, "名字": "值"})
+    @wait
+    //#marker id=intro_1
+    #[cond('flag == "on"')]
+    [Alice] "Hello \"there\"\n"   #voice01
+    [`${name}`] `Count: ${count}${?mood} (${mood})${:}!${/} \` \$ \\`
+    'single\tquoted'
+    plain text line
+    group "outer" {
+        #[while("count > 0")]
+        {
+            #call(paragraph="tick", count=count)
+        }
+        #[loop]
+        {}
+    }
+    ##
+    let x = "##";
+      return x;
+    ##
+    @{ foo({ a: 1 }) }
+    #finish
+}
+
+::tick(count) {
+    #return value=count
+}
+
+::empty {}
+"###;
+
+    #[test]
+    fn test_to_source_round_trip() {
+        let story = parse_story("test", SOURCE).unwrap();
+        let source = story.to_source();
+        assert_eq!(parse_story("test", &source).unwrap(), story, "{}", source);
+        // printing is stable
+        assert_eq!(parse_story("test", &source).unwrap().to_source(), source);
+    }
+
+    #[test]
+    fn test_to_source_layout() {
+        let story = parse_story(
+            "test",
+            "::a(x=1){ #[cond(\"x\")] @show(v=x, f) { \"hi\" } }\n::b{}",
+        )
+        .unwrap();
+        assert_eq!(
+            story.to_source(),
+            concat!(
+                "::a(x = 1) {\n",
+                "    #[cond(\"x\")]\n",
+                "    @show(v=x, f=true)\n",
+                "    {\n",
+                "        \"hi\"\n",
+                "    }\n",
+                "}\n",
+                "\n",
+                "::b {}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_source_multi_speaker() {
+        let config = ParserConfig {
+            multi_speaker: true,
+            ..Default::default()
+        };
+        let source = "::a {\n    [Alice] Hi [Bob] \"Hello\" #tag\n}\n";
+        let story = parse_story_with_config("test", source, &config).unwrap();
+        let printed = story.to_source();
+        assert_eq!(
+            printed,
+            "::a {\n    [Alice] \"Hi\" [Bob] \"Hello\" #tag\n}\n"
+        );
+        assert_eq!(
+            parse_story_with_config("test", &printed, &config).unwrap(),
+            story
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_source_after_json_round_trip() {
+        let story = parse_story("test", SOURCE).unwrap();
+        let json = serde_json::to_string(&story).unwrap();
+        let deserialized: crate::format::Story = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, story);
+        assert_eq!(
+            parse_story("test", &deserialized.to_source()).unwrap(),
+            story
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Static analysis of the variables a paragraph touches.
+//!
+//! Reads are collected from template literals, command and system call arguments and
+//! parameter defaults. Writes are the argument names of `@let` commands and `#set`
+//! system calls, e.g. `#set score=10` writes `score`. Embedded code and attribute
+//! conditions are scripts evaluated by the executor, so they are not inspected and
+//! only flag the usage as [`VariableUsage::opaque`].
+
+use std::collections::BTreeSet;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::format::{
+    Argument, Block, ChildContent, LeadingText, Paragraph, RValue, TemplateLiteral,
+    TemplateLiteralPart, Text,
+};
+
+/// Commands and system calls whose arguments assign variables
+const WRITE_COMMANDS: &[&str] = &["let"];
+const WRITE_SYSTEM_CALLS: &[&str] = &["set"];
+
+/// Variables read and written by a paragraph, named by their dotted path like `player.name`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VariableUsage {
+    pub reads: BTreeSet<String>,
+    pub writes: BTreeSet<String>,
+    /// Whether the paragraph contains embedded code or attribute conditions, which may
+    /// read or write variables that are not listed
+    pub opaque: bool,
+}
+
+impl Paragraph {
+    /// Collect the variables this paragraph reads and writes. References to the
+    /// paragraph's own parameters are local and not reported.
+    pub fn variable_usage(&self) -> VariableUsage {
+        let mut usage = VariableUsage::default();
+        for parameter in &self.parameters {
+            if let Some(value) = &parameter.default_value {
+                usage.read_value(value);
+            }
+        }
+        usage.visit_block(&self.block);
+
+        usage.reads.retain(|name| {
+            let root = name.split('.').next().unwrap_or_default();
+            !self.parameters.iter().any(|p| p.name == root)
+        });
+        usage
+    }
+}
+
+impl VariableUsage {
+    fn visit_block(&mut self, block: &Block) {
+        for child in block.descendants() {
+            if child.attributes.iter().any(|a| a.condition.is_some()) {
+                self.opaque = true;
+            }
+
+            match &child.content {
+                // its children are visited next
+                ChildContent::Block(_) => {}
+                ChildContent::TextLine(leading, text, _) => {
+                    if let LeadingText::TemplateLiteral(template) = leading {
+                        self.read_template(template);
+                    }
+                    if let Text::TemplateLiteral(template) = text {
+                        self.read_template(template);
+                    }
+                }
+                ChildContent::MultiTextLine(segments, _) => {
+                    for (leading, text) in segments {
+                        if let LeadingText::TemplateLiteral(template) = leading {
+                            self.read_template(template);
+                        }
+                        if let Text::TemplateLiteral(template) = text {
+                            self.read_template(template);
+                        }
+                    }
+                }
+                ChildContent::CommandLine(command) => self.visit_arguments(
+                    &command.arguments,
+                    WRITE_COMMANDS.contains(&command.command.as_str()),
+                ),
+                ChildContent::SystemCallLine(call) => self.visit_arguments(
+                    &call.arguments,
+                    WRITE_SYSTEM_CALLS.contains(&call.command.as_str()),
+                ),
+                ChildContent::EmbeddedCode(_) => self.opaque = true,
+                // macros are expanded before a story is analyzed
+                ChildContent::MacroInvocation(_) => {}
+            }
+        }
+    }
+
+    fn visit_arguments(&mut self, arguments: &[Argument], writes: bool) {
+        for argument in arguments {
+            if writes {
+                self.writes.insert(argument.name.clone());
+            }
+            self.read_value(&argument.value);
+        }
+    }
+
+    fn read_template(&mut self, template: &TemplateLiteral) {
+        self.read_template_parts(&template.parts);
+    }
+
+    fn read_template_parts(&mut self, parts: &[TemplateLiteralPart]) {
+        for part in parts {
+            match part {
+                TemplateLiteralPart::Text(_) => {}
+                TemplateLiteralPart::Value(value) => self.read_value(value),
+                TemplateLiteralPart::Conditional {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    self.read_value(condition);
+                    self.read_template_parts(then);
+                    self.read_template_parts(otherwise);
+                }
+            }
+        }
+    }
+
+    fn read_value(&mut self, value: &RValue) {
+        if let RValue::Variable(variable) = value {
+            self.reads.insert(variable.chain.join("."));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_story;
+
+    use super::*;
+
+    fn usage(input: &str) -> VariableUsage {
+        let story = parse_story("test", input).unwrap();
+        story.paragraphs[0].variable_usage()
+    }
+
+    fn set(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_template_reads_and_set_writes() {
+        let usage = usage(
+            r#"
+::entry {
+    [`${player.name}`] `Score: ${score}`
+    {
+        #set score=bonus
+        @show image=portrait
+    }
+}
+"#,
+        );
+        assert_eq!(
+            usage.reads,
+            set(&["bonus", "player.name", "portrait", "score"])
+        );
+        assert_eq!(usage.writes, set(&["score"]));
+        assert!(!usage.opaque);
+    }
+
+    #[test]
+    fn test_parameters_are_not_reported() {
+        let usage = usage("::greet(name, title = honorific) {\n    `Hello ${title} ${name}`\n}\n");
+        assert_eq!(usage.reads, set(&["honorific"]));
+        assert!(usage.writes.is_empty());
+    }
+
+    #[test]
+    fn test_embedded_code_is_opaque() {
+        let usage = usage("::entry {\n    @let flag=true\n    ##\n    a = b;\n    ##\n}\n");
+        assert_eq!(usage.writes, set(&["flag"]));
+        assert!(usage.reads.is_empty());
+        assert!(usage.opaque);
+    }
+}
@@ -0,0 +1,109 @@
+//! Registry of parsers for domain value types of command arguments.
+//!
+//! Command schemas may tag a property with a `format`, e.g. `"format": "duration"`.
+//! Embedders register a parser per tag, which tools such as the language server use to
+//! validate literal argument values, and engines may use to convert them before use.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::format::Literal;
+
+/// Parser of a value format, converting a literal into its canonical form or
+/// explaining why it is invalid
+pub type ValueParser = dyn Fn(&Literal) -> Result<Literal, String> + Send + Sync;
+
+/// Value parsers keyed by the `format` tag of a schema property
+#[derive(Clone, Default)]
+pub struct ValueParsers {
+    parsers: HashMap<String, Arc<ValueParser>>,
+}
+
+impl ValueParsers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `parser` for the values of properties tagged with `format`, replacing
+    /// the parser previously registered for it
+    pub fn register(
+        &mut self,
+        format: impl Into<String>,
+        parser: impl Fn(&Literal) -> Result<Literal, String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.parsers.insert(format.into(), Arc::new(parser));
+        self
+    }
+
+    /// Whether a parser is registered for `format`
+    pub fn contains(&self, format: &str) -> bool {
+        self.parsers.contains_key(format)
+    }
+
+    /// Parse `value` with the parser of `format`, None if no parser is registered for it
+    pub fn parse(&self, format: &str, value: &Literal) -> Option<Result<Literal, String>> {
+        self.parsers.get(format).map(|parser| parser(value))
+    }
+}
+
+impl std::fmt::Debug for ValueParsers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut formats: Vec<&String> = self.parsers.keys().collect();
+        formats.sort();
+        f.debug_struct("ValueParsers")
+            .field("formats", &formats)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Durations like `500ms` or `2s`, converted to milliseconds
+    fn duration(value: &Literal) -> Result<Literal, String> {
+        let text = value
+            .as_string()
+            .map_err(|_| "expected a string".to_string())?;
+        let (number, scale) = if let Some(ms) = text.strip_suffix("ms") {
+            (ms, 1)
+        } else if let Some(s) = text.strip_suffix('s') {
+            (s, 1000)
+        } else {
+            return Err(format!("`{}` is not a duration", text));
+        };
+        number
+            .parse::<i64>()
+            .map(|n| Literal::Integer(n * scale))
+            .map_err(|_| format!("`{}` is not a duration", text))
+    }
+
+    #[test]
+    fn test_register_duration() {
+        let mut parsers = ValueParsers::new();
+        parsers.register("duration", duration);
+
+        assert!(parsers.contains("duration"));
+        assert_eq!(
+            parsers.parse("duration", &Literal::String("500ms".to_string())),
+            Some(Ok(Literal::Integer(500)))
+        );
+        assert_eq!(
+            parsers.parse("duration", &Literal::String("2s".to_string())),
+            Some(Ok(Literal::Integer(2000)))
+        );
+        assert_eq!(
+            parsers.parse("duration", &Literal::String("abc".to_string())),
+            Some(Err("`abc` is not a duration".to_string()))
+        );
+        // no parser for the format
+        assert_eq!(
+            parsers.parse("color", &Literal::String("abc".to_string())),
+            None
+        );
+        assert_eq!(
+            format!("{:?}", parsers),
+            "ValueParsers { formats: [\"duration\"] }"
+        );
+    }
+}
@@ -0,0 +1,65 @@
+mod common;
+
+#[cfg(feature = "cst")]
+mod argument_span_tests {
+    use std::ops::Range;
+
+    use crate::common::{self, Event, RecordingExecutor};
+    use sixu::cst::parser::parse_tolerant;
+    use sixu::format::*;
+    use sixu::parser::parse_story;
+    use sixu::runtime::Runtime;
+
+    const SCRIPT: &str =
+        "::entry {\n    @show(name=\"hero\", src=bg_path)\n    #transition fade\n}\n";
+
+    /// Span of the `src` argument of every command and the `fade` argument of every
+    /// system call
+    fn run(story: Story) -> Vec<(String, Option<Range<usize>>)> {
+        let mut runtime = Runtime::new(RecordingExecutor::default());
+        runtime.add_story(story);
+        runtime.start("test", Some("entry")).unwrap();
+        common::run(&mut runtime).unwrap();
+        runtime
+            .executor()
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Command(line) => Some(("src".to_string(), line.get_argument_span("src"))),
+                Event::SystemCall(line) => {
+                    Some(("fade".to_string(), line.get_argument_span("fade")))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_argument_spans_from_cst() {
+        let story = parse_tolerant("test", SCRIPT).to_ast().unwrap();
+        let spans = run(story);
+
+        let src = spans[0].1.clone().unwrap();
+        assert_eq!(&SCRIPT[src.clone()], "bg_path");
+        assert_eq!(src.start, SCRIPT.find("bg_path").unwrap());
+
+        // A flag without value points at its name
+        let fade = spans[1].1.clone().unwrap();
+        assert_eq!(&SCRIPT[fade], "fade");
+    }
+
+    #[test]
+    fn test_spans_are_absent_without_cst() {
+        let story = parse_story("test", SCRIPT).unwrap();
+        assert_eq!(
+            run(story),
+            vec![("src".to_string(), None), ("fade".to_string(), None)]
+        );
+    }
+
+    #[test]
+    fn test_spans_do_not_affect_equality() {
+        let from_cst = parse_tolerant("test", SCRIPT).to_ast().unwrap();
+        assert_eq!(from_cst, parse_story("test", SCRIPT).unwrap());
+    }
+}
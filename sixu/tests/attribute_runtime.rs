@@ -13,6 +13,8 @@ struct TestExecutor {
     commands: Arc<Mutex<Vec<String>>>,
     /// Collected marker ids
     markers: Arc<Mutex<Vec<String>>>,
+    /// Custom attributes seen by each command, as `command:keyword(condition)`
+    attributes: Arc<Mutex<Vec<String>>>,
     /// Counter for condition evaluation (used to control while loops)
     counter: Arc<Mutex<i32>>,
     /// Condition evaluator: maps condition string to a closure
@@ -26,6 +28,7 @@ impl TestExecutor {
             texts: Arc::new(Mutex::new(Vec::new())),
             commands: Arc::new(Mutex::new(Vec::new())),
             markers: Arc::new(Mutex::new(Vec::new())),
+            attributes: Arc::new(Mutex::new(Vec::new())),
             counter: Arc::new(Mutex::new(0)),
             finished_called: Arc::new(Mutex::new(false)),
         }
@@ -43,6 +46,10 @@ impl TestExecutor {
         self.markers.lock().unwrap().clone()
     }
 
+    fn attributes(&self) -> Vec<String> {
+        self.attributes.lock().unwrap().clone()
+    }
+
     fn eval_condition_str(&self, condition: &str) -> bool {
         match condition.trim() {
             "true" => true,
@@ -52,6 +59,16 @@ impl TestExecutor {
             _ => false,
         }
     }
+
+    /// Expression evaluator used for `switch` expressions
+    fn eval_expression_str(&self, expression: &str) -> Option<Literal> {
+        match expression.trim() {
+            "route" => Some(Literal::Integer(2)),
+            "label" => Some(Literal::String("2".to_string())),
+            "mood" => Some(Literal::String("calm".to_string())),
+            _ => None,
+        }
+    }
 }
 
 impl RuntimeExecutor for TestExecutor {
@@ -66,9 +83,17 @@ impl RuntimeExecutor for TestExecutor {
 
     fn handle_command(
         &mut self,
-        _ctx: &mut RuntimeContext,
+        ctx: &mut RuntimeContext,
         command_line: &ResolvedCommandLine,
     ) -> sixu::error::Result<bool> {
+        for attr in ctx.current_attributes() {
+            self.attributes.lock().unwrap().push(format!(
+                "{}:{}({})",
+                command_line.command,
+                attr.keyword,
+                attr.condition.as_deref().unwrap_or_default()
+            ));
+        }
         self.commands
             .lock()
             .unwrap()
@@ -110,6 +135,13 @@ impl RuntimeExecutor for TestExecutor {
 }
 
 fn run_story(script: &str) -> (Vec<String>, Vec<String>) {
+    let runtime = run(script);
+    let texts = runtime.executor().texts();
+    let commands = runtime.executor().commands();
+    (texts, commands)
+}
+
+fn run(script: &str) -> Runtime<TestExecutor> {
     let (_, story) = parse("test", script).unwrap();
     let executor = TestExecutor::new();
     let mut runtime = Runtime::new(executor);
@@ -132,6 +164,10 @@ fn run_story(script: &str) -> (Vec<String>, Vec<String>) {
             Ok(StepResult::NeedsScript(_)) => {
                 runtime.resume_script(None, true);
             }
+            Ok(StepResult::NeedsExpression(expression)) => {
+                let value = runtime.executor().eval_expression_str(&expression);
+                runtime.resume_expression(value);
+            }
             Ok(StepResult::NeedsStoryFile(_)) => {
                 unimplemented!("story file loading not supported in this test")
             }
@@ -140,9 +176,7 @@ fn run_story(script: &str) -> (Vec<String>, Vec<String>) {
         }
     }
 
-    let texts = runtime.executor().texts();
-    let commands = runtime.executor().commands();
-    (texts, commands)
+    runtime
 }
 
 // ==================== cond / if tests ====================
@@ -298,6 +332,9 @@ fn test_markers_survive_across_text_and_empty_argument_boundaries() {
             Ok(StepResult::NeedsScript(_)) => {
                 runtime.resume_script(None, true);
             }
+            Ok(StepResult::NeedsExpression(_)) => {
+                runtime.resume_expression(None);
+            }
             Ok(StepResult::NeedsStoryFile(_)) => {
                 unimplemented!("story file loading not supported in this test")
             }
@@ -325,6 +362,33 @@ after
     assert_eq!(texts, vec!["after"]);
 }
 
+#[test]
+fn test_custom_attributes_are_visible_to_executor() {
+    let script = r#"
+::entry {
+#[cond("true")]
+#[fadeIn]
+@shown
+#[fadeIn("slow")]
+#[cond("false")]
+@hidden
+#[shake("3")]
+@plain
+@bare
+}
+"#;
+    let runtime = run(script);
+    assert_eq!(
+        runtime.executor().commands(),
+        vec!["shown", "plain", "bare"]
+    );
+    // cond is consumed by the runtime, only the custom attributes reach the executor
+    assert_eq!(
+        runtime.executor().attributes(),
+        vec!["shown:fadeIn()", "plain:shake(3)"]
+    );
+}
+
 // ==================== while tests ====================
 
 #[test]
@@ -416,6 +480,37 @@ fn test_loop_break_immediately() {
     assert_eq!(texts, vec!["after loop"]);
 }
 
+#[test]
+fn test_unterminated_loop_exceeds_step_limit() {
+    let script = r#"
+::entry {
+#[loop]
+{
+  @x
+}
+}
+"#;
+    let (_, story) = parse("test", script).unwrap();
+    let mut runtime = Runtime::new(TestExecutor::new());
+    runtime.add_story(story);
+    runtime.set_max_steps(1000);
+    runtime.start("test", Some("entry")).unwrap();
+
+    assert!(matches!(
+        runtime.step(),
+        Err(RuntimeError::StepLimitExceeded(1000))
+    ));
+    let commands = runtime.executor().commands();
+    assert!(commands.len() < 1000 && commands.iter().all(|c| c == "x"));
+
+    // the budget applies per step, the next step runs again from where it stopped
+    assert!(matches!(
+        runtime.step(),
+        Err(RuntimeError::StepLimitExceeded(1000))
+    ));
+    assert!(runtime.executor().commands().len() > commands.len());
+}
+
 // ==================== #continue tests ====================
 
 #[test]
@@ -489,3 +584,233 @@ done
     assert_eq!(commands, vec!["increment", "increment", "increment"]);
     assert_eq!(texts, vec!["done"]);
 }
+
+// ==================== elif / else tests ====================
+
+#[test]
+fn test_if_true_skips_else() {
+    let script = r#"
+::entry {
+#[if("true")]
+then_branch
+#[else]
+else_branch
+after
+}
+"#;
+    let (texts, _) = run_story(script);
+    assert_eq!(texts, vec!["then_branch", "after"]);
+}
+
+#[test]
+fn test_if_false_runs_else() {
+    let script = r#"
+::entry {
+#[if("false")]
+then_branch
+#[else]
+{
+  else_branch
+  @else_cmd
+}
+after
+}
+"#;
+    let (texts, commands) = run_story(script);
+    assert_eq!(texts, vec!["else_branch", "after"]);
+    assert_eq!(commands, vec!["else_cmd"]);
+}
+
+#[test]
+fn test_elif_chain_runs_first_matching_branch() {
+    let script = r#"
+::entry {
+#[if("false")]
+first
+#[elif("false")]
+second
+#[elif("true")]
+third
+#[elif("true")]
+fourth
+#[else]
+fallback
+after
+}
+"#;
+    let (texts, _) = run_story(script);
+    assert_eq!(texts, vec!["third", "after"]);
+
+    let script = r#"
+::entry {
+#[if("false")]
+first
+#[elif("false")]
+second
+#[else]
+fallback
+}
+"#;
+    let (texts, _) = run_story(script);
+    assert_eq!(texts, vec!["fallback"]);
+}
+
+#[test]
+fn test_else_chain_is_closed_by_other_children() {
+    let script = r#"
+::entry {
+#[if("true")]
+{
+  #[if("false")]
+  inner_then
+}
+#[else]
+outer_else
+between
+#[if("true")]
+then_branch
+plain
+#[else]
+orphan_else
+}
+"#;
+    let (texts, _) = run_story(script);
+    // the inner chain does not leak out of its block, and a plain child closes the
+    // outer chain so the last `else` has no branch to follow and runs
+    assert_eq!(
+        texts,
+        vec!["between", "then_branch", "plain", "orphan_else"]
+    );
+}
+
+// ==================== switch / case tests ====================
+
+#[test]
+fn test_switch_runs_matching_case() {
+    let script = r#"
+::entry {
+#[switch("route")]
+{
+  #[case("1")]
+  {
+    route_one
+  }
+  #[case("2")]
+  {
+    route_two
+    @route_two_cmd
+  }
+  #[case("3")]
+  route_three
+  #[default]
+  fallback
+}
+after
+}
+"#;
+    let (texts, commands) = run_story(script);
+    assert_eq!(texts, vec!["route_two", "after"]);
+    assert_eq!(commands, vec!["route_two_cmd"]);
+}
+
+#[test]
+fn test_switch_runs_default_without_match() {
+    let script = r#"
+::entry {
+#[switch("mood")]
+{
+  #[case("happy")]
+  happy
+  #[default]
+  {
+    fallback
+  }
+  #[case("sad")]
+  sad
+}
+#[switch("unknown")]
+{
+  #[case("calm")]
+  never
+}
+after
+}
+"#;
+    let (texts, _) = run_story(script);
+    // a switch without a matching case nor default runs nothing
+    assert_eq!(texts, vec!["fallback", "after"]);
+}
+
+#[test]
+fn test_switch_compares_typed_values() {
+    let script = r#"
+::entry {
+#[switch("route")]
+{
+  #[case("'2'")]
+  route_string
+  #[case("2")]
+  route_integer
+}
+#[switch("label")]
+{
+  #[case("2")]
+  label_integer
+  #[case("'2'")]
+  label_string
+}
+}
+"#;
+    let (texts, _) = run_story(script);
+    assert_eq!(texts, vec!["route_integer", "label_string"]);
+}
+
+#[test]
+fn test_switch_yields_expression_once() {
+    let script = r#"
+::entry {
+#[switch("route")]
+{
+  #[case("2")]
+  {
+    first
+    second
+  }
+}
+}
+"#;
+    let (_, story) = parse("test", script).unwrap();
+    let mut runtime = Runtime::new(TestExecutor::new());
+    runtime.add_story(story);
+    runtime.start("test", Some("entry")).unwrap();
+
+    match runtime.step() {
+        Ok(StepResult::NeedsExpression(expression)) => assert_eq!(expression, "route"),
+        other => panic!("Expected NeedsExpression, got {:?}", other),
+    }
+    runtime.resume_expression(Some(Literal::Integer(2)));
+    assert!(matches!(runtime.step(), Ok(StepResult::Done)));
+    // the selected case goes on without evaluating the expression again
+    assert!(matches!(runtime.step(), Ok(StepResult::Done)));
+    assert_eq!(runtime.executor().texts(), vec!["first", "second"]);
+}
+
+#[test]
+fn test_switch_case_keeps_custom_attributes() {
+    let script = r#"
+::entry {
+#[switch("mood")]
+{
+  #[case("calm")]
+  #[fadeIn("slow")]
+  @calm_cmd
+}
+}
+"#;
+    let runtime = run(script);
+    assert_eq!(runtime.executor().commands(), vec!["calm_cmd"]);
+    assert_eq!(
+        runtime.executor().attributes(),
+        vec!["calm_cmd:fadeIn(slow)"]
+    );
+}
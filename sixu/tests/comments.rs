@@ -0,0 +1,80 @@
+use sixu::format::{Comment, CommentKind};
+use sixu::parser::{parse_story, parse_story_with_comments};
+
+const SCRIPT: &str = r#"// file header
+/* block
+   comment */
+::entry(name = "guest") { // after brace
+    /* before text */ "Hello // not a comment"
+    @show(image = "bg", /* inline */ fade = 1) // trailing
+    #goto paragraph="next"
+}
+
+// between paragraphs
+::next {
+    ##
+    // inside code
+    ##
+    [name] "line" /**/
+}
+"#;
+
+fn comments_at(source: &str) -> Vec<(&str, CommentKind, String)> {
+    let (_, comments) = parse_story_with_comments("test", source).unwrap();
+    comments
+        .into_iter()
+        .map(|(range, Comment { kind, text })| (&source[range], kind, text))
+        .collect()
+}
+
+#[test]
+fn test_collects_all_comments_with_ranges() {
+    let comments = comments_at(SCRIPT);
+    let expected = [
+        ("// file header", CommentKind::Line, " file header"),
+        (
+            "/* block\n   comment */",
+            CommentKind::Block,
+            " block\n   comment ",
+        ),
+        ("// after brace", CommentKind::Line, " after brace"),
+        ("/* before text */", CommentKind::Block, " before text "),
+        ("/* inline */", CommentKind::Block, " inline "),
+        ("// trailing", CommentKind::Line, " trailing"),
+        (
+            "// between paragraphs",
+            CommentKind::Line,
+            " between paragraphs",
+        ),
+        ("/**/", CommentKind::Block, ""),
+    ];
+    assert_eq!(
+        comments,
+        expected
+            .iter()
+            .map(|(source, kind, text)| (*source, *kind, text.to_string()))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_story_matches_plain_parse() {
+    let (story, _) = parse_story_with_comments("test", SCRIPT).unwrap();
+    assert_eq!(story, parse_story("test", SCRIPT).unwrap());
+}
+
+#[test]
+fn test_offsets_are_byte_offsets() {
+    let source = "::entry {\n    \"你好\" // 注释\n}\n";
+    let (_, comments) = parse_story_with_comments("test", source).unwrap();
+    assert_eq!(comments.len(), 1);
+    let (range, comment) = &comments[0];
+    assert_eq!(range.start, source.find("//").unwrap());
+    assert_eq!(range.end, source.find("\n}").unwrap());
+    assert_eq!(comment.text, " 注释");
+}
+
+#[test]
+fn test_parse_error_is_reported() {
+    assert!(parse_story_with_comments("test", "::entry { // open\n").is_err());
+}
@@ -0,0 +1,255 @@
+//! Recording executor and step driver shared by the runtime tests. Each test crate
+//! uses a different part of it.
+#![allow(dead_code)]
+
+use sixu::error::RuntimeError;
+use sixu::format::*;
+use sixu::parser::parse_story;
+use sixu::runtime::{Runtime, RuntimeContext, RuntimeExecutor, StepResult};
+
+/// What the runtime handed to a [`RecordingExecutor`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Command(ResolvedCommandLine),
+    /// A system call left to the executor
+    SystemCall(ResolvedSystemCallLine),
+    Text {
+        leading: Option<String>,
+        text: Option<String>,
+        tailing: Option<String>,
+    },
+    /// `name` is the `name` local on entry, to see parameters are bound before the hook
+    Enter {
+        story: String,
+        paragraph: String,
+        name: Option<Literal>,
+    },
+    Exit {
+        story: String,
+        paragraph: String,
+    },
+    Finished,
+}
+
+/// Executor recording every event in order. It continues after everything unless
+/// configured otherwise.
+#[derive(Default)]
+pub struct RecordingExecutor {
+    pub events: Vec<Event>,
+    /// Pause after every text line
+    pub pause_after_text: bool,
+    /// Commands pausing the runtime
+    pub pause_on: Vec<&'static str>,
+    /// Answers the conditions met by [`step`], every condition is true without it
+    pub conditions: Option<fn(&RecordingExecutor, &str) -> bool>,
+    /// Called for every command before it is recorded
+    pub on_command: Option<fn(&mut RuntimeContext, &ResolvedCommandLine)>,
+    /// Used as `RuntimeExecutor::rewrite_system_call`
+    pub rewrite: Option<fn(&SystemCallLine) -> Option<SystemCallLine>>,
+    /// Load stories as precompiled JSON instead of source text
+    pub precompiled: bool,
+}
+
+impl RecordingExecutor {
+    /// Names of the commands handled
+    pub fn commands(&self) -> Vec<String> {
+        self.command_lines()
+            .map(|line| line.command.clone())
+            .collect()
+    }
+
+    pub fn command_lines(&self) -> impl Iterator<Item = &ResolvedCommandLine> {
+        self.events.iter().filter_map(|event| match event {
+            Event::Command(line) => Some(line),
+            _ => None,
+        })
+    }
+
+    /// How many times `command` was handled
+    pub fn count(&self, command: &str) -> usize {
+        self.command_lines()
+            .filter(|line| line.command == command)
+            .count()
+    }
+
+    /// Names of the system calls left to the executor
+    pub fn system_calls(&self) -> Vec<String> {
+        self.system_call_lines()
+            .map(|line| line.command.clone())
+            .collect()
+    }
+
+    pub fn system_call_lines(&self) -> impl Iterator<Item = &ResolvedSystemCallLine> {
+        self.events.iter().filter_map(|event| match event {
+            Event::SystemCall(line) => Some(line),
+            _ => None,
+        })
+    }
+
+    /// Text of every text line, empty when a line has none
+    pub fn texts(&self) -> Vec<String> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Text { text, .. } => Some(text.clone().unwrap_or_default()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Leading, text and tailing text of every text line
+    pub fn text_lines(&self) -> Vec<(Option<String>, Option<String>, Option<String>)> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Text {
+                    leading,
+                    text,
+                    tailing,
+                } => Some((leading.clone(), text.clone(), tailing.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every event as a line, e.g. `@command`, `#call`, the text of a text line,
+    /// `enter story::paragraph(name)`, `exit story::paragraph` or `finished`
+    pub fn log(&self) -> Vec<String> {
+        self.events
+            .iter()
+            .map(|event| match event {
+                Event::Command(line) => format!("@{}", line.command),
+                Event::SystemCall(line) => format!("#{}", line.command),
+                Event::Text { text, .. } => text.clone().unwrap_or_default(),
+                Event::Enter {
+                    story,
+                    paragraph,
+                    name: Some(name),
+                } => format!("enter {}::{}({})", story, paragraph, name),
+                Event::Enter {
+                    story, paragraph, ..
+                } => format!("enter {}::{}", story, paragraph),
+                Event::Exit { story, paragraph } => format!("exit {}::{}", story, paragraph),
+                Event::Finished => "finished".to_string(),
+            })
+            .collect()
+    }
+}
+
+impl RuntimeExecutor for RecordingExecutor {
+    fn handle_command(
+        &mut self,
+        ctx: &mut RuntimeContext,
+        command_line: &ResolvedCommandLine,
+    ) -> sixu::error::Result<bool> {
+        if let Some(on_command) = self.on_command {
+            on_command(ctx, command_line);
+        }
+        self.events.push(Event::Command(command_line.clone()));
+        Ok(!self.pause_on.contains(&command_line.command.as_str()))
+    }
+
+    fn rewrite_system_call(&self, systemcall_line: &SystemCallLine) -> Option<SystemCallLine> {
+        self.rewrite.and_then(|rewrite| rewrite(systemcall_line))
+    }
+
+    fn handle_extra_system_call(
+        &mut self,
+        _ctx: &mut RuntimeContext,
+        systemcall_line: &ResolvedSystemCallLine,
+    ) -> sixu::error::Result<bool> {
+        self.events.push(Event::SystemCall(systemcall_line.clone()));
+        Ok(true)
+    }
+
+    fn handle_text(
+        &mut self,
+        _ctx: &mut RuntimeContext,
+        leading: Option<&str>,
+        text: Option<&str>,
+        tailing: Option<&str>,
+    ) -> sixu::error::Result<bool> {
+        self.events.push(Event::Text {
+            leading: leading.map(str::to_string),
+            text: text.map(str::to_string),
+            tailing: tailing.map(str::to_string),
+        });
+        Ok(!self.pause_after_text)
+    }
+
+    fn finished(&mut self, _ctx: &mut RuntimeContext) {
+        self.events.push(Event::Finished);
+    }
+
+    fn on_paragraph_enter(&mut self, ctx: &mut RuntimeContext, story: &str, paragraph: &str) {
+        self.events.push(Event::Enter {
+            story: story.to_string(),
+            paragraph: paragraph.to_string(),
+            name: ctx.get_local("name").cloned(),
+        });
+    }
+
+    fn on_paragraph_exit(&mut self, _ctx: &mut RuntimeContext, story: &str, paragraph: &str) {
+        self.events.push(Event::Exit {
+            story: story.to_string(),
+            paragraph: paragraph.to_string(),
+        });
+    }
+
+    fn parse_story(&self, name: &str, data: &[u8]) -> sixu::error::Result<Story> {
+        #[cfg(feature = "serde")]
+        if self.precompiled {
+            return serde_json::from_slice(data).map_err(|e| RuntimeError::Anyhow(e.into()));
+        }
+        Ok(sixu::parser::parse_story_from_reader(name, data)?)
+    }
+}
+
+/// Runtime of `executor` with `script` loaded as story `test`
+pub fn runtime(script: &str, executor: RecordingExecutor) -> Runtime<RecordingExecutor> {
+    let mut runtime = Runtime::new(executor);
+    runtime.add_story(parse_story("test", script).unwrap());
+    runtime
+}
+
+/// Step until the runtime pauses, returning false once the story has finished.
+/// Conditions are answered by the executor, scripts and expressions resume without
+/// a value.
+pub fn step(runtime: &mut Runtime<RecordingExecutor>) -> Result<bool, RuntimeError> {
+    loop {
+        match runtime.step() {
+            Ok(StepResult::Done) => return Ok(true),
+            Ok(StepResult::NeedsCondition(condition)) => {
+                let executor = runtime.executor();
+                let result = executor
+                    .conditions
+                    .is_none_or(|conditions| conditions(executor, &condition));
+                runtime.resume_condition(result);
+            }
+            Ok(StepResult::NeedsScript(_)) => runtime.resume_script(None, true),
+            Ok(StepResult::NeedsExpression(_)) => runtime.resume_expression(None),
+            Ok(StepResult::NeedsStoryFile(name)) => panic!("unexpected story load: {}", name),
+            Err(RuntimeError::StoryFinished) | Err(RuntimeError::StoryNotStarted) => {
+                return Ok(false)
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Step the started runtime until its story finishes
+pub fn run(runtime: &mut Runtime<RecordingExecutor>) -> Result<(), RuntimeError> {
+    while step(runtime)? {}
+    Ok(())
+}
+
+/// Run `entry` of `script` as story `test` to its end and return the executor
+pub fn run_script(
+    script: &str,
+    executor: RecordingExecutor,
+) -> Result<RecordingExecutor, RuntimeError> {
+    let mut runtime = runtime(script, executor);
+    runtime.start("test", Some("entry"))?;
+    run(&mut runtime)?;
+    Ok(std::mem::take(runtime.executor_mut()))
+}
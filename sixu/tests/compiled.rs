@@ -0,0 +1,62 @@
+//! Compiled binary stories, only built with `--features compiled`
+#![cfg(feature = "compiled")]
+
+use std::fs;
+use std::path::Path;
+
+use sixu::error::CompiledError;
+use sixu::parser::parse_story;
+use sixu::{compile, compile_story, load_compiled, COMPILED_FORMAT_VERSION};
+
+#[test]
+fn test_compiled_story_round_trips() {
+    let source = r#"
+::entry(name: string, count=3, ratio=0.5, flag=true, nothing=null) {
+    #[cond("count > 1")]
+    @show(text="hello", count=count) // keeps spans
+    `hello ${name}`
+    #call paragraph="other" value=-2
+    ## let a = 1; ##
+}
+
+::other {
+    #return value=1
+}
+"#;
+    let story = parse_story("test", source).unwrap();
+    let loaded = load_compiled(&compile("test", source).unwrap()).unwrap();
+    assert_eq!(loaded, story);
+}
+
+#[test]
+fn test_format_fixtures_round_trip() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/format/output");
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(&path).unwrap();
+        let story = parse_story("fixture", &source).unwrap();
+        let loaded = load_compiled(&compile_story(&story).unwrap()).unwrap();
+        assert_eq!(loaded, story, "{}", path.display());
+    }
+}
+
+#[test]
+fn test_version_mismatch_is_an_error() {
+    let mut bytes = compile("test", "::entry {\n    hello\n}\n").unwrap();
+    let newer = COMPILED_FORMAT_VERSION + 1;
+    bytes[4..6].copy_from_slice(&newer.to_le_bytes());
+
+    match load_compiled(&bytes) {
+        Err(CompiledError::VersionMismatch { found, expected }) => {
+            assert_eq!(found, newer);
+            assert_eq!(expected, COMPILED_FORMAT_VERSION);
+        }
+        other => panic!("expected version mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_source_text_is_not_a_compiled_story() {
+    let result = load_compiled(b"::entry {\n}\n");
+    assert!(matches!(result, Err(CompiledError::InvalidHeader)));
+}
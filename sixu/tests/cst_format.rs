@@ -138,6 +138,7 @@ mod format_tests {
         let mut passed = 0;
         let mut failed = 0;
 
+        #[allow(clippy::manual_flatten)]
         for entry in entries {
             if let Ok(entry) = entry {
                 let path = entry.path();
@@ -225,6 +226,26 @@ mod format_tests {
         run_format_test("10_multi_paragraphs");
     }
 
+    #[test]
+    fn test_format_block_label() {
+        run_format_test("11_block_label");
+    }
+
+    #[test]
+    fn test_format_macro() {
+        run_format_test("12_macro");
+    }
+
+    #[test]
+    fn test_format_argument_comments() {
+        run_format_test("13_argument_comments");
+    }
+
+    #[test]
+    fn test_format_parameter_types() {
+        run_format_test("14_parameter_types");
+    }
+
     // 批量测试入口（可选，用于一次性运行所有测试）
     #[test]
     #[ignore] // 默认忽略，使用 cargo test -- --ignored 运行
@@ -0,0 +1,178 @@
+mod common;
+
+use common::RecordingExecutor;
+use sixu::parser::parse_story;
+use sixu::runtime::Runtime;
+
+/// The order is traced statically, so the executor is never called.
+fn runtime(stories: &[(&str, &str)]) -> Runtime<RecordingExecutor> {
+    let mut runtime = Runtime::new(RecordingExecutor::default());
+    for (name, script) in stories {
+        runtime.add_story(parse_story(name, script).unwrap());
+    }
+    runtime
+}
+
+fn pairs(order: &[(&str, &str)]) -> Vec<(String, String)> {
+    order
+        .iter()
+        .map(|(s, p)| (s.to_string(), p.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_straight_chain() {
+    let runtime = runtime(&[
+        (
+            "main",
+            r#"
+::entry {
+    hello
+    #[cond("seen")]
+    @bgm name="again"
+}
+
+::second {
+    {
+        world
+        #goto paragraph="fourth"
+    }
+    never
+}
+
+::third {
+    skipped
+}
+
+::fourth {
+    #replace story="epilogue" paragraph="start"
+}
+"#,
+        ),
+        (
+            "epilogue",
+            r#"
+::start {
+    {
+        #leave
+    }
+    the end
+}
+
+::last {
+    #finish
+}
+
+::unreachable {
+}
+"#,
+        ),
+    ]);
+
+    assert_eq!(
+        runtime.execution_order("main", "entry"),
+        pairs(&[
+            ("main", "entry"),
+            ("main", "second"),
+            ("main", "fourth"),
+            ("epilogue", "start"),
+            ("epilogue", "last"),
+        ])
+    );
+}
+
+#[test]
+fn test_chain_ends_at_conditional_branch() {
+    let runtime = runtime(&[(
+        "main",
+        r#"
+::entry {
+    hello
+}
+
+::choice {
+    #[cond("brave")]
+    #goto paragraph="fight"
+    #goto paragraph="flee"
+}
+
+::fight {
+}
+
+::flee {
+}
+"#,
+    )]);
+
+    assert_eq!(
+        runtime.execution_order("main", "entry"),
+        pairs(&[("main", "entry"), ("main", "choice")])
+    );
+}
+
+#[test]
+fn test_chain_ends_at_variable_target_or_cycle() {
+    let runtime = runtime(&[(
+        "main",
+        r#"
+::entry {
+    #goto paragraph=target
+}
+
+::loop_a {
+    #goto paragraph="loop_b"
+}
+
+::loop_b {
+    #goto paragraph="loop_a"
+}
+"#,
+    )]);
+
+    assert_eq!(
+        runtime.execution_order("main", "entry"),
+        pairs(&[("main", "entry")])
+    );
+    assert_eq!(
+        runtime.execution_order("main", "loop_a"),
+        pairs(&[("main", "loop_a"), ("main", "loop_b")])
+    );
+    assert!(runtime.execution_order("main", "missing").is_empty());
+}
+
+#[test]
+fn test_switch_cases_are_conditional() {
+    let runtime = runtime(&[(
+        "main",
+        r#"
+::entry {
+    #[switch("route")]
+    {
+        #[case("1")]
+        hello
+        @ignored
+        #goto paragraph="never_run"
+    }
+}
+
+::choice {
+    #[switch("route")]
+    {
+        #[case("1")]
+        #goto paragraph="fight"
+        #[default]
+        hello
+    }
+}
+
+::fight {
+}
+"#,
+    )]);
+
+    // only the cases of a switch may run, and none of them is sure to
+    assert_eq!(
+        runtime.execution_order("main", "entry"),
+        pairs(&[("main", "entry"), ("main", "choice")])
+    );
+}
@@ -0,0 +1,113 @@
+//! 格式化幂等性测试：格式化结果再次格式化应保持不变
+
+#[cfg(feature = "cst")]
+mod idempotency_tests {
+    use std::path::{Path, PathBuf};
+
+    use sixu::cst::formatter::{CstFormatter, EmptyCallStyle, FormatOptions};
+    use sixu::cst::parser::parse_tolerant;
+    use sixu::cst::QuoteStyle;
+
+    /// 用于测试的 .sixu 文件所在目录（相对于 workspace 根目录）
+    const FIXTURE_DIRS: &[&str] = &[
+        "sixu/tests/fixtures/format/source",
+        "sixu/tests/fixtures/merge",
+        "sample-project/assets/scenarios",
+        // 含语法错误的文件，覆盖 Error 节点
+        "sixu-lsp/tests/fixtures/diagnostics",
+    ];
+
+    /// 容易产生空行漂移的片段
+    const SNIPPETS: &[&str] = &[
+        "// c\n::a {\n}\n",
+        "::a {\n}\n// c\n::b {\n}\n",
+        "::a {\n}\n\n\n\n::b {\n}\n",
+        "::a {}\n::b {}\n",
+        "\n\n::a {\n    @x\n}\n",
+        "::a {\n\n    @x\n\n}\n",
+        "::a {\n    @x\n\n\n    @y\n}\n\n\n",
+        "@top\n::a {\n}\n",
+        "::a {\n}\n@top\n\n::b {}\n",
+        "/* c */\n\n::a {\n}\n",
+        "::a {\n    {\n\n        @x\n    }\n\n}\n",
+        "::a {\n    // c\n\n}\n",
+        "::a { @x }\n\n// trailing\n",
+        "::a {\n}\n\n// between\n\n::b {\n}\n",
+        "::a {\n    ## x ##\n\n    ## y ##\n    @z\n}\n",
+        "::a {\n    ##\n    x\n    ##\n\n\n    @z\n}\n",
+        "::a {\n    @bad(a=1 b=2)\n\n    @next\n}\n",
+        "::a {\n    @x\n    }\n    @y\n}\n}\n::b {}\n",
+        "\r\n\r\n::a {\r\n\r\n    @x\r\n}\r\n",
+    ];
+
+    fn workspace_root() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .to_path_buf()
+    }
+
+    /// 所有 fixture 文件的路径与内容
+    fn fixtures() -> Vec<(String, String)> {
+        let mut fixtures = Vec::new();
+        for dir in FIXTURE_DIRS {
+            let mut paths: Vec<PathBuf> = std::fs::read_dir(workspace_root().join(dir))
+                .unwrap_or_else(|e| panic!("无法读取目录 {}: {}", dir, e))
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "sixu"))
+                .collect();
+            paths.sort();
+            assert!(!paths.is_empty(), "目录 {} 中没有 .sixu 文件", dir);
+            for path in paths {
+                let content = std::fs::read_to_string(&path).unwrap();
+                fixtures.push((path.display().to_string(), content));
+            }
+        }
+        for (i, snippet) in SNIPPETS.iter().enumerate() {
+            fixtures.push((format!("snippet #{}", i), snippet.to_string()));
+        }
+        fixtures
+    }
+
+    /// 格式化两次，第二次应不产生任何变化
+    fn assert_idempotent(formatter: &CstFormatter, label: &str) {
+        for (name, source) in fixtures() {
+            let once = formatter.format(&parse_tolerant("test", &source));
+            let twice = formatter.format(&parse_tolerant("test", &once));
+            assert_eq!(
+                twice, once,
+                "{} 使用 {} 格式化两次结果不同\n--- 第一次 ---\n{}\n--- 第二次 ---\n{}",
+                name, label, once, twice
+            );
+        }
+    }
+
+    #[test]
+    fn test_default_format_is_idempotent() {
+        assert_idempotent(&CstFormatter::new(), "默认选项");
+    }
+
+    #[test]
+    fn test_format_with_options_is_idempotent() {
+        let formatters = [
+            (
+                CstFormatter::with_options(FormatOptions {
+                    indent_size: 2,
+                    empty_call_style: EmptyCallStyle::Parenthesized,
+                    ..Default::default()
+                }),
+                "2 空格缩进、空括号",
+            ),
+            (
+                CstFormatter::new().with_quote_style(QuoteStyle::Single),
+                "单引号",
+            ),
+            (CstFormatter::new().with_max_width(40), "最大行宽 40"),
+            (CstFormatter::new().with_max_blank_lines(0), "不保留空行"),
+            (CstFormatter::new().with_max_blank_lines(2), "最多两个空行"),
+        ];
+        for (formatter, label) in formatters {
+            assert_idempotent(&formatter, label);
+        }
+    }
+}
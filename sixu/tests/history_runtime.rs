@@ -0,0 +1,87 @@
+mod common;
+
+use common::RecordingExecutor;
+use sixu::error::RuntimeError;
+use sixu::runtime::Runtime;
+
+const SCRIPT: &str = r#"
+::entry {
+    first
+    @setup
+    second
+    #call paragraph="third"
+    fourth
+}
+
+::third {
+    third
+}
+"#;
+
+/// Runtime started at `entry`, pausing after each text line
+fn runtime() -> Runtime<RecordingExecutor> {
+    let executor = RecordingExecutor {
+        pause_after_text: true,
+        ..Default::default()
+    };
+    let mut runtime = common::runtime(SCRIPT, executor);
+    runtime.start("test", Some("entry")).unwrap();
+    runtime
+}
+
+fn step(runtime: &mut Runtime<RecordingExecutor>) {
+    assert!(common::step(runtime).unwrap());
+}
+
+#[test]
+fn test_rewind_replays_text() {
+    let mut runtime = runtime();
+    runtime.set_history_capacity(10);
+
+    for _ in 0..3 {
+        step(&mut runtime);
+    }
+    assert_eq!(runtime.executor().texts(), vec!["first", "second", "third"]);
+    assert_eq!(runtime.history_len(), 3);
+
+    runtime.rewind(2).unwrap();
+    assert_eq!(runtime.history_len(), 1);
+    step(&mut runtime);
+    step(&mut runtime);
+    step(&mut runtime);
+    assert_eq!(
+        runtime.executor().texts(),
+        vec!["first", "second", "third", "second", "third", "fourth"]
+    );
+}
+
+#[test]
+fn test_history_is_bounded() {
+    let mut runtime = runtime();
+    runtime.set_history_capacity(2);
+
+    for _ in 0..3 {
+        step(&mut runtime);
+    }
+    assert_eq!(runtime.history_len(), 2);
+    assert!(matches!(
+        runtime.rewind(3),
+        Err(RuntimeError::NotEnoughHistory(2))
+    ));
+
+    runtime.rewind(2).unwrap();
+    step(&mut runtime);
+    assert_eq!(runtime.executor().texts().last().unwrap(), "second");
+}
+
+#[test]
+fn test_history_is_off_by_default() {
+    let mut runtime = runtime();
+
+    step(&mut runtime);
+    assert_eq!(runtime.history_len(), 0);
+    assert!(matches!(
+        runtime.rewind(1),
+        Err(RuntimeError::NotEnoughHistory(0))
+    ));
+}
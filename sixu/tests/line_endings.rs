@@ -0,0 +1,97 @@
+use sixu::format::ChildContent;
+use sixu::parser::parse_story;
+
+/// 旧版 Mac 风格：每行只以 `\r` 结尾
+const SCRIPT: &str =
+    "::entry {\r    @bg src=\"a.png\"\r    \"first\"\r\r    ##\r    let a = 1;\r    ##\r}\r";
+
+#[test]
+fn test_lone_cr_separates_lines() {
+    let story = parse_story("test", SCRIPT).unwrap();
+    let children = &story.paragraphs[0].block.children;
+    assert_eq!(children.len(), 3);
+    assert!(matches!(children[0].content, ChildContent::CommandLine(_)));
+    assert!(matches!(children[1].content, ChildContent::TextLine(..)));
+    assert!(matches!(children[2].content, ChildContent::EmbeddedCode(_)));
+}
+
+#[test]
+fn test_lone_cr_error_position() {
+    let err = parse_story("test", "::entry {\r    \"first\"\r    @(\r}\r").unwrap_err();
+    assert_eq!((err.line, err.column), (3, 6));
+}
+
+#[cfg(feature = "cst")]
+mod cst {
+    use sixu::cst::formatter::{CstFormatter, LineEnding};
+    use sixu::cst::node::{CstNode, CstTrivia};
+    use sixu::cst::parser::parse_tolerant;
+
+    use super::SCRIPT;
+
+    #[test]
+    fn test_lone_cr_line_numbers() {
+        let cst = parse_tolerant("test", SCRIPT);
+        let CstNode::Paragraph(paragraph) = &cst.nodes[0] else {
+            panic!("expected a paragraph, got {:?}", cst.nodes[0]);
+        };
+        let lines: Vec<usize> = paragraph
+            .block
+            .children
+            .iter()
+            .filter(|node| !matches!(node, CstNode::Trivia(_)))
+            .map(|node| node.span().start_line)
+            .collect();
+        assert_eq!(lines, vec![2, 3, 5]);
+        assert_eq!(paragraph.block.close_brace.start_line, 8);
+    }
+
+    #[test]
+    fn test_lone_cr_whitespace_is_preserved() {
+        let cst = parse_tolerant("test", SCRIPT);
+        let CstNode::Paragraph(paragraph) = &cst.nodes[0] else {
+            panic!("expected a paragraph, got {:?}", cst.nodes[0]);
+        };
+        let blank = paragraph.block.children.iter().find_map(|node| match node {
+            CstNode::Trivia(trivia @ CstTrivia::Whitespace { content, .. })
+                if content.starts_with("\r\r") =>
+            {
+                Some(trivia)
+            }
+            _ => None,
+        });
+        assert!(blank.is_some_and(|trivia| trivia.has_newline()));
+    }
+
+    #[test]
+    fn test_lone_cr_formats_to_lf() {
+        let cst = parse_tolerant("test", SCRIPT);
+        let output = CstFormatter::new().format(&cst);
+        assert_eq!(
+            output,
+            "::entry {\n    @bg src=\"a.png\"\n    \"first\"\n\n    ## let a = 1; ##\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_lone_cr_formats_to_configured_ending() {
+        let cst = parse_tolerant("test", SCRIPT);
+        let expected =
+            "::entry {\n    @bg src=\"a.png\"\n    \"first\"\n\n    ## let a = 1; ##\n}\n";
+        for line_ending in [LineEnding::Cr, LineEnding::CrLf] {
+            let output = CstFormatter::new()
+                .with_line_ending(line_ending)
+                .format(&cst);
+            assert_eq!(output, expected.replace('\n', line_ending.as_str()));
+        }
+    }
+
+    #[test]
+    fn test_mixed_endings_are_unified() {
+        let cst = parse_tolerant("test", "::entry {\r\n    \"a\"\r    \"b\"\n}\n");
+        let output = CstFormatter::new()
+            .with_line_ending(LineEnding::CrLf)
+            .format(&cst);
+        assert_eq!(output, "::entry {\r\n    \"a\"\r\n    \"b\"\r\n}\r\n");
+    }
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::{run_script, RecordingExecutor};
+
+#[test]
+fn test_executor_sees_expanded_macro() {
+    let executor = run_script(
+        r#"
+::macro fadeOut {
+@fade
+@@clear
+}
+
+::macro clear {
+@clearText
+}
+
+::entry {
+@start
+@@fadeOut
+@@clear
+#finish
+}
+"#,
+        RecordingExecutor::default(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        executor.commands(),
+        vec!["start", "fade", "clearText", "clearText"]
+    );
+}
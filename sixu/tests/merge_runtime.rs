@@ -0,0 +1,71 @@
+mod common;
+
+use common::RecordingExecutor;
+use sixu::format::*;
+use sixu::runtime::Runtime;
+use sixu::StorySet;
+
+/// Run `story` from `entry` and return the text lines shown
+fn run(story: Story, entry: Option<&str>) -> Vec<String> {
+    // only the merged story is loaded, any jump out of it would need another story file
+    let mut runtime = Runtime::new(RecordingExecutor::default());
+    runtime.add_story(story);
+    runtime.start("bundle", entry).unwrap();
+    common::run(&mut runtime).unwrap();
+    runtime.executor().texts()
+}
+
+#[test]
+fn test_cross_file_goto_resolves_after_merge() {
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/merge");
+    let set = StorySet::from_files([
+        fixtures.join("prologue.sixu"),
+        fixtures.join("chapter1.sixu"),
+    ])
+    .unwrap();
+    let story = set.merge("bundle").unwrap();
+
+    let names = story
+        .paragraphs
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec![
+            "entry",
+            "prologue.entry",
+            "prologue.aside",
+            "prologue.#end",
+            "chapter1.start",
+            "chapter1.ending",
+            "chapter1.#end"
+        ]
+    );
+
+    assert_eq!(
+        run(story, Some("prologue.entry")),
+        vec!["opening", "aside", "chapter one", "the end"]
+    );
+}
+
+#[test]
+fn test_merged_story_starts_at_first_entry() {
+    let mut set = StorySet::new();
+    set.add(sixu::parser::parse_story("credits", "::roll {\n    credits\n}\n").unwrap());
+    set.add(sixu::parser::parse_story("main", "::entry {\n    hello\n}\n").unwrap());
+    let story = set.merge("bundle").unwrap();
+
+    assert_eq!(run(story, None), vec!["hello"]);
+}
+
+#[test]
+fn test_story_end_does_not_fall_into_next_story() {
+    let mut set = StorySet::new();
+    set.add(sixu::parser::parse_story("first", "::entry {\n    first\n}\n").unwrap());
+    set.add(sixu::parser::parse_story("second", "::entry {\n    second\n}\n").unwrap());
+    let story = set.merge("bundle").unwrap();
+
+    // the first story ends without `#finish`, which finishes it as it would unmerged
+    assert_eq!(run(story, None), vec!["first"]);
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use common::RecordingExecutor;
+use sixu::error::RuntimeError;
+use sixu::format::*;
+use sixu::parser::{parse_story_with_config, ParserConfig};
+use sixu::runtime::{Runtime, StepResult};
+
+const SCRIPT: &str =
+    "::entry {\n    [Alice] Hi! [Bob] \"Hello, Alice.\" #wait\n    [Alice] Bye\n}\n";
+
+fn config() -> ParserConfig {
+    ParserConfig {
+        multi_speaker: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_two_speakers_on_one_line() {
+    let story = parse_story_with_config("test", SCRIPT, &config()).unwrap();
+    let children = &story.paragraphs[0].block.children;
+    assert_eq!(
+        children[0].content,
+        ChildContent::MultiTextLine(
+            vec![
+                (
+                    LeadingText::Text("Alice".to_string()),
+                    Text::Text("Hi!".to_string())
+                ),
+                (
+                    LeadingText::Text("Bob".to_string()),
+                    Text::Text("Hello, Alice.".to_string())
+                ),
+            ],
+            TailingText::Text("wait".to_string())
+        )
+    );
+    assert!(matches!(children[1].content, ChildContent::TextLine(..)));
+}
+
+#[test]
+fn test_runtime_emits_segments_in_order() {
+    let story = parse_story_with_config("test", SCRIPT, &config()).unwrap();
+    let mut runtime = Runtime::new(RecordingExecutor::default());
+    runtime.add_story(story);
+    runtime.start("test", Some("entry")).unwrap();
+    common::run(&mut runtime).unwrap();
+
+    let text = |leading: &str, text: &str, tailing: Option<&str>| {
+        (
+            Some(leading.to_string()),
+            Some(text.to_string()),
+            tailing.map(str::to_string),
+        )
+    };
+    assert_eq!(
+        runtime.executor().text_lines(),
+        vec![
+            text("Alice", "Hi!", None),
+            text("Bob", "Hello, Alice.", Some("wait")),
+            text("Alice", "Bye", None),
+        ]
+    );
+}
+
+#[test]
+fn test_runtime_resumes_after_paused_segment() {
+    let story = parse_story_with_config("test", SCRIPT, &config()).unwrap();
+    let mut runtime = Runtime::new(RecordingExecutor {
+        pause_after_text: true,
+        ..Default::default()
+    });
+    runtime.add_story(story);
+    runtime.start("test", Some("entry")).unwrap();
+
+    let speakers = |runtime: &Runtime<RecordingExecutor>| {
+        runtime
+            .executor()
+            .text_lines()
+            .into_iter()
+            .map(|(leading, _, _)| leading.unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    // each step stops after one segment
+    assert!(matches!(runtime.step(), Ok(StepResult::Done)));
+    assert_eq!(speakers(&runtime), ["Alice"]);
+    assert!(matches!(runtime.step(), Ok(StepResult::Done)));
+    assert_eq!(speakers(&runtime), ["Alice", "Bob"]);
+    assert_eq!(
+        runtime.executor().text_lines()[1].2.as_deref(),
+        Some("wait")
+    );
+    assert!(matches!(runtime.step(), Ok(StepResult::Done)));
+    assert_eq!(speakers(&runtime), ["Alice", "Bob", "Alice"]);
+    assert!(matches!(
+        runtime.step(),
+        Err(RuntimeError::StoryFinished) | Err(RuntimeError::StoryNotStarted)
+    ));
+    assert_eq!(runtime.executor().text_lines().len(), 3);
+}
+
+#[cfg(feature = "cst")]
+mod cst {
+    use sixu::cst::formatter::CstFormatter;
+    use sixu::cst::node::CstNode;
+    use sixu::cst::parser::parse_tolerant_with_config;
+    use sixu::parser::parse_story_with_config;
+
+    use super::{config, SCRIPT};
+
+    #[test]
+    fn test_cst_matches_ast_and_formats() {
+        let cst = parse_tolerant_with_config("test", SCRIPT, &config());
+        let CstNode::Paragraph(paragraph) = &cst.nodes[0] else {
+            panic!("expected a paragraph, got {:?}", cst.nodes[0]);
+        };
+        let story = parse_story_with_config("test", SCRIPT, &config()).unwrap();
+        assert_eq!(paragraph.to_ast().unwrap(), story.paragraphs[0]);
+
+        let formatted = CstFormatter::new().format(&cst);
+        assert_eq!(formatted, SCRIPT);
+    }
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use common::{step, RecordingExecutor};
+use sixu::runtime::Runtime;
+
+/// Executor pausing at `@checkpoint`, where `counter >= 3` holds from the third
+/// `@increment` on
+fn executor() -> RecordingExecutor {
+    RecordingExecutor {
+        pause_on: vec!["checkpoint"],
+        conditions: Some(|executor, condition| match condition {
+            "counter >= 3" => executor.count("increment") >= 3,
+            _ => false,
+        }),
+        ..Default::default()
+    }
+}
+
+fn runtime(script: &str) -> Runtime<RecordingExecutor> {
+    common::runtime(script, executor())
+}
+
+fn run(script: &str) -> Vec<String> {
+    common::run_script(script, executor()).unwrap().commands()
+}
+
+const ROOMS: &str = r#"
+::entry {
+    #call paragraph="welcome"
+    #call paragraph="room"
+    @checkpoint
+    #call paragraph="welcome"
+    #call paragraph="room"
+    @end
+    #finish
+}
+
+#[once]
+::welcome {
+    @welcome
+}
+
+::room {
+    @room
+    #[once]
+    {
+        @tutorial
+        @tutorial_more
+    }
+    #[once]
+    @hint
+}
+"#;
+
+#[test]
+fn test_once_runs_only_the_first_time() {
+    assert_eq!(
+        run(ROOMS),
+        vec![
+            "welcome",
+            "room",
+            "tutorial",
+            "tutorial_more",
+            "hint",
+            "checkpoint",
+            "room",
+            "end",
+        ]
+    );
+}
+
+#[test]
+fn test_once_inside_loop() {
+    let script = r#"
+::entry {
+    #[loop]
+    {
+        @increment
+        {
+            #[once]
+            @tutorial
+        }
+        #[cond("counter >= 3")]
+        #break
+    }
+    #[once]
+    @after
+}
+"#;
+    assert_eq!(
+        run(script),
+        vec!["increment", "tutorial", "increment", "increment", "after"]
+    );
+}
+
+#[test]
+fn test_once_identity() {
+    let mut runtime = runtime(ROOMS);
+    runtime.start("test", Some("entry")).unwrap();
+    step(&mut runtime).unwrap();
+
+    let visited: Vec<_> = runtime.context().visited_once().iter().cloned().collect();
+    assert_eq!(
+        visited,
+        vec!["test::room/1", "test::room/2", "test::welcome"]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_once_survives_save_and_restore() {
+    let mut runtime = runtime(ROOMS);
+    runtime.start("test", Some("entry")).unwrap();
+    assert!(step(&mut runtime).unwrap());
+    assert_eq!(runtime.executor().commands().last().unwrap(), "checkpoint");
+
+    let saved = serde_json::to_string(&runtime.save().unwrap()).unwrap();
+
+    // a new session loaded from the save continues with the tutorial already seen
+    let mut runtime = self::runtime(ROOMS);
+    runtime
+        .restore(serde_json::from_str(&saved).unwrap())
+        .unwrap();
+    common::run(&mut runtime).unwrap();
+    assert_eq!(runtime.executor().commands(), vec!["room", "end"]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_restore_archive_without_save_state() {
+    let mut runtime = runtime(ROOMS);
+    runtime.start("test", Some("entry")).unwrap();
+    assert!(step(&mut runtime).unwrap());
+
+    // archives written before SaveState hold the bare execution stack
+    let saved = serde_json::to_string(&runtime.save().unwrap().stack).unwrap();
+    assert!(saved.starts_with('['));
+    let state: sixu::runtime::SaveState = serde_json::from_str(&saved).unwrap();
+    assert!(state.visited_once.is_empty());
+
+    let mut runtime = self::runtime(ROOMS);
+    runtime.restore(state).unwrap();
+    // nothing was recorded as visited, so the #[once] content runs again
+    common::run(&mut runtime).unwrap();
+    assert_eq!(
+        runtime.executor().commands(),
+        vec![
+            "welcome",
+            "room",
+            "tutorial",
+            "tutorial_more",
+            "hint",
+            "end"
+        ]
+    );
+}
@@ -0,0 +1,170 @@
+mod common;
+
+use common::RecordingExecutor;
+use sixu::error::RuntimeError;
+
+/// Commands and paragraph enter/exit events in order
+fn run(script: &str) -> Vec<String> {
+    common::run_script(script, RecordingExecutor::default())
+        .unwrap()
+        .log()
+}
+
+#[test]
+fn test_call_enters_and_exits_callee() {
+    let script = r#"
+::entry {
+    @before
+    #call paragraph="greet" name="alice"
+    @after
+    #finish
+}
+
+::greet(name) {
+    {
+        @hello
+    }
+    #return
+}
+"#;
+    assert_eq!(
+        run(script),
+        vec![
+            "enter test::entry",
+            "@before",
+            "enter test::greet(alice)",
+            "@hello",
+            "exit test::greet",
+            "@after",
+            "exit test::entry",
+            "finished",
+        ]
+    );
+}
+
+#[test]
+fn test_goto_and_replace_exit_before_enter() {
+    let script = r#"
+::entry {
+    #call paragraph="middle"
+    @unreachable
+}
+
+::middle {
+    {
+        #replace paragraph="swapped"
+    }
+}
+
+::swapped {
+    @swapped
+    #goto paragraph="last"
+}
+
+::last {
+    @last
+}
+"#;
+    assert_eq!(
+        run(script),
+        vec![
+            "enter test::entry",
+            "enter test::middle",
+            "exit test::middle",
+            "enter test::swapped",
+            "@swapped",
+            "exit test::swapped",
+            "exit test::entry",
+            "enter test::last",
+            "@last",
+            "exit test::last",
+            "finished",
+        ]
+    );
+}
+
+#[test]
+fn test_running_into_next_paragraph() {
+    let script = "::entry {\n    @first\n}\n\n::next {\n    @second\n}\n";
+    assert_eq!(
+        run(script),
+        vec![
+            "enter test::entry",
+            "@first",
+            "exit test::entry",
+            "enter test::next",
+            "@second",
+            "exit test::next",
+            "finished",
+        ]
+    );
+}
+
+#[test]
+fn test_failed_binding_does_not_enter_paragraph() {
+    let script = r#"
+::entry {
+    #call paragraph="greet" name="alice" mood="happy"
+}
+
+::greet(name) {
+    @hello
+}
+"#;
+    let mut runtime = common::runtime(script, RecordingExecutor::default());
+    runtime.start("test", Some("entry")).unwrap();
+    let depth = runtime.context().stack().len();
+
+    assert!(matches!(
+        runtime.step(),
+        Err(RuntimeError::UnknownParagraphArgument { .. })
+    ));
+    assert_eq!(runtime.context().stack().len(), depth);
+    assert_eq!(runtime.context().stack()[depth - 1].paragraph, "entry");
+    // neither hook of the callee fires
+    assert_eq!(runtime.executor().log(), vec!["enter test::entry"]);
+}
+
+#[test]
+fn test_failed_jump_keeps_the_stack() {
+    for jump in ["goto", "replace"] {
+        let script = format!(
+            r#"
+::entry {{
+    #call paragraph="middle"
+}}
+
+::middle {{
+    #{} paragraph="greet" typo=1
+}}
+
+::greet(name) {{
+    @hello
+}}
+"#,
+            jump
+        );
+        let mut runtime = common::runtime(&script, RecordingExecutor::default());
+        runtime.start("test", Some("entry")).unwrap();
+
+        // the call runs straight into the jump
+        assert!(
+            matches!(
+                runtime.step(),
+                Err(RuntimeError::UnknownParagraphArgument { .. })
+            ),
+            "{}",
+            jump
+        );
+        let stack = runtime.context().stack();
+        assert_eq!(stack.len(), 2, "{}", jump);
+        assert_eq!(stack[1].paragraph, "middle", "{}", jump);
+        // nothing was left before the error
+        assert_eq!(
+            runtime.executor().log(),
+            vec!["enter test::entry", "enter test::middle"],
+            "{}",
+            jump
+        );
+    }
+}
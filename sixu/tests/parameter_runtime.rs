@@ -0,0 +1,278 @@
+mod common;
+
+use common::RecordingExecutor;
+use sixu::error::RuntimeError;
+
+/// Run `entry` of `script` with `@set` storing its arguments as locals of the running
+/// paragraph
+fn run_story(script: &str) -> Result<RecordingExecutor, RuntimeError> {
+    let executor = RecordingExecutor {
+        on_command: Some(|ctx, command_line| {
+            if command_line.command == "set" {
+                for arg in &command_line.arguments {
+                    assert!(ctx.set_local(&arg.name, arg.value.clone()));
+                }
+            }
+        }),
+        ..Default::default()
+    };
+    common::run_script(script, executor)
+}
+
+/// Every `@show` command as `name=value` pairs, so tests can observe what variables
+/// resolved to
+fn shown(executor: &RecordingExecutor) -> Vec<String> {
+    executor
+        .command_lines()
+        .filter(|command_line| command_line.command == "show")
+        .map(|command_line| {
+            command_line
+                .arguments
+                .iter()
+                .map(|arg| format!("{}={}", arg.name, arg.value))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+#[test]
+fn test_default_references_earlier_parameter() {
+    let executor = run_story(
+        r#"
+::entry {
+#call paragraph="pair" a=1
+#call paragraph="pair" a=2 b=3
+#finish
+}
+
+::pair(a, b=a) {
+@show a=a b=b
+}
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(shown(&executor), vec!["a=1 b=1", "a=2 b=3"]);
+}
+
+#[test]
+fn test_default_chain_is_evaluated_left_to_right() {
+    let executor = run_story(
+        r#"
+::entry {
+#call paragraph="chain" a="x"
+#finish
+}
+
+::chain(a, b=a, c=b) {
+`${a}${b}${c}`
+}
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(executor.texts(), vec!["xxx"]);
+}
+
+#[test]
+fn test_default_forward_reference_is_an_error() {
+    let result = run_story(
+        r#"
+::entry {
+#call paragraph="forward"
+}
+
+::forward(a=b, b=1) {
+@show a=a
+}
+"#,
+    );
+
+    match result {
+        Err(RuntimeError::ForwardParameterReference {
+            parameter,
+            referenced,
+        }) => {
+            assert_eq!(parameter, "a");
+            assert_eq!(referenced, "b");
+        }
+        other => panic!("expected forward reference error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn test_return_value_is_bound_in_caller_scope() {
+    let executor = run_story(
+        r#"
+::entry {
+#call paragraph="double" n=21
+@show result=result
+#finish
+}
+
+::double(n) {
+#[loop]
+{
+    #return value=n
+}
+unreachable
+}
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(shown(&executor), vec!["result=21"]);
+    assert!(executor.texts().is_empty());
+}
+
+#[test]
+fn test_return_without_value_binds_null() {
+    let executor = run_story(
+        r#"
+::entry {
+#call paragraph="noop"
+@show result=result
+#finish
+}
+
+::noop {
+#return
+}
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(shown(&executor), vec!["result=null"]);
+}
+
+#[test]
+fn test_return_from_nested_block_skips_rest_of_paragraph() {
+    let executor = run_story(
+        r#"
+::entry {
+#call paragraph="pick"
+@show result=result
+#finish
+}
+
+::pick {
+{
+    {
+        #return value="inner"
+    }
+    @show skipped=1
+}
+@show skipped=2
+}
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(shown(&executor), vec!["result=inner"]);
+}
+
+#[test]
+fn test_return_outside_call_finishes_the_story() {
+    let executor = run_story(
+        r#"
+::entry {
+@show step=1
+{
+    #return value=1
+}
+@show step=2
+}
+
+::next {
+@show step=3
+}
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(shown(&executor), vec!["step=1"]);
+}
+
+#[test]
+fn test_call_and_goto_bind_arguments() {
+    let executor = run_story(
+        r#"
+::entry {
+#call paragraph="greet" name="Alice"
+#goto paragraph="scene" location="park"
+}
+
+::greet(name) {
+`Hello, ${name}!`
+}
+
+::scene(location, time="morning") {
+@show location=location time=time
+#finish
+}
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(executor.texts(), vec!["Hello, Alice!"]);
+    assert_eq!(shown(&executor), vec!["location=park time=morning"]);
+}
+
+#[test]
+fn test_unknown_argument_is_an_error() {
+    let result = run_story(
+        r#"
+::entry {
+#call paragraph="greet" name="Alice" mood="happy"
+}
+
+::greet(name) {
+`Hello, ${name}!`
+}
+"#,
+    );
+
+    match result {
+        Err(RuntimeError::UnknownParagraphArgument {
+            paragraph,
+            argument,
+        }) => {
+            assert_eq!(paragraph, "greet");
+            assert_eq!(argument, "mood");
+        }
+        other => panic!("expected unknown argument error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn test_locals_do_not_leak_between_calls() {
+    let executor = run_story(
+        r#"
+::entry {
+#call paragraph="visit" name="Alice"
+#call paragraph="visit" name="Bob"
+@show name=name memo=memo
+#finish
+}
+
+::visit(name) {
+@show name=name memo=memo
+@set memo=name
+@show memo=memo
+}
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        shown(&executor),
+        vec![
+            "name=Alice memo=null",
+            "memo=Alice",
+            "name=Bob memo=null",
+            "memo=Bob",
+            "name=null memo=null",
+        ]
+    );
+}
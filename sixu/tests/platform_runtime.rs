@@ -0,0 +1,92 @@
+mod common;
+
+use common::RecordingExecutor;
+use sixu::error::RuntimeError;
+use sixu::runtime::Runtime;
+
+const SCRIPT: &str = r#"
+::entry {
+    intro
+    #[platform("desktop")]
+    {
+        desktop_hint
+    }
+    #[platform("mobile")]
+    #[platform("tablet")]
+    touch_hint
+}
+
+#[platform("mobile")]
+::mobile_tutorial {
+    swipe_to_continue
+}
+
+::ending {
+    the_end
+}
+"#;
+
+fn runtime(flags: &[&str]) -> Runtime<RecordingExecutor> {
+    let mut runtime = common::runtime(SCRIPT, RecordingExecutor::default());
+    runtime
+        .feature_flags_mut()
+        .extend(flags.iter().map(|flag| flag.to_string()));
+    runtime
+}
+
+fn run(runtime: &mut Runtime<RecordingExecutor>, entry: &str) -> Result<Vec<String>, RuntimeError> {
+    runtime.start("test", Some(entry))?;
+    common::run(runtime)?;
+    Ok(runtime.executor().texts())
+}
+
+#[test]
+fn test_paragraph_for_inactive_flag_is_skipped() {
+    let mut runtime = runtime(&[]);
+    assert_eq!(
+        runtime.list_paragraphs("test").unwrap(),
+        vec!["entry", "ending"]
+    );
+    assert_eq!(
+        run(&mut runtime, "entry").unwrap(),
+        vec!["intro", "the_end"]
+    );
+}
+
+#[test]
+fn test_content_for_active_flag_is_included() {
+    let mut runtime = runtime(&["mobile"]);
+    assert_eq!(
+        runtime.list_paragraphs("test").unwrap(),
+        vec!["entry", "mobile_tutorial", "ending"]
+    );
+    assert_eq!(
+        run(&mut runtime, "entry").unwrap(),
+        vec!["intro", "touch_hint", "swipe_to_continue", "the_end"]
+    );
+}
+
+#[test]
+fn test_any_listed_flag_includes_child() {
+    let mut runtime = runtime(&["desktop", "tablet"]);
+    assert_eq!(
+        run(&mut runtime, "entry").unwrap(),
+        vec!["intro", "desktop_hint", "touch_hint", "the_end"]
+    );
+}
+
+#[test]
+fn test_excluded_paragraph_cannot_be_entered() {
+    let mut runtime = runtime(&["desktop"]);
+    assert!(matches!(
+        run(&mut runtime, "mobile_tutorial"),
+        Err(RuntimeError::ParagraphNotFound(name)) if name == "mobile_tutorial"
+    ));
+    assert_eq!(
+        runtime.execution_order("test", "entry"),
+        vec![
+            ("test".to_string(), "entry".to_string()),
+            ("test".to_string(), "ending".to_string())
+        ]
+    );
+}
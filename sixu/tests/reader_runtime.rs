@@ -0,0 +1,165 @@
+mod common;
+
+use std::io::{Cursor, Read};
+
+use common::RecordingExecutor;
+use sixu::error::{ReadError, RuntimeError};
+use sixu::parser::parse_story_from_reader;
+use sixu::runtime::Runtime;
+
+const SCRIPT: &str =
+    "::entry {\n    \"你好\"\n    #goto paragraph=\"next\"\n}\n\n::next {\n    再见\n}\n";
+
+/// Reader failing after handing out `ok` bytes
+struct Failing<'a> {
+    ok: &'a [u8],
+}
+
+impl Read for Failing<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.ok.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "connection reset",
+            ));
+        }
+        let n = self.ok.len().min(buf.len());
+        buf[..n].copy_from_slice(&self.ok[..n]);
+        self.ok = &self.ok[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_load_story_from_in_memory_reader() {
+    let mut runtime = Runtime::new(RecordingExecutor::default());
+    runtime
+        .load_story_from_reader("test", Cursor::new(SCRIPT.as_bytes()))
+        .unwrap();
+    assert_eq!(
+        runtime.list_paragraphs("test").unwrap(),
+        vec!["entry", "next"]
+    );
+
+    runtime.start("test", None).unwrap();
+    common::run(&mut runtime).unwrap();
+    assert_eq!(runtime.executor().texts(), vec!["你好", "再见"]);
+}
+
+#[test]
+fn test_invalid_utf8_is_a_structured_error() {
+    // an invalid byte in the middle of the second line
+    let mut bytes = b"::entry {\n    \"ab".to_vec();
+    bytes.push(0xff);
+    bytes.extend_from_slice(b"\"\n}\n");
+
+    let err = parse_story_from_reader("test", bytes.as_slice()).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ReadError::InvalidUtf8 {
+                offset: 17,
+                line: 2
+            }
+        ),
+        "{:?}",
+        err
+    );
+
+    let mut runtime = Runtime::new(RecordingExecutor::default());
+    let err = runtime.provide_story_data("test", bytes).unwrap_err();
+    assert!(matches!(
+        err,
+        RuntimeError::ReadError(ReadError::InvalidUtf8 { offset: 17, .. })
+    ));
+    assert!(runtime.list_paragraphs("test").is_err());
+}
+
+#[test]
+fn test_io_error_and_parse_error() {
+    let err = parse_story_from_reader("test", Failing { ok: b"::entry {" }).unwrap_err();
+    assert!(matches!(err, ReadError::Io(ref e) if e.kind() == std::io::ErrorKind::ConnectionReset));
+
+    let err = parse_story_from_reader("test", "::entry {".as_bytes()).unwrap_err();
+    assert!(matches!(err, ReadError::Parse(_)));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_executor_parses_precompiled_story() {
+    let story = sixu::parser::parse_story("test", SCRIPT).unwrap();
+    let data = serde_json::to_vec(&story).unwrap();
+
+    let mut runtime = Runtime::new(RecordingExecutor {
+        precompiled: true,
+        ..Default::default()
+    });
+    runtime.provide_story_data("test", data).unwrap();
+    assert_eq!(
+        runtime.list_paragraphs("test").unwrap(),
+        vec!["entry", "next"]
+    );
+
+    runtime.start("test", None).unwrap();
+    common::run(&mut runtime).unwrap();
+    assert_eq!(runtime.executor().texts(), vec!["你好", "再见"]);
+
+    // source text is not a precompiled story
+    let err = runtime.provide_story_data("other", SCRIPT.as_bytes().to_vec());
+    assert!(err.is_err());
+    assert!(runtime.list_paragraphs("other").is_err());
+}
+
+const DUPLICATE_ENTRY: &str = "::entry {\n    first\n}\n\n::entry {\n    second\n}\n";
+
+#[test]
+fn test_strict_runtime_rejects_duplicate_paragraphs() {
+    let mut runtime = Runtime::new_strict(RecordingExecutor::default());
+    let err = runtime
+        .provide_story_data("test", DUPLICATE_ENTRY.as_bytes().to_vec())
+        .unwrap_err();
+    assert!(matches!(err, RuntimeError::DuplicateParagraph(ref name) if name == "entry"));
+    assert!(!runtime.has_story("test"));
+
+    let story = sixu::parser::parse_story("test", DUPLICATE_ENTRY).unwrap();
+    assert!(matches!(
+        runtime.try_add_story(story),
+        Err(RuntimeError::DuplicateParagraph(_))
+    ));
+    assert!(!runtime.has_story("test"));
+
+    // add_story does not bypass the check
+    let story = sixu::parser::parse_story("test", DUPLICATE_ENTRY).unwrap();
+    runtime.add_story(story);
+    assert!(!runtime.has_story("test"));
+
+    // a lenient runtime keeps the story and uses the first paragraph
+    let mut runtime = Runtime::new(RecordingExecutor::default());
+    runtime
+        .provide_story_data("test", DUPLICATE_ENTRY.as_bytes().to_vec())
+        .unwrap();
+    let story = sixu::parser::parse_story("test", DUPLICATE_ENTRY).unwrap();
+    assert_eq!(
+        runtime.get_paragraph("test", "entry").unwrap(),
+        &story.paragraphs[0]
+    );
+}
+
+#[test]
+fn test_strict_runtime_allows_platform_variants() {
+    let script = concat!(
+        "#[platform(\"mobile\")]\n::menu {\n    tap\n}\n\n",
+        "#[platform(\"desktop\")]\n::menu {\n    click\n}\n",
+    );
+    let mut runtime = Runtime::new_strict(RecordingExecutor::default());
+    runtime.feature_flags_mut().insert("desktop".to_string());
+    runtime
+        .load_story_from_reader("test", Cursor::new(script.as_bytes()))
+        .unwrap();
+
+    runtime.feature_flags_mut().insert("mobile".to_string());
+    let err = runtime
+        .load_story_from_reader("other", Cursor::new(script.as_bytes()))
+        .unwrap_err();
+    assert!(matches!(err, RuntimeError::DuplicateParagraph(ref name) if name == "menu"));
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use common::RecordingExecutor;
+use sixu::error::RuntimeError;
+use sixu::format::*;
+use sixu::parser::parse_story;
+use sixu::runtime::{ExecutionState, Runtime, RuntimeContext, RuntimeExecutor, StepResult};
+
+fn variable(path: &str) -> RValue {
+    RValue::Variable(Variable {
+        chain: path.split('.').map(str::to_string).collect(),
+    })
+}
+
+fn object(entries: Vec<(&str, Literal)>) -> Literal {
+    Literal::Object(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+/// Context whose archive holds `a = { b: { c: 42, flag: true }, name: "sixu" }`
+fn context() -> RuntimeContext {
+    let mut ctx = RuntimeContext::new();
+    *ctx.archive_variables_mut() = object(vec![(
+        "a",
+        object(vec![
+            (
+                "b",
+                object(vec![
+                    ("c", Literal::Integer(42)),
+                    ("flag", Literal::Boolean(true)),
+                ]),
+            ),
+            ("name", Literal::String("sixu".to_string())),
+            ("next", Literal::String("ending".to_string())),
+        ]),
+    )]);
+    ctx
+}
+
+#[test]
+fn test_resolve_nested_chain() {
+    let ctx = context();
+    let executor = RecordingExecutor::default();
+
+    let value = executor.resolve_literal(&ctx, &variable("a.b.c")).unwrap();
+    assert_eq!(*value.as_integer().unwrap(), 42);
+    let flag = executor
+        .resolve_literal(&ctx, &variable("a.b.flag"))
+        .unwrap();
+    assert!(*flag.as_boolean().unwrap());
+    let b = executor.resolve_literal(&ctx, &variable("a.b")).unwrap();
+    assert_eq!(b.as_object().unwrap().len(), 2);
+}
+
+#[test]
+fn test_resolve_literal_value() {
+    let ctx = context();
+    let value = RValue::Literal(Literal::Float(1.5));
+    assert_eq!(
+        RecordingExecutor::default()
+            .resolve_literal(&ctx, &value)
+            .unwrap(),
+        Literal::Float(1.5)
+    );
+}
+
+#[test]
+fn test_resolve_missing_key() {
+    let ctx = context();
+    let executor = RecordingExecutor::default();
+
+    let err = executor
+        .resolve_literal(&ctx, &variable("a.x.c"))
+        .unwrap_err();
+    assert!(matches!(&err, RuntimeError::VariableNotFound(path) if path == "a.x"));
+    let err = executor
+        .resolve_literal(&ctx, &variable("missing"))
+        .unwrap_err();
+    assert!(matches!(&err, RuntimeError::VariableNotFound(path) if path == "missing"));
+}
+
+#[test]
+fn test_resolve_through_non_object() {
+    let ctx = context();
+    let err = RecordingExecutor::default()
+        .resolve_literal(&ctx, &variable("a.name.length"))
+        .unwrap_err();
+    assert!(matches!(err, RuntimeError::NotAObject));
+}
+
+#[test]
+fn test_resolve_in_non_object_archive() {
+    let mut ctx = RuntimeContext::new();
+    *ctx.archive_variables_mut() = Literal::Integer(1);
+    let executor = RecordingExecutor::default();
+
+    // fails like `get_variable` instead of looking further in the globals
+    let err = executor
+        .resolve_literal(&ctx, &variable("volume"))
+        .unwrap_err();
+    assert!(matches!(err, RuntimeError::NotAObject));
+    let volume = Variable {
+        chain: vec!["volume".to_string()],
+    };
+    assert!(matches!(
+        executor.get_variable(&ctx, &volume),
+        Err(RuntimeError::NotAObject)
+    ));
+}
+
+#[test]
+fn test_resolve_prefers_locals_then_archive_then_globals() {
+    let mut ctx = context();
+    *ctx.global_variables_mut() = object(vec![
+        ("a", Literal::Integer(0)),
+        ("volume", Literal::Integer(80)),
+    ]);
+    ctx.stack_mut().push(ExecutionState::new(
+        "test".to_string(),
+        "entry".to_string(),
+        Block::default(),
+    ));
+    ctx.set_local(
+        "a",
+        object(vec![("b", object(vec![("c", Literal::Integer(7))]))]),
+    );
+    let executor = RecordingExecutor::default();
+
+    let local = executor.resolve_literal(&ctx, &variable("a.b.c")).unwrap();
+    assert_eq!(local, Literal::Integer(7));
+    let global = executor.resolve_literal(&ctx, &variable("volume")).unwrap();
+    assert_eq!(global, Literal::Integer(80));
+}
+
+/// Runtime over the archive of [`context`], pausing after each text
+fn runtime(script: &str) -> Runtime<RecordingExecutor> {
+    let executor = RecordingExecutor {
+        pause_after_text: true,
+        ..Default::default()
+    };
+    let mut runtime = Runtime::new_with_context(executor, context());
+    runtime.add_story(parse_story("test", script).unwrap());
+    runtime.start("test", Some("entry")).unwrap();
+    runtime
+}
+
+#[test]
+fn test_jump_target_follows_chain() {
+    let mut runtime =
+        runtime("::entry {\n    #goto paragraph=a.next\n}\n\n::ending {\n    hello\n}\n");
+
+    assert!(matches!(runtime.step(), Ok(StepResult::Done)));
+    assert_eq!(
+        runtime.context().stack().last().unwrap().paragraph,
+        "ending"
+    );
+}
+
+#[test]
+fn test_jump_target_missing_variable() {
+    let mut runtime = runtime("::entry {\n    #call paragraph=a.previous\n}\n");
+
+    let err = runtime.step().unwrap_err();
+    assert!(matches!(&err, RuntimeError::VariableNotFound(path) if path == "a.previous"));
+}
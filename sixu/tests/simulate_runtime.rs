@@ -0,0 +1,390 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+
+use common::RecordingExecutor;
+use sixu::format::*;
+use sixu::parser::parse_story;
+use sixu::{SimEvent, SIMULATION_STEP_LIMIT};
+
+/// Simulate `entry` of `script`. `"true"` and `"false"` answer themselves, other
+/// conditions take their answers from `answers` in order and are false afterwards.
+fn simulate(script: &str, answers: &[(&str, &[bool])]) -> Vec<String> {
+    let story = parse_story("test", script).unwrap();
+    let answers: RefCell<HashMap<&str, VecDeque<bool>>> = RefCell::new(
+        answers
+            .iter()
+            .map(|(condition, values)| (*condition, values.iter().copied().collect()))
+            .collect(),
+    );
+    let oracle = |condition: &str| match condition.trim() {
+        "true" => true,
+        "false" => false,
+        other => answers
+            .borrow_mut()
+            .get_mut(other)
+            .and_then(|values| values.pop_front())
+            .unwrap_or(false),
+    };
+
+    story
+        .simulate("entry", oracle)
+        .into_iter()
+        .map(|event| match event {
+            SimEvent::Text { text, .. } => text.unwrap_or_default(),
+            SimEvent::Command(command) => format!("@{}", command.command),
+            SimEvent::SystemCall(call) => format!("#{}", call.command),
+            SimEvent::EnterParagraph { .. } | SimEvent::LeaveParagraph { .. } => {
+                unreachable!("paragraph events are only recorded by record_events")
+            }
+        })
+        .collect()
+}
+
+// ==================== cond / if ====================
+
+#[test]
+fn test_cond_true_and_false() {
+    let events = simulate(
+        "::entry {\n#[cond(\"true\")]\ntext_visible\n#[cond(\"false\")]\ntext_hidden\ntext_after\n}\n",
+        &[],
+    );
+    assert_eq!(events, vec!["text_visible", "text_after"]);
+}
+
+#[test]
+fn test_if_alias_works_same_as_cond() {
+    let events = simulate(
+        "::entry {\n#[if(\"true\")]\nvisible\n#[if(\"false\")]\nhidden\nafter\n}\n",
+        &[],
+    );
+    assert_eq!(events, vec!["visible", "after"]);
+}
+
+#[test]
+fn test_cond_on_block_and_command() {
+    let script = r#"
+::entry {
+#[cond("true")]
+{
+  block_text
+}
+#[cond("false")]
+{
+  hidden_block
+}
+#[cond("true")]
+@visible_cmd arg=1
+#[cond("false")]
+@hidden_cmd arg=2
+@always_cmd arg=3
+}
+"#;
+    assert_eq!(
+        simulate(script, &[]),
+        vec!["block_text", "@visible_cmd", "@always_cmd"]
+    );
+}
+
+#[test]
+fn test_multiple_attributes_only_last_used() {
+    let events = simulate(
+        "::entry {\n#[cond(\"true\")]\n#[cond(\"false\")]\nshould_be_hidden\nafter\n}\n",
+        &[],
+    );
+    assert_eq!(events, vec!["after"]);
+}
+
+#[test]
+fn test_cond_on_systemcall() {
+    let script = r#"
+::entry {
+text_before
+#[cond("false")]
+#goto paragraph="other"
+text_after
+}
+
+::other {
+other_text
+}
+"#;
+    // The story falls through to `other` after `entry` ends
+    assert_eq!(
+        simulate(script, &[]),
+        vec!["text_before", "text_after", "other_text"]
+    );
+}
+
+// ==================== while / loop ====================
+
+#[test]
+fn test_while_loop_with_block() {
+    let script = r#"
+::entry {
+#[while("counter < 3")]
+{
+  @increment
+}
+after_loop
+}
+"#;
+    let events = simulate(script, &[("counter < 3", &[true, true, true])]);
+    assert_eq!(
+        events,
+        vec!["@increment", "@increment", "@increment", "after_loop"]
+    );
+}
+
+#[test]
+fn test_while_false_skips_entirely() {
+    let events = simulate(
+        "::entry {\n#[while(\"false\")]\n{\n  @never_runs\n}\nafter\n}\n",
+        &[],
+    );
+    assert_eq!(events, vec!["after"]);
+}
+
+#[test]
+fn test_while_on_single_command() {
+    let script = "::entry {\n#[while(\"counter < 3\")]\n@increment\n\"after loop\"\n}\n";
+    let events = simulate(script, &[("counter < 3", &[true, true, true])]);
+    assert_eq!(
+        events,
+        vec!["@increment", "@increment", "@increment", "after loop"]
+    );
+}
+
+#[test]
+fn test_loop_with_break() {
+    let script = r#"
+::entry {
+#[loop]
+{
+  @increment
+  #[cond("counter < 3")]
+  #continue
+  #break
+}
+after_loop
+}
+"#;
+    let events = simulate(script, &[("counter < 3", &[true, true, false])]);
+    assert_eq!(
+        events,
+        vec![
+            "@increment",
+            "#continue",
+            "@increment",
+            "#continue",
+            "@increment",
+            "#break",
+            "after_loop"
+        ]
+    );
+}
+
+#[test]
+fn test_loop_break_immediately() {
+    let events = simulate(
+        "::entry {\n#[loop]\n{\n  #break\n}\n\"after loop\"\n}\n",
+        &[],
+    );
+    assert_eq!(events, vec!["#break", "after loop"]);
+}
+
+#[test]
+fn test_continue_skips_rest_of_iteration() {
+    let script = r#"
+::entry {
+#[while("counter < 5")]
+{
+  @increment
+  #[cond("counter < 3")]
+  #continue
+  @after_continue
+}
+done
+}
+"#;
+    let events = simulate(
+        script,
+        &[
+            ("counter < 5", &[true, true, true, true, true]),
+            ("counter < 3", &[true, true, false, false, false]),
+        ],
+    );
+    let commands: Vec<_> = events.iter().filter(|e| e.starts_with('@')).collect();
+    assert_eq!(
+        commands,
+        vec![
+            "@increment",
+            "@increment",
+            "@increment",
+            "@after_continue",
+            "@increment",
+            "@after_continue",
+            "@increment",
+            "@after_continue"
+        ]
+    );
+    assert_eq!(events.last().unwrap(), "done");
+}
+
+#[test]
+fn test_nested_cond_in_while() {
+    let script = r#"
+::entry {
+#[while("counter < 3")]
+{
+  #[cond("true")]
+  @increment
+}
+done
+}
+"#;
+    let events = simulate(script, &[("counter < 3", &[true, true, true])]);
+    assert_eq!(
+        events,
+        vec!["@increment", "@increment", "@increment", "done"]
+    );
+}
+
+// ==================== limits ====================
+
+#[test]
+fn test_endless_loop_is_capped() {
+    let events = simulate("::entry {\n#[loop]\n{\n  @tick\n}\n}\n", &[]);
+    assert!(events.len() <= SIMULATION_STEP_LIMIT);
+    assert!(events.iter().all(|e| e == "@tick"));
+}
+
+#[test]
+fn test_endless_jump_cycle_is_capped() {
+    let events = simulate("::entry {\n#goto paragraph=\"entry\"\n}\n", &[]);
+    assert_eq!(events.len(), SIMULATION_STEP_LIMIT);
+}
+
+// ==================== record_events ====================
+
+const GREETING: &str = r#"
+::entry {
+#[cond("morning")]
+@bgm name="birds"
+Hello
+#call paragraph="farewell"
+#finish
+}
+
+::farewell {
+@fade time=500
+}
+"#;
+
+fn record(morning: bool) -> Vec<SimEvent> {
+    let runtime = common::runtime(GREETING, RecordingExecutor::default());
+    let events = runtime.record_events("test", "entry", |condition| {
+        condition == "morning" && morning
+    });
+    // recording does not reach the executor
+    assert!(runtime.executor().events.is_empty());
+    events
+}
+
+#[test]
+fn test_record_events_with_paragraph_boundaries() {
+    let enter = |paragraph: &str| SimEvent::EnterParagraph {
+        story: "test".to_string(),
+        paragraph: paragraph.to_string(),
+    };
+    let leave = |paragraph: &str| SimEvent::LeaveParagraph {
+        story: "test".to_string(),
+        paragraph: paragraph.to_string(),
+    };
+    let command = |name: &str, arg: &str, value: Literal| {
+        SimEvent::Command(ResolvedCommandLine {
+            command: name.to_string(),
+            arguments: vec![ResolvedArgument {
+                name: arg.to_string(),
+                value,
+                span: None,
+            }],
+        })
+    };
+    let text = SimEvent::Text {
+        leading: None,
+        text: Some("Hello".to_string()),
+        tailing: None,
+    };
+    let call = SimEvent::SystemCall(ResolvedSystemCallLine {
+        command: "call".to_string(),
+        arguments: vec![ResolvedArgument {
+            name: "paragraph".to_string(),
+            value: Literal::String("farewell".to_string()),
+            span: None,
+        }],
+    });
+    let finish = SimEvent::SystemCall(ResolvedSystemCallLine {
+        command: "finish".to_string(),
+        arguments: vec![],
+    });
+    let fade = command("fade", "time", Literal::Integer(500));
+
+    assert_eq!(
+        record(false),
+        vec![
+            enter("entry"),
+            text.clone(),
+            call.clone(),
+            enter("farewell"),
+            fade.clone(),
+            leave("farewell"),
+            finish.clone(),
+            leave("entry"),
+        ]
+    );
+    assert_eq!(
+        record(true),
+        vec![
+            enter("entry"),
+            command("bgm", "name", Literal::String("birds".to_string())),
+            text,
+            call,
+            enter("farewell"),
+            fade,
+            leave("farewell"),
+            finish,
+            leave("entry"),
+        ]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_record_events_serialized_form() {
+    let events = serde_json::to_value(record(false)).unwrap();
+    assert_eq!(
+        events,
+        serde_json::json!([
+            { "EnterParagraph": { "story": "test", "paragraph": "entry" } },
+            { "Text": { "leading": null, "text": "Hello", "tailing": null } },
+            {
+                "SystemCall": {
+                    "command": "call",
+                    "arguments": [{ "name": "paragraph", "value": "farewell" }]
+                }
+            },
+            { "EnterParagraph": { "story": "test", "paragraph": "farewell" } },
+            {
+                "Command": {
+                    "command": "fade",
+                    "arguments": [{ "name": "time", "value": 500 }]
+                }
+            },
+            { "LeaveParagraph": { "story": "test", "paragraph": "farewell" } },
+            { "SystemCall": { "command": "finish", "arguments": [] } },
+            { "LeaveParagraph": { "story": "test", "paragraph": "entry" } },
+        ])
+    );
+}
@@ -0,0 +1,111 @@
+use sixu::error::{SyntaxError, SyntaxErrorKind};
+use sixu::parse_strict;
+
+/// Kind, line, column and message of every error of `script`
+fn errors(script: &str) -> Vec<(SyntaxErrorKind, usize, usize, String)> {
+    parse_strict("test", script)
+        .unwrap_err()
+        .into_iter()
+        .map(|e: SyntaxError| (e.kind, e.line, e.column, e.message))
+        .collect()
+}
+
+#[test]
+fn test_clean_file_passes() {
+    let script = r#"
+::entry(mood="calm") {
+    @show(src="bg-01.png", fade=true) // trailing comment
+    @play src="theme.ogg" volume=0.5 loop
+    @say text=mood next=player.name
+    #[cond("lucky")]
+    #goto(paragraph="ending")
+    "hello"
+}
+::ending { #finish() }
+"#;
+    let story = parse_strict("test", script).unwrap();
+    assert_eq!(story, sixu::parser::parse_story("test", script).unwrap());
+}
+
+#[test]
+fn test_rejects_unquoted_string_values() {
+    let script = "::entry {\n    @show src=bg-01.png\n    @play(src=theme.ogg)\n}\n";
+    assert_eq!(
+        errors(script),
+        vec![
+            (
+                SyntaxErrorKind::UnquotedValue,
+                2,
+                15,
+                "unquoted value `bg-01.png`, quote it as a string".to_string()
+            ),
+            (
+                SyntaxErrorKind::UnquotedValue,
+                3,
+                15,
+                "unquoted value `theme.ogg`, quote it as a string".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_rejects_trailing_content_after_parenthesized_call() {
+    let script = "::entry {\n    @show(a=1) extra\n    #goto(paragraph=\"a\") \"text\"\n}\n";
+    assert_eq!(
+        errors(script),
+        vec![
+            (
+                SyntaxErrorKind::TrailingContent,
+                2,
+                16,
+                "unexpected `extra` after the argument list".to_string()
+            ),
+            (
+                SyntaxErrorKind::TrailingContent,
+                3,
+                26,
+                "unexpected `\"text\"` after the argument list".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_rejects_misspelled_literals() {
+    let script = "::entry {\n    @show fade=ture visible=False\n    @show fade=time\n}\n";
+    assert_eq!(
+        errors(script),
+        vec![
+            (
+                SyntaxErrorKind::SuspiciousIdentifier,
+                2,
+                16,
+                "`ture` looks like a misspelled `true`".to_string()
+            ),
+            (
+                SyntaxErrorKind::SuspiciousIdentifier,
+                2,
+                29,
+                "`False` looks like a misspelled `false`".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_reports_syntax_errors() {
+    let errors = parse_strict(
+        "test",
+        "::entry {\n    @show src=a.png\n    @cmd(a=1 b=2)\n}\n",
+    )
+    .unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].kind, SyntaxErrorKind::UnquotedValue);
+    assert_eq!(errors[1].kind, SyntaxErrorKind::Parse);
+    assert_eq!((errors[1].line, errors[1].column), (3, 14));
+    assert_eq!(
+        errors[1].to_string(),
+        "line 3, column 14: in command argument list, expected `,` or `)`, found `b`"
+    );
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use common::RecordingExecutor;
+use sixu::error::RuntimeError;
+use sixu::format::*;
+
+/// Executor aliasing `#jumpScene scene=...` into a built-in `#goto`
+fn executor() -> RecordingExecutor {
+    RecordingExecutor {
+        rewrite: Some(|systemcall_line| {
+            if systemcall_line.command != "jumpScene" {
+                return None;
+            }
+            let scene = systemcall_line.get_argument("scene")?.clone();
+            Some(SystemCallLine {
+                command: "goto".to_string(),
+                arguments: vec![Argument {
+                    name: "paragraph".to_string(),
+                    value: scene,
+                    span: None,
+                }],
+            })
+        }),
+        ..Default::default()
+    }
+}
+
+fn run_story(script: &str) -> Result<RecordingExecutor, RuntimeError> {
+    common::run_script(script, executor())
+}
+
+#[test]
+fn test_rewritten_system_call_jumps_like_goto() {
+    let executor = run_story(
+        r#"
+::entry {
+before
+#jumpScene scene="target"
+skipped
+}
+
+::skipped {
+also skipped
+}
+
+::target {
+arrived
+#finish
+}
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(executor.texts(), vec!["before", "arrived"]);
+    assert!(executor.system_calls().is_empty());
+}
+
+#[test]
+fn test_system_call_not_rewritten_is_handled_normally() {
+    let executor = run_story(
+        r#"
+::entry {
+#jumpScene
+#custom
+done
+#finish
+}
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(executor.texts(), vec!["done"]);
+    assert_eq!(executor.system_calls(), vec!["jumpScene", "custom"]);
+}
+
+#[test]
+fn test_rewritten_call_resolves_arguments_as_built_in() {
+    let script = r#"
+::entry {
+#jumpScene scene=scenes.next
+}
+
+::target {
+arrived
+}
+"#;
+    let mut runtime = common::runtime(script, executor());
+    *runtime.context_mut().archive_variables_mut() = Literal::Object(
+        [(
+            "scenes".to_string(),
+            Literal::Object(
+                [("next".to_string(), Literal::String("target".to_string()))]
+                    .into_iter()
+                    .collect(),
+            ),
+        )]
+        .into_iter()
+        .collect(),
+    );
+    runtime.start("test", Some("entry")).unwrap();
+    common::run(&mut runtime).unwrap();
+
+    // the rewrite runs first, so `scenes.next` is resolved as the target of a `#goto`
+    assert_eq!(runtime.executor().texts(), vec!["arrived"]);
+}
@@ -0,0 +1,136 @@
+use sixu::error::RuntimeError;
+use sixu::format::*;
+use sixu::runtime::{RuntimeContext, RuntimeExecutor, DEFAULT_TEMPLATE_RECURSION_LIMIT};
+
+/// Test executor whose variables named `nested` expand to the template
+/// `<${nested}>`, producing an unbounded interpolation chain, while `name`
+/// expands to plain text.
+struct NestingExecutor {
+    limit: usize,
+}
+
+impl NestingExecutor {
+    fn template(name: &str) -> TemplateLiteral {
+        TemplateLiteral {
+            parts: vec![
+                TemplateLiteralPart::Text("<".to_string()),
+                TemplateLiteralPart::Value(RValue::Variable(Variable {
+                    chain: vec![name.to_string()],
+                })),
+                TemplateLiteralPart::Text(">".to_string()),
+            ],
+        }
+    }
+}
+
+impl RuntimeExecutor for NestingExecutor {
+    fn handle_command(
+        &mut self,
+        _ctx: &mut RuntimeContext,
+        _command_line: &ResolvedCommandLine,
+    ) -> sixu::error::Result<bool> {
+        Ok(true)
+    }
+
+    fn handle_extra_system_call(
+        &mut self,
+        _ctx: &mut RuntimeContext,
+        _systemcall_line: &ResolvedSystemCallLine,
+    ) -> sixu::error::Result<bool> {
+        Ok(true)
+    }
+
+    fn handle_text(
+        &mut self,
+        _ctx: &mut RuntimeContext,
+        _leading: Option<&str>,
+        _text: Option<&str>,
+        _tailing: Option<&str>,
+    ) -> sixu::error::Result<bool> {
+        Ok(true)
+    }
+
+    fn finished(&mut self, _ctx: &mut RuntimeContext) {}
+
+    fn template_recursion_limit(&self) -> usize {
+        self.limit
+    }
+
+    fn interpolate_value(
+        &self,
+        ctx: &RuntimeContext,
+        value: &RValue,
+        depth: usize,
+    ) -> sixu::error::Result<String> {
+        match value {
+            RValue::Variable(v) if v.chain == ["nested"] => {
+                self.calculate_template_literal_at_depth(ctx, &Self::template("nested"), depth + 1)
+            }
+            RValue::Variable(v) if v.chain == ["name"] => Ok("sixu".to_string()),
+            _ => Ok(self.get_rvalue(ctx, value)?.to_string()),
+        }
+    }
+}
+
+#[test]
+fn test_self_referential_template_hits_recursion_limit() {
+    let executor = NestingExecutor {
+        limit: DEFAULT_TEMPLATE_RECURSION_LIMIT,
+    };
+    let ctx = RuntimeContext::new();
+
+    let result = executor.calculate_template_literal(&ctx, &NestingExecutor::template("nested"));
+
+    match result {
+        Err(RuntimeError::TemplateRecursionLimit(limit)) => {
+            assert_eq!(limit, DEFAULT_TEMPLATE_RECURSION_LIMIT)
+        }
+        other => panic!("expected recursion limit error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_nested_template_within_limit_is_expanded() {
+    let executor = NestingExecutor { limit: 4 };
+    let ctx = RuntimeContext::new();
+
+    let result = executor
+        .calculate_template_literal(&ctx, &NestingExecutor::template("name"))
+        .unwrap();
+    assert_eq!(result, "<sixu>");
+
+    let result = executor.calculate_template_literal(&ctx, &NestingExecutor::template("nested"));
+    assert!(matches!(
+        result,
+        Err(RuntimeError::TemplateRecursionLimit(4))
+    ));
+}
+
+fn parse_template(source: &str) -> TemplateLiteral {
+    let story = sixu::parser::parse_story("test", &format!("::p {{\n{}\n}}\n", source)).unwrap();
+    match &story.paragraphs[0].block.children[0].content {
+        ChildContent::TextLine(_, Text::TemplateLiteral(template), _) => template.clone(),
+        other => panic!("expected a template text line, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_conditional_span_renders_branch_by_condition() {
+    let executor = NestingExecutor { limit: 4 };
+    let template = parse_template("`Hi${?met} again${:}, ${name}${/}!`");
+
+    let mut ctx = RuntimeContext::new();
+    *ctx.archive_variables_mut() = Literal::Object(Default::default());
+    let result = executor
+        .calculate_template_literal(&ctx, &template)
+        .unwrap();
+    assert_eq!(result, "Hi, sixu!");
+
+    if let Literal::Object(variables) = ctx.archive_variables_mut() {
+        variables.insert("met".to_string(), Literal::Boolean(true));
+    }
+    let result = executor
+        .calculate_template_literal(&ctx, &template)
+        .unwrap();
+    assert_eq!(result, "Hi again!");
+}
@@ -0,0 +1,47 @@
+//! `Story::to_source` prints every story of the repository back to source which
+//! parses to an equal story
+
+use std::path::{Path, PathBuf};
+
+use sixu::parser::parse_story;
+
+const FIXTURE_DIRS: &[&str] = &[
+    "sixu/tests/fixtures/format/source",
+    "sixu/tests/fixtures/merge",
+    "sample-project/assets/scenarios",
+];
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .to_path_buf()
+}
+
+#[test]
+fn test_fixtures_round_trip_through_source() {
+    let mut printed = 0;
+    for dir in FIXTURE_DIRS {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(workspace_root().join(dir))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "sixu"))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty(), "no .sixu files in {}", dir);
+
+        for path in paths {
+            let content = std::fs::read_to_string(&path).unwrap();
+            // some fixtures contain deliberate syntax errors
+            let Ok(story) = parse_story("test", &content) else {
+                continue;
+            };
+            printed += 1;
+            let source = story.to_source();
+            let reparsed = parse_story("test", &source)
+                .unwrap_or_else(|e| panic!("{}: {}\n{}", path.display(), e, source));
+            assert_eq!(reparsed, story, "{}\n{}", path.display(), source);
+        }
+    }
+    assert!(printed > 0);
+}
@@ -0,0 +1,150 @@
+//! Structured runtime traces, only built with `--features tracing`
+#![cfg(feature = "tracing")]
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use sixu::parser::parse_story;
+
+/// An event name with its fields rendered as strings
+type Captured = (String, HashMap<String, String>);
+
+/// Subscriber recording every event of the `sixu` targets
+#[derive(Default)]
+struct Capture {
+    events: Arc<Mutex<Vec<Captured>>>,
+    next_span: AtomicU64,
+}
+
+#[derive(Default)]
+struct Fields(HashMap<String, String>);
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl Subscriber for Capture {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target().starts_with("sixu")
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(self.next_span.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        self.events
+            .lock()
+            .unwrap()
+            .push((event.metadata().name().to_string(), fields.0));
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+fn trace(script: &str) -> Vec<Captured> {
+    let story = parse_story("test", script).unwrap();
+    let capture = Capture::default();
+    let events = capture.events.clone();
+    tracing::subscriber::with_default(capture, || {
+        story.simulate("entry", |condition| condition == "true");
+    });
+    let events = events.lock().unwrap().clone();
+    events
+}
+
+fn named<'a>(events: &'a [Captured], name: &str) -> Vec<&'a HashMap<String, String>> {
+    events
+        .iter()
+        .filter(|(event, _)| event == name)
+        .map(|(_, fields)| fields)
+        .collect()
+}
+
+// `other` comes first so that `entry` does not fall through to it
+const SCRIPT: &str = r#"
+::other {
+    {
+        nested
+    }
+}
+
+::entry {
+    first
+    #[cond("false")]
+    hidden
+    #call paragraph="other"
+}
+"#;
+
+#[test]
+fn test_frames_are_traced() {
+    let events = trace(SCRIPT);
+
+    let pushes: Vec<_> = named(&events, "frame_push")
+        .into_iter()
+        .map(|f| (f["paragraph"].as_str(), f["depth"].as_str()))
+        .collect();
+    assert_eq!(pushes, vec![("entry", "1"), ("other", "2"), ("other", "3")]);
+
+    let pops: Vec<_> = named(&events, "frame_pop")
+        .into_iter()
+        .map(|f| (f["paragraph"].as_str(), f["depth"].as_str()))
+        .collect();
+    assert_eq!(pops, vec![("other", "2"), ("other", "1"), ("entry", "0")]);
+    assert!(named(&events, "frame_push")
+        .iter()
+        .all(|f| f["story"] == "test"));
+}
+
+#[test]
+fn test_lines_conditions_and_system_calls_are_traced() {
+    let events = trace(SCRIPT);
+
+    let lines: Vec<_> = named(&events, "line")
+        .into_iter()
+        .map(|f| (f["paragraph"].as_str(), f["line"].as_str()))
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            ("entry", "0"),
+            ("entry", "1"),
+            ("entry", "2"),
+            ("other", "0"),
+            ("other", "0"),
+        ]
+    );
+
+    let conditions = named(&events, "condition");
+    assert_eq!(conditions.len(), 1);
+    assert_eq!(conditions[0]["keyword"], "cond");
+    assert_eq!(conditions[0]["condition"], "false");
+    assert_eq!(conditions[0]["result"], "false");
+
+    let calls = named(&events, "system_call");
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0]["command"], "call");
+    assert_eq!(calls[0]["rewritten"], "false");
+}
@@ -0,0 +1,103 @@
+use sixu::format::{ChildContent, Story, TemplateLiteralPart, Text};
+use sixu::parser::{parse_story_with_config, ParserConfig, UnknownEscape};
+
+const SCRIPT: &str = "::entry {\n    \"a\\qb\"\n    `a\\qb`\n}\n";
+
+fn config(unknown_escape: UnknownEscape) -> ParserConfig {
+    ParserConfig {
+        unknown_escape,
+        ..Default::default()
+    }
+}
+
+/// The quoted text line and the template text line of the story
+fn strings(story: &Story) -> (String, String) {
+    let children = &story.paragraphs[0].block.children;
+    let ChildContent::TextLine(_, Text::Text(text), _) = &children[0].content else {
+        panic!("expected a text line, got {:?}", children[0].content);
+    };
+    let ChildContent::TextLine(_, Text::TemplateLiteral(template), _) = &children[1].content else {
+        panic!("expected a template line, got {:?}", children[1].content);
+    };
+    let [TemplateLiteralPart::Text(template)] = template.parts.as_slice() else {
+        panic!("expected a single text part, got {:?}", template.parts);
+    };
+    (text.clone(), template.clone())
+}
+
+#[test]
+fn test_error_is_the_default() {
+    let err = sixu::parser::parse_story("test", SCRIPT).unwrap_err();
+    assert!(err.contexts.contains(&"unknown escape sequence"));
+}
+
+#[test]
+fn test_strip_drops_backslash() {
+    let story = parse_story_with_config("test", SCRIPT, &config(UnknownEscape::Strip)).unwrap();
+    assert_eq!(strings(&story), ("aqb".to_string(), "aqb".to_string()));
+}
+
+#[test]
+fn test_keep_preserves_backslash() {
+    let story = parse_story_with_config("test", SCRIPT, &config(UnknownEscape::Keep)).unwrap();
+    assert_eq!(strings(&story), ("a\\qb".to_string(), "a\\qb".to_string()));
+}
+
+#[test]
+fn test_error_rejects_story() {
+    let err = parse_story_with_config("test", SCRIPT, &config(UnknownEscape::Error)).unwrap_err();
+    assert_eq!(err.line, 2);
+    assert!(err.contexts.contains(&"unknown escape sequence"));
+}
+
+#[test]
+fn test_dollar_escape_is_template_only() {
+    let script = "::entry {\n    \"a\\$b\"\n    `a\\$b`\n}\n";
+    let err = parse_story_with_config("test", script, &config(UnknownEscape::Error)).unwrap_err();
+    assert_eq!(err.line, 2);
+
+    let story = parse_story_with_config("test", script, &config(UnknownEscape::Keep)).unwrap();
+    assert_eq!(strings(&story), ("a\\$b".to_string(), "a$b".to_string()));
+}
+
+#[test]
+fn test_known_escapes_are_unaffected() {
+    let script = "::entry {\n    \"a\\nb\\u{41}\"\n    `\\$\\``\n}\n";
+    let story = parse_story_with_config("test", script, &config(UnknownEscape::Error)).unwrap();
+    assert_eq!(strings(&story), ("a\nbA".to_string(), "$`".to_string()));
+}
+
+#[cfg(feature = "cst")]
+mod cst {
+    use sixu::cst::node::CstNode;
+    use sixu::cst::parser::{parse_tolerant, parse_tolerant_with_config};
+
+    use super::*;
+
+    fn has_error(nodes: &[CstNode]) -> bool {
+        nodes.iter().any(|node| match node {
+            CstNode::Error { .. } => true,
+            CstNode::Paragraph(paragraph) => has_error(&paragraph.block.children),
+            _ => false,
+        })
+    }
+
+    #[test]
+    fn test_cst_policies_match_ast() {
+        let strip = parse_tolerant_with_config("test", SCRIPT, &config(UnknownEscape::Strip));
+        assert_eq!(
+            strings(&strip.to_ast().unwrap()),
+            ("aqb".to_string(), "aqb".to_string())
+        );
+
+        let keep = parse_tolerant_with_config("test", SCRIPT, &config(UnknownEscape::Keep));
+        assert_eq!(
+            strings(&keep.to_ast().unwrap()),
+            ("a\\qb".to_string(), "a\\qb".to_string())
+        );
+
+        let error = parse_tolerant("test", SCRIPT);
+        assert!(has_error(&error.nodes));
+        assert!(!has_error(&keep.nodes));
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::disallowed_names)]
+
 use sixu::error::RuntimeError;
 use sixu::format::*;
 use sixu::parser::parse;
@@ -69,6 +71,9 @@ fn main() {
                 sample.runtime.executor_mut().last_value += force_parse_int;
                 sample.runtime.resume_script(None, false);
             }
+            Ok(StepResult::NeedsExpression(_)) => {
+                unreachable!("expressions not used in this test")
+            }
             Ok(StepResult::NeedsStoryFile(_)) => {
                 unreachable!("story file loading not used in this test")
             }
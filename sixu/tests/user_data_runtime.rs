@@ -0,0 +1,57 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use common::{step, RecordingExecutor};
+
+/// Shared state an embedder attaches to the context
+#[derive(Default)]
+struct Mixer {
+    played: AtomicUsize,
+}
+
+/// Executor playing a sound on the mixer for every `@sound`, which pauses
+fn executor() -> RecordingExecutor {
+    RecordingExecutor {
+        pause_on: vec!["sound"],
+        on_command: Some(|ctx, command_line| {
+            if command_line.command == "sound" {
+                let mixer = ctx.user_data::<Mixer>().expect("mixer should be attached");
+                mixer.played.fetch_add(1, Ordering::SeqCst);
+            }
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_user_data_is_shared_across_steps() {
+    let script = "::entry {\n    @sound\n    @sound\n    @sound\n}\n";
+    let mut runtime = common::runtime(script, executor());
+    runtime.context_mut().set_user_data(Mixer::default());
+    runtime.start("test", Some("entry")).unwrap();
+
+    // every @sound pauses the runtime, the mixer keeps counting across steps
+    for played in 1..=3 {
+        assert!(step(&mut runtime).unwrap());
+        let mixer = runtime.context().user_data::<Mixer>().unwrap();
+        assert_eq!(mixer.played.load(Ordering::SeqCst), played);
+    }
+    assert!(!step(&mut runtime).unwrap());
+    assert_eq!(runtime.executor().commands(), vec!["sound"; 3]);
+
+    // clones share the user data, a wrong type is not found
+    let snapshot = runtime.context().clone();
+    assert_eq!(
+        snapshot
+            .user_data::<Mixer>()
+            .unwrap()
+            .played
+            .load(Ordering::SeqCst),
+        3
+    );
+    assert!(snapshot.user_data::<String>().is_none());
+
+    runtime.context_mut().clear_user_data();
+    assert!(runtime.context().user_data::<Mixer>().is_none());
+}